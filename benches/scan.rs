@@ -0,0 +1,59 @@
+//! Scans of a generated tree of about 20,000 entries: projects with manifests and
+//! sources, hidden directories and files, as large checkouts have.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use workspace_manager::{scan, ScanOptions};
+
+/// 40 groups of 25 projects, each with a manifest, a `.git` directory and sources.
+fn generate_tree(root: &Path) -> std::io::Result<()> {
+    for group in 0..40 {
+        for project in 0..25 {
+            let dir = root.join(format!("group{}/project{}", group, project));
+            fs::create_dir_all(dir.join("src/nested"))?;
+            fs::create_dir_all(dir.join(".git/objects"))?;
+            let manifest = if project % 2 == 0 {
+                "Cargo.toml"
+            } else {
+                "package.json"
+            };
+            fs::write(dir.join(manifest), "")?;
+            for file in 0..12 {
+                fs::write(dir.join(format!("src/file{}.rs", file)), "")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let temp = TempDir::new().unwrap();
+    generate_tree(temp.path()).unwrap();
+
+    let markers = ScanOptions {
+        max_depth: 3,
+        markers: vec![
+            "Cargo.toml".into(),
+            "package.json".into(),
+            "go.mod".into(),
+            "pyproject.toml".into(),
+        ],
+        ..Default::default()
+    };
+    c.bench_function("scan with markers", |b| {
+        b.iter(|| scan(temp.path(), &markers).unwrap())
+    });
+
+    let everything = ScanOptions {
+        max_depth: 4,
+        ..Default::default()
+    };
+    c.bench_function("scan everything", |b| {
+        b.iter(|| scan(temp.path(), &everything).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_scan);
+criterion_main!(benches);
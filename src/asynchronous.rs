@@ -0,0 +1,135 @@
+//! Async variants of the scan, generation, read and write functions, for callers
+//! running on tokio. Enabled by the `async` feature.
+//!
+//! Reading uses `tokio::fs`. Scanning, generating and writing run the blocking code
+//! on tokio's blocking threads: they make many small file system calls, and writing
+//! replaces the file atomically, which `tokio::fs` can't do any better.
+
+use crate::builder::WorkspaceBuilder;
+use crate::error::{Result, WorkspaceError};
+use crate::filesystem::RealFs;
+use crate::io::WriteOptions;
+use crate::jsonc;
+use crate::model::{ChangeReport, WorkspaceFile};
+use crate::options::GenerateOptions;
+use crate::scan::{scan_until, ScanOptions, ScanOutcome};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Like [`scan`](crate::scan::scan). Dropping the future stops the scan at the next
+/// directory entry.
+pub async fn scan(base: impl Into<PathBuf>, opts: ScanOptions) -> Result<ScanOutcome> {
+    let base = base.into();
+    let stop = Arc::new(AtomicBool::new(false));
+    let _guard = StopOnDrop(Arc::clone(&stop));
+    blocking(move || scan_until(&RealFs, &base, &opts, &stop)).await
+}
+
+/// Like [`read_workspace`](crate::read_workspace).
+pub async fn read_workspace(path: impl AsRef<Path>) -> Result<WorkspaceFile> {
+    let path = path.as_ref();
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(WorkspaceError::io(path))?;
+    serde_json::from_str(&jsonc::to_json(&content)).map_err(|source| {
+        WorkspaceError::ParseWorkspace {
+            path: path.to_path_buf(),
+            source,
+        }
+    })
+}
+
+/// Like [`write_workspace`](crate::write_workspace). The file is written even when
+/// the future is dropped after it started.
+pub async fn write_workspace(
+    path: impl Into<PathBuf>,
+    workspace: WorkspaceFile,
+    options: WriteOptions,
+) -> Result<bool> {
+    let path = path.into();
+    blocking(move || crate::io::write_workspace(&path, &workspace, &options)).await
+}
+
+/// Like [`create_workspace`](crate::create_workspace).
+pub async fn create_workspace(
+    opts: GenerateOptions,
+    base_dir: impl Into<PathBuf>,
+) -> Result<(WorkspaceFile, ChangeReport)> {
+    let base_dir = base_dir.into();
+    blocking(move || crate::workspace::create_workspace(&opts, &base_dir)).await
+}
+
+impl WorkspaceBuilder {
+    /// Like [`build_with_changes`](WorkspaceBuilder::build_with_changes), on a blocking
+    /// thread.
+    pub async fn build_async(self) -> Result<(WorkspaceFile, ChangeReport)> {
+        blocking(move || self.build_with_changes()).await
+    }
+}
+
+/// Sets its flag when dropped, telling a blocking scan to stop.
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Runs `work` on a blocking thread, passing on its panics.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    match tokio::task::spawn_blocking(work).await {
+        Ok(result) => result,
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(WorkspaceError::Other(err.into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{FilterDecision, FolderFilter, ScanContext};
+    use anyhow::Result;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// Keeps every directory, slowly, counting them.
+    #[derive(Debug, Default)]
+    struct Slow(AtomicUsize);
+
+    impl FolderFilter for Slow {
+        fn include(&self, _dir: &Path, _ctx: &ScanContext) -> FilterDecision {
+            std::thread::sleep(Duration::from_millis(2));
+            self.0.fetch_add(1, Ordering::Relaxed);
+            FilterDecision::pass()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dropping_the_scan_stops_it() -> Result<()> {
+        let temp = TempDir::new()?;
+        for i in 0..500 {
+            std::fs::create_dir(temp.path().join(format!("dir{}", i)))?;
+        }
+        let slow = Arc::new(Slow::default());
+        let opts = ScanOptions {
+            filters: vec![slow.clone()],
+            ..Default::default()
+        };
+
+        let scanning = scan(temp.path(), opts);
+        assert!(tokio::time::timeout(Duration::from_millis(50), scanning)
+            .await
+            .is_err());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let stopped_at = slow.0.load(Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(slow.0.load(Ordering::Relaxed), stopped_at);
+        assert!(stopped_at < 500, "{}", stopped_at);
+        Ok(())
+    }
+}
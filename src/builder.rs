@@ -0,0 +1,666 @@
+//! [`WorkspaceBuilder`], for generating a workspace from code without going through
+//! the command line parser.
+
+use crate::error::{Result, WorkspaceError};
+use crate::filesystem::{FileSystem, RealFs};
+use crate::filter::FolderFilter;
+use crate::model::{ChangeReport, WorkspaceFile};
+use crate::naming::{default_namer, FolderNamer};
+use crate::options::{
+    check_name, default_name_dir, dir_name, GenerateOptions, NameStyle, SortOrder, TaskCommand,
+    TaskGroupKind, TaskPanel, TaskReveal, TaskType, DEFAULT_WARN_FOLDERS,
+};
+use crate::workspace::{existing_workspace, generate_workspace};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// How the managed Update Workspace task is written. The default is the task the
+/// command line writes without any `--task-*` flags.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskSpec {
+    /// Label of the task, [`UPDATE_TASK_LABEL`](crate::UPDATE_TASK_LABEL) when not set
+    pub label: Option<String>,
+    /// When VS Code reveals the terminal of the task
+    pub reveal: TaskReveal,
+    /// Which terminal panel the task runs in
+    pub panel: TaskPanel,
+    /// Task group the task is listed in
+    pub group: Option<TaskGroupKind>,
+    /// How the task invokes this tool
+    pub command: TaskCommand,
+    /// Whether the task runs directly or through the shell
+    pub task_type: TaskType,
+    /// Command a shell task runs after regenerating
+    pub shell_command: Option<String>,
+    /// Whether VS Code runs the task when the workspace is opened
+    pub run_on_open: bool,
+    /// Arguments the task passes to this tool
+    pub args: Vec<String>,
+}
+
+/// Generates a workspace the way the `generate` command does, configured with
+/// setters instead of flags.
+///
+/// ```
+/// use workspace_manager::{SortOrder, TaskSpec, WorkspaceBuilder};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let projects = tempfile::TempDir::new()?;
+/// std::fs::create_dir(projects.path().join("api"))?;
+/// std::fs::create_dir(projects.path().join("web"))?;
+///
+/// let workspace = WorkspaceBuilder::new(projects.path())
+///     .name("team")
+///     .exclude_current(true)
+///     .sort(SortOrder::Name)
+///     .with_task(TaskSpec {
+///         args: vec!["--name".to_string(), "team".to_string()],
+///         ..Default::default()
+///     })
+///     .build()?;
+/// let names: Vec<&str> = workspace.folders.iter().map(|f| f.name.as_str()).collect();
+/// assert_eq!(names, ["📦 api", "📦 web"]);
+/// assert!(workspace.update_task().is_some());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The folder paths are relative to [`base_dir`](WorkspaceBuilder::base_dir), where
+/// the workspace file is meant to be written, the scan path unless set. Without
+/// [`existing`](WorkspaceBuilder::existing), the file of that name in `base_dir` is
+/// regenerated when there is one, like the command line does; nothing is written
+/// either way.
+#[derive(Debug, Clone)]
+pub struct WorkspaceBuilder {
+    options: GenerateOptions,
+    base_dir: Option<PathBuf>,
+    existing: Option<WorkspaceFile>,
+    fs: Arc<dyn FileSystem>,
+    namers: Vec<Arc<dyn FolderNamer>>,
+    filters: Vec<Arc<dyn FolderFilter>>,
+}
+
+impl WorkspaceBuilder {
+    /// A builder for the workspace of the directories in `scan_path`, with the same
+    /// defaults as the command line.
+    pub fn new(scan_path: impl Into<PathBuf>) -> Self {
+        Self::from_options(GenerateOptions {
+            scan_path: scan_path.into(),
+            min_folders: 1,
+            warn_folders: DEFAULT_WARN_FOLDERS,
+            ..Default::default()
+        })
+    }
+
+    /// A builder starting from options that were already resolved, e.g. by
+    /// [`GenerateArgs::resolve`](crate::GenerateArgs::resolve).
+    pub fn from_options(options: GenerateOptions) -> Self {
+        Self {
+            options,
+            base_dir: None,
+            existing: None,
+            fs: Arc::new(RealFs),
+            namers: vec![],
+            filters: vec![],
+        }
+    }
+
+    /// The options the workspace will be generated with.
+    pub fn options(&self) -> &GenerateOptions {
+        &self.options
+    }
+
+    /// Workspace name, used for the file name and the root folder entry. Defaults to
+    /// the name of the git repository the scan path is in, or of the base directory
+    /// outside of one.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.options.name = name.into();
+        self
+    }
+
+    /// Leaves the base directory itself out of the folders.
+    pub fn exclude_current(mut self, exclude: bool) -> Self {
+        self.options.exclude_current = exclude;
+        self
+    }
+
+    /// Leaves out directories whose name matches the glob `pattern`. May be called
+    /// several times.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.options.exclude.push(pattern.into());
+        self
+    }
+
+    /// Leaves out directories named exactly `name`. May be called several times.
+    pub fn exclude_name(mut self, name: impl Into<String>) -> Self {
+        self.options.exclude_name.push(name.into());
+        self
+    }
+
+    /// Compares the names given to [`exclude_name`](Self::exclude_name) ignoring case.
+    pub fn exclude_name_ci(mut self, ignore_case: bool) -> Self {
+        self.options.exclude_name_ci = ignore_case;
+        self
+    }
+
+    /// Leaves out directories whose path relative to the base directory, with `/`
+    /// between components, matches `regex`. May be called several times.
+    pub fn exclude_regex(mut self, regex: impl Into<String>) -> Self {
+        self.options.exclude_regex.push(regex.into());
+        self
+    }
+
+    /// Keeps only directories whose relative path matches `regex` or another given
+    /// this way.
+    pub fn include_regex(mut self, regex: impl Into<String>) -> Self {
+        self.options.include_regex.push(regex.into());
+        self
+    }
+
+    /// Leaves out directories that are already folders of the workspace file at
+    /// `path`. May be called several times.
+    pub fn exclude_in(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.exclude_in.push(path.into());
+        self
+    }
+
+    /// Includes directories whose name starts with a dot.
+    pub fn include_hidden(mut self, include: bool) -> Self {
+        self.options.include_hidden = include;
+        self
+    }
+
+    /// Only includes directories containing `file`. May be called several times, to
+    /// include directories containing any of the files.
+    pub fn marker(mut self, file: impl Into<String>) -> Self {
+        self.options.markers.push(file.into());
+        self
+    }
+
+    /// Makes folders of `dirs` instead of scanning, like `--paths-from`; the scan
+    /// filters don't apply. See [`read_candidates`](crate::scan::read_candidates)
+    /// for reading them from a list.
+    pub fn candidates(mut self, dirs: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.options.candidates = Some(dirs.into_iter().collect());
+        self
+    }
+
+    /// Makes folders of the directories listed in the manifest at `path` instead of
+    /// scanning, like `--folders-file`; see [`manifest`](crate::manifest) for its
+    /// formats. Scans too when `merge_scan`, adding the directories found after
+    /// those listed.
+    pub fn folders_file(mut self, path: impl Into<PathBuf>, merge_scan: bool) -> Self {
+        self.options.folders_file = Some(path.into());
+        self.options.merge_scan = merge_scan;
+        self
+    }
+
+    /// Keeps the scan in the file at `path` and reuses it while the scanned
+    /// directories don't change, like `--cache`, which uses
+    /// [`default_cache_path`](crate::cache::default_cache_path). Not used when
+    /// [`filter`](WorkspaceBuilder::filter) was called.
+    pub fn cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.cache = Some(path.into());
+        self
+    }
+
+    /// Order of the scanned folders.
+    pub fn sort(mut self, sort: SortOrder) -> Self {
+        self.options.sort = sort;
+        self
+    }
+
+    /// What folder display names are made from.
+    pub fn name_style(mut self, style: NameStyle) -> Self {
+        self.options.name_style = style;
+        self
+    }
+
+    /// Prefix for folder display names instead of `📦 `.
+    pub fn folder_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.options.folder_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Leaves the emoji out of generated display names.
+    pub fn no_emoji(mut self, no_emoji: bool) -> Self {
+        self.options.no_emoji = no_emoji;
+        self
+    }
+
+    /// Sets a workspace setting. Later calls for the same key win.
+    pub fn setting(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.options.settings.push((key.into(), value));
+        self
+    }
+
+    /// Fewest folders the scan must find, or 0 to accept an empty scan.
+    pub fn min_folders(mut self, min: usize) -> Self {
+        self.options.min_folders = min;
+        self
+    }
+
+    /// Most folders the scan may find.
+    pub fn max_folders(mut self, max: usize) -> Self {
+        self.options.max_folders = Some(max);
+        self
+    }
+
+    /// Adds only the first `limit` folders the existing workspace file doesn't have,
+    /// like `--limit`.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.options.limit = Some(limit);
+        self
+    }
+
+    /// Directory the workspace file is written to, the scan path by default. The
+    /// current directory of the process is never used.
+    pub fn base_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = Some(dir.into());
+        self
+    }
+
+    /// Writes folder paths relative to `dir` instead of
+    /// [`base_dir`](Self::base_dir), like `--relative-to`. `dir` should be canonical.
+    pub fn relative_to(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.relative_to = Some(dir.into());
+        self
+    }
+
+    /// Writes the managed task as `task`, replacing the one an existing workspace has.
+    pub fn with_task(mut self, task: TaskSpec) -> Self {
+        let options = &mut self.options;
+        options.update_task = true;
+        options.no_task = false;
+        options.task_label = task.label;
+        options.task_reveal = task.reveal;
+        options.task_panel = task.panel;
+        options.task_group = task.group;
+        options.task_command = task.command;
+        options.task_type = task.task_type;
+        options.task_shell_command = task.shell_command;
+        options.task_run_on_open = task.run_on_open;
+        options.task_args = task.args;
+        self
+    }
+
+    /// Leaves the managed task out, removing the one an existing workspace has.
+    pub fn without_task(mut self) -> Self {
+        self.options.update_task = false;
+        self.options.no_task = true;
+        self
+    }
+
+    /// Regenerates `workspace` instead of the file in the base directory, keeping its
+    /// settings, tasks, custom names and deselected folders.
+    pub fn existing(mut self, workspace: WorkspaceFile) -> Self {
+        self.existing = Some(workspace);
+        self
+    }
+
+    /// Scans and reads the existing workspace in `fs` instead of on disk, e.g. a
+    /// [`MemoryFs`](crate::filesystem::MemoryFs) in tests.
+    pub fn filesystem(mut self, fs: impl FileSystem + 'static) -> Self {
+        self.fs = Arc::new(fs);
+        self
+    }
+
+    /// Names scanned folders with `namer` when it gives a name. Namers are tried in
+    /// the order they are added, then the name [`name_style`](Self::name_style),
+    /// [`folder_prefix`](Self::folder_prefix) and [`no_emoji`](Self::no_emoji) describe
+    /// is used.
+    pub fn namer(mut self, namer: impl FolderNamer + 'static) -> Self {
+        self.namers.push(Arc::new(namer));
+        self
+    }
+
+    /// Leaves out scanned directories `filter` excludes. Filters are asked in the order
+    /// they are added, after those of the other options; see [`FolderFilter`] for the
+    /// whole order.
+    pub fn filter(mut self, filter: impl FolderFilter + 'static) -> Self {
+        self.filters.push(Arc::new(filter));
+        self
+    }
+
+    /// Generates the workspace.
+    pub fn build(self) -> Result<WorkspaceFile> {
+        Ok(self.build_with_changes()?.0)
+    }
+
+    /// Generates the workspace, with what changed compared to the existing one. Every
+    /// folder counts as added when there is no existing workspace.
+    pub fn build_with_changes(self) -> Result<(WorkspaceFile, ChangeReport)> {
+        let fs = self.fs.as_ref();
+        let mut options = self.options;
+        options.scan_path = fs
+            .canonicalize(&options.scan_path)
+            .map_err(|_| WorkspaceError::ScanPathNotFound(options.scan_path.clone()))?;
+        let base_dir = match &self.base_dir {
+            Some(dir) => fs.canonicalize(dir).map_err(WorkspaceError::io(dir))?,
+            None => options.scan_path.clone(),
+        };
+        if options.name.is_empty() {
+            let is_repo_root = |dir: &Path| fs.exists(&dir.join(".git"));
+            options.name = dir_name(default_name_dir(
+                &options.scan_path,
+                &base_dir,
+                is_repo_root,
+            ));
+        }
+        check_name(&options.name)?;
+        let existing = match self.existing {
+            Some(existing) => Some(existing),
+            None => existing_workspace(fs, &base_dir.join(options.workspace_filename()))?,
+        };
+        let changes_from = existing.clone();
+        let mut namers = self.namers;
+        namers.push(default_namer(&options));
+        let workspace =
+            generate_workspace(fs, &namers, &self.filters, &options, &base_dir, existing)?;
+        let changes = ChangeReport::new(changes_from.as_ref(), &workspace);
+        Ok((workspace, changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{MemoryFs, Operation};
+    use crate::io::{write_workspace, write_workspace_with, WriteOptions};
+    use crate::model::WorkspaceFolder;
+    use crate::scan::read_candidates_with;
+    use crate::tasks::UPDATE_TASK_LABEL;
+    use anyhow::Result;
+    use std::fs;
+    use std::io::{Cursor, ErrorKind};
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn projects(dirs: &[&str]) -> Result<TempDir> {
+        let temp = TempDir::new()?;
+        for dir in dirs {
+            fs::create_dir_all(temp.path().join(dir))?;
+        }
+        Ok(temp)
+    }
+
+    fn names(workspace: &WorkspaceFile) -> Vec<&str> {
+        workspace.folders.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_builder_defaults() -> Result<()> {
+        let temp = projects(&["work/api", "work/.cache"])?;
+        let work = temp.path().join("work");
+        // The workspace file goes in the scan path unless base_dir() says otherwise
+        let workspace = WorkspaceBuilder::new(&work).build()?;
+        assert_eq!(names(&workspace), vec!["🏗️ work", "📦 api"]);
+        assert_eq!(workspace.folders[1].path, "api");
+        assert_eq!(workspace.update_task().unwrap().label, UPDATE_TASK_LABEL);
+
+        let builder = WorkspaceBuilder::new(&work);
+        assert_eq!(builder.options().min_folders, 1);
+        assert_eq!(builder.options().warn_folders, DEFAULT_WARN_FOLDERS);
+        assert!(WorkspaceBuilder::new(temp.path().join("missing"))
+            .build()
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_scan_setters() -> Result<()> {
+        let temp = projects(&[
+            "work/api",
+            "work/web",
+            "work/notes",
+            "work/.tools",
+            "work/legacy",
+            "out",
+        ])?;
+        for dir in ["api", "web", ".tools", "legacy"] {
+            fs::write(temp.path().join("work").join(dir).join("Makefile"), "")?;
+        }
+        let builder = || {
+            WorkspaceBuilder::new(temp.path().join("work"))
+                .base_dir(temp.path().join("out"))
+                .exclude_current(true)
+                .sort(SortOrder::Name)
+        };
+
+        let workspace = builder()
+            .name("team")
+            .include_hidden(true)
+            .marker("Makefile")
+            .exclude("leg*")
+            .exclude("web")
+            .build()?;
+        assert_eq!(names(&workspace), vec!["📦 .tools", "📦 api"]);
+        assert_eq!(workspace.folders[1].path, "../work/api");
+
+        let workspace = builder()
+            .name_style(NameStyle::Relpath)
+            .folder_prefix("- ")
+            .build()?;
+        assert_eq!(
+            names(&workspace),
+            vec!["- api", "- legacy", "- notes", "- web"]
+        );
+
+        let workspace = builder().exclude_current(false).no_emoji(true).build()?;
+        assert_eq!(
+            names(&workspace),
+            vec!["out", "api", "legacy", "notes", "web"]
+        );
+
+        let workspace = builder()
+            .setting("files.autoSave", serde_json::json!("off"))
+            .build()?;
+        assert_eq!(
+            workspace.settings.unwrap()["files.autoSave"],
+            serde_json::json!("off")
+        );
+
+        assert!(builder().exclude("*").build().is_err());
+        assert!(builder().exclude("*").min_folders(0).build().is_ok());
+        assert!(builder().max_folders(3).build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_tasks() -> Result<()> {
+        let temp = projects(&["api"])?;
+        let builder = || WorkspaceBuilder::new(temp.path()).base_dir(temp.path());
+
+        let workspace = builder()
+            .with_task(TaskSpec {
+                label: Some("Refresh".to_string()),
+                group: Some(TaskGroupKind::Build),
+                task_type: TaskType::Shell,
+                shell_command: Some("make".to_string()),
+                run_on_open: true,
+                args: vec!["--exclude-current".to_string()],
+                ..Default::default()
+            })
+            .build()?;
+        let task = workspace.update_task().unwrap();
+        assert_eq!(task.label, "Refresh");
+        assert_eq!(task.task_type, "shell");
+        assert!(task.command.ends_with("--exclude-current && make"));
+        assert!(task.run_options.is_some());
+
+        let workspace = builder().without_task().existing(workspace).build()?;
+        assert!(workspace.tasks.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_existing() -> Result<()> {
+        let temp = projects(&["api", "web"])?;
+        let builder = || {
+            WorkspaceBuilder::new(temp.path())
+                .base_dir(temp.path())
+                .name("team")
+                .exclude_current(true)
+                .sort(SortOrder::Name)
+        };
+
+        let mut existing = WorkspaceFile::default();
+        existing
+            .folders
+            .push(WorkspaceFolder::new("api", "Backend"));
+        existing
+            .folders
+            .push(WorkspaceFolder::new("gone", "📦 gone"));
+        let (workspace, changes) = builder().existing(existing).build_with_changes()?;
+        assert_eq!(names(&workspace), vec!["📦 api", "📦 web"]);
+        let added: Vec<&str> = changes
+            .folders_added
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        let removed: Vec<&str> = changes
+            .folders_removed
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!((added, removed), (vec!["web"], vec!["gone"]));
+        assert_eq!(changes.folders_renamed.len(), 1);
+        // An existing workspace keeps its tasks unless with_task() asks otherwise
+        assert!(workspace.tasks.is_none());
+
+        // Without existing(), the file in the base directory is regenerated
+        write_workspace(
+            &temp.path().join("team.code-workspace"),
+            &workspace,
+            &WriteOptions::default(),
+        )?;
+        let (_, changes) = builder().build_with_changes()?;
+        assert!(changes.is_empty());
+
+        // A file that isn't a valid workspace is never taken for an empty one
+        fs::write(temp.path().join("team.code-workspace"), "{\"folders\": [")?;
+        let err = builder().build_with_changes().unwrap_err();
+        assert!(
+            matches!(err, WorkspaceError::ParseWorkspace { .. }),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_candidates() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_dir("/work/projects/api");
+        fs.add_dir("/work/projects/web");
+        fs.add_dir("/work/other/lib");
+        fs.add_file("/work/projects/README.md", "");
+        let read = |input: &[u8], separator| {
+            read_candidates_with(&fs, Cursor::new(input), separator, Path::new("/work"))
+        };
+
+        let input =
+            "projects/web\n/work/other/lib\r\n\nprojects/README.md\nmissing\nprojects/web\n";
+        let candidates = read(input.as_bytes(), b'\n')?;
+        assert_eq!(
+            candidates,
+            vec![
+                PathBuf::from("/work/projects/web"),
+                PathBuf::from("/work/other/lib")
+            ]
+        );
+        let candidates = read(b"projects/api\0projects/web\0", b'\0')?;
+        assert_eq!(candidates.len(), 2);
+
+        // Exactly the directories listed, in their order, whatever the filters
+        let workspace = WorkspaceBuilder::new("/work/projects")
+            .filesystem(fs.clone())
+            .base_dir("/work")
+            .exclude_current(true)
+            .exclude("api")
+            .candidates(read(b"projects/api\nother/lib\n", b'\n')?)
+            .build()?;
+        let paths: Vec<&str> = workspace.folders.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["projects/api", "other/lib"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_folders_file() -> Result<()> {
+        let fs = MemoryFs::new();
+        for dir in ["api", "web", "docs", "shared/lib"] {
+            fs.add_dir(Path::new("/repo").join(dir));
+        }
+        fs.add_file(
+            "/repo/folders.json",
+            r#"[{"path": "shared/lib", "name": "Shared"}, {"path": "web"}, {"path": "gone"}]"#,
+        );
+        let builder = |merge_scan| {
+            WorkspaceBuilder::new("/repo")
+                .filesystem(fs.clone())
+                .exclude_current(true)
+                .exclude("docs")
+                .sort(SortOrder::Name)
+                .folders_file("/repo/folders.json", merge_scan)
+        };
+
+        let workspace = builder(false).build()?;
+        assert_eq!(names(&workspace), vec!["Shared", "📦 web"]);
+        assert_eq!(workspace.metadata().custom_names, vec!["shared/lib"]);
+
+        // The scanned directories come after those listed, each once
+        let workspace = builder(true).build()?;
+        assert_eq!(
+            names(&workspace),
+            vec!["Shared", "📦 web", "📦 api", "📦 shared"]
+        );
+
+        // A name from the manifest wins over the one in the file
+        let mut renamed = workspace.clone();
+        renamed.folders[0].name = "Libraries".to_string();
+        let workspace = builder(false).existing(renamed).build()?;
+        assert_eq!(workspace.folders[0].name, "Shared");
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_filesystem() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_dir("/work/projects/api");
+        fs.add_dir("/work/projects/web");
+        fs.add_file(
+            "/work/team.code-workspace",
+            r#"{"folders": [{"path": "projects/api", "name": "Backend"}]}"#,
+        );
+
+        let (workspace, changes) = WorkspaceBuilder::new("/work/projects")
+            .filesystem(fs.clone())
+            .base_dir("/work")
+            .name("team")
+            .exclude_current(true)
+            .sort(SortOrder::Name)
+            .build_with_changes()?;
+        assert_eq!(names(&workspace), vec!["📦 api", "📦 web"]);
+        assert_eq!(workspace.folders[1].path, "projects/web");
+        // The existing file was read from `fs`
+        assert_eq!(changes.folders_added.len(), 1);
+        assert_eq!(changes.folders_renamed.len(), 1);
+
+        // Generating doesn't write; a failing write leaves the file as it was
+        let path = Path::new("/work/team.code-workspace");
+        fs.fail(Operation::Write, path, ErrorKind::ReadOnlyFilesystem);
+        let err =
+            write_workspace_with(&fs, path, &workspace, &WriteOptions::default()).unwrap_err();
+        assert!(matches!(err, WorkspaceError::Write { .. }));
+        assert!(fs
+            .file(path)
+            .is_some_and(|content| !content.contains("web")));
+
+        let err = WorkspaceBuilder::new("/work/missing")
+            .filesystem(fs)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, WorkspaceError::ScanPathNotFound(_)));
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+//! The scan cache, enabled with `--cache`: the folders a scan found, kept with what
+//! they depend on, so that a refresh where nothing changed reuses the folders instead
+//! of scanning again.
+//!
+//! A refresh lists the scan path once and compares its subdirectories with those of
+//! the cached scan, so writing the workspace file there doesn't count as a change.
+//! With markers or `--contains`, the directories the scan looked in are compared by
+//! modification time, which changes when entries are added to, removed from or
+//! renamed in them. Edits to the content of files go unnoticed; `--no-cache` scans
+//! anyway.
+
+use crate::console;
+use crate::error::Result;
+use crate::filesystem::FileSystem;
+use crate::options::GenerateOptions;
+use crate::scan::{filtered_scan, ScanOptions, SkipReason};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Version of the cache format. Files of any other version are ignored.
+const CACHE_VERSION: u32 = 1;
+
+/// A scan and what it depended on.
+#[derive(Debug, Serialize, Deserialize)]
+struct ScanCache {
+    version: u32,
+    scan_path: PathBuf,
+    /// The options of the scan, as [`ScanOptions`] debug-prints them
+    options: String,
+    /// The directories in the scan path, sorted
+    subdirectories: Vec<PathBuf>,
+    /// The directories the scan looked in for markers
+    fingerprints: Vec<Fingerprint>,
+    directories: Vec<PathBuf>,
+}
+
+/// What identifies the state of a directory's listing.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    path: PathBuf,
+    /// Modification time in nanoseconds since the epoch
+    modified: u128,
+    /// Inode number, which changes when the directory is replaced; 0 where there is none
+    inode: u64,
+}
+
+impl Fingerprint {
+    /// The fingerprint of the directory at `path`, or `None` if it can't be looked up.
+    fn of(path: &Path) -> Option<Fingerprint> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(Fingerprint {
+            path: path.to_path_buf(),
+            modified: modified.as_nanos(),
+            inode,
+        })
+    }
+
+    fn is_current(&self) -> bool {
+        Fingerprint::of(&self.path).as_ref() == Some(self)
+    }
+}
+
+/// Where `--cache` keeps the scan of `scan_path`: a file named after a hash of the
+/// path in the platform's cache directory, e.g. `~/.cache/workspace-manager`.
+pub fn default_cache_path(scan_path: &Path) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    scan_path.hash(&mut hasher);
+    ProjectDirs::from("", "", "workspace-manager").map(|dirs| {
+        dirs.cache_dir()
+            .join(format!("{:016x}.cache", hasher.finish()))
+    })
+}
+
+/// The folders of the scan for `opts`, from the cache file at `cache` when it holds a
+/// scan of the same path with the same options and nothing it depends on changed
+/// since. Otherwise the scan runs and the cache is rewritten. A cache that can't be
+/// read, parsed or written only costs the scan.
+pub(crate) fn cached_directories(
+    fs: &dyn FileSystem,
+    opts: &GenerateOptions,
+    cache: &Path,
+) -> Result<Vec<PathBuf>> {
+    let options = format!("{:?}", ScanOptions::from(opts));
+    // Listed before scanning, so changes made during the scan show up next time
+    let subdirectories = subdirectories(fs, &opts.scan_path);
+    if let Some(directories) = subdirectories
+        .as_ref()
+        .and_then(|subdirectories| load(cache, &opts.scan_path, &options, subdirectories))
+    {
+        if opts.verbose {
+            console::diagnostic(format_args!(
+                "Reusing the scan cached in {}",
+                cache.display()
+            ));
+        }
+        return Ok(directories);
+    }
+
+    let outcome = filtered_scan(fs, opts, &[])?;
+    // Directories without a marker were looked in too, and gaining one changes them
+    let looked_in = outcome.directories.iter().chain(
+        outcome
+            .skipped
+            .iter()
+            .filter(|skipped| {
+                matches!(
+                    skipped.reason,
+                    SkipReason::NoMarker | SkipReason::NoMatchingFile
+                )
+            })
+            .map(|skipped| &skipped.path),
+    );
+    let fingerprints = match opts.markers.is_empty() && opts.contains.is_empty() {
+        true => Some(vec![]),
+        false => looked_in.map(|dir| Fingerprint::of(dir)).collect(),
+    };
+    if let (Some(subdirectories), Some(fingerprints)) = (subdirectories, fingerprints) {
+        let entry = ScanCache {
+            version: CACHE_VERSION,
+            scan_path: opts.scan_path.clone(),
+            options,
+            subdirectories,
+            fingerprints,
+            directories: outcome.directories.clone(),
+        };
+        if let Err(err) = store(cache, &entry) {
+            console::warning(format_args!(
+                "failed to write the scan cache {}: {}",
+                cache.display(),
+                err
+            ));
+        }
+    }
+    Ok(outcome.directories)
+}
+
+/// The directories in `dir`, sorted, or `None` if it can't be listed.
+fn subdirectories(fs: &dyn FileSystem, dir: &Path) -> Option<Vec<PathBuf>> {
+    let mut subdirectories: Vec<PathBuf> = fs
+        .read_dir_entries(dir)
+        .ok()?
+        .into_iter()
+        .filter(|entry| entry.is_dir)
+        .map(|entry| entry.path)
+        .collect();
+    subdirectories.sort();
+    Some(subdirectories)
+}
+
+/// The folders cached in `cache` if they are still those of a scan of `scan_path`
+/// with `options`, which now has `subdirectories`.
+fn load(
+    cache: &Path,
+    scan_path: &Path,
+    options: &str,
+    subdirectories: &[PathBuf],
+) -> Option<Vec<PathBuf>> {
+    let content = fs::read_to_string(cache).ok()?;
+    let entry: ScanCache = serde_json::from_str(&content).ok()?;
+    let valid = entry.version == CACHE_VERSION
+        && entry.scan_path == scan_path
+        && entry.options == options
+        && entry.subdirectories == subdirectories
+        && entry.fingerprints.iter().all(Fingerprint::is_current);
+    valid.then_some(entry.directories)
+}
+
+fn store(cache: &Path, entry: &ScanCache) -> std::io::Result<()> {
+    if let Some(dir) = cache.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    crate::io::replace_file(cache, &serde_json::to_vec(entry)?, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::RealFs;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    fn scan(opts: &GenerateOptions, cache: &Path) -> Result<Vec<String>> {
+        let mut names: Vec<String> = cached_directories(&RealFs, opts, cache)?
+            .iter()
+            .map(|dir| dir.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Renames the folder `from` to `to` among the folders cached in `cache`, leaving
+    /// what they depend on alone.
+    fn edit_found(cache: &Path, from: &str, to: &str) -> Result<()> {
+        let content = fs::read_to_string(cache)?;
+        let (depends, found) = content.split_once("\"directories\":").unwrap();
+        let found = found.replace(&format!("/{}\"", from), &format!("/{}\"", to));
+        fs::write(cache, format!("{}\"directories\":{}", depends, found))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_hit_and_miss() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path().join("projects");
+        fs::create_dir_all(root.join("api"))?;
+        fs::create_dir_all(root.join("web"))?;
+        let cache = temp.path().join("cache/scan.cache");
+        let opts = GenerateOptions {
+            scan_path: root.clone(),
+            ..Default::default()
+        };
+
+        assert_eq!(scan(&opts, &cache)?, ["api", "web"]);
+        assert!(cache.exists());
+
+        // A hit returns the cached folders without scanning: a cache edited to name
+        // another folder is believed as long as the scan path looks the same, and
+        // writing a file such as the workspace file there doesn't change that
+        edit_found(&cache, "web", "docs")?;
+        fs::write(root.join("projects.code-workspace"), "{}")?;
+        assert_eq!(scan(&opts, &cache)?, ["api", "docs"]);
+
+        // Creating a directory changes the scan path, so the scan runs again
+        fs::create_dir(root.join("lib"))?;
+        assert_eq!(scan(&opts, &cache)?, ["api", "lib", "web"]);
+        assert_eq!(scan(&opts, &cache)?, ["api", "lib", "web"]);
+
+        // Different options don't use the cache of others
+        let excluding = GenerateOptions {
+            exclude: vec!["lib".to_string()],
+            ..opts.clone()
+        };
+        assert_eq!(scan(&excluding, &cache)?, ["api", "web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_notices_new_markers() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path().join("projects");
+        fs::create_dir_all(root.join("api"))?;
+        fs::create_dir_all(root.join("web"))?;
+        fs::write(root.join("api/Cargo.toml"), "")?;
+        let cache = temp.path().join("scan.cache");
+        let opts = GenerateOptions {
+            scan_path: root.clone(),
+            markers: vec!["Cargo.toml".to_string(), "package.json".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(scan(&opts, &cache)?, ["api"]);
+        fs::write(root.join("web/package.json"), "{}")?;
+        assert_eq!(scan(&opts, &cache)?, ["api", "web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_cache_falls_back_to_scanning() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path().join("projects");
+        fs::create_dir_all(root.join("api"))?;
+        let cache = temp.path().join("scan.cache");
+        let opts = GenerateOptions {
+            scan_path: root.clone(),
+            ..Default::default()
+        };
+
+        for stale in ["not json", "{\"version\": 1}", ""] {
+            fs::write(&cache, stale)?;
+            assert_eq!(scan(&opts, &cache)?, ["api"]);
+        }
+
+        // A cache of another version is ignored, even when nothing changed
+        scan(&opts, &cache)?;
+        edit_found(&cache, "api", "gone")?;
+        let content = fs::read_to_string(&cache)?;
+        let version = format!("\"version\":{}", CACHE_VERSION);
+        fs::write(&cache, content.replace(&version, "\"version\":0"))?;
+        assert_eq!(scan(&opts, &cache)?, ["api"]);
+
+        // As is one whose directory was replaced
+        fs::remove_dir_all(&root)?;
+        fs::create_dir_all(root.join("docs"))?;
+        assert_eq!(scan(&opts, &cache)?, ["docs"]);
+        Ok(())
+    }
+}
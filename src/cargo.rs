@@ -0,0 +1,135 @@
+//! Members of Cargo workspaces, read from the root manifest without running cargo.
+
+use crate::WorkspaceMember;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The members of the Cargo workspace rooted at `root`, in the order the manifest
+/// lists them, or nothing when `root` has no `[workspace]` table. Glob patterns are
+/// expanded, `exclude` entries and members outside `root` are skipped, and so are
+/// directories without a `Cargo.toml`. The root package itself is not included.
+pub fn workspace_members(root: &Path) -> Result<Vec<WorkspaceMember>> {
+    let manifest_path = root.join("Cargo.toml");
+    let Ok(content) = fs::read_to_string(&manifest_path) else {
+        return Ok(vec![]);
+    };
+    let manifest: toml::Table = content
+        .parse()
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+    let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+        return Ok(vec![]);
+    };
+    let strings = |key: &str| -> Vec<&str> {
+        workspace
+            .get(key)
+            .and_then(|value| value.as_array())
+            .map(|items| items.iter().filter_map(|item| item.as_str()).collect())
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = strings("exclude")
+        .into_iter()
+        .map(|path| lexical(&root.join(path)))
+        .collect();
+
+    let mut members: Vec<WorkspaceMember> = vec![];
+    for pattern in strings("members") {
+        let full = root.join(pattern);
+        let dirs: Vec<PathBuf> = match glob::glob(&full.to_string_lossy()) {
+            Ok(paths) => paths.filter_map(|path| path.ok()).collect(),
+            Err(_) => vec![full],
+        };
+        for dir in dirs {
+            let dir = lexical(&dir);
+            if dir == lexical(root)
+                || !dir.starts_with(lexical(root))
+                || excluded.iter().any(|excluded| dir.starts_with(excluded))
+                || members.iter().any(|member| member.dir == dir)
+            {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(dir.join("Cargo.toml")) else {
+                continue;
+            };
+            let package = content
+                .parse::<toml::Table>()
+                .ok()
+                .and_then(|manifest| {
+                    let name = manifest.get("package")?.get("name")?.as_str()?;
+                    Some(name.to_string())
+                })
+                .unwrap_or_else(|| {
+                    dir.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned()
+                });
+            members.push(WorkspaceMember { dir, package });
+        }
+    }
+    Ok(members)
+}
+
+/// `path` with `.` and `..` components resolved without touching the file system.
+pub(crate) fn lexical(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_crate(dir: &Path, name: &str) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join("Cargo.toml"),
+            format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n", name),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_workspace_members() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path().join("repo");
+        fs::create_dir(&root)?;
+        fs::write(
+            root.join("Cargo.toml"),
+            r#"
+[package]
+name = "repo"
+
+[workspace]
+members = [".", "crates/*", "tools/gen", "../outside", "missing"]
+exclude = ["crates/experimental"]
+"#,
+        )?;
+        write_crate(&root.join("crates/core"), "repo-core")?;
+        write_crate(&root.join("crates/cli"), "repo-cli")?;
+        write_crate(&root.join("crates/experimental"), "repo-experimental")?;
+        fs::create_dir_all(root.join("crates/notes"))?;
+        write_crate(&root.join("tools/gen"), "codegen")?;
+        write_crate(&temp.path().join("outside"), "outside")?;
+
+        let members = workspace_members(&root)?;
+        let packages: Vec<&str> = members.iter().map(|m| m.package.as_str()).collect();
+        assert_eq!(packages, vec!["repo-cli", "repo-core", "codegen"]);
+        assert_eq!(members[2].dir, root.join("tools").join("gen"));
+
+        // Not a workspace
+        assert!(workspace_members(&root.join("crates/core"))?.is_empty());
+        assert!(workspace_members(temp.path())?.is_empty());
+        Ok(())
+    }
+}
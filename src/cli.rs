@@ -0,0 +1,1182 @@
+//! Command-line interface: the subcommands and their arguments, the examples shown
+//! by `--help`, and shell completions and the man page generated from them.
+
+use crate::config;
+use crate::console::ColorChoice;
+use crate::filter::TimeSpec;
+use crate::options::{
+    CollapseNested, EmitTarget, ExportFormat, GitStatusMode, MissingFolders, NameStyle,
+    SettingsMerge, SortOrder, TaskCommand, TaskGroupKind, TaskPanel, TaskReveal, TaskType,
+    UninitializedSubmodules, WorktreeMode,
+};
+use anyhow::{Context, Result};
+use clap::builder::BoolishValueParser;
+use clap::{CommandFactory, Parser, Subcommand, ValueHint};
+use clap_complete::Shell;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "VS Code workspace manager that creates workspace entries for folders",
+    after_help = examples_help(),
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Options for `generate`, accepted without the subcommand name so that bare
+    /// invocations (and tasks written by older versions) keep working
+    #[command(flatten)]
+    pub generate: GenerateArgs,
+
+    /// When to color messages: auto colors terminals unless NO_COLOR is set
+    #[arg(long, value_enum, global = true, default_value_t, value_name = "WHEN")]
+    pub color: ColorChoice,
+}
+
+impl Cli {
+    /// The command to run, falling back to `generate` when none was given.
+    pub fn into_command(self) -> Command {
+        self.command
+            .unwrap_or(Command::Generate(Box::new(self.generate)))
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Scan a directory and create or update the workspace file (default)
+    Generate(Box<GenerateArgs>),
+
+    /// Write a commented .workspace-manager.toml into the scan path, setting the
+    /// options given as flags and listing every other key; --force overwrites one
+    Init(Box<GenerateArgs>),
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate the completion script for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+
+    /// Regenerate a workspace file with the options stored in its Update Workspace task
+    Update(UpdateArgs),
+
+    /// Regenerate every workspace file under a directory that has an Update Workspace
+    /// task (exit code 1 when any fails)
+    RefreshAll(RefreshAllArgs),
+
+    /// Append a single folder to an existing workspace file
+    Add(AddArgs),
+
+    /// Remove folders from a workspace file by display name or path
+    Remove(RemoveArgs),
+
+    /// Change the display name of a folder
+    Rename(RenameArgs),
+
+    /// Show the folders of a workspace file
+    List(ListArgs),
+
+    /// Remove folders whose directories no longer exist
+    Clean(CleanArgs),
+
+    /// Combine several workspace files into one
+    Merge(MergeArgs),
+
+    /// Compare two workspace files semantically (exit code 3 when they differ)
+    Diff(DiffArgs),
+
+    /// Print folder paths for use in scripts
+    Export(ExportArgs),
+
+    /// Check a workspace file for problems (exit code 3 on errors)
+    Doctor(DoctorArgs),
+
+    /// Summarize a workspace file: its folders by kind of project, missing ones, and
+    /// whether it has its managed task
+    Stats(StatsArgs),
+
+    /// Clone the repositories of missing folders from the remotes recorded for them
+    /// (exit code 1 when any clone fails)
+    Restore(RestoreArgs),
+
+    /// Delete a workspace file and forget it in the registry, or with
+    /// --clean-task-only keep the file without its managed task
+    #[command(alias = "uninstall")]
+    Delete(DeleteArgs),
+
+    /// Manage the Update Workspace task of existing workspace files
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+
+    /// Show and refresh the workspace files generated on this machine
+    Workspaces {
+        #[command(subcommand)]
+        command: WorkspacesCommand,
+    },
+
+    /// Write the workspace-manager.1 man page
+    #[command(hide = true)]
+    Mangen {
+        /// Directory the man page is written to
+        #[arg(long, default_value = ".", value_hint = ValueHint::DirPath)]
+        out_dir: PathBuf,
+    },
+}
+
+/// Example invocations shown in `--help` and in the EXAMPLES section of the man page.
+pub const EXAMPLES: &[(&str, &str)] = &[
+    (
+        "Create a workspace file for the current directory",
+        "workspace-manager",
+    ),
+    (
+        "Scan a specific path (the current directory is included by default)",
+        "workspace-manager --path ~/projects",
+    ),
+    (
+        "Leave the current directory out of the workspace",
+        "workspace-manager -p ~/projects --exclude-current",
+    ),
+    (
+        "Use a custom workspace name and refresh the Update Workspace task",
+        "workspace-manager -p . -n my-workspace --update-task",
+    ),
+    (
+        "Install zsh completions",
+        "workspace-manager completions zsh > ~/.zfunc/_workspace-manager",
+    ),
+];
+
+fn examples_help() -> String {
+    let mut help = String::from("Examples:");
+    for (description, command) in EXAMPLES {
+        help.push_str(&format!("\n  # {}\n  {}\n", description, command));
+    }
+    help
+}
+
+#[derive(clap::Args, Debug, Clone, Default, PartialEq)]
+#[command(group(
+    clap::ArgGroup::new("settings_sources")
+        .args(["reapply_template", "copy_settings_from", "copy_extensions_from"])
+        .multiple(true)
+))]
+pub struct GenerateArgs {
+    /// Path to scan for workspace folders
+    #[arg(
+        short,
+        long,
+        default_value = ".",
+        env = "WORKSPACE_MANAGER_PATH",
+        value_hint = ValueHint::DirPath,
+        help = "Directory to scan for workspace folders"
+    )]
+    pub path: String,
+
+    /// Exclude current directory from workspace
+    #[arg(
+        short,
+        long,
+        overrides_with = "no_exclude_current",
+        help = "Exclude current directory from workspace (default: include)"
+    )]
+    pub exclude_current: bool,
+
+    /// Include the current directory even when a configuration excludes it
+    // `--include-current` is the spelling of early versions, kept for their tasks
+    #[arg(long, alias = "include-current", overrides_with = "exclude_current")]
+    pub no_exclude_current: bool,
+
+    /// Name for the workspace file (without .code-workspace extension)
+    #[arg(short, long, help = "Custom name for the workspace file")]
+    pub name: Option<String>,
+
+    /// Load and update exactly this workspace file, whatever its name; folder paths are
+    /// relative to its directory, and --name then only labels the root folder entry
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = ["dual", "group_by_parent", "output_dir"]
+    )]
+    pub workspace_file: Option<String>,
+
+    /// Don't create the Update Workspace task, and remove one added earlier
+    #[arg(long, overrides_with = "task", conflicts_with = "update_task")]
+    pub no_task: bool,
+
+    /// Create the Update Workspace task even when a configuration disables it
+    #[arg(long, overrides_with = "no_task")]
+    pub task: bool,
+
+    /// Label of the managed task (default: "Update Workspace")
+    #[arg(long, value_name = "LABEL")]
+    pub task_label: Option<String>,
+
+    /// When VS Code shows the terminal of the managed task (default: silent)
+    #[arg(long, value_enum, value_name = "WHEN")]
+    pub task_reveal: Option<TaskReveal>,
+
+    /// Terminal panel the managed task runs in (default: dedicated)
+    #[arg(long, value_enum)]
+    pub task_panel: Option<TaskPanel>,
+
+    /// List the managed task in the build or test group
+    #[arg(long, value_enum, value_name = "GROUP")]
+    pub task_group: Option<TaskGroupKind>,
+
+    /// How the managed task invokes this tool; remembered in .workspace-manager.toml
+    #[arg(long, value_enum, value_name = "KIND")]
+    pub task_command: Option<TaskCommand>,
+
+    /// Run the managed task directly or through the shell
+    #[arg(long, value_enum)]
+    pub task_type: Option<TaskType>,
+
+    /// With --task-type shell, a command to run after regenerating, e.g. "git add -A"
+    #[arg(long, value_name = "STR")]
+    pub task_shell_command: Option<String>,
+
+    /// Run the managed task whenever the workspace is opened, keeping it current
+    #[arg(long, overrides_with = "no_task_run_on_open")]
+    pub task_run_on_open: bool,
+
+    /// Don't run the managed task on open even when a configuration asks for it
+    #[arg(long, overrides_with = "task_run_on_open")]
+    pub no_task_run_on_open: bool,
+
+    /// Add build and test tasks for each folder with a Cargo.toml, package.json or go.mod
+    #[arg(long, overrides_with = "no_folder_tasks")]
+    pub folder_tasks: bool,
+
+    /// Leave out, and remove, the per-folder tasks even when a configuration adds them
+    #[arg(long, overrides_with = "folder_tasks")]
+    pub no_folder_tasks: bool,
+
+    /// Hide the scanned folders inside the current-directory folder through files.exclude
+    #[arg(long, overrides_with = "no_hide_nested")]
+    pub hide_nested: bool,
+
+    /// Show scanned folders nested in the current-directory folder again
+    #[arg(long, overrides_with = "hide_nested")]
+    pub no_hide_nested: bool,
+
+    /// Append the checked-out branch to the display name of each git folder
+    #[arg(long, overrides_with = "no_show_branch")]
+    pub show_branch: bool,
+
+    /// Leave branches out of folder display names
+    #[arg(long, overrides_with = "show_branch")]
+    pub no_show_branch: bool,
+
+    /// List the Cargo.toml of every Rust folder in rust-analyzer.linkedProjects
+    #[arg(long, overrides_with = "no_rust_analyzer_links")]
+    pub rust_analyzer_links: bool,
+
+    /// Remove the rust-analyzer.linkedProjects entries added by --rust-analyzer-links
+    #[arg(long, overrides_with = "rust_analyzer_links")]
+    pub no_rust_analyzer_links: bool,
+
+    /// Recommend VS Code extensions for the kinds of project found in the folders
+    #[arg(long, overrides_with = "no_recommend_extensions")]
+    pub recommend_extensions: bool,
+
+    /// Remove the extension recommendations added by --recommend-extensions
+    #[arg(long, overrides_with = "recommend_extensions")]
+    pub no_recommend_extensions: bool,
+
+    /// Exclude the build and dependency directories of the kinds of project found,
+    /// such as target/ for cargo, from search and file watching
+    #[arg(long, overrides_with = "no_exclude_heavy_dirs")]
+    pub exclude_heavy_dirs: bool,
+
+    /// Remove the exclusions added by --exclude-heavy-dirs
+    #[arg(long, overrides_with = "exclude_heavy_dirs")]
+    pub no_exclude_heavy_dirs: bool,
+
+    /// Record the origin URL of each git folder in the workspace file, for restore
+    #[arg(long, overrides_with = "no_record_remotes")]
+    pub record_remotes: bool,
+
+    /// Remove the URLs recorded by --record-remotes
+    #[arg(long, overrides_with = "record_remotes")]
+    pub no_record_remotes: bool,
+
+    /// Merge the debug configurations of a launch.json-style file into the workspace,
+    /// replacing those with the same name
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub launch_template: Option<String>,
+
+    /// Start a new workspace file from a copy of this one, leaving out its folders
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub template: Option<String>,
+
+    /// Merge the --template into an existing workspace file too, its values winning
+    #[arg(long)]
+    pub reapply_template: bool,
+
+    /// How --reapply-template, --copy-settings-from and --copy-extensions-from combine
+    /// settings both files have (default: ours)
+    #[arg(long, value_enum, requires = "settings_sources")]
+    pub settings_merge: Option<SettingsMerge>,
+
+    /// Merge the settings section of this workspace file into the workspace, leaving
+    /// the rest of the file out
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub copy_settings_from: Option<String>,
+
+    /// Merge the extensions section of this workspace file into the workspace, leaving
+    /// the rest of the file out
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub copy_extensions_from: Option<String>,
+
+    /// In a workspace with a remoteAuthority, write folders as vscode-remote:// URIs
+    /// on that host instead of local relative paths
+    #[arg(long, overrides_with = "no_respect_remote_authority")]
+    pub respect_remote_authority: bool,
+
+    /// Write folders as relative paths even when the workspace has a remoteAuthority
+    #[arg(long, overrides_with = "respect_remote_authority")]
+    pub no_respect_remote_authority: bool,
+
+    /// Write folders as absolute paths under this directory, where a dev container
+    /// mounts each repository by name, e.g. /workspaces
+    #[arg(long, value_name = "PATH")]
+    pub container_root: Option<String>,
+
+    /// Write folder paths relative to this directory instead of the directory of the
+    /// workspace file, e.g. ~ to share the file between machines with the same home
+    /// layout
+    #[arg(long, value_name = "PATH", conflicts_with = "container_root")]
+    pub relative_to: Option<String>,
+
+    /// Make folders of the directories listed in FILE, one per line, instead of
+    /// scanning; `-` reads them from stdin
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["paths_from0", "watch", "interactive", "group_by_parent"]
+    )]
+    pub paths_from: Option<String>,
+
+    /// Like --paths-from, with the directories separated by NUL characters, e.g. from
+    /// `fd -0`
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["watch", "interactive", "group_by_parent"]
+    )]
+    pub paths_from0: Option<String>,
+
+    /// Make folders of the directories listed in FILE instead of scanning: one path
+    /// per line, relative to FILE, with # comments, or a JSON array of {"path",
+    /// "name"} objects when FILE ends in .json
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["paths_from", "paths_from0"])]
+    pub folders_file: Option<String>,
+
+    /// With --folders-file, also scan and add the directories found after those listed
+    #[arg(long, overrides_with = "no_merge_scan")]
+    pub merge_scan: bool,
+
+    /// Only use the directories listed with --folders-file
+    #[arg(long, overrides_with = "merge_scan")]
+    pub no_merge_scan: bool,
+
+    /// What to do when --folders-file lists paths that aren't directories (default:
+    /// warn)
+    #[arg(long, value_enum, value_name = "ACTION")]
+    pub missing_folders: Option<MissingFolders>,
+
+    /// With --container-root, write both NAME.code-workspace with host paths and
+    /// NAME.container.code-workspace with container paths
+    #[arg(long, requires = "container_root", conflicts_with_all = ["watch", "interactive"])]
+    pub dual: bool,
+
+    /// Write one workspace per directory below the scan path instead, each with the
+    /// directories under it as folders
+    #[arg(long, conflicts_with_all = ["watch", "interactive", "dual", "open"])]
+    pub group_by_parent: bool,
+
+    /// With --group-by-parent, how many levels below the scan path the grouping
+    /// directories are (default: 1)
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "group_by_parent",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub group_depth: Option<u32>,
+
+    /// With --group-by-parent, write the workspace files into this directory rather
+    /// than into each grouping directory
+    #[arg(long, value_name = "DIR", requires = "group_by_parent", value_hint = ValueHint::DirPath)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Also add the folders of .code-workspace files found in scanned directories
+    #[arg(long, overrides_with = "no_absorb_workspaces")]
+    pub absorb_workspaces: bool,
+
+    /// Don't look inside scanned directories for .code-workspace files
+    #[arg(long, overrides_with = "absorb_workspaces")]
+    pub no_absorb_workspaces: bool,
+
+    /// Also add the members of Cargo workspaces at the scan path and in scanned
+    /// directories, named after their packages
+    #[arg(long, overrides_with = "no_cargo_members")]
+    pub cargo_members: bool,
+
+    /// Don't add the members of Cargo workspaces
+    #[arg(long, overrides_with = "cargo_members")]
+    pub no_cargo_members: bool,
+
+    /// Also add the packages of npm, yarn and pnpm workspaces at the scan path and in
+    /// scanned directories, named after their packages
+    #[arg(long, overrides_with = "no_node_packages")]
+    pub node_packages: bool,
+
+    /// Don't add the packages of node workspaces
+    #[arg(long, overrides_with = "node_packages")]
+    pub no_node_packages: bool,
+
+    /// Also add the initialized submodules listed in .gitmodules at the scan path and in
+    /// scanned directories
+    #[arg(long, overrides_with = "no_submodules")]
+    pub submodules: bool,
+
+    /// Don't add git submodules
+    #[arg(long, overrides_with = "submodules")]
+    pub no_submodules: bool,
+
+    /// What to do with submodules that aren't checked out yet (default: skip)
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub uninitialized_submodules: Option<UninitializedSubmodules>,
+
+    /// Force update of workspace tasks
+    #[arg(
+        short,
+        long,
+        // `--update-tasks` is the spelling of early versions, kept for their tasks
+        alias = "update-tasks",
+        env = "WORKSPACE_MANAGER_UPDATE_TASK",
+        value_parser = BoolishValueParser::new(),
+        help = "Update workspace task even if file exists"
+    )]
+    pub update_task: bool,
+
+    /// Open the workspace in VS Code after writing it
+    #[arg(
+        long,
+        env = "WORKSPACE_MANAGER_OPEN",
+        value_parser = BoolishValueParser::new()
+    )]
+    pub open: bool,
+
+    /// Editor command used by --open (default: first of code, code-insiders, codium, code-oss)
+    #[arg(long, value_name = "CMD", value_hint = ValueHint::CommandName)]
+    pub editor_cmd: Option<String>,
+
+    /// Comma-separated editor commands to look for, in order of preference
+    #[arg(
+        long,
+        value_name = "CMDS",
+        value_delimiter = ',',
+        env = "WORKSPACE_MANAGER_EDITOR_PRIORITY"
+    )]
+    pub editor_priority: Vec<String>,
+
+    /// Keep running and regenerate whenever directories are added or removed
+    #[arg(
+        long,
+        env = "WORKSPACE_MANAGER_WATCH",
+        value_parser = BoolishValueParser::new()
+    )]
+    pub watch: bool,
+
+    /// Seconds without further changes before --watch regenerates
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 1.5,
+        env = "WORKSPACE_MANAGER_DEBOUNCE"
+    )]
+    pub debounce: f64,
+
+    /// Choose which scanned folders to keep from a checklist before writing
+    #[arg(short, long, conflicts_with = "watch")]
+    pub interactive: bool,
+
+    /// Record folders deselected with --interactive so regeneration leaves them out
+    #[arg(long, requires = "interactive")]
+    pub remember: bool,
+
+    /// Set a workspace setting, e.g. files.trimTrailingWhitespace=true; the value is
+    /// parsed as JSON and taken as a string otherwise (repeatable)
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub set: Vec<String>,
+
+    /// Remove a workspace setting (repeatable)
+    #[arg(long = "unset", value_name = "KEY")]
+    pub unset: Vec<String>,
+
+    /// Leave out directories whose name matches this glob (repeatable)
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Leave out directories with exactly this name (repeatable)
+    #[arg(long = "exclude-name", value_name = "NAME")]
+    pub exclude_name: Vec<String>,
+
+    /// Leave out directories whose path relative to the scan path, with / between
+    /// components, matches this regex, e.g. '-\d{4}-\d{2}-\d{2}$' (repeatable)
+    #[arg(long = "exclude-regex", value_name = "RE", allow_hyphen_values = true)]
+    pub exclude_regex: Vec<String>,
+
+    /// Keep only directories whose relative path matches this regex; the others are
+    /// still looked inside (repeatable)
+    #[arg(long = "include-regex", value_name = "RE", allow_hyphen_values = true)]
+    pub include_regex: Vec<String>,
+
+    /// Leave out directories that are already folders of this workspace file
+    /// (repeatable)
+    #[arg(long = "exclude-in", value_name = "FILE")]
+    pub exclude_in: Vec<String>,
+
+    /// Compare --exclude-name names ignoring case
+    #[arg(long, overrides_with = "no_exclude_name_ci")]
+    pub exclude_name_ci: bool,
+
+    /// Compare --exclude-name names exactly even when a configuration ignores case
+    #[arg(long, overrides_with = "exclude_name_ci")]
+    pub no_exclude_name_ci: bool,
+
+    /// Include directories whose name starts with a dot
+    #[arg(long, overrides_with = "no_include_hidden")]
+    pub include_hidden: bool,
+
+    /// Leave hidden directories out even when a configuration includes them
+    #[arg(long, overrides_with = "include_hidden")]
+    pub no_include_hidden: bool,
+
+    /// Prefix for folder display names (default: "📦 ")
+    #[arg(long, value_name = "PREFIX", allow_hyphen_values = true)]
+    pub folder_prefix: Option<String>,
+
+    /// Order of the scanned folders (default: as read from disk)
+    #[arg(long, value_enum)]
+    pub sort: Option<SortOrder>,
+
+    /// What folder display names are made from (default: the directory name)
+    #[arg(long, value_enum)]
+    pub name_style: Option<NameStyle>,
+
+    /// How to treat git worktrees among the scanned directories (default: like any
+    /// other directory)
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub worktrees: Option<WorktreeMode>,
+
+    /// Drop one side of folders nested inside other folders, instead of warning about
+    /// them; the "." folder doesn't count
+    #[arg(long, value_enum, value_name = "SIDE")]
+    pub collapse_nested: Option<CollapseNested>,
+
+    /// Keep one folder of those that are the same directory reached through symlinks,
+    /// preferring the one that isn't a link
+    #[arg(long)]
+    pub canonical_dedupe: bool,
+
+    /// Merge folders listed several times with differently written paths, keeping
+    /// the customized name, and apply --collapse-nested to the existing folders too
+    #[arg(long)]
+    pub fix_overlaps: bool,
+
+    /// Mark or filter git folders by uncommitted changes, running `git status`
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub git_status: Option<GitStatusMode>,
+
+    /// Only include directories containing this file, e.g. Cargo.toml (repeatable)
+    #[arg(long = "marker", value_name = "FILE")]
+    pub markers: Vec<String>,
+
+    /// Only include directories directly containing a file matching this glob, e.g.
+    /// '*.tf' (repeatable; any pattern may match)
+    #[arg(long = "contains", value_name = "GLOB")]
+    pub contains: Vec<String>,
+
+    /// Let --contains also search subdirectories, down to DEPTH levels below the
+    /// directory (default: 3)
+    #[arg(long, value_name = "DEPTH", num_args = 0..=1, default_missing_value = "3")]
+    pub contains_recursive: Option<usize>,
+
+    /// Only include directories modified within TIME, an age such as 30d, 12h or 2w
+    /// (units s, m, h, d, w) or a date such as 2024-01-01 (UTC). A directory counts as
+    /// modified when it or an entry directly inside it was; the others are still
+    /// looked inside
+    #[arg(long, value_name = "TIME")]
+    pub newer_than: Option<TimeSpec>,
+
+    /// Only include directories not modified within TIME, like --newer-than
+    #[arg(long, value_name = "TIME")]
+    pub older_than: Option<TimeSpec>,
+
+    /// Also look inside the scanned directories, down to N levels below the scan
+    /// path (default: 1, the directories directly inside it)
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Only include directories at least N levels below the scan path, still looking
+    /// inside shallower ones (default: 1)
+    #[arg(long, value_name = "N")]
+    pub min_depth: Option<usize>,
+
+    /// Fail without writing anything when the scan finds fewer folders (default: 1)
+    #[arg(long, value_name = "N")]
+    pub min_folders: Option<usize>,
+
+    /// Write the workspace even when the scan finds fewer than --min-folders folders
+    #[arg(long)]
+    pub allow_empty: bool,
+
+    /// Warn when the scan finds more folders than this (default: 100, 0 to never warn)
+    #[arg(long, value_name = "N")]
+    pub warn_folders: Option<usize>,
+
+    /// Fail without writing anything when the scan finds more folders than this
+    #[arg(long, value_name = "N")]
+    pub max_folders: Option<usize>,
+
+    /// Leave out directories with more than N entries directly inside them, such as
+    /// data dumps, without looking inside them (default: no limit)
+    #[arg(long, value_name = "N")]
+    pub max_entries: Option<usize>,
+
+    /// Warn about folders holding more than MB megabytes of files, adding up the
+    /// sizes down to 3 levels below each; they are still added (default: no warning)
+    #[arg(long, value_name = "MB")]
+    pub warn_size: Option<usize>,
+
+    /// Write the workspace even when the scan finds more than --max-folders folders
+    #[arg(long)]
+    pub force: bool,
+
+    /// Stop after the first N new folders, in the order they are written, keeping the
+    /// folders already in the file. Never recorded in the task
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Also write the folders in another editor's project format (repeatable)
+    #[arg(long = "emit", value_enum, value_name = "TARGET")]
+    pub emit: Vec<EmitTarget>,
+
+    /// Leave the emoji out of generated display names
+    #[arg(long, overrides_with = "emoji")]
+    pub no_emoji: bool,
+
+    /// Keep the emoji in display names even when a configuration turns them off
+    #[arg(long, overrides_with = "no_emoji")]
+    pub emoji: bool,
+
+    /// Reuse the previous scan while the scanned directories are unchanged
+    #[arg(long, overrides_with = "no_cache")]
+    pub cache: bool,
+
+    /// Scan even when a configuration turns the scan cache on
+    #[arg(long, overrides_with = "cache")]
+    pub no_cache: bool,
+
+    /// Rewrite only the folders array of an existing file, leaving every other byte,
+    /// comments included, as it is
+    #[arg(long)]
+    pub surgical: bool,
+
+    /// Check the workspace file against the VS Code workspace schema before writing it,
+    /// failing on content VS Code would reject
+    #[arg(long)]
+    pub validate: bool,
+
+    /// Indent the JSON with N spaces (default: like the existing file, or 2 spaces)
+    #[arg(long, value_name = "N", conflicts_with = "tabs")]
+    pub indent: Option<usize>,
+
+    /// Indent the JSON with tabs
+    #[arg(long)]
+    pub tabs: bool,
+
+    /// Whether the file ends with a newline (default: false)
+    #[arg(long, value_name = "BOOL")]
+    pub final_newline: Option<bool>,
+
+    /// Only add newly found folders, at the end and sorted among themselves, never
+    /// removing, renaming or reordering those already in the file. Recorded in the
+    /// project configuration
+    #[arg(long, conflicts_with_all = ["prune", "replace", "interactive"])]
+    pub append: bool,
+
+    /// Drop the folders the scan no longer finds, keeping those added with `add`
+    /// (default; overrides a configured --append)
+    #[arg(long, conflicts_with = "replace")]
+    pub prune: bool,
+
+    /// Make the folders exactly those the scan finds, also dropping those added with
+    /// `add`, after confirming the folders to remove. Never recorded
+    #[arg(long)]
+    pub replace: bool,
+
+    /// Remove folders with --replace without asking
+    #[arg(long, short = 'y', requires = "replace")]
+    pub yes: bool,
+
+    /// Write a new workspace file here, even when a parent directory has one to update
+    #[arg(long)]
+    pub no_discover: bool,
+
+    /// Apply the [profile.NAME] tables of the configuration files
+    #[arg(long, value_name = "NAME", env = config::PROFILE_ENV)]
+    pub profile: Option<String>,
+
+    /// Report where each option value came from and why each directory left out of
+    /// the scan was
+    #[arg(
+        short,
+        long,
+        env = "WORKSPACE_MANAGER_VERBOSE",
+        value_parser = BoolishValueParser::new()
+    )]
+    pub verbose: bool,
+
+    /// Print only the absolute path of each workspace file written, or left as it was
+    /// because it was up to date, on standard output; every other message goes to
+    /// standard error. For scripts, e.g. `code "$(workspace-manager --print-path)"`
+    #[arg(long)]
+    pub print_path: bool,
+
+    /// Print what generating would change, folders, the update task, settings and
+    /// extension recommendations, without writing anything. With --print-path, the
+    /// report goes to standard error and the paths that would be written to standard output
+    #[arg(long, conflicts_with_all = ["watch", "interactive", "open", "group_by_parent"])]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct UpdateArgs {
+    /// Workspace file to update (default: choose one from the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
+    /// Name of the workspace to update, i.e. NAME.code-workspace
+    #[arg(short, long, conflicts_with = "file")]
+    pub name: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RefreshAllArgs {
+    /// Directory to look for workspace files in
+    #[arg(long, default_value = ".", value_hint = ValueHint::DirPath)]
+    pub root: PathBuf,
+
+    /// How many levels below the root to look
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    pub max_depth: usize,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct AddArgs {
+    /// Directory to add, relative to the current directory or absolute
+    #[arg(value_hint = ValueHint::DirPath)]
+    pub folder: PathBuf,
+
+    /// Display name for the folder (default: "📦 <directory name>")
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// Workspace file to modify (default: the single .code-workspace file in the current directory)
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    pub workspace_file: Option<PathBuf>,
+
+    /// Add the folder even if its path is already in the workspace
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RemoveArgs {
+    /// Display name of the folder, with or without its emoji prefix
+    #[arg(required_unless_present = "path", conflicts_with = "path")]
+    pub name: Option<String>,
+
+    /// Path of the folder, relative to the current directory or absolute
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub path: Option<PathBuf>,
+
+    /// Workspace file to modify (default: the single .code-workspace file in the current directory)
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    pub workspace_file: Option<PathBuf>,
+
+    /// Allow removing the last folder of the workspace
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RenameArgs {
+    /// Current display name followed by the new one; with --path only the new name
+    #[arg(required = true, num_args = 1..=2, value_names = ["OLD", "NEW"])]
+    pub names: Vec<String>,
+
+    /// Select the folder by path instead of by its current name
+    #[arg(long, value_hint = ValueHint::DirPath)]
+    pub path: Option<PathBuf>,
+
+    /// Keep the existing emoji prefix and only replace the text after it
+    #[arg(short, long)]
+    pub keep_prefix: bool,
+
+    /// Workspace file to modify (default: the single .code-workspace file in the current directory)
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    pub workspace_file: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ListArgs {
+    /// Workspace file to read (default: the single .code-workspace file in the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
+    /// Print the folders as JSON
+    #[arg(long, conflicts_with = "tree")]
+    pub json: bool,
+
+    /// Draw the folders as a tree of their path components, with the kinds of project
+    /// found in each
+    #[arg(long)]
+    pub tree: bool,
+
+    /// With --tree, only show directories down to N levels, counting the folders
+    /// below them instead
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "tree",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub depth: Option<u32>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CleanArgs {
+    /// Workspace file to clean (default: the single .code-workspace file in the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
+    /// Report what would be removed without writing the file
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MergeArgs {
+    /// Workspace files to combine, in priority order
+    #[arg(required = true, num_args = 2.., value_hint = ValueHint::FilePath)]
+    pub files: Vec<PathBuf>,
+
+    /// File to write the combined workspace to
+    #[arg(short, long, value_hint = ValueHint::FilePath)]
+    pub output: PathBuf,
+
+    /// Input whose folder names, settings and tasks win conflicts (default: the first)
+    #[arg(long, value_hint = ValueHint::FilePath)]
+    pub prefer: Option<PathBuf>,
+
+    /// How settings both inputs have are combined (default: the --prefer input wins)
+    #[arg(long, value_enum)]
+    pub settings_merge: Option<SettingsMerge>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DiffArgs {
+    /// Original workspace file
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub old: PathBuf,
+
+    /// Workspace file to compare against it
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub new: PathBuf,
+
+    /// Print the differences as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// Workspace file to read (default: the single .code-workspace file in the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
+    /// Print paths as written in the file instead of absolute paths
+    #[arg(long, conflicts_with = "names")]
+    pub relative: bool,
+
+    /// Print display names instead of paths
+    #[arg(long)]
+    pub names: bool,
+
+    /// Print uri folders as-is instead of skipping them
+    #[arg(long)]
+    pub include_uris: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ExportFormat::Lines)]
+    pub format: ExportFormat,
+
+    /// Print a Markdown table of the folders with the kind of project, git remote and
+    /// branch of each, e.g. for a wiki
+    #[arg(long, conflicts_with_all = ["relative", "names", "include_uris", "format"])]
+    pub markdown: bool,
+
+    /// Write to this file instead of standard output
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DoctorArgs {
+    /// Workspace file to check (default: the single .code-workspace file in the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
+    /// Print the findings as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Point a managed task whose binary no longer exists at this one, as its
+    /// --task-command says, before checking
+    #[arg(long)]
+    pub fix: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct StatsArgs {
+    /// Workspace file to summarize (default: the single .code-workspace file in the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
+    /// Print the summary as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Seconds to wait for the folders to be probed before counting the rest as timed
+    /// out
+    #[arg(long, value_name = "SECONDS", default_value_t = crate::stats::PROBE_TIMEOUT.as_secs())]
+    pub timeout: u64,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RestoreArgs {
+    /// Workspace file to restore (default: the single .code-workspace file in the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub file: Option<PathBuf>,
+
+    /// List what would be cloned without cloning anything
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// How many repositories to clone at once
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub jobs: u32,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DeleteArgs {
+    /// Workspace file to delete, or the name of one in the current directory, i.e.
+    /// NAME.code-workspace
+    #[arg(value_name = "NAME|FILE", value_hint = ValueHint::FilePath)]
+    pub target: String,
+
+    /// Delete without asking
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Keep the file, removing only its managed task and its registry entry
+    #[arg(long)]
+    pub clean_task_only: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum WorkspacesCommand {
+    /// List registered workspace files, flagging those that no longer exist
+    List {
+        /// Print the registry entries as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Regenerate registered workspace files with their stored options
+    Refresh(RefreshArgs),
+
+    /// Forget workspace files that no longer exist
+    Prune,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TasksCommand {
+    /// Remove the Update Workspace task, leaving other tasks alone
+    Remove(TaskRemoveArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TaskRemoveArgs {
+    /// Workspace files to modify (default: the single .code-workspace file in the current directory)
+    #[arg(value_hint = ValueHint::FilePath)]
+    pub files: Vec<PathBuf>,
+
+    /// Label of the task to remove (default: the label recorded in each file)
+    #[arg(long, value_name = "LABEL")]
+    pub label: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RefreshArgs {
+    /// Workspace files to refresh
+    #[arg(value_hint = ValueHint::FilePath, required_unless_present = "all")]
+    pub files: Vec<PathBuf>,
+
+    /// Refresh every registered workspace file
+    #[arg(long, conflicts_with = "files")]
+    pub all: bool,
+}
+
+/// Writes the completion script for `shell` covering every flag and subcommand.
+pub fn generate_completions(shell: Shell, out: &mut dyn Write) {
+    let mut cmd = Cli::command();
+    let bin_name = cmd.get_name().to_string();
+    clap_complete::generate(shell, &mut cmd, bin_name, out);
+}
+
+/// Renders the man page from the clap definitions, with an EXAMPLES section.
+pub fn render_man_page(out: &mut dyn Write) -> std::io::Result<()> {
+    // The examples get their own section below, so keep them out of the generic one
+    let cmd = Cli::command().after_help(None::<&str>);
+    let man = clap_mangen::Man::new(cmd);
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    man.render_options_section(out)?;
+    man.render_subcommands_section(out)?;
+
+    let mut roff = roff::Roff::new();
+    roff.control("SH", ["EXAMPLES"]);
+    for (description, command) in EXAMPLES {
+        roff.control("TP", [] as [&str; 0]);
+        roff.text([roff::roman(*description)]);
+        roff.control("nf", [] as [&str; 0]);
+        roff.text([roff::bold(*command)]);
+        roff.control("fi", [] as [&str; 0]);
+    }
+    roff.to_writer(out)?;
+
+    man.render_version_section(out)?;
+    man.render_authors_section(out)
+}
+
+/// Writes `workspace-manager.1` into `out_dir`, creating the directory if needed.
+pub fn generate_man_page(out_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    let path = out_dir.join(format!("{}.1", Cli::command().get_name()));
+    let mut buffer = Vec::new();
+    render_man_page(&mut buffer)?;
+    fs::write(&path, buffer).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_cli_defaults_to_generation_without_subcommand() {
+        let cli = Cli::try_parse_from(["workspace-manager", "--path", "x", "-e"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.generate.path, "x");
+        assert!(cli.generate.exclude_current);
+
+        let cli = Cli::try_parse_from(["workspace-manager", "completions", "zsh"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Completions { shell: Shell::Zsh })
+        ));
+    }
+
+    #[test]
+    fn test_generate_subcommand_matches_bare_flags() {
+        let explicit =
+            Cli::try_parse_from(["workspace-manager", "generate", "-p", "x", "-n", "ws"])
+                .unwrap()
+                .into_command();
+        let bare = Cli::try_parse_from(["workspace-manager", "-p", "x", "-n", "ws"])
+            .unwrap()
+            .into_command();
+
+        match (explicit, bare) {
+            (Command::Generate(a), Command::Generate(b)) => {
+                assert_eq!(a.to_task_args(), b.to_task_args());
+            }
+            other => panic!("expected generate commands, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_task_args_from_older_versions_still_parse() {
+        // Tasks written before subcommands existed contain only bare flags
+        let old_task_args = ["--name", "ws", "--exclude-current", "--path", "."];
+        let cli = Cli::try_parse_from(
+            std::iter::once("workspace-manager").chain(old_task_args.iter().copied()),
+        )
+        .unwrap();
+
+        match cli.into_command() {
+            Command::Generate(args) => assert_eq!(args.to_task_args(), old_task_args),
+            other => panic!("expected generate command, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_generate_completions() {
+        let mut out = Vec::new();
+        generate_completions(Shell::Bash, &mut out);
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("--path"));
+        assert!(script.contains("--exclude-current"));
+        assert!(script.contains("completions"));
+    }
+
+    #[test]
+    fn test_generate_man_page() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = generate_man_page(&temp.path().join("man"))?;
+
+        assert_eq!(path.file_name().unwrap(), "workspace-manager.1");
+        let page = fs::read_to_string(&path)?;
+        assert!(page.contains(".SH EXAMPLES"));
+        assert!(page.contains("exclude\\-current"));
+        assert!(!page.contains(".SH EXTRA"));
+        assert!(!page.contains("mangen"));
+        Ok(())
+    }
+}
@@ -0,0 +1,1257 @@
+//! Configuration files providing defaults for generation: the user's global
+//! `config.toml` and `.workspace-manager.toml` in the scan root. Later layers win,
+//! and explicit command-line flags override both.
+
+use crate::console::EmojiChoice;
+use crate::filter::TimeSpec;
+use crate::{
+    CollapseNested, EmitTarget, GitStatusMode, MissingFolders, NameStyle, SettingsMerge, SortOrder,
+    TaskCommand, TaskGroupKind, TaskPanel, TaskReveal, TaskType, UninitializedSubmodules,
+    WorktreeMode,
+};
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Selects a profile when `--profile` is not given.
+pub const PROFILE_ENV: &str = "WORKSPACE_MANAGER_PROFILE";
+
+/// Prefix of the environment variables overriding configuration values, e.g.
+/// `WORKSPACE_MANAGER_EXCLUDE` for `exclude`.
+pub const ENV_PREFIX: &str = "WORKSPACE_MANAGER_";
+
+/// File name of the project configuration, looked up in the scan root.
+pub const PROJECT_CONFIG_FILE: &str = ".workspace-manager.toml";
+
+/// The user's configuration file, `~/.config/workspace-manager/config.toml` on Linux
+/// and the platform's configuration directory elsewhere.
+pub fn global_config_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "workspace-manager").map(|dirs| dirs.config_dir().join("config.toml"))
+}
+
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    /// Workspace name, used for the file name and the root folder entry
+    pub name: Option<String>,
+    /// Leave the current directory out of the workspace
+    pub exclude_current: Option<bool>,
+    /// Don't write the Update Workspace task
+    pub no_task: Option<bool>,
+    /// Label of the managed task
+    pub task_label: Option<String>,
+    /// When VS Code reveals the terminal of the managed task
+    pub task_reveal: Option<TaskReveal>,
+    /// Which terminal panel the managed task runs in
+    pub task_panel: Option<TaskPanel>,
+    /// Task group the managed task is listed in
+    pub task_group: Option<TaskGroupKind>,
+    /// How the managed task invokes this tool
+    pub task_command: Option<TaskCommand>,
+    /// Whether the managed task runs directly or through the shell
+    pub task_type: Option<TaskType>,
+    /// Command a shell task runs after regenerating
+    pub task_shell_command: Option<String>,
+    /// Whether VS Code runs the managed task when the workspace is opened
+    pub task_run_on_open: Option<bool>,
+    /// Whether to generate build and test tasks for each folder
+    pub folder_tasks: Option<bool>,
+    /// Whether to hide scanned folders nested in the current-directory folder
+    pub hide_nested: Option<bool>,
+    /// Whether to append the checked-out branch to the names of git folders
+    pub show_branch: Option<bool>,
+    /// Whether to list Rust folders in rust-analyzer.linkedProjects
+    pub rust_analyzer_links: Option<bool>,
+    /// Whether to add the folders of workspace files inside scanned directories
+    pub absorb_workspaces: Option<bool>,
+    /// Whether to add the members of Cargo workspaces
+    pub cargo_members: Option<bool>,
+    /// Whether to add the packages of node workspaces
+    pub node_packages: Option<bool>,
+    /// Whether to add git submodules
+    pub submodules: Option<bool>,
+    /// What to do with submodules that aren't checked out
+    pub uninitialized_submodules: Option<UninitializedSubmodules>,
+    /// Whether to recommend extensions for the kinds of project found
+    pub recommend_extensions: Option<bool>,
+    /// Whether to exclude the build directories of the kinds of project found from
+    /// search and file watching
+    pub exclude_heavy_dirs: Option<bool>,
+    /// Whether to record the origin URL of each git folder
+    pub record_remotes: Option<bool>,
+    /// Extensions to recommend per project type, e.g. `cargo = ["rust-lang.rust-analyzer"]`,
+    /// replacing the built-in list of each type given
+    pub extensions: Option<BTreeMap<String, Vec<String>>>,
+    /// launch.json-style file merged into the launch section
+    pub launch_template: Option<String>,
+    /// Workspace file new workspace files start from
+    pub template: Option<String>,
+    /// Workspace file whose settings section is merged into the workspace
+    pub copy_settings_from: Option<String>,
+    /// Workspace file whose extensions section is merged into the workspace
+    pub copy_extensions_from: Option<String>,
+    /// How settings both the workspace and a file they are copied from have combine
+    pub settings_merge: Option<SettingsMerge>,
+    /// Whether to write folders as URIs on the workspace's remoteAuthority
+    pub respect_remote_authority: Option<bool>,
+    /// Directory of the dev container that folders are written under
+    pub container_root: Option<String>,
+    /// Directory folder paths are written relative to
+    pub relative_to: Option<String>,
+    /// Manifest listing the directories to make folders of
+    pub folders_file: Option<String>,
+    /// Whether to also scan with `folders_file`
+    pub merge_scan: Option<bool>,
+    /// What to do with paths in `folders_file` that aren't directories
+    pub missing_folders: Option<MissingFolders>,
+    /// Whether to write a host and a container file from one scan
+    pub dual: Option<bool>,
+    /// Glob patterns of directory names to leave out
+    pub exclude: Option<Vec<String>>,
+    /// Exact directory names to leave out
+    pub exclude_name: Option<Vec<String>>,
+    /// Whether to compare `exclude_name` ignoring case
+    pub exclude_name_ci: Option<bool>,
+    /// Regexes of paths relative to the scan root to leave out
+    pub exclude_regex: Option<Vec<String>>,
+    /// Regexes of paths relative to the scan root to keep
+    pub include_regex: Option<Vec<String>>,
+    /// Workspace files whose folders are left out
+    pub exclude_in: Option<Vec<String>>,
+    /// Include directories whose name starts with a dot
+    pub include_hidden: Option<bool>,
+    /// Prefix for folder display names, `📦 ` by default
+    pub folder_prefix: Option<String>,
+    /// Order of the scanned folders
+    pub sort: Option<SortOrder>,
+    /// What folder display names are made from
+    pub name_style: Option<NameStyle>,
+    /// How to treat git worktrees
+    pub worktrees: Option<WorktreeMode>,
+    /// Which of two nested folders to keep
+    pub collapse_nested: Option<CollapseNested>,
+    /// Whether to keep one folder of those that are the same directory through symlinks
+    pub canonical_dedupe: Option<bool>,
+    /// Whether to merge folders listed several times and collapse existing nested ones
+    pub fix_overlaps: Option<bool>,
+    /// Whether to mark or filter git folders by uncommitted changes
+    pub git_status: Option<GitStatusMode>,
+    /// Only include directories containing one of these files
+    pub markers: Option<Vec<String>>,
+    /// Only include directories containing a file matching one of these globs
+    pub contains: Option<Vec<String>>,
+    /// How many levels below each directory `contains` searches
+    pub contains_recursive: Option<usize>,
+    /// Only include directories modified within this age or since this date
+    pub newer_than: Option<TimeSpec>,
+    /// Only include directories not modified within this age or since this date
+    pub older_than: Option<TimeSpec>,
+    /// How many levels below the scan path to look
+    pub max_depth: Option<usize>,
+    /// How many levels below the scan path directories start to be included
+    pub min_depth: Option<usize>,
+    /// Other project files to write with the same folders
+    pub emit: Option<Vec<EmitTarget>>,
+    /// Leave the emoji out of generated display names
+    pub no_emoji: Option<bool>,
+    /// Whether generated display names start with an emoji: `always`, `never`, or
+    /// `auto` to leave them out on Windows consoles using a legacy code page
+    pub emoji: Option<EmojiChoice>,
+    /// Whether to reuse the previous scan while the scanned directories are unchanged
+    pub cache: Option<bool>,
+    /// Whether to rewrite only the folders array of an existing workspace file
+    pub surgical: Option<bool>,
+    /// Whether to check workspace files against the workspace schema before writing
+    pub validate: Option<bool>,
+    /// Spaces per level of indentation of written workspace files
+    pub indent: Option<usize>,
+    /// Whether written workspace files are indented with tabs, overriding `indent`
+    pub tabs: Option<bool>,
+    /// Whether written workspace files end with a newline
+    pub final_newline: Option<bool>,
+    /// Whether regeneration only adds folders to an existing workspace file; the
+    /// other modes are chosen on the command line
+    pub append: Option<bool>,
+    /// Whether a run without a name updates a workspace file in a parent directory
+    pub discover: Option<bool>,
+    /// Fewest folders a scan must find for the workspace to be written
+    pub min_folders: Option<usize>,
+    /// Whether to write the workspace even when the scan finds too few folders
+    pub allow_empty: Option<bool>,
+    /// Number of folders above which a scan prints a warning
+    pub warn_folders: Option<usize>,
+    /// Most folders a scan may find for the workspace to be written
+    pub max_folders: Option<usize>,
+    /// Most entries a directory may have directly inside it to be scanned
+    pub max_entries: Option<usize>,
+    /// Megabytes of files above which a folder gets a warning
+    pub warn_size: Option<usize>,
+    /// Named `[profile.<name>]` tables overlaying the values above when selected
+    pub profile: BTreeMap<String, Config>,
+    /// Keys this version does not know about; reported, not rejected
+    #[serde(flatten)]
+    pub unknown: toml::Table,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Config> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Reads the configuration at `path`, or `None` when there is no such file.
+    pub fn load(path: &Path) -> Result<Option<Config>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Config::parse(&text)
+            .map(Some)
+            .with_context(|| format!("Invalid configuration in {}", path.display()))
+    }
+
+    /// Reads the configuration values set through `WORKSPACE_MANAGER_*` variables,
+    /// looked up with `var`. Lists are colon-separated; booleans accept
+    /// `1`/`true`/`yes`/`on` and `0`/`false`/`no`/`off`.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Config> {
+        let get = |key: &str| var(&format!("{}{}", ENV_PREFIX, key.to_uppercase()));
+        let list = |key: &str| {
+            get(key).map(|value| {
+                value
+                    .split(':')
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+        };
+        let flag = |key: &str| -> Result<Option<bool>> {
+            get(key)
+                .map(|value| {
+                    parse_bool(&value).with_context(|| {
+                        format!(
+                            "Invalid value '{}' for {}{}",
+                            value,
+                            ENV_PREFIX,
+                            key.to_uppercase()
+                        )
+                    })
+                })
+                .transpose()
+        };
+        let number = |key: &str| -> Result<Option<usize>> {
+            get(key)
+                .map(|value| {
+                    value.parse().with_context(|| {
+                        format!(
+                            "Invalid value '{}' for {}{}",
+                            value,
+                            ENV_PREFIX,
+                            key.to_uppercase()
+                        )
+                    })
+                })
+                .transpose()
+        };
+        let time = |key: &str| -> Result<Option<TimeSpec>> {
+            get(key)
+                .map(|value| {
+                    value.parse().map_err(anyhow::Error::msg).with_context(|| {
+                        format!(
+                            "Invalid value '{}' for {}{}",
+                            value,
+                            ENV_PREFIX,
+                            key.to_uppercase()
+                        )
+                    })
+                })
+                .transpose()
+        };
+        Ok(Config {
+            name: get("name"),
+            exclude_current: flag("exclude_current")?,
+            no_task: flag("no_task")?,
+            task_label: get("task_label"),
+            exclude: list("exclude"),
+            exclude_name: list("exclude_name"),
+            exclude_name_ci: flag("exclude_name_ci")?,
+            exclude_regex: list("exclude_regex"),
+            include_regex: list("include_regex"),
+            exclude_in: list("exclude_in"),
+            include_hidden: flag("include_hidden")?,
+            folder_prefix: get("folder_prefix"),
+            task_reveal: choice(&var, "task_reveal")?,
+            task_panel: choice(&var, "task_panel")?,
+            task_group: choice(&var, "task_group")?,
+            task_command: choice(&var, "task_command")?,
+            task_type: choice(&var, "task_type")?,
+            task_shell_command: get("task_shell_command"),
+            task_run_on_open: flag("task_run_on_open")?,
+            folder_tasks: flag("folder_tasks")?,
+            hide_nested: flag("hide_nested")?,
+            show_branch: flag("show_branch")?,
+            rust_analyzer_links: flag("rust_analyzer_links")?,
+            absorb_workspaces: flag("absorb_workspaces")?,
+            cargo_members: flag("cargo_members")?,
+            node_packages: flag("node_packages")?,
+            submodules: flag("submodules")?,
+            uninitialized_submodules: choice(&var, "uninitialized_submodules")?,
+            recommend_extensions: flag("recommend_extensions")?,
+            exclude_heavy_dirs: flag("exclude_heavy_dirs")?,
+            record_remotes: flag("record_remotes")?,
+            launch_template: get("launch_template"),
+            template: get("template"),
+            copy_settings_from: get("copy_settings_from"),
+            copy_extensions_from: get("copy_extensions_from"),
+            settings_merge: choice(&var, "settings_merge")?,
+            respect_remote_authority: flag("respect_remote_authority")?,
+            container_root: get("container_root"),
+            relative_to: get("relative_to"),
+            folders_file: get("folders_file"),
+            merge_scan: flag("merge_scan")?,
+            missing_folders: choice(&var, "missing_folders")?,
+            dual: flag("dual")?,
+            sort: choice(&var, "sort")?,
+            name_style: choice(&var, "name_style")?,
+            worktrees: choice(&var, "worktrees")?,
+            collapse_nested: choice(&var, "collapse_nested")?,
+            canonical_dedupe: flag("canonical_dedupe")?,
+            fix_overlaps: flag("fix_overlaps")?,
+            git_status: choice(&var, "git_status")?,
+            markers: list("markers"),
+            contains: list("contains"),
+            contains_recursive: number("contains_recursive")?,
+            newer_than: time("newer_than")?,
+            older_than: time("older_than")?,
+            max_depth: number("max_depth")?,
+            min_depth: number("min_depth")?,
+            emit: choices(&var, "emit")?,
+            no_emoji: flag("no_emoji")?,
+            emoji: choice(&var, "emoji")?,
+            cache: flag("cache")?,
+            surgical: flag("surgical")?,
+            validate: flag("validate")?,
+            indent: number("indent")?,
+            tabs: flag("tabs")?,
+            final_newline: flag("final_newline")?,
+            append: flag("append")?,
+            discover: flag("discover")?,
+            min_folders: number("min_folders")?,
+            allow_empty: flag("allow_empty")?,
+            warn_folders: number("warn_folders")?,
+            max_folders: number("max_folders")?,
+            max_entries: number("max_entries")?,
+            warn_size: number("warn_size")?,
+            ..Default::default()
+        })
+    }
+
+    /// Names of the keys that were ignored because they are not recognized,
+    /// including those inside profiles.
+    pub fn unknown_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.unknown.keys().cloned().collect();
+        for (name, profile) in &self.profile {
+            keys.extend(
+                profile
+                    .unknown_keys()
+                    .into_iter()
+                    .map(|key| format!("profile.{}.{}", name, key)),
+            );
+        }
+        keys
+    }
+}
+
+/// Reads a value-enum setting such as `WORKSPACE_MANAGER_SORT` through `var`.
+fn choice<T: clap::ValueEnum>(
+    var: impl Fn(&str) -> Option<String>,
+    key: &str,
+) -> Result<Option<T>> {
+    let name = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+    let Some(value) = var(&name) else {
+        return Ok(None);
+    };
+    parse_choice(&name, &value).map(Some)
+}
+
+/// Reads a colon-separated list of value-enum settings, like [`choice`].
+fn choices<T: clap::ValueEnum>(
+    var: impl Fn(&str) -> Option<String>,
+    key: &str,
+) -> Result<Option<Vec<T>>> {
+    let name = format!("{}{}", ENV_PREFIX, key.to_uppercase());
+    let Some(value) = var(&name) else {
+        return Ok(None);
+    };
+    value
+        .split(':')
+        .filter(|item| !item.is_empty())
+        .map(|item| parse_choice(&name, item))
+        .collect::<Result<_>>()
+        .map(Some)
+}
+
+fn parse_choice<T: clap::ValueEnum>(name: &str, value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| {
+        let expected: Vec<String> = T::value_variants().iter().map(crate::value_name).collect();
+        anyhow::anyhow!(
+            "Invalid value '{}' for {}: expected {}",
+            value,
+            name,
+            expected.join(" or ")
+        )
+    })
+}
+
+/// Sets a top-level `key` in the configuration file at `path`, creating the file
+/// if needed and keeping the rest of it as written. Returns whether the file
+/// changed.
+pub fn remember(path: &Path, key: &str, value: impl Into<toml_edit::Value>) -> Result<bool> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let mut document: toml_edit::DocumentMut = text
+        .parse()
+        .with_context(|| format!("Invalid configuration in {}", path.display()))?;
+    let value = value.into();
+    if document
+        .get(key)
+        .and_then(|item| item.as_value())
+        .is_some_and(|existing| existing.to_string().trim() == value.to_string().trim())
+    {
+        return Ok(false);
+    }
+    document[key] = toml_edit::value(value);
+    crate::write_atomic(path, document.to_string().as_bytes())?;
+    Ok(true)
+}
+
+/// Every key of a configuration file with an example value and what it sets, in the
+/// order `init` writes them. The tables come last, so uncommenting one doesn't take
+/// in the keys after it.
+pub const CONFIG_KEYS: &[(&str, &str, &str)] = &[
+    (
+        "name",
+        "\"team\"",
+        "Workspace name, used for the file name and the root folder entry",
+    ),
+    (
+        "exclude_current",
+        "true",
+        "Leave the current directory out of the workspace",
+    ),
+    (
+        "discover",
+        "false",
+        "Whether a run without a name updates a workspace file in a parent directory",
+    ),
+    (
+        "folders_file",
+        "\"folders.txt\"",
+        "Manifest listing the directories to make folders of instead of scanning",
+    ),
+    (
+        "merge_scan",
+        "true",
+        "Whether to also scan with folders_file",
+    ),
+    (
+        "missing_folders",
+        "\"error\"",
+        "What to do with paths in folders_file that aren't directories: warn or error",
+    ),
+    (
+        "exclude",
+        "[\"build\", \"tmp-*\"]",
+        "Glob patterns of directory names to leave out",
+    ),
+    (
+        "exclude_name",
+        "[\"target\"]",
+        "Exact directory names to leave out",
+    ),
+    (
+        "exclude_name_ci",
+        "true",
+        "Whether to compare exclude_name ignoring case",
+    ),
+    (
+        "exclude_regex",
+        "[\"^archive/\"]",
+        "Regexes of paths relative to the scan root to leave out",
+    ),
+    (
+        "include_regex",
+        "[\"^(api|web)$\"]",
+        "Regexes of paths relative to the scan root to keep",
+    ),
+    (
+        "exclude_in",
+        "[\"other.code-workspace\"]",
+        "Workspace files whose folders are left out",
+    ),
+    (
+        "include_hidden",
+        "true",
+        "Include directories whose name starts with a dot",
+    ),
+    (
+        "markers",
+        "[\"Cargo.toml\", \"package.json\"]",
+        "Only include directories containing one of these files",
+    ),
+    (
+        "contains",
+        "[\"*.tf\"]",
+        "Only include directories containing a file matching one of these globs",
+    ),
+    (
+        "contains_recursive",
+        "3",
+        "How many levels below each directory contains searches",
+    ),
+    (
+        "newer_than",
+        "\"30d\"",
+        "Only include directories modified within this age or since this date",
+    ),
+    (
+        "older_than",
+        "\"2024-01-01\"",
+        "Only include directories not modified within this age or since this date",
+    ),
+    (
+        "max_depth",
+        "2",
+        "How many levels below the scan path to look",
+    ),
+    (
+        "min_depth",
+        "2",
+        "How many levels below the scan path directories start to be included",
+    ),
+    (
+        "max_entries",
+        "5000",
+        "Leave out directories with more entries directly inside them than this",
+    ),
+    (
+        "warn_size",
+        "500",
+        "Megabytes of files above which a folder gets a warning",
+    ),
+    (
+        "min_folders",
+        "1",
+        "Fewest folders a scan must find for the workspace to be written",
+    ),
+    (
+        "allow_empty",
+        "true",
+        "Whether to write the workspace even when the scan finds too few folders",
+    ),
+    (
+        "warn_folders",
+        "100",
+        "Number of folders above which a scan prints a warning",
+    ),
+    (
+        "max_folders",
+        "200",
+        "Most folders a scan may find for the workspace to be written",
+    ),
+    (
+        "sort",
+        "\"name\"",
+        "Order of the scanned folders: none or name",
+    ),
+    (
+        "name_style",
+        "\"relpath\"",
+        "What folder display names are made from: basename or relpath",
+    ),
+    (
+        "folder_prefix",
+        "\"🦀 \"",
+        "Prefix for folder display names, 📦 by default",
+    ),
+    (
+        "no_emoji",
+        "true",
+        "Leave the emoji out of generated display names",
+    ),
+    (
+        "emoji",
+        "\"auto\"",
+        "Whether display names start with an emoji: always, never, or auto",
+    ),
+    (
+        "show_branch",
+        "true",
+        "Whether to append the checked-out branch to the names of git folders",
+    ),
+    (
+        "worktrees",
+        "\"group\"",
+        "How to treat git worktrees: group, annotate or skip-linked",
+    ),
+    (
+        "git_status",
+        "\"annotate\"",
+        "Git folders with uncommitted changes: annotate marks them, only-dirty keeps only them",
+    ),
+    (
+        "collapse_nested",
+        "\"keep-outer\"",
+        "Which of two nested folders to keep: keep-outer or keep-inner",
+    ),
+    (
+        "canonical_dedupe",
+        "true",
+        "Whether to keep one folder of those that are the same directory through symlinks",
+    ),
+    (
+        "fix_overlaps",
+        "true",
+        "Whether to merge folders listed several times and collapse existing nested ones",
+    ),
+    (
+        "hide_nested",
+        "true",
+        "Whether to hide scanned folders nested in the current-directory folder",
+    ),
+    (
+        "absorb_workspaces",
+        "true",
+        "Whether to add the folders of workspace files inside scanned directories",
+    ),
+    (
+        "cargo_members",
+        "true",
+        "Whether to add the members of Cargo workspaces",
+    ),
+    (
+        "node_packages",
+        "true",
+        "Whether to add the packages of node workspaces",
+    ),
+    ("submodules", "true", "Whether to add git submodules"),
+    (
+        "uninitialized_submodules",
+        "\"warn\"",
+        "What to do with submodules that aren't checked out: skip, warn or include",
+    ),
+    (
+        "record_remotes",
+        "true",
+        "Whether to record the origin URL of each git folder",
+    ),
+    ("no_task", "true", "Don't write the Update Workspace task"),
+    (
+        "task_label",
+        "\"Refresh Folders\"",
+        "Label of the managed task",
+    ),
+    (
+        "task_reveal",
+        "\"never\"",
+        "When VS Code reveals the terminal of the managed task: always, silent or never",
+    ),
+    (
+        "task_panel",
+        "\"shared\"",
+        "Which terminal panel the managed task runs in: shared, dedicated or new",
+    ),
+    (
+        "task_group",
+        "\"build\"",
+        "Task group the managed task is listed in: build or test",
+    ),
+    (
+        "task_command",
+        "\"portable\"",
+        "How the managed task runs this tool: absolute or portable",
+    ),
+    (
+        "task_type",
+        "\"shell\"",
+        "Whether the managed task runs directly or through the shell: process or shell",
+    ),
+    (
+        "task_shell_command",
+        "\"git add *.code-workspace\"",
+        "Command a shell task runs after regenerating",
+    ),
+    (
+        "task_run_on_open",
+        "true",
+        "Whether VS Code runs the managed task when the workspace is opened",
+    ),
+    (
+        "folder_tasks",
+        "true",
+        "Whether to generate build and test tasks for each folder",
+    ),
+    (
+        "rust_analyzer_links",
+        "true",
+        "Whether to list Rust folders in rust-analyzer.linkedProjects",
+    ),
+    (
+        "recommend_extensions",
+        "true",
+        "Whether to recommend extensions for the kinds of project found",
+    ),
+    (
+        "exclude_heavy_dirs",
+        "true",
+        "Whether to keep the build directories of the projects found out of search and watching",
+    ),
+    (
+        "launch_template",
+        "\"launch.json\"",
+        "launch.json-style file merged into the launch section",
+    ),
+    (
+        "template",
+        "\"base.code-workspace\"",
+        "Workspace file new workspace files start from",
+    ),
+    (
+        "copy_settings_from",
+        "\"../team.code-workspace\"",
+        "Workspace file whose settings section is merged into the workspace",
+    ),
+    (
+        "copy_extensions_from",
+        "\"../team.code-workspace\"",
+        "Workspace file whose extensions section is merged into the workspace",
+    ),
+    (
+        "settings_merge",
+        "\"theirs\"",
+        "How settings both the workspace and a copied file have combine: ours, theirs, \
+         union-deep or error",
+    ),
+    (
+        "respect_remote_authority",
+        "true",
+        "Whether to write folders as URIs on the workspace's remoteAuthority",
+    ),
+    (
+        "container_root",
+        "\"/workspaces\"",
+        "Directory of the dev container that folders are written under",
+    ),
+    (
+        "dual",
+        "true",
+        "Whether to write a host and a container file from one scan",
+    ),
+    (
+        "relative_to",
+        "\"..\"",
+        "Directory folder paths are written relative to",
+    ),
+    (
+        "emit",
+        "[\"sublime\", \"zed\"]",
+        "Other project files to write with the same folders",
+    ),
+    (
+        "append",
+        "true",
+        "Whether regeneration only adds folders to an existing workspace file",
+    ),
+    (
+        "surgical",
+        "true",
+        "Whether to rewrite only the folders array of an existing workspace file",
+    ),
+    (
+        "validate",
+        "true",
+        "Whether to check workspace files against the workspace schema before writing",
+    ),
+    (
+        "indent",
+        "4",
+        "Spaces per level of indentation of written workspace files",
+    ),
+    (
+        "tabs",
+        "true",
+        "Whether written workspace files are indented with tabs, overriding indent",
+    ),
+    (
+        "final_newline",
+        "true",
+        "Whether written workspace files end with a newline",
+    ),
+    (
+        "cache",
+        "true",
+        "Whether to reuse the previous scan while the scanned directories are unchanged",
+    ),
+    (
+        "extensions",
+        "[extensions]\nnpm = [\"svelte.svelte-vscode\"]",
+        "Extensions to recommend per project type, replacing the built-in list of each type given",
+    ),
+    (
+        "profile",
+        "[profile.work]\nmarkers = [\".git\"]",
+        "Named profiles, selected with --profile, overlaying the values above",
+    ),
+];
+
+/// A commented project configuration setting the keys `value_of` has a value for and
+/// showing every other key of [`CONFIG_KEYS`] commented out, with its example value.
+pub fn scaffold(value_of: impl Fn(&str) -> Option<toml_edit::Value>) -> String {
+    let mut text = String::from(
+        "# Defaults for workspace-manager when scanning this directory. Flags given on the\n\
+         # command line still win. Uncomment a key to set it.\n",
+    );
+    for (key, example, about) in CONFIG_KEYS {
+        text.push_str(&format!("\n# {}\n", about));
+        match value_of(key) {
+            Some(value) => text.push_str(&format!("{} = {}\n", key, value.to_string().trim())),
+            // Tables are shown whole
+            None if example.starts_with('[') && example.contains('\n') => {
+                for line in example.lines() {
+                    text.push_str(&format!("# {}\n", line));
+                }
+            }
+            None => text.push_str(&format!("# {} = {}\n", key, example)),
+        }
+    }
+    text
+}
+
+/// The `WORKSPACE_MANAGER_*` variables set in the process environment, sorted.
+pub fn environment_overrides() -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+        .collect();
+    vars.sort();
+    vars
+}
+
+/// Parses the usual spellings of a boolean environment value.
+pub fn parse_bool(value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" | "" => Ok(false),
+        _ => anyhow::bail!("expected true or false"),
+    }
+}
+
+/// Where a resolved option value came from, lowest precedence first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    Default,
+    GlobalConfig,
+    GlobalProfile,
+    ProjectConfig,
+    ProjectProfile,
+    Environment,
+    CommandLine,
+}
+
+impl Source {
+    /// The source of a profile table inside the configuration file `self`.
+    fn profile(self) -> Source {
+        match self {
+            Source::GlobalConfig => Source::GlobalProfile,
+            Source::ProjectConfig => Source::ProjectProfile,
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::Default => "default",
+            Source::GlobalConfig => "global config",
+            Source::GlobalProfile => "profile in global config",
+            Source::ProjectConfig => "project config",
+            Source::ProjectProfile => "profile in project config",
+            Source::Environment => "environment",
+            Source::CommandLine => "command line",
+        })
+    }
+}
+
+/// The source of each resolved option, in resolution order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    entries: Vec<(&'static str, Source)>,
+}
+
+impl Provenance {
+    pub fn get(&self, field: &str) -> Option<Source> {
+        self.entries
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, source)| *source)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Source)> + '_ {
+        self.entries.iter().copied()
+    }
+}
+
+/// Configuration files in increasing order of precedence.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLayers {
+    layers: Vec<(Source, Config)>,
+}
+
+impl ConfigLayers {
+    /// Stacks the configuration sources in order of precedence: the global
+    /// configuration, the project configuration, the `profile` table of each
+    /// (overlaying the file it is in) and finally the environment.
+    pub fn build(
+        global: Option<Config>,
+        project: Option<Config>,
+        profile: Option<&str>,
+        env: Config,
+    ) -> Result<ConfigLayers> {
+        let mut layers = ConfigLayers::default();
+        if let Some(global) = global {
+            layers.push(Source::GlobalConfig, global);
+        }
+        if let Some(project) = project {
+            layers.push(Source::ProjectConfig, project);
+        }
+        if let Some(profile) = profile {
+            layers.select_profile(profile)?;
+        }
+        layers.push(Source::Environment, env);
+        Ok(layers)
+    }
+
+    /// Adds `config` on top of the layers pushed so far.
+    pub fn push(&mut self, source: Source, config: Config) {
+        self.layers.push((source, config));
+    }
+
+    /// Overlays the `[profile.<name>]` table of each configuration file on top of
+    /// that file. Fails when no file defines the profile.
+    pub fn select_profile(&mut self, name: &str) -> Result<()> {
+        let available: BTreeSet<&str> = self
+            .layers
+            .iter()
+            .flat_map(|(_, config)| config.profile.keys().map(String::as_str))
+            .collect();
+        if !available.contains(name) {
+            let available: Vec<&str> = available.into_iter().collect();
+            anyhow::bail!(
+                "Unknown profile '{}' (available: {})",
+                name,
+                match available.is_empty() {
+                    true => "none".to_string(),
+                    false => available.join(", "),
+                }
+            );
+        }
+
+        let mut layers = vec![];
+        for (source, mut config) in self.layers.drain(..) {
+            let profile = config.profile.remove(name);
+            layers.push((source, config));
+            if let Some(profile) = profile {
+                layers.push((source.profile(), profile));
+            }
+        }
+        self.layers = layers;
+        Ok(())
+    }
+
+    /// Resolves `field`: the command-line value if given, otherwise the value of
+    /// the highest layer that sets it. Records where the value came from.
+    pub fn resolve<T>(
+        &self,
+        provenance: &mut Provenance,
+        field: &'static str,
+        cli: Option<T>,
+        get: impl Fn(&Config) -> Option<T>,
+    ) -> Option<T> {
+        let (value, source) = match cli {
+            Some(value) => (Some(value), Source::CommandLine),
+            None => self
+                .layers
+                .iter()
+                .rev()
+                .find_map(|(source, config)| get(config).map(|value| (Some(value), *source)))
+                .unwrap_or((None, Source::Default)),
+        };
+        provenance.entries.push((field, source));
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() -> Result<()> {
+        let config = Config::parse(
+            r#"
+            name = "team"
+            exclude = ["build", "tmp-*"]
+            include_hidden = true
+            folder_prefix = ""
+            sort = "name"
+            markers = ["Cargo.toml"]
+            colour = "blue"
+            "#,
+        )?;
+        assert_eq!(config.name.as_deref(), Some("team"));
+        assert_eq!(config.exclude, Some(vec!["build".into(), "tmp-*".into()]));
+        assert_eq!(config.include_hidden, Some(true));
+        assert_eq!(config.folder_prefix.as_deref(), Some(""));
+        assert_eq!(config.sort, Some(SortOrder::Name));
+        assert_eq!(config.markers, Some(vec!["Cargo.toml".into()]));
+        assert_eq!(config.unknown_keys(), vec!["colour"]);
+        assert_eq!(
+            Config::parse("emoji = \"auto\"")?.emoji,
+            Some(EmojiChoice::Auto)
+        );
+        assert!(Config::parse("emoji = \"sometimes\"").is_err());
+        assert_eq!(
+            Config::parse("older_than = \"2024-01-01\"")?
+                .older_than
+                .map(|spec| spec.to_string()),
+            Some("2024-01-01".to_string())
+        );
+        assert!(Config::parse("newer_than = \"soon\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_layers_resolve_by_precedence() -> Result<()> {
+        let mut layers = ConfigLayers::default();
+        layers.push(
+            Source::GlobalConfig,
+            Config::parse("sort = \"name\"\nfolder_prefix = \"> \"\nno_emoji = true")?,
+        );
+        layers.push(
+            Source::ProjectConfig,
+            Config::parse("folder_prefix = \"# \"")?,
+        );
+        let mut provenance = Provenance::default();
+
+        let sort = layers.resolve(&mut provenance, "sort", None, |c| c.sort);
+        let prefix = layers.resolve(&mut provenance, "folder_prefix", None, |c| {
+            c.folder_prefix.clone()
+        });
+        let emoji = layers.resolve(&mut provenance, "no_emoji", Some(false), |c| c.no_emoji);
+        let name = layers.resolve(&mut provenance, "name", None, |c| c.name.clone());
+
+        assert_eq!(sort, Some(SortOrder::Name));
+        assert_eq!(prefix.as_deref(), Some("# "));
+        assert_eq!(emoji, Some(false));
+        assert_eq!(name, None);
+        assert_eq!(provenance.get("sort"), Some(Source::GlobalConfig));
+        assert_eq!(provenance.get("folder_prefix"), Some(Source::ProjectConfig));
+        assert_eq!(provenance.get("no_emoji"), Some(Source::CommandLine));
+        assert_eq!(provenance.get("name"), Some(Source::Default));
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_overlays_base_config() -> Result<()> {
+        let mut layers = ConfigLayers::default();
+        layers.push(
+            Source::GlobalConfig,
+            Config::parse(
+                r#"
+                exclude = ["build"]
+                sort = "name"
+
+                [profile.oss]
+                folder_prefix = ""
+                "#,
+            )?,
+        );
+        layers.push(
+            Source::ProjectConfig,
+            Config::parse(
+                r#"
+                markers = ["Cargo.toml"]
+
+                [profile.work]
+                exclude = []
+                markers = [".git"]
+                "#,
+            )?,
+        );
+
+        let err = layers.clone().select_profile("home").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown profile 'home' (available: oss, work)"
+        );
+
+        layers.select_profile("work")?;
+        let mut provenance = Provenance::default();
+        // The profile clears the exclude list set by the global base config
+        let exclude = layers.resolve(&mut provenance, "exclude", None, |c| c.exclude.clone());
+        let markers = layers.resolve(&mut provenance, "markers", None, |c| c.markers.clone());
+        let sort = layers.resolve(&mut provenance, "sort", None, |c| c.sort);
+        let prefix = layers.resolve(&mut provenance, "folder_prefix", None, |c| {
+            c.folder_prefix.clone()
+        });
+
+        assert_eq!(exclude, Some(vec![]));
+        assert_eq!(markers, Some(vec![".git".to_string()]));
+        assert_eq!(sort, Some(SortOrder::Name));
+        assert_eq!(prefix, None);
+        assert_eq!(provenance.get("exclude"), Some(Source::ProjectProfile));
+        assert_eq!(provenance.get("sort"), Some(Source::GlobalConfig));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_from_env() -> Result<()> {
+        let vars: BTreeMap<&str, &str> = [
+            ("WORKSPACE_MANAGER_NAME", "ci"),
+            ("WORKSPACE_MANAGER_EXCLUDE", "build:tmp-*::"),
+            ("WORKSPACE_MANAGER_INCLUDE_HIDDEN", "Yes"),
+            ("WORKSPACE_MANAGER_NO_EMOJI", "0"),
+            ("WORKSPACE_MANAGER_SORT", "name"),
+            ("WORKSPACE_MANAGER_EMOJI", "never"),
+            ("WORKSPACE_MANAGER_NEWER_THAN", "2w"),
+            ("OTHER", "ignored"),
+        ]
+        .into_iter()
+        .collect();
+        let config = Config::from_env(|key| vars.get(key).map(|value| value.to_string()))?;
+        assert_eq!(config.name.as_deref(), Some("ci"));
+        assert_eq!(config.exclude, Some(vec!["build".into(), "tmp-*".into()]));
+        assert_eq!(config.include_hidden, Some(true));
+        assert_eq!(config.no_emoji, Some(false));
+        assert_eq!(config.sort, Some(SortOrder::Name));
+        assert_eq!(config.emoji, Some(EmojiChoice::Never));
+        assert_eq!(
+            config.newer_than,
+            Some(TimeSpec::Age {
+                amount: 2,
+                unit: 'w'
+            })
+        );
+        assert_eq!(config.markers, None);
+
+        let err = Config::from_env(|key| {
+            (key == "WORKSPACE_MANAGER_NO_EMOJI").then(|| "maybe".to_string())
+        })
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("WORKSPACE_MANAGER_NO_EMOJI"),
+            "{}",
+            err
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_environment_sits_between_config_and_flags() -> Result<()> {
+        let mut layers = ConfigLayers::default();
+        layers.push(
+            Source::ProjectConfig,
+            Config::parse("name = \"file\"\nsort = \"name\"")?,
+        );
+        layers.push(
+            Source::Environment,
+            Config::from_env(|key| (key == "WORKSPACE_MANAGER_NAME").then(|| "env".to_string()))?,
+        );
+        let mut provenance = Provenance::default();
+        let name = layers.resolve(&mut provenance, "name", None, |c| c.name.clone());
+        let sort = layers.resolve(&mut provenance, "sort", None, |c| c.sort);
+        let flag = layers.resolve(&mut provenance, "name", Some("cli".to_string()), |c| {
+            c.name.clone()
+        });
+        assert_eq!(name.as_deref(), Some("env"));
+        assert_eq!(sort, Some(SortOrder::Name));
+        assert_eq!(flag.as_deref(), Some("cli"));
+        assert_eq!(provenance.get("name"), Some(Source::Environment));
+        Ok(())
+    }
+
+    #[test]
+    fn test_remember_keeps_the_rest_of_the_file() -> Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        let path = temp.path().join(PROJECT_CONFIG_FILE);
+        fs::write(
+            &path,
+            "# team defaults\nsort = \"name\"\n\n[profile.work]\nexclude = []\n",
+        )?;
+
+        assert!(remember(&path, "no_task", true)?);
+        assert!(!remember(&path, "no_task", true)?);
+        let text = fs::read_to_string(&path)?;
+        assert!(text.starts_with("# team defaults\n"));
+        let config = Config::parse(&text)?;
+        assert_eq!(config.no_task, Some(true));
+        assert!(config.profile["work"].no_task.is_none());
+
+        let fresh = temp.path().join("new.toml");
+        assert!(remember(&fresh, "no_task", true)?);
+        assert_eq!(fs::read_to_string(&fresh)?, "no_task = true\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config_reports_the_line() {
+        let err = Config::parse("name = \"ok\"\nexclude = 3\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_config_keys() -> Result<()> {
+        // The examples, uncommented, set every field and nothing unknown
+        let text: Vec<String> = CONFIG_KEYS
+            .iter()
+            .map(
+                |(key, example, _)| match example.starts_with(&format!("[{}", key)) {
+                    true => example.to_string(),
+                    false => format!("{} = {}", key, example),
+                },
+            )
+            .collect();
+        let config = Config::parse(&text.join("\n"))?;
+        assert_eq!(config.unknown_keys(), Vec::<String>::new());
+        assert_eq!(
+            config.profile["work"].markers,
+            Some(vec![".git".to_string()])
+        );
+        let fields = format!(
+            "{:?}",
+            Config {
+                profile: BTreeMap::new(),
+                ..config
+            }
+        );
+        assert!(!fields.contains("None"), "{}", fields);
+
+        let scaffold =
+            scaffold(|key| (key == "markers").then(|| ["Cargo.toml"].into_iter().collect()));
+        let about = "# Only include directories containing one of these files";
+        assert!(scaffold.contains(&format!("\n{}\nmarkers = [\"Cargo.toml\"]\n", about)));
+        assert!(scaffold.contains("\n# exclude = [\"build\", \"tmp-*\"]\n"));
+        assert!(scaffold.contains("\n# [profile.work]\n# markers = [\".git\"]\n"));
+        assert_eq!(
+            Config::parse(&scaffold)?.markers,
+            Some(vec!["Cargo.toml".to_string()])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+//! How messages on the terminal are decorated: whether they are colored, following
+//! `--color` and `NO_COLOR`, and whether folder names keep their emoji, which some
+//! terminals show as boxes, and whether trees are drawn with box-drawing characters.
+//! The workspace file is affected only by `emoji`, which decides whether generated
+//! names start with one.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+/// When to color output, as `--color` asks.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color output to terminals, unless `NO_COLOR` is set or `TERM` is `dumb`
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether generated folder names start with an emoji, as the `emoji` setting asks.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiChoice {
+    /// Emoji unless the console uses a legacy Windows code page
+    Auto,
+    #[default]
+    Always,
+    Never,
+}
+
+impl EmojiChoice {
+    /// Whether generated names get an emoji, on a console using `code_page`, the
+    /// Windows console output code page, or `None` elsewhere.
+    pub fn in_file(self, code_page: Option<u32>) -> bool {
+        match self {
+            EmojiChoice::Always => true,
+            EmojiChoice::Never => false,
+            EmojiChoice::Auto => code_page.is_none_or(|page| page == UTF8_CODE_PAGE),
+        }
+    }
+}
+
+/// The Windows code page of UTF-8.
+pub const UTF8_CODE_PAGE: u32 = 65001;
+
+/// The colors messages use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Green => "32",
+            Color::Yellow => "33",
+        }
+    }
+}
+
+/// The decoration of one output stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    /// Whether to color text
+    pub color: bool,
+    /// Whether folder names are printed with their emoji
+    pub emoji: bool,
+    /// Whether trees are drawn with box-drawing characters rather than ASCII
+    pub box_drawing: bool,
+}
+
+impl Style {
+    /// The decoration of a stream that is a terminal when `is_tty`, in an environment
+    /// whose variables `env` looks up, on a Windows console using `code_page` or
+    /// elsewhere when `None`.
+    ///
+    /// Colors follow `choice`; on `auto`, only terminals are colored, and not when
+    /// `NO_COLOR` is set to anything but an empty string or `TERM` is `dumb`. Emoji
+    /// follow [`emoji_supported`] and box drawing [`box_drawing_supported`].
+    pub fn detect(
+        choice: ColorChoice,
+        is_tty: bool,
+        env: impl Fn(&str) -> Option<String>,
+        code_page: Option<u32>,
+    ) -> Style {
+        let set = |key: &str| env(key).is_some_and(|value| !value.is_empty());
+        let color = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                is_tty && !set("NO_COLOR") && env("TERM").as_deref() != Some("dumb")
+            }
+        };
+        let emoji = emoji_supported(is_tty, &env, code_page);
+        let box_drawing = box_drawing_supported(is_tty, &env, code_page);
+        Style {
+            color,
+            emoji,
+            box_drawing,
+        }
+    }
+
+    /// `text` in `color`, when coloring.
+    pub fn paint<'a>(&self, text: &'a str, color: Color) -> Cow<'a, str> {
+        match self.color {
+            true => Cow::Owned(format!("\x1b[{}m{}\x1b[0m", color.code(), text)),
+            false => Cow::Borrowed(text),
+        }
+    }
+
+    /// A line of a diff or change list, colored by its leading `+`, `-` or `~`.
+    pub fn diff_line<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        match line.trim_start().chars().next() {
+            Some('+') => self.paint(line, Color::Green),
+            Some('-') => self.paint(line, Color::Red),
+            Some('~') => self.paint(line, Color::Yellow),
+            _ => Cow::Borrowed(line),
+        }
+    }
+
+    /// `text`, a folder display name or a message with some, as printed: without its
+    /// emoji, and the space after each, when they can't be shown.
+    pub fn name<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.emoji || !text.chars().any(is_emoji) {
+            return Cow::Borrowed(text);
+        }
+        let mut plain = String::with_capacity(text.len());
+        let mut after_emoji = false;
+        for c in text.chars() {
+            if is_emoji(c) {
+                after_emoji = true;
+                continue;
+            }
+            if !(after_emoji && c == ' ') {
+                plain.push(c);
+            }
+            after_emoji = false;
+        }
+        Cow::Owned(plain)
+    }
+}
+
+/// Whether a stream that is a terminal when `is_tty`, in an environment whose
+/// variables `env` looks up, shows emoji, on a Windows console using `code_page` or
+/// elsewhere when `None`.
+///
+/// Output that is piped or redirected keeps them. The Linux console shows boxes, as
+/// do Windows consoles on a legacy code page, unless they are Windows Terminal or the
+/// terminal of an editor, which render them either way.
+pub fn emoji_supported(
+    is_tty: bool,
+    env: impl Fn(&str) -> Option<String>,
+    code_page: Option<u32>,
+) -> bool {
+    let set = |key: &str| env(key).is_some_and(|value| !value.is_empty());
+    let modern_windows = set("WT_SESSION") || set("TERM_PROGRAM");
+    match code_page {
+        _ if !is_tty => true,
+        _ if env("TERM").as_deref() == Some("linux") => false,
+        Some(page) => page == UTF8_CODE_PAGE || modern_windows,
+        None => true,
+    }
+}
+
+/// Whether a stream that is a terminal when `is_tty`, in an environment whose
+/// variables `env` looks up, shows box-drawing characters, on a Windows console using
+/// `code_page` or elsewhere when `None`.
+///
+/// Output that is piped or redirected gets them. Elsewhere they need a UTF-8 locale,
+/// as named by the first of `LC_ALL`, `LC_CTYPE` and `LANG` that is set, and on
+/// Windows the UTF-8 code page or a terminal that renders them either way.
+pub fn box_drawing_supported(
+    is_tty: bool,
+    env: impl Fn(&str) -> Option<String>,
+    code_page: Option<u32>,
+) -> bool {
+    let set = |key: &str| env(key).filter(|value| !value.is_empty());
+    let modern_windows = set("WT_SESSION").is_some() || set("TERM_PROGRAM").is_some();
+    match code_page {
+        _ if !is_tty => true,
+        _ if env("TERM").as_deref() == Some("dumb") => false,
+        Some(page) => page == UTF8_CODE_PAGE || modern_windows,
+        None => match ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().find_map(set) {
+            Some(locale) => {
+                let locale = locale.to_ascii_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            }
+            None => true,
+        },
+    }
+}
+
+/// The output code page of the Windows console the process is attached to, or `None`
+/// elsewhere and without a console.
+pub fn console_code_page() -> Option<u32> {
+    #[cfg(windows)]
+    {
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetConsoleOutputCP() -> u32;
+        }
+        // SAFETY: takes no arguments and only reads the console's state
+        let page = unsafe { GetConsoleOutputCP() };
+        (page != 0).then_some(page)
+    }
+    #[cfg(not(windows))]
+    None
+}
+
+/// Whether `c` is part of an emoji: pictographs, symbols, and the joiners and
+/// variation selectors combining them.
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0xFE0F | 0x200D
+    )
+}
+
+static CHOICE: OnceLock<ColorChoice> = OnceLock::new();
+
+/// Sets the color choice of the process, from `--color`. Only the first call counts;
+/// without one, `auto` applies.
+pub fn init(choice: ColorChoice) {
+    let _ = CHOICE.set(choice);
+}
+
+fn detect(is_tty: bool) -> Style {
+    let choice = CHOICE.get().copied().unwrap_or_default();
+    Style::detect(
+        choice,
+        is_tty,
+        |key| std::env::var(key).ok(),
+        console_code_page(),
+    )
+}
+
+/// The decoration of standard output.
+pub fn stdout() -> Style {
+    detect(std::io::stdout().is_terminal())
+}
+
+/// The decoration of standard error.
+pub fn stderr() -> Style {
+    detect(std::io::stderr().is_terminal())
+}
+
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Sends the messages of [`status`] to standard error from now on, keeping standard
+/// output for what scripts read, such as the path printed by `--print-path`.
+pub fn status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// The decoration of the stream [`status`] prints to.
+pub fn status_style() -> Style {
+    match STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        true => stderr(),
+        false => stdout(),
+    }
+}
+
+/// Prints `message`, a note on what a run did, to standard output, or to standard
+/// error after [`status_to_stderr`].
+pub fn status(message: impl fmt::Display) {
+    match STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        true => eprintln!("{}", message),
+        false => println!("{}", message),
+    }
+}
+
+/// Prints `message`, a detail `--verbose` asks for, to standard error.
+pub fn diagnostic(message: impl fmt::Display) {
+    eprintln!("{}", message);
+}
+
+/// Prints `message` to standard error as a warning.
+pub fn warning(message: impl fmt::Display) {
+    eprintln!("{} {}", stderr().paint("Warning:", Color::Yellow), message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_color_detection() {
+        let color = |choice, is_tty, vars: &[(&str, &str)]| {
+            Style::detect(choice, is_tty, env(vars), None).color
+        };
+        assert!(color(ColorChoice::Auto, true, &[]));
+        assert!(!color(ColorChoice::Auto, false, &[]));
+        assert!(!color(ColorChoice::Auto, true, &[("NO_COLOR", "1")]));
+        // An empty NO_COLOR doesn't count
+        assert!(color(ColorChoice::Auto, true, &[("NO_COLOR", "")]));
+        assert!(!color(ColorChoice::Auto, true, &[("TERM", "dumb")]));
+        assert!(color(ColorChoice::Always, false, &[("NO_COLOR", "1")]));
+        assert!(!color(ColorChoice::Never, true, &[]));
+    }
+
+    #[test]
+    fn test_emoji_detection() {
+        let emoji = |is_tty, vars: &[(&str, &str)], code_page| {
+            Style::detect(ColorChoice::Auto, is_tty, env(vars), code_page).emoji
+        };
+        assert!(emoji(true, &[("TERM", "xterm-256color")], None));
+        assert!(!emoji(true, &[("TERM", "linux")], None));
+        // A Windows console on a legacy code page, such as 437 or 1252
+        assert!(!emoji(true, &[], Some(437)));
+        assert!(!emoji(true, &[], Some(1252)));
+        assert!(emoji(true, &[], Some(UTF8_CODE_PAGE)));
+        assert!(emoji(true, &[("WT_SESSION", "5f2a")], Some(437)));
+        assert!(emoji(true, &[("TERM_PROGRAM", "vscode")], Some(1252)));
+        // Output that is piped or redirected keeps the names as they are
+        assert!(emoji(false, &[], Some(437)));
+    }
+
+    #[test]
+    fn test_box_drawing_detection() {
+        let box_drawing = |is_tty, vars: &[(&str, &str)], code_page| {
+            Style::detect(ColorChoice::Auto, is_tty, env(vars), code_page).box_drawing
+        };
+        assert!(box_drawing(true, &[], None));
+        assert!(box_drawing(true, &[("LANG", "en_GB.UTF-8")], None));
+        assert!(box_drawing(true, &[("LANG", "C.utf8")], None));
+        assert!(!box_drawing(true, &[("LANG", "C")], None));
+        assert!(!box_drawing(true, &[("LANG", "en_US.ISO-8859-1")], None));
+        // LC_ALL overrides LANG, and an empty one doesn't count
+        assert!(!box_drawing(
+            true,
+            &[("LC_ALL", "POSIX"), ("LANG", "en_US.UTF-8")],
+            None
+        ));
+        assert!(box_drawing(
+            true,
+            &[("LC_ALL", ""), ("LANG", "en_US.UTF-8")],
+            None
+        ));
+        assert!(!box_drawing(true, &[("TERM", "dumb")], None));
+        assert!(!box_drawing(true, &[], Some(437)));
+        assert!(box_drawing(true, &[("WT_SESSION", "5f2a")], Some(437)));
+        assert!(box_drawing(true, &[], Some(UTF8_CODE_PAGE)));
+        assert!(box_drawing(false, &[("LANG", "C")], Some(437)));
+    }
+
+    #[test]
+    fn test_emoji_in_file() {
+        assert!(EmojiChoice::Always.in_file(Some(437)));
+        assert!(!EmojiChoice::Never.in_file(None));
+        assert!(EmojiChoice::Auto.in_file(None));
+        assert!(EmojiChoice::Auto.in_file(Some(UTF8_CODE_PAGE)));
+        assert!(!EmojiChoice::Auto.in_file(Some(1252)));
+    }
+
+    #[test]
+    fn test_style_rendering() {
+        let plain = Style {
+            color: false,
+            emoji: false,
+            box_drawing: false,
+        };
+        assert_eq!(plain.name("📦 api"), "api");
+        assert_eq!(plain.name("🏗️ team"), "team");
+        assert_eq!(plain.name("👨‍💻 dev tools"), "dev tools");
+        assert_eq!(plain.name("数据 service"), "数据 service");
+        assert_eq!(
+            plain.name("~ folder api renamed '📦 api' -> '🦀 api'"),
+            "~ folder api renamed 'api' -> 'api'"
+        );
+        assert_eq!(plain.paint("x", Color::Red), "x");
+        assert_eq!(
+            plain.diff_line("+ folder 'api' (api)"),
+            "+ folder 'api' (api)"
+        );
+
+        let rich = Style {
+            color: true,
+            emoji: true,
+            box_drawing: true,
+        };
+        assert_eq!(rich.name("📦 api"), "📦 api");
+        assert_eq!(
+            rich.diff_line("  - api (api)"),
+            "\x1b[31m  - api (api)\x1b[0m"
+        );
+        assert_eq!(
+            rich.diff_line("~ setting 'a': 1 -> 2"),
+            "\x1b[33m~ setting 'a': 1 -> 2\x1b[0m"
+        );
+        assert_eq!(rich.diff_line("No differences"), "No differences");
+    }
+}
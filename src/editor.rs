@@ -0,0 +1,135 @@
+//! Finding the VS Code command line launcher and opening workspaces with it.
+
+use crate::validate::find_executable;
+use anyhow::{Context, Result};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Editor launchers probed on `PATH`, in default priority order.
+pub const EDITOR_CANDIDATES: &[&str] = &["code", "code-insiders", "codium", "code-oss"];
+
+/// Environment variable naming the editor command, overriding discovery.
+pub const EDITOR_ENV: &str = "WORKSPACE_MANAGER_EDITOR";
+
+/// Resolves the editor command using `lookup` to search for executables.
+///
+/// An explicit command (`--editor-cmd`) wins, then `env_editor`, then the first of
+/// `priority` that `lookup` finds. Explicit commands must exist too, so a typo is
+/// reported instead of silently falling back to another editor.
+pub fn discover_editor_with(
+    explicit: Option<&str>,
+    env_editor: Option<&str>,
+    priority: &[&str],
+    lookup: impl Fn(&str) -> Option<PathBuf>,
+) -> Result<PathBuf> {
+    if let Some(command) = explicit.or(env_editor).filter(|c| !c.is_empty()) {
+        return lookup(command)
+            .with_context(|| format!("Editor command '{}' was not found on PATH", command));
+    }
+
+    priority
+        .iter()
+        .find_map(|candidate| lookup(candidate))
+        .with_context(|| {
+            format!(
+                "No editor found on PATH (looked for {}); install the VS Code shell command, pass --editor-cmd or set {}",
+                priority.join(", "),
+                EDITOR_ENV
+            )
+        })
+}
+
+/// Resolves the editor command from `explicit`, the environment and `PATH`.
+/// An empty `priority` means [`EDITOR_CANDIDATES`].
+pub fn discover_editor(explicit: Option<&str>, priority: &[String]) -> Result<PathBuf> {
+    let priority: Vec<&str> = if priority.is_empty() {
+        EDITOR_CANDIDATES.to_vec()
+    } else {
+        priority.iter().map(String::as_str).collect()
+    };
+    discover_editor_with(
+        explicit,
+        env::var(EDITOR_ENV).ok().as_deref(),
+        &priority,
+        find_executable,
+    )
+}
+
+/// Starts the editor on `workspace_file` without waiting for it to exit.
+pub fn open_workspace(editor: &Path, workspace_file: &Path) -> Result<()> {
+    Command::new(editor)
+        .arg(workspace_file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {}", editor.display()))?;
+    Ok(())
+}
+
+/// Whether `editor` is Zed, which opens folders rather than workspace files.
+pub fn is_zed(editor: &Path) -> bool {
+    editor.file_stem().is_some_and(|stem| stem == "zed")
+}
+
+/// Starts the editor on every one of `folders` without waiting for it to exit.
+pub fn open_folders(editor: &Path, folders: &[PathBuf]) -> Result<()> {
+    Command::new(editor)
+        .args(folders)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {}", editor.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup_from(installed: &'static [&'static str]) -> impl Fn(&str) -> Option<PathBuf> {
+        move |command| {
+            installed
+                .contains(&command)
+                .then(|| PathBuf::from("/usr/bin").join(command))
+        }
+    }
+
+    #[test]
+    fn test_discover_editor_priority() -> Result<()> {
+        let lookup = lookup_from(&["codium", "code-insiders"]);
+        let editor = discover_editor_with(None, None, EDITOR_CANDIDATES, &lookup)?;
+        assert_eq!(editor, PathBuf::from("/usr/bin/code-insiders"));
+
+        let editor = discover_editor_with(None, None, &["codium", "code-insiders"], &lookup)?;
+        assert_eq!(editor, PathBuf::from("/usr/bin/codium"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_editor_overrides() -> Result<()> {
+        let lookup = lookup_from(&["code", "codium", "code-oss"]);
+        let editor = discover_editor_with(None, Some("codium"), EDITOR_CANDIDATES, &lookup)?;
+        assert_eq!(editor, PathBuf::from("/usr/bin/codium"));
+
+        let editor =
+            discover_editor_with(Some("code-oss"), Some("codium"), EDITOR_CANDIDATES, &lookup)?;
+        assert_eq!(editor, PathBuf::from("/usr/bin/code-oss"));
+
+        let editor = discover_editor_with(None, Some(""), EDITOR_CANDIDATES, &lookup)?;
+        assert_eq!(editor, PathBuf::from("/usr/bin/code"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_editor_errors() {
+        let lookup = lookup_from(&["code"]);
+        let err = discover_editor_with(Some("subl"), None, EDITOR_CANDIDATES, &lookup).unwrap_err();
+        assert!(err.to_string().contains("'subl'"));
+
+        let err = discover_editor_with(None, None, &["codium"], lookup_from(&[])).unwrap_err();
+        assert!(err.to_string().contains("looked for codium"));
+    }
+}
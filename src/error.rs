@@ -0,0 +1,323 @@
+//! [`WorkspaceError`], the error of the functions that generate, read and edit
+//! workspaces, so callers can tell a missing scan path from a malformed file or a
+//! failed write.
+
+use std::path::{Path, PathBuf};
+
+/// `Result` with [`WorkspaceError`] as the default error.
+pub type Result<T, E = WorkspaceError> = std::result::Result<T, E>;
+
+/// Why generating, reading or editing a workspace failed. The messages are written to
+/// be shown to users as they are and name the offending path where there is one.
+#[derive(Debug, thiserror::Error)]
+pub enum WorkspaceError {
+    /// The directory to scan doesn't exist or can't be resolved
+    #[error("Scan path '{}' not found", .0.display())]
+    ScanPathNotFound(PathBuf),
+    /// Reading a file or listing a directory failed
+    #[error("Failed to read {}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// Writing a file failed; the previous content, if any, is left in place
+    #[error("Failed to write {}", path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A workspace file isn't JSON with comments in the shape of a workspace
+    #[error("Failed to parse workspace file {}", path.display())]
+    ParseWorkspace {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// Workspace content that isn't JSON with comments in the shape of a workspace
+    #[error("Invalid workspace content")]
+    InvalidWorkspace(#[source] serde_json::Error),
+    /// A workspace name that can't be used as a file name
+    #[error("Invalid workspace name '{0}': it must be a file name without a directory")]
+    InvalidName(String),
+    /// An `--exclude` pattern that isn't a valid glob
+    #[error("Invalid exclude pattern '{pattern}'")]
+    InvalidPattern {
+        pattern: String,
+        source: glob::PatternError,
+    },
+    /// An `--exclude-regex` or `--include-regex` pattern that isn't a valid regex
+    #[error("Invalid regex '{pattern}'")]
+    InvalidRegex {
+        pattern: String,
+        source: regex::Error,
+    },
+    /// A `--min-depth` deeper than the `--max-depth` the scan stops at
+    #[error("--min-depth {min} is greater than --max-depth {max}, so nothing would be found")]
+    InvalidDepth { min: usize, max: usize },
+    /// There is no workspace file in the directory
+    #[error("No .code-workspace file found in {}", .0.display())]
+    NoWorkspaceFile(PathBuf),
+    /// There are several workspace files in the directory and none is named after it
+    #[error(
+        "Several workspace files found, choose one with --workspace-file:\n  {}",
+        display_paths(.0)
+    )]
+    AmbiguousWorkspaceFile(Vec<PathBuf>),
+    /// A parent directory has several workspace files that could be the one to update
+    #[error(
+        "Several workspace files found in a parent directory; run there, give a --name \
+         or pass --no-discover to write a new one here:\n  {}",
+        display_paths(.0)
+    )]
+    AmbiguousDiscovery(Vec<PathBuf>),
+    /// The scan found fewer folders than required
+    #[error("{message}")]
+    TooFewFolders { found: usize, message: String },
+    /// The scan found more folders than allowed
+    #[error(
+        "Found {found} folders in '{}', more than --max-folders {max}; nothing was written\n\
+         Narrow the scan with --exclude or --marker, or use --force to write it anyway",
+        scan_path.display()
+    )]
+    TooManyFolders {
+        scan_path: PathBuf,
+        found: usize,
+        max: usize,
+    },
+    /// A folder with this path is already in the workspace
+    #[error("Folder '{0}' is already in the workspace (use --force to add it anyway)")]
+    FolderExists(String),
+    /// No folder matches the name or paths given
+    #[error("No matching folder found")]
+    NoMatchingFolder,
+    /// Several folders match where one was expected; `candidates` describes each
+    #[error(
+        "'{selector}' matches several folders, use --path or the exact name:\n  {}",
+        candidates.join("\n  ")
+    )]
+    AmbiguousFolder {
+        selector: String,
+        candidates: Vec<String>,
+    },
+    /// Renaming would give two folders the same display name
+    #[error("Another folder ({path}) is already named '{name}'")]
+    NameTaken { path: String, name: String },
+    /// The content about to be written doesn't match the workspace file schema; the
+    /// file is left as it was
+    #[error(
+        "Refusing to write {}, which doesn't match the workspace file schema:\n  {}",
+        path.display(),
+        display_violations(.violations)
+    )]
+    SchemaViolation {
+        path: PathBuf,
+        violations: Vec<crate::schema::SchemaViolation>,
+    },
+    /// Any other failure, such as an invalid configuration or setting
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl WorkspaceError {
+    /// Wraps a read error on `path`, for `map_err`.
+    pub(crate) fn io(path: &Path) -> impl FnOnce(std::io::Error) -> WorkspaceError + '_ {
+        move |source| WorkspaceError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Wraps a write error on `path`, for `map_err`.
+    pub(crate) fn write(path: &Path) -> impl FnOnce(std::io::Error) -> WorkspaceError + '_ {
+        move |source| WorkspaceError::Write {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+/// The exit status of the command line, so scripts can tell outcomes apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitCode {
+    /// The command did what was asked, including when there was nothing to do
+    Success = 0,
+    /// Any failure without a code of its own
+    Failure = 1,
+    /// The arguments were invalid, as clap also reports for unknown flags
+    Usage = 2,
+    /// A check found problems: `diff` found differences or `doctor` found errors
+    CheckFailed = 3,
+    /// The scan found fewer folders than `--min-folders` or more than `--max-folders`
+    FolderCount = 4,
+    /// A workspace file isn't JSON with comments in the shape of a workspace
+    MalformedWorkspace = 5,
+}
+
+impl ExitCode {
+    /// The code for `err`, from the first [`WorkspaceError`] in its chain.
+    pub fn from_error(err: &anyhow::Error) -> ExitCode {
+        err.chain()
+            .find_map(|cause| cause.downcast_ref::<WorkspaceError>())
+            .map_or(ExitCode::Failure, WorkspaceError::exit_code)
+    }
+}
+
+impl From<ExitCode> for std::process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        std::process::ExitCode::from(code as u8)
+    }
+}
+
+impl WorkspaceError {
+    /// The exit status of the command line when it fails with this error.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            WorkspaceError::InvalidName(_)
+            | WorkspaceError::InvalidPattern { .. }
+            | WorkspaceError::InvalidRegex { .. }
+            | WorkspaceError::InvalidDepth { .. } => ExitCode::Usage,
+            WorkspaceError::TooFewFolders { .. } | WorkspaceError::TooManyFolders { .. } => {
+                ExitCode::FolderCount
+            }
+            WorkspaceError::ParseWorkspace { .. } | WorkspaceError::InvalidWorkspace(_) => {
+                ExitCode::MalformedWorkspace
+            }
+            _ => ExitCode::Failure,
+        }
+    }
+}
+
+/// Recovers a [`WorkspaceError`] that passed through code using `anyhow`, so it keeps
+/// its variant; anything else becomes [`WorkspaceError::Other`].
+impl From<anyhow::Error> for WorkspaceError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<WorkspaceError>() {
+            Ok(err) => err,
+            Err(err) => WorkspaceError::Other(err),
+        }
+    }
+}
+
+fn display_violations(violations: &[crate::schema::SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n  ")
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join("\n  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        locate_workspace_file, read_workspace, rename_folder, write_workspace, FolderSelector,
+        WorkspaceBuilder, WorkspaceFile, WorkspaceFolder, WriteOptions,
+    };
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_error_variants() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path();
+
+        let missing = root.join("missing");
+        let err = WorkspaceBuilder::new(&missing).build().unwrap_err();
+        assert!(matches!(&err, WorkspaceError::ScanPathNotFound(path) if *path == missing));
+        assert!(err.to_string().contains("missing"), "{}", err);
+
+        let err = WorkspaceBuilder::new(root).name("a/b").build().unwrap_err();
+        assert!(matches!(err, WorkspaceError::InvalidName(name) if name == "a/b"));
+
+        let file = root.join("broken.code-workspace");
+        fs::write(&file, "{\"folders\": 3}")?;
+        let err = read_workspace(&file).unwrap_err();
+        assert!(matches!(&err, WorkspaceError::ParseWorkspace { path, .. } if *path == file));
+        assert!(err.to_string().contains("broken.code-workspace"));
+        assert!(matches!(
+            read_workspace(&missing).unwrap_err(),
+            WorkspaceError::Io { .. }
+        ));
+
+        let unwritable = missing.join("team.code-workspace");
+        let err = write_workspace(
+            &unwritable,
+            &WorkspaceFile::default(),
+            &WriteOptions::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(&err, WorkspaceError::Write { path, .. } if *path == unwritable));
+
+        let err = locate_workspace_file(&missing, None).unwrap_err();
+        assert!(matches!(err, WorkspaceError::Io { .. }));
+        fs::remove_file(&file)?;
+        let err = locate_workspace_file(root, None).unwrap_err();
+        assert!(matches!(err, WorkspaceError::NoWorkspaceFile(_)));
+
+        let mut workspace = WorkspaceFile::default();
+        workspace
+            .folders
+            .push(WorkspaceFolder::new("api", "📦 api"));
+        workspace
+            .folders
+            .push(WorkspaceFolder::new("web", "📦 web"));
+        let selector = FolderSelector::Name("docs".to_string());
+        let err = rename_folder(&mut workspace, &selector, "x", false).unwrap_err();
+        assert!(matches!(err, WorkspaceError::NoMatchingFolder));
+        let selector = FolderSelector::Name("api".to_string());
+        let err = rename_folder(&mut workspace, &selector, "📦 web", false).unwrap_err();
+        assert!(matches!(err, WorkspaceError::NameTaken { .. }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_survives_anyhow() {
+        let err: anyhow::Error = WorkspaceError::NoMatchingFolder.into();
+        let err = WorkspaceError::from(err.context("while renaming"));
+        assert!(matches!(err, WorkspaceError::NoMatchingFolder));
+
+        let err = WorkspaceError::from(anyhow::anyhow!("something else"));
+        assert!(matches!(&err, WorkspaceError::Other(_)));
+        assert_eq!(err.to_string(), "something else");
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let malformed = serde_json::from_str::<WorkspaceFile>("[]").unwrap_err();
+        let cases = [
+            (WorkspaceError::NoMatchingFolder, ExitCode::Failure),
+            (WorkspaceError::InvalidName("a/b".into()), ExitCode::Usage),
+            (
+                WorkspaceError::InvalidDepth { min: 3, max: 2 },
+                ExitCode::Usage,
+            ),
+            (
+                WorkspaceError::InvalidWorkspace(malformed),
+                ExitCode::MalformedWorkspace,
+            ),
+            (
+                WorkspaceError::TooFewFolders {
+                    found: 0,
+                    message: String::new(),
+                },
+                ExitCode::FolderCount,
+            ),
+        ];
+        for (err, code) in cases {
+            assert_eq!(err.exit_code(), code, "{:?}", err);
+            // Found under the context added on the way up
+            let err = anyhow::Error::from(err).context("while generating");
+            assert_eq!(ExitCode::from_error(&err), code);
+        }
+        let err = anyhow::anyhow!("something else");
+        assert_eq!(ExitCode::from_error(&err), ExitCode::Failure);
+        assert_eq!(ExitCode::MalformedWorkspace as u8, 5);
+    }
+}
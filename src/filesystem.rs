@@ -0,0 +1,434 @@
+//! [`FileSystem`], the file operations reading, scanning and writing workspaces go
+//! through, with [`RealFs`] for the disk and [`MemoryFs`] for tests that shouldn't need
+//! temporary directories or want a file operation to fail.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// The file operations used to read, scan and write workspaces.
+pub trait FileSystem: std::fmt::Debug + Send + Sync {
+    /// The content of the file at `path`.
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+    /// Replaces the file at `path` with `content`, so that readers never see it
+    /// partially written.
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()>;
+    /// Whether anything exists at `path`, following links.
+    fn exists(&self, path: &Path) -> bool;
+    /// Whether `path` is a directory, following links.
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Whether `path` itself is a symbolic link.
+    fn is_symlink(&self, path: &Path) -> bool;
+    /// The paths of the entries directly inside the directory `path`.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    /// The entries directly inside the directory `path` with their types, which
+    /// scans use to avoid looking up each entry again. The default asks
+    /// [`is_dir`](FileSystem::is_dir) and [`is_symlink`](FileSystem::is_symlink) about
+    /// each entry of [`read_dir`](FileSystem::read_dir).
+    fn read_dir_entries(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        Ok(self
+            .read_dir(path)?
+            .into_iter()
+            .map(|path| DirEntry {
+                is_dir: self.is_dir(&path),
+                is_symlink: self.is_symlink(&path),
+                path,
+            })
+            .collect())
+    }
+    /// How many entries are directly inside the directory `path`, counting no further
+    /// than `limit`, so a huge directory isn't listed in full. The default counts
+    /// those of [`read_dir`](FileSystem::read_dir).
+    fn count_entries(&self, path: &Path, limit: usize) -> io::Result<usize> {
+        Ok(self.read_dir(path)?.len().min(limit))
+    }
+    /// `path` made absolute, with links resolved.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+    /// The directory relative paths are resolved against.
+    fn current_dir(&self) -> io::Result<PathBuf>;
+    /// When the file or directory at `path` was last modified, following links. The
+    /// default fails as unsupported.
+    fn modified(&self, _path: &Path) -> io::Result<SystemTime> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// An entry of a directory listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    /// Whether it is a directory, or a link to one
+    pub is_dir: bool,
+    /// Whether it is a symbolic link
+    pub is_symlink: bool,
+}
+
+/// The file system of the machine. Writes are atomic, as with
+/// [`write_atomic`](crate::write_atomic).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        crate::io::replace_file(path, content, false)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        path.is_symlink()
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        std::fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect()
+    }
+
+    /// Uses the type the listing gives each entry, so only links are looked up.
+    fn read_dir_entries(&self, path: &Path) -> io::Result<Vec<DirEntry>> {
+        let mut entries = vec![];
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            let is_symlink = file_type.is_symlink();
+            let is_dir = match is_symlink {
+                true => path.is_dir(),
+                false => file_type.is_dir(),
+            };
+            entries.push(DirEntry {
+                path,
+                is_dir,
+                is_symlink,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn count_entries(&self, path: &Path, limit: usize) -> io::Result<usize> {
+        Ok(std::fs::read_dir(path)?.take(limit).count())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        path.canonicalize()
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        std::env::current_dir()
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        std::fs::metadata(path)?.modified()
+    }
+}
+
+/// An operation of [`MemoryFs`] that can be made to fail with [`MemoryFs::fail`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    ReadToString,
+    Write,
+    ReadDir,
+}
+
+/// A file system held in memory, for tests. Clones share the same files, so a test
+/// can keep one to look at or change what the code under test sees.
+///
+/// Relative paths are resolved against the current directory, `/` unless set with
+/// [`MemoryFs::with_current_dir`]. There are no links: paths are canonical once `.`
+/// and `..` are resolved.
+///
+/// ```
+/// use std::io::ErrorKind;
+/// use std::path::Path;
+/// use workspace_manager::filesystem::{FileSystem, MemoryFs, Operation};
+///
+/// let fs = MemoryFs::new();
+/// fs.add_file("/projects/api/Cargo.toml", "");
+/// assert!(fs.is_dir(Path::new("/projects/api")));
+///
+/// let file = Path::new("/projects/team.code-workspace");
+/// fs.fail(Operation::Write, file, ErrorKind::PermissionDenied);
+/// assert!(fs.write(file, b"{}").is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFs {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+    dirs: BTreeSet<PathBuf>,
+    modified: BTreeMap<PathBuf, SystemTime>,
+    current_dir: PathBuf,
+    failures: Vec<(Operation, PathBuf, io::ErrorKind)>,
+}
+
+impl MemoryFs {
+    /// An empty file system with just `/`.
+    pub fn new() -> Self {
+        Self::with_current_dir("/")
+    }
+
+    /// An empty file system with `dir` as its current directory.
+    pub fn with_current_dir(dir: impl AsRef<Path>) -> Self {
+        let fs = Self::default();
+        let dir = normalize(Path::new("/"), dir.as_ref());
+        fs.add_dir(&dir);
+        fs.state().current_dir = dir;
+        fs
+    }
+
+    /// Creates the directory `path` and those above it.
+    pub fn add_dir(&self, path: impl AsRef<Path>) {
+        let mut state = self.state();
+        let path = state.resolve(path.as_ref());
+        state.dirs.extend(path.ancestors().map(Path::to_path_buf));
+    }
+
+    /// Creates or replaces the file `path`, creating the directories above it.
+    pub fn add_file(&self, path: impl AsRef<Path>, content: impl Into<Vec<u8>>) {
+        let mut state = self.state();
+        let path = state.resolve(path.as_ref());
+        state
+            .dirs
+            .extend(path.ancestors().skip(1).map(Path::to_path_buf));
+        state.files.insert(path, content.into());
+    }
+
+    /// Removes the file or the directory, with everything inside it, at `path`.
+    pub fn remove(&self, path: impl AsRef<Path>) {
+        let mut state = self.state();
+        let path = state.resolve(path.as_ref());
+        state.files.retain(|file, _| !file.starts_with(&path));
+        state.dirs.retain(|dir| !dir.starts_with(&path));
+        state.modified.retain(|entry, _| !entry.starts_with(&path));
+    }
+
+    /// Sets when the file or directory `path` was last modified. Those whose time
+    /// isn't set were last modified at the epoch.
+    pub fn set_modified(&self, path: impl AsRef<Path>, time: SystemTime) {
+        let mut state = self.state();
+        let path = state.resolve(path.as_ref());
+        state.modified.insert(path, time);
+    }
+
+    /// The content of the file `path`, if there is one.
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<String> {
+        let state = self.state();
+        let path = state.resolve(path.as_ref());
+        let content = state.files.get(&path)?;
+        Some(String::from_utf8_lossy(content).into_owned())
+    }
+
+    /// Makes `operation` on `path` fail with `kind` from now on.
+    pub fn fail(&self, operation: Operation, path: impl AsRef<Path>, kind: io::ErrorKind) {
+        let mut state = self.state();
+        let path = state.resolve(path.as_ref());
+        state.failures.push((operation, path, kind));
+    }
+
+    fn state(&self) -> MutexGuard<'_, MemoryState> {
+        // A test that panicked while holding the lock leaves consistent state behind
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The resolved `path`, unless `operation` on it was made to fail.
+    fn check(
+        &self,
+        operation: Operation,
+        path: &Path,
+    ) -> io::Result<(MutexGuard<'_, MemoryState>, PathBuf)> {
+        let state = self.state();
+        let path = state.resolve(path);
+        let failure = state
+            .failures
+            .iter()
+            .find(|(failing, failing_path, _)| *failing == operation && *failing_path == path);
+        match failure {
+            Some((_, _, kind)) => Err(io::Error::from(*kind)),
+            None => Ok((state, path)),
+        }
+    }
+}
+
+impl MemoryState {
+    fn resolve(&self, path: &Path) -> PathBuf {
+        normalize(&self.current_dir, path)
+    }
+}
+
+/// `path` made absolute against `current_dir`, with `.` and `..` resolved.
+fn normalize(current_dir: &Path, path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::from("/");
+    for component in current_dir.join(path).components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    normalized
+}
+
+fn not_found() -> io::Error {
+    io::ErrorKind::NotFound.into()
+}
+
+impl FileSystem for MemoryFs {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        let (state, path) = self.check(Operation::ReadToString, path)?;
+        let content = state.files.get(&path).ok_or_else(not_found)?;
+        String::from_utf8(content.clone())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    fn write(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        let (mut state, path) = self.check(Operation::Write, path)?;
+        if state.dirs.contains(&path) {
+            return Err(io::ErrorKind::IsADirectory.into());
+        }
+        if !path.parent().is_some_and(|dir| state.dirs.contains(dir)) {
+            return Err(not_found());
+        }
+        state.files.insert(path, content.to_vec());
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        let state = self.state();
+        let path = state.resolve(path);
+        state.files.contains_key(&path) || state.dirs.contains(&path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        let state = self.state();
+        state.dirs.contains(&state.resolve(path))
+    }
+
+    fn is_symlink(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let (state, path) = self.check(Operation::ReadDir, path)?;
+        if !state.dirs.contains(&path) {
+            return Err(not_found());
+        }
+        let inside = |entry: &&PathBuf| entry.parent() == Some(path.as_path());
+        Ok(state
+            .dirs
+            .iter()
+            .chain(state.files.keys())
+            .filter(inside)
+            .cloned()
+            .collect())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        let path = self.state().resolve(path);
+        match self.exists(&path) {
+            true => Ok(path),
+            false => Err(not_found()),
+        }
+    }
+
+    fn current_dir(&self) -> io::Result<PathBuf> {
+        Ok(self.state().current_dir.clone())
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        if !self.exists(path) {
+            return Err(not_found());
+        }
+        let state = self.state();
+        let time = state.modified.get(&state.resolve(path)).copied();
+        Ok(time.unwrap_or(SystemTime::UNIX_EPOCH))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_fs() -> io::Result<()> {
+        let fs = MemoryFs::with_current_dir("/home/me");
+        fs.add_file("projects/api/Cargo.toml", "[package]");
+        fs.add_dir("/home/me/projects/web");
+
+        assert!(fs.is_dir(Path::new("/home/me/projects")));
+        assert!(fs.exists(Path::new("projects/api/Cargo.toml")));
+        assert!(!fs.is_dir(Path::new("projects/api/Cargo.toml")));
+        assert_eq!(
+            fs.read_dir(Path::new("projects"))?,
+            vec![
+                PathBuf::from("/home/me/projects/api"),
+                PathBuf::from("/home/me/projects/web")
+            ]
+        );
+        assert_eq!(
+            fs.canonicalize(Path::new("projects/web/../api/."))?,
+            PathBuf::from("/home/me/projects/api")
+        );
+        assert!(fs.canonicalize(Path::new("missing")).is_err());
+
+        fs.write(Path::new("projects/api/notes.txt"), b"hi")?;
+        assert_eq!(
+            fs.read_to_string(Path::new("/home/me/projects/api/notes.txt"))?,
+            "hi"
+        );
+        let err = fs.write(Path::new("missing/notes.txt"), b"hi").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Clones share the files; failures apply to the one path and operation
+        let shared = fs.clone();
+        shared.fail(
+            Operation::ReadDir,
+            "projects/web",
+            io::ErrorKind::PermissionDenied,
+        );
+        let err = fs.read_dir(Path::new("/home/me/projects/web")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(fs.read_dir(Path::new("projects")).is_ok());
+
+        shared.remove("projects/api");
+        assert!(!fs.exists(Path::new("projects/api/Cargo.toml")));
+        assert_eq!(fs.file("projects/api/notes.txt"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_entries() -> io::Result<()> {
+        let temp = tempfile::TempDir::new()?;
+        for i in 0..10 {
+            std::fs::write(temp.path().join(format!("{}.txt", i)), "")?;
+        }
+        assert_eq!(RealFs.count_entries(temp.path(), 4)?, 4);
+        assert_eq!(RealFs.count_entries(temp.path(), 20)?, 10);
+
+        let fs = MemoryFs::new();
+        fs.add_file("/data/a.csv", "");
+        fs.add_file("/data/b.csv", "");
+        assert_eq!(fs.count_entries(Path::new("/data"), 1)?, 1);
+        assert!(fs.count_entries(Path::new("/missing"), 1).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1037 @@
+//! [`FolderFilter`], how a scan decides which directories become folders, with the
+//! filters [`ScanOptions`] is made of and room for others from code.
+
+use crate::console;
+use crate::error::{Result, WorkspaceError};
+use crate::filesystem::{DirEntry, FileSystem};
+use crate::io::read_workspace_with;
+use crate::scan::{Gitignore, ScanOptions, SkipReason};
+use regex::Regex;
+use serde::Deserialize;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// What a [`FolderFilter`] decided about a directory met by a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    /// Keep it as far as this filter is concerned, with what made it match when the
+    /// filter selects directories, e.g. `marker 'Cargo.toml'`
+    Include(Option<String>),
+    /// Leave it out. Unless `descend`, the directories inside it are left out too
+    Exclude { reason: SkipReason, descend: bool },
+}
+
+impl FilterDecision {
+    /// Keeps the directory without a reason, for filters it doesn't concern.
+    pub fn pass() -> Self {
+        FilterDecision::Include(None)
+    }
+}
+
+/// Where a scan is when it asks a [`FolderFilter`] about a directory, and what it
+/// already knows about the directory, so filters don't each look it up again.
+#[derive(Debug, Clone, Copy)]
+pub struct ScanContext<'a> {
+    /// The file system the scan looks in
+    pub fs: &'a dyn FileSystem,
+    /// The directory the scan started from
+    pub base: &'a Path,
+    /// 1 for the directories directly inside `base`
+    pub depth: usize,
+    /// The name of the directory
+    pub name: &'a str,
+    /// Whether the directory is a symbolic link
+    pub is_symlink: bool,
+    pub(crate) dir: &'a Path,
+    pub(crate) gitignore: &'a Gitignore,
+    /// The entries of the directory, listed the first time they are needed
+    pub(crate) entries: &'a OnceCell<Option<Vec<DirEntry>>>,
+}
+
+impl ScanContext<'_> {
+    /// The entries directly inside the directory, listed once for all the filters and
+    /// for looking inside it later. `None` when it can't be listed.
+    pub fn entries(&self) -> Option<&[DirEntry]> {
+        self.entries
+            .get_or_init(|| self.fs.read_dir_entries(self.dir).ok())
+            .as_deref()
+    }
+}
+
+/// Decides whether a directory met by a scan becomes a folder.
+///
+/// A scan asks its filters in the order of [`ScanOptions::all_filters`]: symlinks,
+/// hidden directories, exclude patterns, excluded names, `.gitignore` files,
+/// include patterns, exclude regexes, include regexes, entry counts, markers, contained
+/// files, modification times, then the filters added to [`ScanOptions::filters`] or with
+/// [`WorkspaceBuilder::filter`](crate::WorkspaceBuilder::filter). The first to
+/// exclude a directory decides; later ones aren't asked about it.
+///
+/// ```
+/// use std::path::Path;
+/// use workspace_manager::filter::{FilterDecision, FolderFilter, ScanContext};
+/// use workspace_manager::{SkipReason, WorkspaceBuilder};
+///
+/// /// Only the repositories a team owns, e.g. from an API lookup.
+/// #[derive(Debug)]
+/// struct OwnedBy(Vec<&'static str>);
+///
+/// impl FolderFilter for OwnedBy {
+///     fn include(&self, dir: &Path, _ctx: &ScanContext) -> FilterDecision {
+///         let name = dir.file_name().unwrap_or_default().to_string_lossy();
+///         match self.0.contains(&name.as_ref()) {
+///             true => FilterDecision::Include(Some("owned by the team".into())),
+///             false => FilterDecision::Exclude {
+///                 reason: SkipReason::Filtered("owned by another team".into()),
+///                 descend: false,
+///             },
+///         }
+///     }
+/// }
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let projects = tempfile::TempDir::new()?;
+/// std::fs::create_dir(projects.path().join("api"))?;
+/// std::fs::create_dir(projects.path().join("web"))?;
+///
+/// let workspace = WorkspaceBuilder::new(projects.path())
+///     .exclude_current(true)
+///     .filter(OwnedBy(vec!["api"]))
+///     .build()?;
+/// assert_eq!(workspace.folders.len(), 1);
+/// assert_eq!(workspace.folders[0].name, "📦 api");
+/// # Ok(())
+/// # }
+/// ```
+pub trait FolderFilter: std::fmt::Debug + Send + Sync {
+    /// Whether the directory `dir` is kept.
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision;
+}
+
+/// Leaves out symlinks to directories, without looking inside them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SymlinkFilter;
+
+impl FolderFilter for SymlinkFilter {
+    fn include(&self, _dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        match ctx.is_symlink {
+            true => exclude(SkipReason::Symlink),
+            false => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Leaves out directories whose name starts with a dot, without looking inside them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HiddenFilter;
+
+impl FolderFilter for HiddenFilter {
+    fn include(&self, _dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        match ctx.name.starts_with('.') {
+            true => exclude(SkipReason::Hidden),
+            false => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Leaves out directories whose name matches one of the patterns, without looking
+/// inside them.
+#[derive(Debug, Clone)]
+pub struct ExcludeFilter {
+    patterns: Vec<(String, glob::Pattern)>,
+}
+
+impl ExcludeFilter {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        Ok(ExcludeFilter {
+            patterns: compile(patterns)?,
+        })
+    }
+}
+
+impl FolderFilter for ExcludeFilter {
+    fn include(&self, _dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        match self.patterns.iter().find(|(_, p)| p.matches(ctx.name)) {
+            Some((pattern, _)) => exclude(SkipReason::Excluded(pattern.clone())),
+            None => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Leaves out directories with one of the names, without looking inside them.
+#[derive(Debug, Clone)]
+pub struct ExcludeNameFilter {
+    names: Vec<String>,
+    ignore_case: bool,
+}
+
+impl ExcludeNameFilter {
+    /// Compares names exactly, or ignoring case when `ignore_case`.
+    pub fn new(names: &[String], ignore_case: bool) -> Self {
+        ExcludeNameFilter {
+            names: names.to_vec(),
+            ignore_case,
+        }
+    }
+}
+
+impl FolderFilter for ExcludeNameFilter {
+    fn include(&self, _dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        let matches = |name: &&String| match self.ignore_case {
+            true => name.to_lowercase() == ctx.name.to_lowercase(),
+            false => *name == ctx.name,
+        };
+        match self.names.iter().find(matches) {
+            Some(name) => exclude(SkipReason::ExcludedName(name.clone())),
+            None => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Leaves out directories ignored by the `.gitignore` files of the directories the
+/// scan went through, without looking inside them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GitignoreFilter;
+
+impl FolderFilter for GitignoreFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        match ctx.gitignore.ignores(dir) {
+            true => exclude(SkipReason::Gitignored),
+            false => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Keeps only directories whose name matches one of the patterns, still looking
+/// inside the others.
+#[derive(Debug, Clone)]
+pub struct IncludeFilter {
+    patterns: Vec<(String, glob::Pattern)>,
+}
+
+impl IncludeFilter {
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        Ok(IncludeFilter {
+            patterns: compile(patterns)?,
+        })
+    }
+}
+
+impl FolderFilter for IncludeFilter {
+    fn include(&self, _dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        match self.patterns.iter().find(|(_, p)| p.matches(ctx.name)) {
+            Some((pattern, _)) => {
+                FilterDecision::Include(Some(format!("included by '{}'", pattern)))
+            }
+            None => FilterDecision::Exclude {
+                reason: SkipReason::NotIncluded,
+                descend: true,
+            },
+        }
+    }
+}
+
+/// Leaves out directories whose path relative to the scan's base directory, with `/`
+/// between components, matches one of the regexes, without looking inside them.
+#[derive(Debug, Clone)]
+pub struct ExcludeRegexFilter {
+    regexes: Vec<Regex>,
+}
+
+impl ExcludeRegexFilter {
+    pub fn new(regexes: &[String]) -> Result<Self> {
+        Ok(ExcludeRegexFilter {
+            regexes: compile_regexes(regexes)?,
+        })
+    }
+}
+
+impl FolderFilter for ExcludeRegexFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        let relative = relative_path(dir, ctx.base);
+        match self.regexes.iter().find(|regex| regex.is_match(&relative)) {
+            Some(regex) => exclude(SkipReason::ExcludedByRegex(regex.to_string())),
+            None => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Keeps only directories whose relative path, as for [`ExcludeRegexFilter`], matches
+/// one of the regexes, still looking inside the others.
+#[derive(Debug, Clone)]
+pub struct IncludeRegexFilter {
+    regexes: Vec<Regex>,
+}
+
+impl IncludeRegexFilter {
+    pub fn new(regexes: &[String]) -> Result<Self> {
+        Ok(IncludeRegexFilter {
+            regexes: compile_regexes(regexes)?,
+        })
+    }
+}
+
+impl FolderFilter for IncludeRegexFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        let relative = relative_path(dir, ctx.base);
+        match self.regexes.iter().find(|regex| regex.is_match(&relative)) {
+            Some(regex) => FilterDecision::Include(Some(format!("included by regex '{}'", regex))),
+            None => FilterDecision::Exclude {
+                reason: SkipReason::NotIncludedByRegex,
+                descend: true,
+            },
+        }
+    }
+}
+
+/// Leaves out directories that are already folders of other workspace files, as
+/// `--exclude-in` does, without looking inside them.
+#[derive(Debug, Clone, Default)]
+pub struct ReferencedFilter {
+    /// Canonical folder paths, with the workspace file listing each
+    folders: HashMap<PathBuf, PathBuf>,
+}
+
+impl ReferencedFilter {
+    /// The folders of the workspace files at `workspaces`, read leniently from `fs`,
+    /// with relative paths resolved against the directory of their file. A file that
+    /// can't be read or parsed is skipped with a warning, as are folders that don't
+    /// exist.
+    pub fn load(fs: &dyn FileSystem, workspaces: &[PathBuf]) -> Self {
+        let mut folders = HashMap::new();
+        for file in workspaces {
+            let workspace = match read_workspace_with(fs, file) {
+                Ok(workspace) => workspace,
+                Err(err) => {
+                    console::warning(format_args!(
+                        "{:#}; its folders aren't excluded",
+                        anyhow::Error::from(err)
+                    ));
+                    continue;
+                }
+            };
+            let dir = file.parent().unwrap_or(Path::new(""));
+            for folder in workspace.folders.iter().filter(|f| !f.path.is_empty()) {
+                if let Ok(path) = fs.canonicalize(&dir.join(&folder.path)) {
+                    folders.entry(path).or_insert_with(|| file.clone());
+                }
+            }
+        }
+        ReferencedFilter { folders }
+    }
+}
+
+impl FolderFilter for ReferencedFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        let listed = ctx
+            .fs
+            .canonicalize(dir)
+            .ok()
+            .and_then(|path| self.folders.get(&path));
+        match listed {
+            Some(file) => exclude(SkipReason::Referenced(file.clone())),
+            None => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Leaves out directories with more than `max` entries directly inside them, such as
+/// data dumps, without looking inside them. Counting stops past `max`, so a huge
+/// directory isn't listed in full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryCountFilter {
+    pub max: usize,
+}
+
+impl FolderFilter for EntryCountFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        // Listed already by an earlier filter, the entries cost nothing more to count
+        let count = match ctx.entries.get() {
+            Some(Some(entries)) => Ok(entries.len()),
+            _ => ctx.fs.count_entries(dir, self.max.saturating_add(1)),
+        };
+        match count {
+            Ok(count) if count > self.max => exclude(SkipReason::TooManyEntries(self.max)),
+            // A directory that can't be listed is reported when the scan looks inside
+            _ => FilterDecision::pass(),
+        }
+    }
+}
+
+/// Keeps only directories containing one of the marker files, still looking inside
+/// the others. With several markers, the directory is listed once rather than each
+/// marker looked up, except for markers below a subdirectory, such as `src/main.rs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkerFilter {
+    pub markers: Vec<String>,
+}
+
+impl MarkerFilter {
+    fn contains(&self, dir: &Path, ctx: &ScanContext, marker: &str) -> bool {
+        let nested = marker.contains(['/', '\\']);
+        let entries = match self.markers.len() > 1 && !nested {
+            true => ctx.entries(),
+            false => None,
+        };
+        match entries {
+            Some(entries) => entries
+                .iter()
+                .any(|entry| entry.path.file_name().is_some_and(|name| name == marker)),
+            None => ctx.fs.exists(&dir.join(marker)),
+        }
+    }
+}
+
+impl FolderFilter for MarkerFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        match self
+            .markers
+            .iter()
+            .find(|marker| self.contains(dir, ctx, marker))
+        {
+            Some(marker) => FilterDecision::Include(Some(format!("marker '{}'", marker))),
+            None => FilterDecision::Exclude {
+                reason: SkipReason::NoMarker,
+                descend: true,
+            },
+        }
+    }
+}
+
+/// Keeps only directories containing a file whose name matches one of the patterns,
+/// still looking inside the others. Subdirectories are searched down to `depth`
+/// levels, skipping hidden ones such as `.git`, and the search stops at the first
+/// match.
+#[derive(Debug, Clone)]
+pub struct ContainsFilter {
+    patterns: Vec<(String, glob::Pattern)>,
+    depth: usize,
+}
+
+impl ContainsFilter {
+    /// Looks at the files directly inside each directory with a `depth` of 1.
+    pub fn new(patterns: &[String], depth: usize) -> Result<Self> {
+        Ok(ContainsFilter {
+            patterns: compile(patterns)?,
+            depth: depth.max(1),
+        })
+    }
+
+    /// The first file in `entries`, or below them within `depth` levels, whose name
+    /// matches, with the pattern it matches.
+    fn find(
+        &self,
+        fs: &dyn FileSystem,
+        entries: &[DirEntry],
+        depth: usize,
+    ) -> Option<(PathBuf, &str)> {
+        for entry in entries.iter().filter(|entry| !entry.is_dir) {
+            let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+            if let Some((pattern, _)) = self.patterns.iter().find(|(_, p)| p.matches(&name)) {
+                return Some((entry.path.clone(), pattern));
+            }
+        }
+        if depth <= 1 {
+            return None;
+        }
+        entries
+            .iter()
+            .filter(|entry| entry.is_dir && !entry.is_symlink)
+            .filter(|entry| {
+                !entry
+                    .path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            })
+            .find_map(|entry| {
+                let inner = fs.read_dir_entries(&entry.path).ok()?;
+                self.find(fs, &inner, depth - 1)
+            })
+    }
+}
+
+impl FolderFilter for ContainsFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        let found = ctx
+            .entries()
+            .and_then(|entries| self.find(ctx.fs, entries, self.depth));
+        match found {
+            Some((file, pattern)) => FilterDecision::Include(Some(format!(
+                "contains '{}' matching '{}'",
+                relative_path(&file, dir),
+                pattern
+            ))),
+            None => FilterDecision::Exclude {
+                reason: SkipReason::NoMatchingFile,
+                descend: true,
+            },
+        }
+    }
+}
+
+/// A bound of `--newer-than` or `--older-than`: an age counted back from the start of
+/// the scan, such as `30d`, or a date, such as `2024-01-01`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum TimeSpec {
+    /// `amount` seconds (`s`), minutes (`m`), hours (`h`), days (`d`) or weeks (`w`),
+    /// as given by `unit`
+    Age { amount: u64, unit: char },
+    /// The start of the day, in UTC
+    Date { year: i32, month: u32, day: u32 },
+}
+
+impl TimeSpec {
+    /// The point in time the bound stands for, with ages counted back from `now`.
+    pub fn cutoff(self, now: SystemTime) -> SystemTime {
+        match self {
+            TimeSpec::Age { amount, unit } => {
+                let age = Duration::from_secs(amount.saturating_mul(unit_seconds(unit)));
+                now.checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH)
+            }
+            TimeSpec::Date { year, month, day } => {
+                let days = days_since_epoch(year, month, day).max(0) as u64;
+                SystemTime::UNIX_EPOCH + Duration::from_secs(days * 86_400)
+            }
+        }
+    }
+}
+
+impl FromStr for TimeSpec {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, String> {
+        let invalid = || {
+            format!(
+                "invalid time '{}': expected an age such as 30d, 12h or 2w, or a date such \
+                 as 2024-01-01",
+                text
+            )
+        };
+        // Only ASCII digits, so no sign or space slips through `parse`
+        let number = |digits: &str| -> Option<u64> {
+            match !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                true => digits.parse().ok(),
+                false => None,
+            }
+        };
+        if let Some(unit) = text.chars().last().filter(|unit| "smhdw".contains(*unit)) {
+            let amount = number(&text[..text.len() - 1]).ok_or_else(invalid)?;
+            amount.checked_mul(unit_seconds(unit)).ok_or_else(invalid)?;
+            return Ok(TimeSpec::Age { amount, unit });
+        }
+        let parts: Vec<&str> = text.split('-').collect();
+        let [year, month, day] = parts[..] else {
+            return Err(invalid());
+        };
+        if (year.len(), month.len(), day.len()) != (4, 2, 2) {
+            return Err(invalid());
+        }
+        let (Some(year), Some(month), Some(day)) = (number(year), number(month), number(day))
+        else {
+            return Err(invalid());
+        };
+        let (year, month, day) = (year as i32, month as u32, day as u32);
+        if year < 1970 || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month)
+        {
+            return Err(invalid());
+        }
+        Ok(TimeSpec::Date { year, month, day })
+    }
+}
+
+impl TryFrom<String> for TimeSpec {
+    type Error = String;
+
+    fn try_from(text: String) -> std::result::Result<Self, String> {
+        text.parse()
+    }
+}
+
+/// The bound as it is written, e.g. `30d` or `2024-01-01`.
+impl fmt::Display for TimeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeSpec::Age { amount, unit } => write!(f, "{}{}", amount, unit),
+            TimeSpec::Date { year, month, day } => {
+                write!(f, "{:04}-{:02}-{:02}", year, month, day)
+            }
+        }
+    }
+}
+
+fn unit_seconds(unit: char) -> u64 {
+    match unit {
+        'm' => 60,
+        'h' => 3_600,
+        'd' => 86_400,
+        'w' => 7 * 86_400,
+        _ => 1,
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to the date, by the proleptic Gregorian calendar.
+fn days_since_epoch(year: i32, month: u32, day: u32) -> i64 {
+    // Counted in years starting in March, so the leap day ends the year
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((i64::from(month) + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Keeps only directories last modified after `--newer-than` and before
+/// `--older-than`, still looking inside the others. A directory's own modification
+/// time only changes when entries are added to, removed from or renamed in it, so it
+/// counts as modified when the newest of it and the entries directly inside it was.
+/// Nothing deeper is looked up. Directories whose times can't be read are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModifiedFilter {
+    newer_than: Option<(TimeSpec, SystemTime)>,
+    older_than: Option<(TimeSpec, SystemTime)>,
+}
+
+impl ModifiedFilter {
+    /// Counts the ages of `newer_than` and `older_than` back from `now`.
+    pub fn new(
+        newer_than: Option<TimeSpec>,
+        older_than: Option<TimeSpec>,
+        now: SystemTime,
+    ) -> Self {
+        let bound = |spec: TimeSpec| (spec, spec.cutoff(now));
+        ModifiedFilter {
+            newer_than: newer_than.map(bound),
+            older_than: older_than.map(bound),
+        }
+    }
+}
+
+/// The newest modification time of `dir` and of the entries directly inside it, or
+/// `None` when none can be read.
+pub fn last_modified(dir: &Path, ctx: &ScanContext) -> Option<SystemTime> {
+    let entries = ctx.entries().unwrap_or_default();
+    std::iter::once(dir)
+        .chain(entries.iter().map(|entry| entry.path.as_path()))
+        .filter_map(|path| ctx.fs.modified(path).ok())
+        .max()
+}
+
+impl FolderFilter for ModifiedFilter {
+    fn include(&self, dir: &Path, ctx: &ScanContext) -> FilterDecision {
+        let modified = last_modified(dir, ctx);
+        let mut reasons = vec![];
+        if let Some((spec, cutoff)) = self.newer_than {
+            if modified.is_none_or(|modified| modified < cutoff) {
+                return FilterDecision::Exclude {
+                    reason: SkipReason::NotModifiedWithin(spec),
+                    descend: true,
+                };
+            }
+            reasons.push(format!("modified within --newer-than {}", spec));
+        }
+        if let Some((spec, cutoff)) = self.older_than {
+            if modified.is_none_or(|modified| modified >= cutoff) {
+                return FilterDecision::Exclude {
+                    reason: SkipReason::ModifiedWithin(spec),
+                    descend: true,
+                };
+            }
+            reasons.push(format!("not modified within --older-than {}", spec));
+        }
+        FilterDecision::Include(Some(reasons.join(", ")))
+    }
+}
+
+impl ScanOptions {
+    /// The filters a scan with these options asks, in order: those of the options
+    /// that are set, then [`ScanOptions::filters`]. Fails on an invalid pattern.
+    pub fn all_filters(&self) -> Result<Vec<Arc<dyn FolderFilter>>> {
+        let mut filters: Vec<Arc<dyn FolderFilter>> = vec![];
+        if !self.follow_symlinks {
+            filters.push(Arc::new(SymlinkFilter));
+        }
+        if !self.include_hidden {
+            filters.push(Arc::new(HiddenFilter));
+        }
+        if !self.excludes.is_empty() {
+            filters.push(Arc::new(ExcludeFilter::new(&self.excludes)?));
+        }
+        if !self.exclude_names.is_empty() {
+            filters.push(Arc::new(ExcludeNameFilter::new(
+                &self.exclude_names,
+                self.exclude_names_ignore_case,
+            )));
+        }
+        if self.respect_gitignore {
+            filters.push(Arc::new(GitignoreFilter));
+        }
+        if !self.includes.is_empty() {
+            filters.push(Arc::new(IncludeFilter::new(&self.includes)?));
+        }
+        if !self.exclude_regexes.is_empty() {
+            filters.push(Arc::new(ExcludeRegexFilter::new(&self.exclude_regexes)?));
+        }
+        if !self.include_regexes.is_empty() {
+            filters.push(Arc::new(IncludeRegexFilter::new(&self.include_regexes)?));
+        }
+        if let Some(max) = self.max_entries {
+            filters.push(Arc::new(EntryCountFilter { max }));
+        }
+        if !self.markers.is_empty() {
+            filters.push(Arc::new(MarkerFilter {
+                markers: self.markers.clone(),
+            }));
+        }
+        if !self.contains.is_empty() {
+            filters.push(Arc::new(ContainsFilter::new(
+                &self.contains,
+                self.contains_depth,
+            )?));
+        }
+        if self.newer_than.is_some() || self.older_than.is_some() {
+            filters.push(Arc::new(ModifiedFilter::new(
+                self.newer_than,
+                self.older_than,
+                SystemTime::now(),
+            )));
+        }
+        filters.extend(self.filters.iter().cloned());
+        Ok(filters)
+    }
+}
+
+fn exclude(reason: SkipReason) -> FilterDecision {
+    FilterDecision::Exclude {
+        reason,
+        descend: false,
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<(String, glob::Pattern)>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let compiled =
+                glob::Pattern::new(pattern).map_err(|source| WorkspaceError::InvalidPattern {
+                    pattern: pattern.clone(),
+                    source,
+                })?;
+            Ok((pattern.clone(), compiled))
+        })
+        .collect()
+}
+
+/// Compiles `regexes`, failing on the first invalid one.
+pub(crate) fn compile_regexes(regexes: &[String]) -> Result<Vec<Regex>> {
+    regexes
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|source| WorkspaceError::InvalidRegex {
+                pattern: pattern.clone(),
+                source,
+            })
+        })
+        .collect()
+}
+
+/// `dir` relative to `base`, with `/` between components on every platform.
+fn relative_path(dir: &Path, base: &Path) -> String {
+    dir.strip_prefix(base)
+        .unwrap_or(dir)
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFs;
+    use crate::scan::scan_with;
+    use anyhow::Result;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    /// Leaves out `web`, remembering every directory it was asked about.
+    #[derive(Debug, Default)]
+    struct NotWeb(Mutex<Vec<PathBuf>>);
+
+    impl FolderFilter for NotWeb {
+        fn include(&self, dir: &Path, _ctx: &ScanContext) -> FilterDecision {
+            self.0.lock().unwrap().push(dir.to_path_buf());
+            match dir.ends_with("web") {
+                true => FilterDecision::Exclude {
+                    reason: SkipReason::Filtered("not web".into()),
+                    descend: false,
+                },
+                false => FilterDecision::Include(Some("not web".into())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_order() -> Result<()> {
+        let fs = MemoryFs::new();
+        for dir in [".cache", "api", "build", "docs", "web/app", "lib"] {
+            fs.add_dir(Path::new("/src").join(dir));
+        }
+        fs.add_file("/src/api/Cargo.toml", "");
+        fs.add_file("/src/web/Cargo.toml", "");
+        fs.add_file("/src/web/app/Cargo.toml", "");
+        fs.add_file("/src/build/Cargo.toml", "");
+        fs.add_file("/src/.gitignore", "lib\n");
+
+        let custom = Arc::new(NotWeb::default());
+        let opts = ScanOptions {
+            max_depth: 2,
+            excludes: vec!["build".into()],
+            respect_gitignore: true,
+            markers: vec!["Cargo.toml".into()],
+            filters: vec![custom.clone()],
+            ..Default::default()
+        };
+        let outcome = scan_with(&fs, Path::new("/src"), &opts)?;
+        assert_eq!(outcome.directories, vec![PathBuf::from("/src/api")]);
+        let reasons: Vec<(String, String)> = outcome
+            .skipped
+            .iter()
+            .map(|entry| (entry.path.display().to_string(), entry.reason.to_string()))
+            .collect();
+        let expected = [
+            ("/src/.cache", "hidden"),
+            ("/src/build", "excluded by 'build'"),
+            ("/src/docs", "contains no marker file"),
+            ("/src/lib", "ignored by .gitignore"),
+            ("/src/web", "not web"),
+        ];
+        let expected: Vec<(String, String)> = expected
+            .iter()
+            .map(|(path, reason)| (path.to_string(), reason.to_string()))
+            .collect();
+        assert_eq!(reasons, expected);
+
+        // Filters after the first to exclude aren't asked, and nothing below a
+        // directory excluded for good is looked at
+        let asked = custom.0.lock().unwrap().clone();
+        assert_eq!(
+            asked,
+            vec![PathBuf::from("/src/api"), PathBuf::from("/src/web")]
+        );
+
+        let found: Vec<_> =
+            crate::scan::scan_iter_with(&fs, Path::new("/src"), &opts).collect::<Result<_, _>>()?;
+        assert_eq!(found[0].reasons, vec!["marker 'Cargo.toml'", "not web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_spec() {
+        let parse = |text: &str| text.parse::<TimeSpec>();
+        assert_eq!(
+            parse("30d"),
+            Ok(TimeSpec::Age {
+                amount: 30,
+                unit: 'd'
+            })
+        );
+        assert_eq!(
+            parse("2024-02-29"),
+            Ok(TimeSpec::Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            })
+        );
+        for text in ["12h", "2w", "90m", "45s", "2024-01-01"] {
+            assert_eq!(parse(text).unwrap().to_string(), text);
+        }
+        for text in [
+            "",
+            "d",
+            "30",
+            "-3d",
+            "+3d",
+            "1.5d",
+            "30 d",
+            "30y",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-1-01",
+            "1969-12-31",
+            "yesterday",
+        ] {
+            let err = parse(text).unwrap_err();
+            assert!(
+                err.starts_with(&format!("invalid time '{}'", text)),
+                "{}",
+                err
+            );
+        }
+        assert!(parse("99999999999999999w").is_err());
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        assert_eq!(
+            parse("2d").unwrap().cutoff(now),
+            now - Duration::from_secs(2 * 86_400)
+        );
+        assert_eq!(
+            parse("2024-01-01").unwrap().cutoff(now),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200)
+        );
+        assert_eq!(
+            parse("2000-03-01").unwrap().cutoff(now),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(951_868_800)
+        );
+    }
+
+    #[test]
+    fn test_modified_filter() -> Result<()> {
+        let fs = MemoryFs::new();
+        let now = SystemTime::now();
+        let days_ago = |days: u64| now - Duration::from_secs(days * 86_400);
+        for dir in ["active", "fresh", "stale"] {
+            fs.add_dir(Path::new("/src").join(dir));
+            fs.set_modified(Path::new("/src").join(dir), days_ago(60));
+        }
+        // Editing a file doesn't touch its directory
+        fs.add_file("/src/active/main.rs", "");
+        fs.set_modified("/src/active/main.rs", days_ago(1));
+        fs.set_modified("/src/fresh", days_ago(2));
+        fs.add_file("/src/stale/old.rs", "");
+        fs.set_modified("/src/stale/old.rs", days_ago(90));
+
+        let scan = |opts: &ScanOptions| -> Result<(Vec<PathBuf>, Vec<String>)> {
+            let outcome = scan_with(&fs, Path::new("/src"), opts)?;
+            let skipped = outcome
+                .skipped
+                .iter()
+                .map(|entry| format!("{}: {}", entry.path.display(), entry.reason))
+                .collect();
+            Ok((outcome.directories, skipped))
+        };
+        let newer = ScanOptions {
+            newer_than: Some("30d".parse().unwrap()),
+            sort: crate::SortOrder::Name,
+            ..Default::default()
+        };
+        let (found, skipped) = scan(&newer)?;
+        assert_eq!(
+            found,
+            vec![PathBuf::from("/src/active"), PathBuf::from("/src/fresh")]
+        );
+        assert_eq!(
+            skipped,
+            ["/src/stale: not modified within --newer-than 30d"]
+        );
+
+        let older = ScanOptions {
+            newer_than: None,
+            older_than: Some("30d".parse().unwrap()),
+            ..newer.clone()
+        };
+        assert_eq!(scan(&older)?.0, vec![PathBuf::from("/src/stale")]);
+        let window = ScanOptions {
+            newer_than: Some("7d".parse().unwrap()),
+            older_than: Some("2d".parse().unwrap()),
+            ..newer.clone()
+        };
+        let (found, skipped) = scan(&window)?;
+        assert_eq!(found, vec![PathBuf::from("/src/fresh")]);
+        assert_eq!(skipped[0], "/src/active: modified within --older-than 2d");
+
+        let found: Vec<_> = crate::scan::scan_iter_with(&fs, Path::new("/src"), &window)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            found[0].reasons,
+            vec!["modified within --newer-than 7d, not modified within --older-than 2d"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_count_filter() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_file("/src/api/Cargo.toml", "");
+        for i in 0..5 {
+            fs.add_file(format!("/src/data/dump{}.csv", i), "");
+        }
+        fs.add_dir("/src/data/nested");
+        let opts = ScanOptions {
+            max_depth: 2,
+            max_entries: Some(3),
+            ..Default::default()
+        };
+        let outcome = scan_with(&fs, Path::new("/src"), &opts)?;
+        assert_eq!(outcome.directories, vec![PathBuf::from("/src/api")]);
+        // Nothing inside it is looked at, so /src/data/nested isn't met at all
+        assert_eq!(outcome.skipped.len(), 1);
+        assert_eq!(outcome.skipped[0].path, Path::new("/src/data"));
+        assert_eq!(
+            outcome.skipped[0].reason.to_string(),
+            "more than 3 entries (--max-entries)"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_referenced_folders_are_left_out() -> Result<()> {
+        let fs = MemoryFs::new();
+        for dir in ["api", "web", "docs", "tools"] {
+            fs.add_dir(Path::new("/src").join(dir));
+        }
+        // Relative paths are resolved against the file's own directory
+        fs.add_file(
+            "/ws/clients.code-workspace",
+            r#"{
+                // hand-written, with trailing commas
+                "folders": [{"path": "../src/api"}, {"path": "/src/web/"},],
+            }"#,
+        );
+        fs.add_file(
+            "/ws/all/everything.code-workspace",
+            r#"{"folders": [{"path": "../../src/docs"}, {"uri": "file:///src/tools"}]}"#,
+        );
+        fs.add_file("/ws/broken.code-workspace", "{\"folders\": 3}");
+
+        let opts = crate::GenerateOptions {
+            scan_path: PathBuf::from("/src"),
+            exclude_in: vec![
+                PathBuf::from("/ws/clients.code-workspace"),
+                PathBuf::from("/ws/broken.code-workspace"),
+                PathBuf::from("/ws/missing.code-workspace"),
+                PathBuf::from("/ws/all/everything.code-workspace"),
+            ],
+            ..Default::default()
+        };
+        // Unreadable files only cost their folders
+        let outcome = crate::scan::filtered_scan(&fs, &opts, &[])?;
+        assert_eq!(outcome.directories, vec![PathBuf::from("/src/tools")]);
+        let skipped = crate::SkippedEntry {
+            path: PathBuf::from("/src/api"),
+            reason: SkipReason::Referenced(PathBuf::from("/ws/clients.code-workspace")),
+        };
+        assert!(outcome.skipped.contains(&skipped));
+        assert_eq!(
+            skipped.reason.to_string(),
+            "already in /ws/clients.code-workspace"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! Detection of the kind of project in a folder, and the build and test tasks
+//! generated for each workspace folder from it. The labels of generated tasks are
+//! recorded in the metadata section, so regeneration replaces them without touching
+//! tasks the user wrote.
+
+use crate::{remove_update_task, Task, TaskGroup, Tasks, WorkspaceFile, WorkspaceFolder};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// The build tool of a project, recognized by its manifest file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Go,
+    Python,
+}
+
+impl Ecosystem {
+    pub const ALL: [Ecosystem; 4] = [
+        Ecosystem::Cargo,
+        Ecosystem::Npm,
+        Ecosystem::Go,
+        Ecosystem::Python,
+    ];
+
+    /// The ecosystem of the project in `dir`, if any. When several manifests are
+    /// present the first of [`Ecosystem::ALL`] wins.
+    pub fn detect(dir: &Path) -> Option<Ecosystem> {
+        Ecosystem::detect_all(dir).into_iter().next()
+    }
+
+    /// Every ecosystem with a manifest in `dir`.
+    pub fn detect_all(dir: &Path) -> Vec<Ecosystem> {
+        Ecosystem::ALL
+            .into_iter()
+            .filter(|ecosystem| {
+                ecosystem
+                    .manifests()
+                    .iter()
+                    .any(|manifest| dir.join(manifest).is_file())
+            })
+            .collect()
+    }
+
+    fn manifests(self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Cargo => &["Cargo.toml"],
+            Ecosystem::Npm => &["package.json"],
+            Ecosystem::Go => &["go.mod"],
+            Ecosystem::Python => &["pyproject.toml", "setup.py"],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ecosystem::Cargo => "cargo",
+            Ecosystem::Npm => "npm",
+            Ecosystem::Go => "go",
+            Ecosystem::Python => "python",
+        }
+    }
+
+    /// The VS Code extensions recommended for this kind of project by default.
+    pub fn default_extensions(self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Cargo => &["rust-lang.rust-analyzer"],
+            Ecosystem::Npm => &["dbaeumer.vscode-eslint", "esbenp.prettier-vscode"],
+            Ecosystem::Go => &["golang.go"],
+            Ecosystem::Python => &["ms-python.python"],
+        }
+    }
+
+    /// The directories of build output and installed dependencies this kind of
+    /// project fills, which VS Code needn't watch or search.
+    pub fn heavy_dirs(self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Cargo => &["target"],
+            Ecosystem::Npm => &["node_modules"],
+            // Modules live outside the project, and vendor/ is source worth searching
+            Ecosystem::Go => &[],
+            Ecosystem::Python => &[".venv", "__pycache__"],
+        }
+    }
+
+    /// The build and test commands for the project in `dir`, with the task group
+    /// each belongs to. npm projects only get the scripts their package.json defines.
+    fn commands(self, dir: &Path) -> Vec<(&'static str, &'static str)> {
+        match self {
+            Ecosystem::Cargo => vec![("build", "cargo build"), ("test", "cargo test")],
+            Ecosystem::Go => vec![("build", "go build ./..."), ("test", "go test ./...")],
+            // No single build command; tests are usually run through the extension
+            Ecosystem::Python => vec![],
+            Ecosystem::Npm => {
+                let scripts = fs::read_to_string(dir.join("package.json"))
+                    .ok()
+                    .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+                    .map(|package| package["scripts"].clone())
+                    .unwrap_or_default();
+                [("build", "npm run build"), ("test", "npm test")]
+                    .into_iter()
+                    .filter(|(group, _)| scripts.get(group).is_some())
+                    .collect()
+            }
+        }
+    }
+
+    fn problem_matcher(self) -> Value {
+        match self {
+            Ecosystem::Cargo => json!(["$rustc"]),
+            Ecosystem::Npm | Ecosystem::Python => json!([]),
+            Ecosystem::Go => json!(["$go"]),
+        }
+    }
+}
+
+/// The tasks for `folder`, which lives in `dir`. They run in the folder through
+/// `${workspaceFolder:NAME}`, so their working directory follows the display name.
+pub fn tasks_for_folder(folder: &WorkspaceFolder, dir: &Path) -> Vec<Task> {
+    let Some(ecosystem) = Ecosystem::detect(dir) else {
+        return vec![];
+    };
+    let short_name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| folder.name.clone());
+    ecosystem
+        .commands(dir)
+        .into_iter()
+        .map(|(group, command)| {
+            let mut other = serde_json::Map::new();
+            other.insert(
+                "options".to_string(),
+                json!({"cwd": format!("${{workspaceFolder:{}}}", folder.name)}),
+            );
+            let kind = if group == "build" { "Build" } else { "Test" };
+            Task {
+                label: format!("{}: {} ({})", kind, short_name, ecosystem.name()),
+                task_type: "shell".to_string(),
+                command: command.to_string(),
+                group: Some(TaskGroup::Kind(group.to_string())),
+                problem_matcher: Some(ecosystem.problem_matcher()),
+                other,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Replaces the previously generated folder tasks of `workspace` with tasks for
+/// its current folders, or only removes them when `enabled` is false. Folder paths
+/// are resolved against `workspace_dir`. Tasks of the user are never replaced, even
+/// when a generated label would collide with one of theirs.
+pub fn sync_folder_tasks(workspace: &mut WorkspaceFile, workspace_dir: &Path, enabled: bool) {
+    let mut metadata = workspace.metadata();
+    let mut tasks = workspace.tasks.take();
+    for label in &metadata.folder_tasks {
+        tasks = remove_update_task(tasks, label);
+    }
+    metadata.folder_tasks.clear();
+
+    if enabled {
+        let generated: Vec<Task> = workspace
+            .folders
+            .iter()
+            .filter_map(|folder| Some((folder, folder.resolve(workspace_dir)?)))
+            .flat_map(|(folder, dir)| tasks_for_folder(folder, &dir))
+            .collect();
+        let section = tasks.get_or_insert_with(|| Tasks {
+            version: "2.0.0".to_string(),
+            ..Default::default()
+        });
+        for task in generated {
+            if section
+                .tasks
+                .iter()
+                .any(|existing| existing.label == task.label)
+            {
+                continue;
+            }
+            metadata.folder_tasks.push(task.label.clone());
+            section.tasks.push(task);
+        }
+        if section.tasks.is_empty() && section.other.is_empty() {
+            tasks = None;
+        }
+    }
+    workspace.tasks = tasks;
+    workspace.set_metadata(metadata);
+}
+
+/// Removes generated folder tasks whose folder is no longer in `workspace`,
+/// returning how many were removed.
+pub fn prune_folder_tasks(workspace: &mut WorkspaceFile) -> usize {
+    let mut metadata = workspace.metadata();
+    let names: Vec<String> = workspace
+        .folders
+        .iter()
+        .map(|folder| format!("${{workspaceFolder:{}}}", folder.name))
+        .collect();
+    let stale: Vec<String> = workspace
+        .tasks
+        .iter()
+        .flat_map(|tasks| &tasks.tasks)
+        .filter(|task| metadata.folder_tasks.contains(&task.label))
+        .filter(|task| {
+            let cwd = task
+                .other
+                .get("options")
+                .and_then(|options| options.get("cwd"));
+            !cwd.and_then(Value::as_str)
+                .is_some_and(|cwd| names.iter().any(|name| name == cwd))
+        })
+        .map(|task| task.label.clone())
+        .collect();
+    for label in &stale {
+        workspace.tasks = remove_update_task(workspace.tasks.take(), label);
+    }
+    metadata.folder_tasks.retain(|label| !stale.contains(label));
+    workspace.set_metadata(metadata);
+    stale.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_workspace;
+    use tempfile::TempDir;
+
+    fn labels(workspace: &WorkspaceFile) -> Vec<String> {
+        workspace
+            .tasks
+            .iter()
+            .flat_map(|tasks| &tasks.tasks)
+            .map(|task| task.label.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_and_tasks_for_folder() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let api = temp.path().join("api");
+        let web = temp.path().join("web");
+        fs::create_dir_all(&api)?;
+        fs::create_dir_all(&web)?;
+        fs::write(api.join("Cargo.toml"), "")?;
+        fs::write(web.join("package.json"), r#"{"scripts": {"test": "jest"}}"#)?;
+
+        assert_eq!(Ecosystem::detect(&api), Some(Ecosystem::Cargo));
+        assert_eq!(Ecosystem::detect(temp.path()), None);
+
+        let folder = WorkspaceFolder::new("api", "📦 api");
+        let tasks = tasks_for_folder(&folder, &api);
+        assert_eq!(tasks.len(), 2);
+        let build = serde_json::to_value(&tasks[0])?;
+        assert_eq!(build["label"], "Build: api (cargo)");
+        assert_eq!(build["command"], "cargo build");
+        assert_eq!(build["group"], "build");
+        assert_eq!(build["options"]["cwd"], "${workspaceFolder:📦 api}");
+
+        // Only the scripts the package defines
+        let tasks = tasks_for_folder(&WorkspaceFolder::new("web", "📦 web"), &web);
+        let labels: Vec<&str> = tasks.iter().map(|task| task.label.as_str()).collect();
+        assert_eq!(labels, vec!["Test: web (npm)"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_folder_tasks() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        for dir in ["api", "cli"] {
+            fs::create_dir(temp.path().join(dir))?;
+            fs::write(temp.path().join(dir).join("Cargo.toml"), "")?;
+        }
+        let mut workspace = parse_workspace(
+            r#"{"folders": [{"path": "api", "name": "📦 api"}, {"path": "cli", "name": "📦 cli"}],
+                "tasks": {"version": "2.0.0", "tasks": [
+                    {"label": "Build: cli (cargo)", "type": "shell", "command": "make cli"}
+                ]}}"#,
+        )?;
+
+        sync_folder_tasks(&mut workspace, temp.path(), true);
+        assert_eq!(
+            labels(&workspace),
+            vec![
+                "Build: cli (cargo)",
+                "Build: api (cargo)",
+                "Test: api (cargo)",
+                "Test: cli (cargo)"
+            ]
+        );
+        // The user's task with a colliding label is kept as it was
+        assert_eq!(
+            workspace.tasks.as_ref().unwrap().tasks[0].command,
+            "make cli"
+        );
+
+        // Renamed folders get their tasks regenerated with the new name
+        workspace.folders[0].name = "API".to_string();
+        sync_folder_tasks(&mut workspace, temp.path(), true);
+        let tasks = &workspace.tasks.as_ref().unwrap().tasks;
+        assert_eq!(tasks.len(), 4);
+        assert_eq!(tasks[1].other["options"]["cwd"], "${workspaceFolder:API}");
+
+        workspace.folders.remove(1);
+        assert_eq!(prune_folder_tasks(&mut workspace), 1);
+        assert_eq!(
+            labels(&workspace),
+            vec![
+                "Build: cli (cargo)",
+                "Build: api (cargo)",
+                "Test: api (cargo)"
+            ]
+        );
+
+        sync_folder_tasks(&mut workspace, temp.path(), false);
+        assert_eq!(labels(&workspace), vec!["Build: cli (cargo)"]);
+        assert!(workspace.metadata().folder_tasks.is_empty());
+        Ok(())
+    }
+}
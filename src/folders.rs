@@ -0,0 +1,823 @@
+//! Editing the folders of an existing workspace: adding, selecting, removing,
+//! renaming, listing and exporting them.
+
+use crate::error::{Result, WorkspaceError};
+use crate::folder_tasks::Ecosystem;
+use crate::model::{
+    duplicate_folders, folder_key, normalize_folder_path, WorkspaceFile, WorkspaceFolder,
+};
+use crate::options::{ExportField, ExportFormat};
+use anyhow::Context;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Removes the folders at `indices` (in ascending order). With `remember`, their
+/// paths are recorded in the metadata section so regeneration does not add them back.
+pub fn exclude_folders(
+    workspace: &mut WorkspaceFile,
+    indices: &[usize],
+    remember: bool,
+) -> Vec<WorkspaceFolder> {
+    let mut removed = vec![];
+    for &index in indices.iter().rev() {
+        removed.insert(0, workspace.folders.remove(index));
+    }
+    if remember {
+        let mut metadata = workspace.metadata();
+        for folder in removed.iter().filter(|f| !f.path.is_empty()) {
+            let path = normalize_folder_path(&folder.path);
+            if !metadata.excluded_paths.contains(&path) {
+                metadata.excluded_paths.push(path);
+            }
+        }
+        workspace.set_metadata(metadata);
+    }
+    removed
+}
+
+/// A workspace folder as shown by `list`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FolderListing {
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// Whether the directory exists; `None` for uri folders, which aren't checked
+    pub exists: Option<bool>,
+    /// The kinds of project in the directory, e.g. `cargo`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub project_types: Vec<String>,
+}
+
+/// Describes every folder of `workspace`, checking paths against `workspace_dir`.
+pub fn list_folders(workspace: &WorkspaceFile, workspace_dir: &Path) -> Vec<FolderListing> {
+    workspace
+        .folders
+        .iter()
+        .map(|folder| {
+            let dir = folder.resolve(workspace_dir);
+            FolderListing {
+                name: folder.name.clone(),
+                path: folder.path.clone(),
+                uri: folder.uri.clone(),
+                exists: dir.as_ref().map(|dir| dir.is_dir()),
+                project_types: dir
+                    .map(|dir| Ecosystem::detect_all(&dir))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|ecosystem| ecosystem.name().to_string())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// A directory of the tree shown by `list --tree`, named by one component of the
+/// normalized paths of the folders at or below it.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FolderTreeNode {
+    /// The path component; `/` for the root of absolute paths, and the whole uri of
+    /// uri folders
+    pub label: String,
+    /// The folders at exactly this path, usually none or one
+    pub folders: Vec<FolderListing>,
+    /// The directories below, ordered by label
+    pub children: Vec<FolderTreeNode>,
+    /// How many folders below were left out by the depth limit
+    pub collapsed: usize,
+}
+
+/// Groups `listing` into a tree by the components of the folders' normalized paths,
+/// e.g. `services` with `api` and `web` below it for `services/api` and
+/// `./services/web`. With `depth`, directories more than `depth` levels down are left
+/// out, and counted in the `collapsed` of the directory above them.
+pub fn folder_tree(listing: &[FolderListing], depth: Option<usize>) -> Vec<FolderTreeNode> {
+    let mut roots: Vec<FolderTreeNode> = vec![];
+    for folder in listing {
+        let components: Vec<String> = match &folder.uri {
+            Some(uri) => vec![uri.clone()],
+            None => {
+                let normalized = normalize_folder_path(&folder.path);
+                let mut components: Vec<String> = vec![];
+                if let Some(relative) = normalized.strip_prefix('/') {
+                    components.push("/".to_string());
+                    components.extend(
+                        relative
+                            .split('/')
+                            .filter(|part| !part.is_empty())
+                            .map(String::from),
+                    );
+                } else {
+                    components.extend(normalized.split('/').map(String::from));
+                }
+                components
+            }
+        };
+
+        let mut nodes = &mut roots;
+        for (level, component) in components.iter().enumerate() {
+            let index = match nodes.iter().position(|node| &node.label == component) {
+                Some(index) => index,
+                None => {
+                    nodes.push(FolderTreeNode {
+                        label: component.clone(),
+                        folders: vec![],
+                        children: vec![],
+                        collapsed: 0,
+                    });
+                    nodes.len() - 1
+                }
+            };
+            let node = &mut nodes[index];
+            if depth.is_some_and(|depth| level + 1 >= depth) && level + 1 < components.len() {
+                node.collapsed += 1;
+                break;
+            }
+            if level + 1 == components.len() {
+                node.folders.push(folder.clone());
+            }
+            nodes = &mut node.children;
+        }
+    }
+    sort_tree(&mut roots);
+    roots
+}
+
+fn sort_tree(nodes: &mut [FolderTreeNode]) {
+    nodes.sort_by(|a, b| a.label.cmp(&b.label));
+    for node in nodes {
+        sort_tree(&mut node.children);
+    }
+}
+
+/// Appends `folder` to `workspace` with a path relative to `workspace_dir`, the
+/// directory containing the workspace file.
+///
+/// Fails when the path is already present unless `force` is set.
+pub fn add_folder(
+    workspace: &mut WorkspaceFile,
+    workspace_dir: &Path,
+    folder: &Path,
+    name: Option<&str>,
+    force: bool,
+) -> Result<WorkspaceFolder> {
+    let relative =
+        pathdiff::diff_paths(folder, workspace_dir).context("Failed to calculate relative path")?;
+    let path = match relative.to_string_lossy().as_ref() {
+        "" => ".".to_string(),
+        relative => relative.to_string(),
+    };
+
+    let normalized = normalize_folder_path(&path);
+    if !force
+        && workspace.folders.iter().any(|existing| {
+            !existing.path.is_empty() && normalize_folder_path(&existing.path) == normalized
+        })
+    {
+        return Err(WorkspaceError::FolderExists(path));
+    }
+
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => format!(
+            "📦 {}",
+            folder
+                .file_name()
+                .context("Invalid folder name")?
+                .to_string_lossy()
+        ),
+    };
+
+    // Adding a folder explicitly undoes an earlier --remember exclusion, and keeps
+    // it through regeneration
+    let mut metadata = workspace.metadata();
+    metadata
+        .excluded_paths
+        .retain(|excluded| excluded != &normalized);
+    if !metadata.added_paths.contains(&normalized) {
+        metadata.added_paths.push(normalized);
+    }
+    workspace.set_metadata(metadata);
+
+    let entry = WorkspaceFolder::new(path, name);
+    workspace.folders.push(entry.clone());
+    Ok(entry)
+}
+
+/// A display name without its leading emoji prefix, e.g. `api` for `📦 api`.
+pub fn strip_name_prefix(name: &str) -> &str {
+    match name.split_once(' ') {
+        Some((prefix, rest)) if !prefix.chars().any(char::is_alphanumeric) => rest,
+        _ => name,
+    }
+}
+
+/// How [`select_folders`] identifies workspace folders.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FolderSelector {
+    /// Display name, matched exactly or without the emoji prefix
+    Name(String),
+    /// Any of these paths, compared after [`normalize_folder_path`]
+    Path(Vec<String>),
+}
+
+/// Indices of the folders matching `selector`.
+///
+/// An exact display name match wins over prefix-less matches; several prefix-less
+/// matches are ambiguous and produce an error listing the candidates.
+pub fn select_folders(workspace: &WorkspaceFile, selector: &FolderSelector) -> Result<Vec<usize>> {
+    let indices: Vec<usize> = match selector {
+        FolderSelector::Path(paths) => {
+            let paths: Vec<String> = paths.iter().map(|p| normalize_folder_path(p)).collect();
+            workspace
+                .folders
+                .iter()
+                .enumerate()
+                .filter(|(_, folder)| {
+                    !folder.path.is_empty() && paths.contains(&normalize_folder_path(&folder.path))
+                })
+                .map(|(index, _)| index)
+                .collect()
+        }
+        FolderSelector::Name(name) => {
+            let exact: Vec<usize> = workspace
+                .folders
+                .iter()
+                .enumerate()
+                .filter(|(_, folder)| folder.name == *name)
+                .map(|(index, _)| index)
+                .collect();
+            if !exact.is_empty() {
+                return Ok(exact);
+            }
+
+            let loose: Vec<usize> = workspace
+                .folders
+                .iter()
+                .enumerate()
+                .filter(|(_, folder)| strip_name_prefix(&folder.name) == strip_name_prefix(name))
+                .map(|(index, _)| index)
+                .collect();
+            if loose.len() > 1 {
+                return Err(ambiguous(workspace, name, &loose));
+            }
+            loose
+        }
+    };
+    Ok(indices)
+}
+
+/// The error for `selector` matching the folders at `indices` where one was expected.
+fn ambiguous(workspace: &WorkspaceFile, selector: &str, indices: &[usize]) -> WorkspaceError {
+    let candidates = indices
+        .iter()
+        .map(|&index| {
+            let folder = &workspace.folders[index];
+            format!("'{}' ({})", folder.name, folder.path)
+        })
+        .collect();
+    WorkspaceError::AmbiguousFolder {
+        selector: selector.to_string(),
+        candidates,
+    }
+}
+
+/// Removes the folders matching `selector`, returning the removed entries.
+pub fn remove_folders(
+    workspace: &mut WorkspaceFile,
+    selector: &FolderSelector,
+) -> Result<Vec<WorkspaceFolder>> {
+    let indices = select_folders(workspace, selector)?;
+    let mut removed = vec![];
+    for index in indices.into_iter().rev() {
+        removed.insert(0, workspace.folders.remove(index));
+    }
+    Ok(removed)
+}
+
+/// Merges the entries of `workspace` that are one folder written in several ways
+/// (see [`duplicate_folders`]) into the first of them, whose path is normalized. It
+/// takes the first name without the generated emoji prefix, which the user chose,
+/// or else the first name, and the keys other than path and name of each entry,
+/// the earlier entries winning. Returns the merged folders by normalized path.
+pub fn merge_duplicate_folders(workspace: &mut WorkspaceFile) -> Vec<String> {
+    let mut merged = vec![];
+    let mut dropped = vec![];
+    for group in duplicate_folders(&workspace.folders) {
+        let entries: Vec<&WorkspaceFolder> = group
+            .iter()
+            .map(|index| &workspace.folders[*index])
+            .collect();
+        let names = || {
+            entries
+                .iter()
+                .map(|entry| entry.name.as_str())
+                .filter(|name| !name.is_empty())
+        };
+        let name = names()
+            .find(|name| name_prefix(name).is_none())
+            .or_else(|| names().next())
+            .unwrap_or_default()
+            .to_string();
+        let mut other = serde_json::Map::new();
+        for entry in &entries {
+            for (key, value) in &entry.other {
+                other.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+        }
+
+        let folder = &mut workspace.folders[group[0]];
+        if folder.uri.is_none() {
+            folder.path = normalize_folder_path(&folder.path);
+        }
+        folder.name = name;
+        folder.other = other;
+        merged.push(folder_key(folder));
+        dropped.extend_from_slice(&group[1..]);
+    }
+    let mut index = 0;
+    workspace.folders.retain(|_| {
+        index += 1;
+        !dropped.contains(&(index - 1))
+    });
+    merged
+}
+
+/// Removes folders whose directory no longer exists, resolving relative paths
+/// against `workspace_dir`. Folders given by uri are never removed.
+pub fn remove_missing_folders(
+    workspace: &mut WorkspaceFile,
+    workspace_dir: &Path,
+) -> Vec<WorkspaceFolder> {
+    let (kept, removed) =
+        workspace
+            .folders
+            .drain(..)
+            .partition(|folder| match folder.resolve(workspace_dir) {
+                Some(path) => path.is_dir(),
+                None => true,
+            });
+    workspace.folders = kept;
+    removed
+}
+
+/// Resolves `path` against `dir`, removing `.` and `..` components lexically.
+pub fn absolutize(dir: &Path, path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in dir.join(path).components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+/// The values `export` prints for `workspace`, plus the uri folders that were
+/// skipped because `include_uris` is false.
+pub fn export_folders<'a>(
+    workspace: &'a WorkspaceFile,
+    workspace_dir: &Path,
+    field: ExportField,
+    include_uris: bool,
+) -> (Vec<String>, Vec<&'a WorkspaceFolder>) {
+    let mut values = vec![];
+    let mut skipped = vec![];
+    for folder in &workspace.folders {
+        if field == ExportField::Name {
+            values.push(folder.name.clone());
+            continue;
+        }
+        match (&folder.uri, field) {
+            (Some(uri), _) if include_uris => values.push(uri.clone()),
+            (Some(_), _) => skipped.push(folder),
+            (None, ExportField::RelativePath) => values.push(folder.path.clone()),
+            (None, _) => values.push(
+                absolutize(workspace_dir, Path::new(&folder.path))
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+        }
+    }
+    (values, skipped)
+}
+
+/// Joins exported values in the given format.
+pub fn format_export(values: &[String], format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Json => {
+            let json = serde_json::to_string_pretty(values).map_err(anyhow::Error::from)?;
+            format!("{}\n", json)
+        }
+        ExportFormat::Lines => values.iter().map(|value| format!("{}\n", value)).collect(),
+        ExportFormat::Nul => values.iter().map(|value| format!("{}\0", value)).collect(),
+    })
+}
+
+/// The leading emoji prefix of a display name, if it has one.
+pub fn name_prefix(name: &str) -> Option<&str> {
+    let stripped = strip_name_prefix(name);
+    (stripped.len() != name.len()).then(|| name[..name.len() - stripped.len()].trim_end())
+}
+
+/// Renames the single folder matching `selector` and records it as user-customized
+/// so regeneration keeps the new name. Returns the old and new display names.
+pub fn rename_folder(
+    workspace: &mut WorkspaceFile,
+    selector: &FolderSelector,
+    new_name: &str,
+    keep_prefix: bool,
+) -> Result<(String, String)> {
+    let indices = select_folders(workspace, selector)?;
+    let index = match indices.as_slice() {
+        [index] => *index,
+        [] => return Err(WorkspaceError::NoMatchingFolder),
+        _ => {
+            let selector = match selector {
+                FolderSelector::Name(name) => name.clone(),
+                FolderSelector::Path(paths) => paths.join(", "),
+            };
+            return Err(ambiguous(workspace, &selector, &indices));
+        }
+    };
+
+    let old_name = workspace.folders[index].name.clone();
+    let new_name = match name_prefix(&old_name) {
+        Some(prefix) if keep_prefix => format!("{} {}", prefix, strip_name_prefix(new_name)),
+        _ => new_name.to_string(),
+    };
+    if let Some(other) = workspace
+        .folders
+        .iter()
+        .enumerate()
+        .find(|(other, folder)| *other != index && folder.name == new_name)
+    {
+        return Err(WorkspaceError::NameTaken {
+            path: other.1.path.clone(),
+            name: new_name,
+        });
+    }
+
+    workspace.folders[index].name = new_name.clone();
+    let path = normalize_folder_path(&workspace.folders[index].path);
+    let mut metadata = workspace.metadata();
+    if !metadata.custom_names.contains(&path) {
+        metadata.custom_names.push(path);
+    }
+    workspace.set_metadata(metadata);
+
+    Ok((old_name, new_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{read_workspace, write_workspace, WriteOptions};
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_add_folder_to_empty_workspace() -> Result<()> {
+        let temp = TempDir::new()?;
+        let file = temp.path().join("ws.code-workspace");
+        fs::write(
+            &file,
+            r#"{"folders": [], "settings": {"editor.tabSize": 2}}"#,
+        )?;
+        fs::create_dir(temp.path().join("protos"))?;
+
+        let mut workspace = read_workspace(&file)?;
+        let added = add_folder(
+            &mut workspace,
+            temp.path(),
+            &temp.path().join("protos"),
+            Some("Protos"),
+            false,
+        )?;
+        write_workspace(&file, &workspace, &WriteOptions::default())?;
+
+        assert_eq!(added.path, "protos");
+        let written = read_workspace(&file)?;
+        assert_eq!(
+            written.folders,
+            vec![WorkspaceFolder::new("protos", "Protos")]
+        );
+        assert_eq!(written.settings.as_ref().unwrap()["editor.tabSize"], 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_duplicate_folder() -> Result<()> {
+        let temp = TempDir::new()?;
+        let mut workspace: WorkspaceFile =
+            serde_json::from_str(r#"{"folders": [{"path": "./api/"}]}"#)?;
+        let api = temp.path().join("api");
+
+        let err = add_folder(&mut workspace, temp.path(), &api, None, false).unwrap_err();
+        assert!(err.to_string().contains("already in the workspace"));
+        assert_eq!(workspace.folders.len(), 1);
+
+        add_folder(&mut workspace, temp.path(), &api, None, true)?;
+        assert_eq!(workspace.folders.len(), 2);
+        assert_eq!(workspace.folders[1].name, "📦 api");
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_absolute_folder_is_relativized() -> Result<()> {
+        let temp = TempDir::new()?;
+        let workspace_dir = temp.path().join("workspaces");
+        let shared = temp.path().join("shared-protos");
+        let mut workspace = WorkspaceFile::default();
+
+        let added = add_folder(&mut workspace, &workspace_dir, &shared, None, false)?;
+
+        assert_eq!(Path::new(&added.path), Path::new("../shared-protos"));
+        assert_eq!(added.name, "📦 shared-protos");
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_folders_by_name() -> Result<()> {
+        let workspace: WorkspaceFile = serde_json::from_str(
+            r#"{"folders": [
+                {"path": ".", "name": "🏗️ api"},
+                {"path": "services/api", "name": "📦 api"},
+                {"path": "web", "name": "📦 web"}
+            ]}"#,
+        )?;
+
+        let by_name = |name: &str| select_folders(&workspace, &FolderSelector::Name(name.into()));
+        assert_eq!(by_name("web")?, vec![2]);
+        assert_eq!(by_name("📦 web")?, vec![2]);
+        assert_eq!(by_name("📦 api")?, vec![1]);
+        assert!(by_name("missing")?.is_empty());
+
+        let err = by_name("api").unwrap_err().to_string();
+        assert!(err.contains("services/api"), "{}", err);
+        assert!(err.contains("🏗️ api"), "{}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_folders_by_path() -> Result<()> {
+        let mut workspace: WorkspaceFile = serde_json::from_str(
+            r#"{"folders": [{"path": "services/api", "name": "a"}, {"path": "web", "name": "w"}]}"#,
+        )?;
+
+        let removed = remove_folders(
+            &mut workspace,
+            &FolderSelector::Path(vec!["./services/api/".into()]),
+        )?;
+
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].name, "a");
+        assert_eq!(workspace.folders.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_folder() -> Result<()> {
+        let mut workspace: WorkspaceFile = serde_json::from_str(
+            r#"{"folders": [{"path": "./api", "name": "📦 api"}, {"path": "web", "name": "📦 web"}]}"#,
+        )?;
+
+        let (old, new) = rename_folder(
+            &mut workspace,
+            &FolderSelector::Name("api".into()),
+            "Backend",
+            true,
+        )?;
+        assert_eq!(old, "📦 api");
+        assert_eq!(new, "📦 Backend");
+        assert_eq!(workspace.metadata().custom_names, vec!["api"]);
+
+        let err = rename_folder(
+            &mut workspace,
+            &FolderSelector::Path(vec!["web".into()]),
+            "📦 Backend",
+            false,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("already named"));
+
+        rename_folder(
+            &mut workspace,
+            &FolderSelector::Path(vec!["web".into()]),
+            "Frontend",
+            false,
+        )?;
+        assert_eq!(workspace.folders[1].name, "Frontend");
+        assert_eq!(workspace.metadata().custom_names, vec!["api", "web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_folders_remembers_paths() -> Result<()> {
+        let temp = TempDir::new()?;
+        let mut workspace = WorkspaceFile {
+            folders: vec![
+                WorkspaceFolder::new(".", "🏗️ root"),
+                WorkspaceFolder::new("./api/", "📦 api"),
+                WorkspaceFolder::new("web", "📦 web"),
+            ],
+            ..Default::default()
+        };
+
+        let removed = exclude_folders(&mut workspace, &[1, 2], true);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(workspace.folders.len(), 1);
+        assert_eq!(workspace.metadata().excluded_paths, vec!["api", "web"]);
+
+        // Adding a folder back explicitly lifts its exclusion
+        add_folder(
+            &mut workspace,
+            temp.path(),
+            &temp.path().join("api"),
+            None,
+            false,
+        )?;
+        assert_eq!(workspace.metadata().excluded_paths, vec!["web"]);
+
+        let mut forgetful = WorkspaceFile {
+            folders: vec![WorkspaceFolder::new("api", "📦 api")],
+            ..Default::default()
+        };
+        exclude_folders(&mut forgetful, &[0], false);
+        assert!(forgetful.other.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_duplicate_folders() -> Result<()> {
+        let mut workspace = crate::io::parse_workspace(
+            r#"{"folders": [
+                {"path": ".", "name": "🏗️ team"},
+                {"path": "./services", "name": "📦 services"},
+                {"path": "services/", "name": "Services", "color": "blue"},
+                {"path": "./services/api", "name": "📦 api", "color": "red"},
+                {"path": "services/./api/", "name": "📦 api (old)", "pinned": true},
+                {"path": "./services/web/"},
+                {"path": "services/web"}
+            ]}"#,
+        )?;
+
+        let merged = merge_duplicate_folders(&mut workspace);
+
+        assert_eq!(merged, ["services", "services/api", "services/web"]);
+        let folders = serde_json::to_value(&workspace.folders)?;
+        assert_eq!(
+            folders,
+            serde_json::json!([
+                {"path": ".", "name": "🏗️ team"},
+                {"path": "services", "name": "Services", "color": "blue"},
+                {"path": "services/api", "name": "📦 api", "color": "red", "pinned": true},
+                {"path": "services/web"}
+            ])
+        );
+        assert!(merge_duplicate_folders(&mut workspace).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_folders() -> Result<()> {
+        let temp = TempDir::new()?;
+        fs::create_dir(temp.path().join("api"))?;
+        fs::write(temp.path().join("api").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("api").join("package.json"), "{}")?;
+        let workspace = crate::io::parse_workspace(
+            r#"{"folders": [
+                {"path": "api", "name": "📦 api"},
+                {"path": "gone", "name": "📦 gone"},
+                {"uri": "vscode-remote://ssh-remote+host/srv", "name": "srv"}
+            ]}"#,
+        )?;
+        let listing = list_folders(&workspace, temp.path());
+        assert_eq!(listing[0].exists, Some(true));
+        assert_eq!(listing[0].project_types, ["cargo", "npm"]);
+        assert_eq!(listing[1].exists, Some(false));
+        assert!(listing[1].project_types.is_empty());
+        assert_eq!(listing[2].exists, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_folder_tree() {
+        let listing: Vec<FolderListing> = [
+            "services/web",
+            "./services/api",
+            "libs/core",
+            "services",
+            "/srv/data",
+            "services/api/plugins/auth",
+        ]
+        .iter()
+        .map(|path| FolderListing {
+            name: path.to_string(),
+            path: path.to_string(),
+            uri: None,
+            exists: Some(true),
+            project_types: vec![],
+        })
+        .collect();
+        fn labels(nodes: &[FolderTreeNode]) -> Vec<&str> {
+            nodes.iter().map(|node| node.label.as_str()).collect()
+        }
+
+        let tree = folder_tree(&listing, None);
+        assert_eq!(labels(&tree), ["/", "libs", "services"]);
+        assert_eq!(labels(&tree[0].children), ["srv"]);
+        assert_eq!(tree[0].children[0].children[0].folders[0].path, "/srv/data");
+        // A directory that is a folder itself and has folders below it
+        let services = &tree[2];
+        assert_eq!(services.folders[0].path, "services");
+        assert_eq!(labels(&services.children), ["api", "web"]);
+        assert_eq!(services.children[0].folders[0].path, "./services/api");
+        assert!(tree[1].folders.is_empty());
+        assert_eq!(tree[1].collapsed, 0);
+
+        let tree = folder_tree(&listing, Some(2));
+        let services = &tree[2];
+        assert_eq!(labels(&services.children), ["api", "web"]);
+        assert!(services.children[0].children.is_empty());
+        assert_eq!(services.children[0].collapsed, 1);
+        assert_eq!(services.children[0].folders.len(), 1);
+        let tree = folder_tree(&listing, Some(1));
+        assert!(tree[2].children.is_empty());
+        assert_eq!(tree[2].collapsed, 3);
+        assert_eq!(tree[2].folders.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_missing_folders() -> Result<()> {
+        let temp = TempDir::new()?;
+        let kept_absolute = temp.path().join("kept-absolute");
+        fs::create_dir(&kept_absolute)?;
+        fs::create_dir(temp.path().join("kept"))?;
+        let mut workspace: WorkspaceFile = serde_json::from_value(serde_json::json!({
+            "folders": [
+                {"path": "kept", "name": "Kept"},
+                {"path": "gone", "name": "Gone"},
+                {"path": kept_absolute, "name": "Absolute"},
+                {"path": temp.path().join("gone-absolute"), "name": "Gone absolute"},
+                {"uri": "file:///does/not/exist", "name": "Uri"}
+            ],
+            "settings": {"z": 1, "a": 2}
+        }))?;
+
+        let removed = remove_missing_folders(&mut workspace, temp.path());
+
+        let names = |folders: &[WorkspaceFolder]| -> Vec<String> {
+            folders.iter().map(|f| f.name.clone()).collect()
+        };
+        assert_eq!(names(&removed), vec!["Gone", "Gone absolute"]);
+        assert_eq!(names(&workspace.folders), vec!["Kept", "Absolute", "Uri"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_folders() -> Result<()> {
+        let workspace: WorkspaceFile = serde_json::from_str(
+            r#"{"folders": [
+                {"path": ".", "name": "Root"},
+                {"path": "../shared/./protos", "name": "Protos"},
+                {"uri": "vscode-remote://ssh-remote+box/srv", "name": "Remote"}
+            ]}"#,
+        )?;
+        let dir = Path::new("/work/project");
+
+        let (values, skipped) = export_folders(&workspace, dir, ExportField::AbsolutePath, false);
+        assert_eq!(values, vec!["/work/project", "/work/shared/protos"]);
+        assert_eq!(skipped.len(), 1);
+
+        let (values, skipped) = export_folders(&workspace, dir, ExportField::RelativePath, true);
+        assert_eq!(
+            values,
+            vec![
+                ".",
+                "../shared/./protos",
+                "vscode-remote://ssh-remote+box/srv"
+            ]
+        );
+        assert!(skipped.is_empty());
+
+        let (values, _) = export_folders(&workspace, dir, ExportField::Name, false);
+        assert_eq!(values, vec!["Root", "Protos", "Remote"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_export() -> Result<()> {
+        let values = vec!["a b".to_string(), "c".to_string()];
+        assert_eq!(format_export(&values, ExportFormat::Lines)?, "a b\nc\n");
+        assert_eq!(format_export(&values, ExportFormat::Nul)?, "a b\0c\0");
+        let json: Vec<String> = serde_json::from_str(&format_export(&values, ExportFormat::Json)?)?;
+        assert_eq!(json, values);
+        Ok(())
+    }
+}
@@ -0,0 +1,630 @@
+//! Git submodules, worktrees, when their HEAD last moved and where they were cloned
+//! from, read from `.gitmodules` and the `.git` directory without running git, and
+//! working tree status, which does need git.
+
+use crate::cargo::lexical;
+use crate::console;
+use crate::{UninitializedSubmodules, WorkspaceMember};
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long `git status` may take in one repository before it is given up on.
+pub const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A `[submodule "name"]` section of `.gitmodules`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submodule {
+    pub name: String,
+    /// Path of the submodule relative to the repository root, as written in the file
+    pub path: String,
+}
+
+/// The submodules listed in the `.gitmodules` `content`, in file order. Sections
+/// without a `path` are skipped, and so is anything that doesn't look like a key or a
+/// section header. Keys are case-insensitive and values may be quoted as git allows.
+pub fn parse_gitmodules(content: &str) -> Vec<Submodule> {
+    let mut submodules: Vec<Submodule> = vec![];
+    let mut section: Option<Submodule> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            submodules.extend(section.take().filter(|s| !s.path.is_empty()));
+            let Some(header) = header.split(']').next() else {
+                continue;
+            };
+            let (kind, name) = header
+                .split_once(char::is_whitespace)
+                .unwrap_or((header, ""));
+            if kind.eq_ignore_ascii_case("submodule") {
+                section = Some(Submodule {
+                    name: value(name),
+                    path: String::new(),
+                });
+            }
+            continue;
+        }
+        let (Some(submodule), Some((key, raw))) = (section.as_mut(), line.split_once('=')) else {
+            continue;
+        };
+        if key.trim().eq_ignore_ascii_case("path") {
+            submodule.path = value(raw);
+        }
+    }
+    submodules.extend(section.filter(|s| !s.path.is_empty()));
+    submodules
+}
+
+/// The first `key` of the `[kind "name"]` section of the git config `content`, e.g.
+/// `url` of `[remote "origin"]`. Section kinds and keys are case-insensitive, names
+/// aren't, as git has them.
+pub fn config_value(content: &str, kind: &str, name: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.split(']').next().unwrap_or_default();
+            let (header_kind, header_name) = header
+                .split_once(char::is_whitespace)
+                .unwrap_or((header, ""));
+            in_section = header_kind.eq_ignore_ascii_case(kind) && value(header_name) == name;
+            continue;
+        }
+        let Some((line_key, raw)) = line.split_once('=') else {
+            continue;
+        };
+        if in_section && line_key.trim().eq_ignore_ascii_case(key) {
+            return Some(value(raw));
+        }
+    }
+    None
+}
+
+/// The URL of the `origin` remote of the worktree at `dir`, from the config of its
+/// repository, or `None` when `dir` isn't the top of a worktree or has no origin.
+pub fn origin_url(dir: &Path) -> Option<String> {
+    let (_, common) = git_dirs(dir)?;
+    let config = fs::read_to_string(common.join("config")).ok()?;
+    config_value(&config, "remote", "origin", "url").filter(|url| !url.is_empty())
+}
+
+/// A git config value: quotes removed, `\"`, `\\`, `\t` and `\n` unescaped, and a
+/// `#` or `;` comment outside quotes cut off.
+fn value(raw: &str) -> String {
+    let mut result = String::new();
+    let mut quoted = false;
+    let mut chars = raw.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' => match chars.next() {
+                Some('t') => result.push('\t'),
+                Some('n') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {}
+            },
+            '#' | ';' if !quoted => break,
+            c => result.push(c),
+        }
+    }
+    result.trim_end().to_string()
+}
+
+/// The submodules of the repository at `root` with their resolved directories, or
+/// nothing when it has no `.gitmodules`. Submodules outside `root` are skipped.
+pub fn submodules(root: &Path) -> Result<Vec<(Submodule, PathBuf)>> {
+    let path = root.join(".gitmodules");
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let root = lexical(root);
+    Ok(parse_gitmodules(&content)
+        .into_iter()
+        .filter_map(|submodule| {
+            let dir = lexical(&root.join(&submodule.path));
+            (dir != root && dir.starts_with(&root)).then_some((submodule, dir))
+        })
+        .collect())
+}
+
+/// Whether the submodule checked out at `dir` is initialized. `git submodule update
+/// --init` fills the directory, which is left empty until then.
+pub fn is_initialized(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+/// The submodules of the repository at `root` as workspace members named after their
+/// directories. Uninitialized submodules are handled as `uninitialized` says; missing
+/// directories are always skipped.
+pub fn submodule_members(
+    root: &Path,
+    uninitialized: UninitializedSubmodules,
+) -> Result<Vec<WorkspaceMember>> {
+    let mut members = vec![];
+    for (submodule, dir) in submodules(root)? {
+        if !dir.is_dir() {
+            continue;
+        }
+        if !is_initialized(&dir) {
+            match uninitialized {
+                UninitializedSubmodules::Skip => continue,
+                UninitializedSubmodules::Warn => {
+                    console::warning(format_args!(
+                        "skipping uninitialized submodule {} (run `git submodule \
+                         update --init`)",
+                        dir.display()
+                    ));
+                    continue;
+                }
+                UninitializedSubmodules::Include => {}
+            }
+        }
+        let package = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(submodule.name);
+        members.push(WorkspaceMember { dir, package });
+    }
+    Ok(members)
+}
+
+/// The git worktree checked out at a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Worktree {
+    /// The branch checked out, or the abbreviated commit when HEAD is detached
+    pub head: String,
+    /// The directory of the main worktree; the worktree's own directory unless linked
+    pub main: PathBuf,
+    /// Whether this is a linked worktree made by `git worktree add`
+    pub linked: bool,
+}
+
+/// The worktree checked out at `dir`, or `None` when `dir` is not the top of one. A
+/// linked worktree has a `.git` file pointing at its git directory inside the main
+/// repository, whose `commondir` leads back to the main `.git`.
+pub fn worktree(dir: &Path) -> Option<Worktree> {
+    let (git_dir, common) = git_dirs(dir)?;
+    // A submodule's .git file points at .git/modules/<name>, which has no commondir
+    let (main, linked) = if common == git_dir {
+        (dir.to_path_buf(), false)
+    } else {
+        let main = match common.file_name() {
+            Some(name) if name == ".git" => common.parent()?.to_path_buf(),
+            _ => common.clone(),
+        };
+        (main, true)
+    };
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    let head = match head.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string()
+        }
+        None => head.chars().take(8).collect(),
+    };
+    Some(Worktree { head, main, linked })
+}
+
+/// The git directory of the worktree at `dir` and the common one holding its refs,
+/// which differ only for a linked worktree.
+fn git_dirs(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    let dot_git = dir.join(".git");
+    if dot_git.is_dir() {
+        return Some((dot_git.clone(), dot_git));
+    }
+    let content = fs::read_to_string(&dot_git).ok()?;
+    let git_dir = lexical(&dir.join(content.trim().strip_prefix("gitdir:")?.trim()));
+    let common = match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => lexical(&git_dir.join(common.trim())),
+        Err(_) => git_dir.clone(),
+    };
+    Some((git_dir, common))
+}
+
+/// When the HEAD of the worktree at `dir` last moved, by the modification time of
+/// the branch it is on, which each commit rewrites, or of `packed-refs` when the
+/// branch is packed. A detached HEAD counts from its own file. `None` when `dir`
+/// isn't the top of a worktree or has no commits.
+pub fn last_activity(dir: &Path) -> Option<SystemTime> {
+    let (git_dir, common) = git_dirs(dir)?;
+    let head = git_dir.join("HEAD");
+    let file = match fs::read_to_string(&head).ok()?.trim().strip_prefix("ref:") {
+        Some(reference) => {
+            let loose = common.join(reference.trim());
+            match loose.is_file() {
+                true => loose,
+                false => common.join("packed-refs"),
+            }
+        }
+        None => head,
+    };
+    fs::metadata(file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Whether the working tree at `dir` has uncommitted changes, untracked files
+/// included, by `git status --porcelain`. Fails when git can't be run, exits with an
+/// error or takes longer than `timeout`.
+pub fn is_dirty(dir: &Path, timeout: Duration) -> Result<bool> {
+    let mut child = Command::new("git")
+        .args(["--no-optional-locks", "status", "--porcelain"])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to run git")?;
+    // Read as it comes, so a long listing can't fill the pipe and stall git
+    let mut stdout = child.stdout.take().context("Failed to read git output")?;
+    let reader = thread::spawn(move || {
+        let mut output = vec![];
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("git status timed out after {}s", timeout.as_secs());
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let output = reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("Failed to read git output"))??;
+    if !status.success() {
+        anyhow::bail!("git status failed ({})", status);
+    }
+    Ok(!output.is_empty())
+}
+
+/// [`is_dirty`] for each of `dirs`, checked on several threads at once. The results
+/// are in the order of `dirs`.
+pub fn dirty_statuses(dirs: &[PathBuf], timeout: Duration) -> Vec<Result<bool>> {
+    in_parallel(dirs, |dir| is_dirty(dir, timeout))
+        .into_iter()
+        .map(|result| result.unwrap_or_else(|| Err(anyhow::anyhow!("git status failed"))))
+        .collect()
+}
+
+/// [`last_activity`] for each of `dirs`, read on several threads at once, which pays
+/// off on network file systems. The results are in the order of `dirs`.
+pub fn last_activities(dirs: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    in_parallel(dirs, last_activity)
+        .into_iter()
+        .map(Option::flatten)
+        .collect()
+}
+
+/// `check` for each of `dirs` on several threads, in the order of `dirs`; `None` for
+/// those whose thread panicked.
+fn in_parallel<T: Send>(dirs: &[PathBuf], check: impl Fn(&Path) -> T + Sync) -> Vec<Option<T>> {
+    let workers = thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .min(dirs.len());
+    let next = AtomicUsize::new(0);
+    let done: Vec<(usize, T)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(dir) = dirs.get(index) else {
+                            break done;
+                        };
+                        done.push((index, check(dir)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+    let mut results: Vec<Option<T>> = dirs.iter().map(|_| None).collect();
+    for (index, result) in done {
+        results[index] = Some(result);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_config_value() {
+        let content = r#"
+[core]
+	bare = false
+[remote "upstream"]
+	url = git@example.com:upstream/api.git
+[Remote "origin"]
+	fetch = +refs/heads/*:refs/remotes/origin/*
+	URL = "git@example.com:org/api.git" ; the fork
+	url = https://example.com/org/api.git
+[branch "main"]
+	remote = origin
+"#;
+        let origin = config_value(content, "remote", "origin", "url");
+        assert_eq!(origin.as_deref(), Some("git@example.com:org/api.git"));
+        assert_eq!(config_value(content, "remote", "ORIGIN", "url"), None);
+        assert_eq!(
+            config_value(content, "branch", "main", "remote").as_deref(),
+            Some("origin")
+        );
+        assert_eq!(config_value(content, "core", "", "url"), None);
+    }
+
+    #[test]
+    fn test_origin_url() -> Result<()> {
+        let temp = TempDir::new()?;
+        let repo = temp.path().join("api");
+        fs::create_dir_all(repo.join(".git"))?;
+        fs::write(
+            repo.join(".git").join("config"),
+            "[remote \"origin\"]\n\turl = git@example.com:org/api.git\n",
+        )?;
+        assert_eq!(
+            origin_url(&repo).as_deref(),
+            Some("git@example.com:org/api.git")
+        );
+
+        // A linked worktree shares the config of its main repository
+        let linked = temp.path().join("api-hotfix");
+        let git_dir = repo.join(".git").join("worktrees").join("api-hotfix");
+        fs::create_dir_all(&git_dir)?;
+        fs::write(git_dir.join("commondir"), "../..")?;
+        fs::create_dir(&linked)?;
+        fs::write(
+            linked.join(".git"),
+            format!("gitdir: {}", git_dir.display()),
+        )?;
+        assert_eq!(
+            origin_url(&linked).as_deref(),
+            Some("git@example.com:org/api.git")
+        );
+
+        fs::create_dir_all(temp.path().join("local").join(".git"))?;
+        assert_eq!(origin_url(&temp.path().join("local")), None);
+        assert_eq!(origin_url(temp.path()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_gitmodules() {
+        let content = r#"
+# vendored code
+[submodule "vendor/zlib"]
+	path = vendor/zlib
+	url = https://github.com/madler/zlib.git
+[submodule "docs theme"]
+	url = git@example.com:theme.git ; no path yet
+[core]
+	path = not/a/submodule
+[submodule "third_party/my lib"]
+	Path = "third_party/my lib" # quoted
+	branch = main
+[submodule "odd"]
+	path=third_party/a\"b
+"#;
+        assert_eq!(
+            parse_gitmodules(content),
+            vec![
+                Submodule {
+                    name: "vendor/zlib".to_string(),
+                    path: "vendor/zlib".to_string()
+                },
+                Submodule {
+                    name: "third_party/my lib".to_string(),
+                    path: "third_party/my lib".to_string()
+                },
+                Submodule {
+                    name: "odd".to_string(),
+                    path: "third_party/a\"b".to_string()
+                },
+            ]
+        );
+        assert!(parse_gitmodules("path = loose\n[submodule]\n").is_empty());
+    }
+
+    #[test]
+    fn test_submodule_members() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path();
+        fs::create_dir_all(root.join("vendor/zlib"))?;
+        fs::write(
+            root.join("vendor/zlib/.git"),
+            "gitdir: ../../.git/modules/zlib",
+        )?;
+        fs::create_dir_all(root.join("third_party/empty"))?;
+        fs::write(
+            root.join(".gitmodules"),
+            "[submodule \"zlib\"]\npath = vendor/zlib\n\
+             [submodule \"empty\"]\npath = third_party/empty\n\
+             [submodule \"gone\"]\npath = third_party/gone\n\
+             [submodule \"out\"]\npath = ../elsewhere\n",
+        )?;
+
+        let names = |mode| -> Result<Vec<String>> {
+            Ok(submodule_members(root, mode)?
+                .into_iter()
+                .map(|m| m.package)
+                .collect())
+        };
+        assert_eq!(names(UninitializedSubmodules::Skip)?, vec!["zlib"]);
+        assert_eq!(names(UninitializedSubmodules::Warn)?, vec!["zlib"]);
+        assert_eq!(
+            names(UninitializedSubmodules::Include)?,
+            vec!["zlib", "empty"]
+        );
+        assert!(submodule_members(&root.join("vendor"), UninitializedSubmodules::Skip)?.is_empty());
+        Ok(())
+    }
+
+    /// A main worktree at `root/name` on `branch` and a linked one at `root/linked`.
+    fn worktrees(root: &Path, name: &str, branch: &str, linked: &str, head: &str) -> Result<()> {
+        let git_dir = root.join(name).join(".git");
+        fs::create_dir_all(git_dir.join("worktrees").join(linked))?;
+        fs::write(
+            git_dir.join("HEAD"),
+            format!("ref: refs/heads/{}\n", branch),
+        )?;
+        let linked_git = git_dir.join("worktrees").join(linked);
+        fs::write(linked_git.join("HEAD"), head)?;
+        fs::write(linked_git.join("commondir"), "../..\n")?;
+        fs::create_dir_all(root.join(linked))?;
+        fs::write(
+            root.join(linked).join(".git"),
+            format!("gitdir: ../{}/.git/worktrees/{}\n", name, linked),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_worktree() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path();
+        worktrees(
+            root,
+            "myrepo",
+            "main",
+            "myrepo-hotfix",
+            "ref: refs/heads/hotfix/login\n",
+        )?;
+        worktrees(
+            root,
+            "other",
+            "dev",
+            "other-bisect",
+            "0123456789abcdef0123\n",
+        )?;
+
+        let main = worktree(&root.join("myrepo")).unwrap();
+        assert_eq!(main.head, "main");
+        assert_eq!(main.main, root.join("myrepo"));
+        assert!(!main.linked);
+
+        let linked = worktree(&root.join("myrepo-hotfix")).unwrap();
+        assert_eq!(linked.head, "hotfix/login");
+        assert_eq!(linked.main, root.join("myrepo"));
+        assert!(linked.linked);
+
+        // Detached HEAD, and an absolute gitdir
+        fs::write(
+            root.join("other-bisect/.git"),
+            format!(
+                "gitdir: {}",
+                root.join("other/.git/worktrees/other-bisect").display()
+            ),
+        )?;
+        let detached = worktree(&root.join("other-bisect")).unwrap();
+        assert_eq!(detached.head, "01234567");
+        assert_eq!(detached.main, root.join("other"));
+
+        // Submodules also have a .git file, but are checkouts of their own
+        fs::create_dir_all(root.join("myrepo/.git/modules/lib"))?;
+        fs::write(
+            root.join("myrepo/.git/modules/lib/HEAD"),
+            "ref: refs/heads/main",
+        )?;
+        fs::create_dir_all(root.join("myrepo/lib"))?;
+        fs::write(root.join("myrepo/lib/.git"), "gitdir: ../.git/modules/lib")?;
+        let submodule = worktree(&root.join("myrepo/lib")).unwrap();
+        assert_eq!(submodule.main, root.join("myrepo/lib"));
+        assert!(!submodule.linked);
+        assert_eq!(worktree(root), None);
+        Ok(())
+    }
+
+    /// Sets the modification time of the file at `path` to `secs` after the epoch.
+    fn touch(path: &Path, secs: u64) -> Result<()> {
+        if !path.exists() {
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, "0123456789abcdef0123\n")?;
+        }
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(time)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_activity() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path();
+        worktrees(
+            root,
+            "myrepo",
+            "main",
+            "myrepo-hotfix",
+            "ref: refs/heads/hotfix\n",
+        )?;
+        let at = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+        // No commits on the branch yet
+        assert_eq!(last_activity(&root.join("myrepo")), None);
+        touch(&root.join("myrepo/.git/refs/heads/main"), 1_000)?;
+        assert_eq!(last_activity(&root.join("myrepo")), at(1_000));
+        // The linked worktree's branch is in the main repository, packed
+        touch(&root.join("myrepo/.git/packed-refs"), 2_000)?;
+        assert_eq!(last_activity(&root.join("myrepo-hotfix")), at(2_000));
+        touch(&root.join("myrepo/.git/refs/heads/hotfix"), 3_000)?;
+        assert_eq!(last_activity(&root.join("myrepo-hotfix")), at(3_000));
+        // A detached HEAD moves with its own file
+        fs::write(root.join("myrepo/.git/HEAD"), "0123456789abcdef0123\n")?;
+        touch(&root.join("myrepo/.git/HEAD"), 4_000)?;
+        assert_eq!(last_activity(&root.join("myrepo")), at(4_000));
+
+        let dirs = [root.join("myrepo-hotfix"), root.to_path_buf()];
+        assert_eq!(last_activities(&dirs), vec![at(3_000), None]);
+        Ok(())
+    }
+
+    /// Runs `git init` in `dir`, or returns false when git isn't installed.
+    fn git_init(dir: &Path) -> bool {
+        fs::create_dir_all(dir).unwrap();
+        Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(dir)
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    #[test]
+    fn test_dirty_statuses() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (clean, dirty) = (temp.path().join("clean"), temp.path().join("dirty"));
+        if !git_init(&clean) || !git_init(&dirty) {
+            return Ok(());
+        }
+        fs::write(dirty.join("notes.txt"), "untracked")?;
+        let missing = temp.path().join("missing");
+        let statuses = dirty_statuses(&[dirty, missing, clean], STATUS_TIMEOUT);
+        assert!(statuses[0].as_ref().is_ok_and(|dirty| *dirty));
+        assert!(statuses[1].is_err());
+        assert!(statuses[2].as_ref().is_ok_and(|dirty| !*dirty));
+        assert!(dirty_statuses(&[], STATUS_TIMEOUT).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,930 @@
+//! Reading, writing and locating workspace files. Files are parsed leniently, as VS
+//! Code writes them, and written atomically.
+
+use crate::console;
+use crate::error::{Result, WorkspaceError};
+use crate::filesystem::{FileSystem, RealFs};
+use crate::jsonc;
+use crate::model::{WorkspaceFile, WorkspaceFolder};
+use crate::schema;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reads and parses a workspace file. Comments and trailing commas are accepted,
+/// as VS Code itself allows them.
+pub fn read_workspace(path: &Path) -> Result<WorkspaceFile> {
+    read_workspace_with(&RealFs, path)
+}
+
+/// Like [`read_workspace`], reading from `fs`.
+pub fn read_workspace_with(fs: &dyn FileSystem, path: &Path) -> Result<WorkspaceFile> {
+    let content = fs.read_to_string(path).map_err(WorkspaceError::io(path))?;
+    serde_json::from_str(&jsonc::to_json(&content)).map_err(|source| {
+        WorkspaceError::ParseWorkspace {
+            path: path.to_path_buf(),
+            source,
+        }
+    })
+}
+
+/// Parses workspace file content, accepting comments and trailing commas.
+pub fn parse_workspace(content: &str) -> Result<WorkspaceFile> {
+    serde_json::from_str(&jsonc::to_json(content)).map_err(WorkspaceError::InvalidWorkspace)
+}
+
+/// How [`write_workspace`] writes a file. The default leaves an unchanged file alone
+/// and indents like the existing file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Copy the previous content to `<file>.bak` before replacing it
+    pub backup: bool,
+    /// Leave the file alone when it already has exactly this content, so editors
+    /// watching it see no change
+    pub skip_unchanged: bool,
+    /// Indent like the existing file rather than with two spaces, when `indent` isn't set
+    pub preserve_indent: bool,
+    /// Indentation of the written JSON; when not set, that of the existing file or two
+    /// spaces
+    pub indent: Option<Indent>,
+    /// End the file with a newline
+    pub final_newline: bool,
+    /// Rewrite only the `folders` array, leaving every other byte of the existing
+    /// file, comments included, as it is. The whole file is written when the array
+    /// can't be located.
+    pub folders_only: bool,
+    /// Check the content against the workspace file schema before writing it, and
+    /// fail rather than write content VS Code would reject. Debug builds always check
+    pub validate: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            backup: false,
+            skip_unchanged: true,
+            preserve_indent: true,
+            indent: None,
+            final_newline: false,
+            folders_only: false,
+            validate: false,
+        }
+    }
+}
+
+/// The indentation of written JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    /// That many spaces per level
+    Spaces(usize),
+    /// A tab per level
+    Tabs,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl Indent {
+    /// The whitespace of one level.
+    pub fn unit(self) -> String {
+        match self {
+            Indent::Spaces(width) => " ".repeat(width),
+            Indent::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+/// Serializes `workspace` as pretty-printed JSON and atomically writes it to `path`,
+/// as `options` asks. The top-level keys keep the order they have in the existing
+/// file. Returns whether the file was written.
+pub fn write_workspace(
+    path: &Path,
+    workspace: &WorkspaceFile,
+    options: &WriteOptions,
+) -> Result<bool> {
+    write_workspace_with(&RealFs, path, workspace, options)
+}
+
+/// Like [`write_workspace`], reading and writing `fs`.
+pub fn write_workspace_with(
+    fs: &dyn FileSystem,
+    path: &Path,
+    workspace: &WorkspaceFile,
+    options: &WriteOptions,
+) -> Result<bool> {
+    let existing = fs.read_to_string(path).ok();
+    let folders_only = match (&existing, options.folders_only) {
+        (Some(existing), true) => {
+            let updated = replace_folders_in_text(existing, &workspace.folders)?;
+            if updated.is_none() {
+                console::warning("could not locate the folders array, rewriting the whole file");
+            } else if !only_folders_differ(existing, workspace) {
+                console::warning(format_args!(
+                    "only the folders of {} were rewritten; other changes were left out",
+                    path.display()
+                ));
+            }
+            updated
+        }
+        _ => None,
+    };
+    let content = match folders_only {
+        Some(content) => content,
+        None => {
+            let mut value = serde_json::to_value(workspace).map_err(anyhow::Error::from)?;
+            if let Some(existing) = existing
+                .as_deref()
+                .and_then(|content| serde_json::from_str(&jsonc::to_json(content)).ok())
+            {
+                keep_key_order(&mut value, &existing);
+            }
+            // An indentation asked for wins over the existing file's, so changing it
+            // rewrites the file
+            let indent = match (options.indent, &existing, options.preserve_indent) {
+                (Some(indent), _, _) => Some(indent.unit()),
+                (None, Some(existing), true) => detect_indent(existing),
+                _ => None,
+            };
+            let indent = indent.unwrap_or_else(|| Indent::default().unit());
+            let mut content = to_pretty_json(&value, &indent)?;
+            if options.final_newline {
+                content.push('\n');
+            }
+            content
+        }
+    };
+
+    if options.skip_unchanged && existing.as_deref() == Some(content.as_str()) {
+        return Ok(false);
+    }
+    if options.validate || cfg!(debug_assertions) {
+        let violations = schema::validate_document(&content);
+        if !violations.is_empty() {
+            return Err(WorkspaceError::SchemaViolation {
+                path: path.to_path_buf(),
+                violations,
+            });
+        }
+    }
+    if let (Some(existing), true) = (&existing, options.backup) {
+        let backup = backup_path(path);
+        fs.write(&backup, existing.as_bytes())
+            .map_err(WorkspaceError::write(&backup))?;
+    }
+    fs.write(path, content.as_bytes())
+        .map_err(WorkspaceError::write(path))?;
+    Ok(true)
+}
+
+/// Whether `workspace` is the workspace in the file `content` apart from its folders.
+fn only_folders_differ(content: &str, workspace: &WorkspaceFile) -> bool {
+    match serde_json::from_str::<WorkspaceFile>(&jsonc::to_json(content)) {
+        Ok(existing) => {
+            WorkspaceFile {
+                folders: workspace.folders.clone(),
+                ..existing
+            } == *workspace
+        }
+        Err(_) => false,
+    }
+}
+
+/// Like [`write_workspace`] with the default [`WriteOptions`]. Kept for
+/// compatibility.
+pub fn write_workspace_if_changed(path: &Path, workspace: &WorkspaceFile) -> Result<bool> {
+    write_workspace(path, workspace, &WriteOptions::default())
+}
+
+/// Where [`WriteOptions::backup`] keeps the previous content of `path`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// The indentation of the first indented line of `content`, when it is made of
+/// spaces or tabs only.
+fn detect_indent(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let trimmed = line.trim_start_matches([' ', '\t']);
+        let indent = &line[..line.len() - trimmed.len()];
+        (!indent.is_empty() && !trimmed.is_empty()).then(|| indent.to_string())
+    })
+}
+
+fn to_pretty_json(value: &impl Serialize, indent: &str) -> Result<String> {
+    let mut buffer = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+    value
+        .serialize(&mut serializer)
+        .map_err(anyhow::Error::from)?;
+    Ok(String::from_utf8(buffer).map_err(anyhow::Error::from)?)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Orders the top-level keys of `workspace` as they are in `existing`, so keys such as
+/// `remoteAuthority` stay where the user or VS Code put them. New keys go last.
+fn keep_key_order(workspace: &mut serde_json::Value, existing: &serde_json::Value) {
+    let (Some(map), Some(existing)) = (workspace.as_object_mut(), existing.as_object()) else {
+        return;
+    };
+    let mut ordered = serde_json::Map::new();
+    for key in existing.keys() {
+        if let Some(value) = map.shift_remove(key) {
+            ordered.insert(key.clone(), value);
+        }
+    }
+    ordered.append(map);
+    *map = ordered;
+}
+
+/// Writes `content` to a temporary file next to `path` and renames it into place,
+/// so readers never observe a partially written file.
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    replace_file(path, content, false).map_err(WorkspaceError::write(path))
+}
+
+/// Like [`write_atomic`], for scripts: on unix the file is made executable before it
+/// is moved into place.
+pub fn write_atomic_executable(path: &Path, content: &[u8]) -> Result<()> {
+    replace_file(path, content, true).map_err(WorkspaceError::write(path))
+}
+
+pub(crate) fn replace_file(path: &Path, content: &[u8], executable: bool) -> std::io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().ok_or(std::io::ErrorKind::InvalidInput)?;
+    let temp_path = dir.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = fs::write(&temp_path, content)
+        .and_then(|_| match executable {
+            true => make_executable(&temp_path),
+            false => Ok(()),
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Finds the workspace file to operate on in `dir`.
+///
+/// An explicit file always wins. Otherwise the single `.code-workspace` file in `dir`
+/// is used, falling back to the file named after `dir` when there are several.
+pub fn locate_workspace_file(dir: &Path, explicit: Option<&Path>) -> Result<PathBuf> {
+    locate_workspace_file_with(&RealFs, dir, explicit)
+}
+
+/// Like [`locate_workspace_file`], looking in `fs`.
+pub fn locate_workspace_file_with(
+    fs: &dyn FileSystem,
+    dir: &Path,
+    explicit: Option<&Path>,
+) -> Result<PathBuf> {
+    if let Some(path) = explicit {
+        return Ok(dir.join(path));
+    }
+
+    let mut candidates = find_workspace_files_with(fs, dir)?;
+
+    if candidates.len() == 1 {
+        return Ok(candidates.remove(0));
+    }
+
+    let derived = dir.join(format!(
+        "{}.code-workspace",
+        dir.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    if candidates.contains(&derived) {
+        return Ok(derived);
+    }
+
+    match candidates.is_empty() {
+        true => Err(WorkspaceError::NoWorkspaceFile(dir.to_path_buf())),
+        false => Err(WorkspaceError::AmbiguousWorkspaceFile(candidates)),
+    }
+}
+
+/// How many parent directories [`discover_workspace_file`] looks in.
+pub const DISCOVERY_DEPTH: usize = 3;
+
+/// The workspace file to update when generating into `dir`, which has none: the
+/// `.code-workspace` file in the closest of the [`DISCOVERY_DEPTH`] directories above
+/// it that has one. Fails when that directory has several.
+pub fn discover_workspace_file(dir: &Path) -> Result<Option<PathBuf>> {
+    discover_workspace_file_with(&RealFs, dir)
+}
+
+/// Like [`discover_workspace_file`], looking in `fs`.
+pub fn discover_workspace_file_with(fs: &dyn FileSystem, dir: &Path) -> Result<Option<PathBuf>> {
+    for parent in dir.ancestors().skip(1).take(DISCOVERY_DEPTH) {
+        // A parent that can't be listed has nothing to update
+        let mut found = find_workspace_files_with(fs, parent).unwrap_or_default();
+        match found.len() {
+            0 => continue,
+            1 => return Ok(found.pop()),
+            _ => return Err(WorkspaceError::AmbiguousDiscovery(found)),
+        }
+    }
+    Ok(None)
+}
+
+/// The `.code-workspace` files in `root` and in the directories below it, down to
+/// `max_depth` levels, sorted by path. Hidden directories, symbolic links and
+/// directories that can't be listed aren't looked in.
+pub fn find_workspace_files_below(root: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+    find_workspace_files_below_with(&RealFs, root, max_depth)
+}
+
+/// Like [`find_workspace_files_below`], looking in `fs`.
+pub fn find_workspace_files_below_with(
+    fs: &dyn FileSystem,
+    root: &Path,
+    max_depth: usize,
+) -> Result<Vec<PathBuf>> {
+    let mut files = find_workspace_files_with(fs, root)?;
+    let mut dirs = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        if depth > 0 {
+            files.extend(find_workspace_files_with(fs, &dir).unwrap_or_default());
+        }
+        if depth == max_depth {
+            continue;
+        }
+        for path in fs.read_dir(&dir).unwrap_or_default() {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if !hidden && fs.is_dir(&path) && !fs.is_symlink(&path) {
+                dirs.push((path, depth + 1));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// All `.code-workspace` files directly inside `dir`, sorted by path.
+pub fn find_workspace_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    find_workspace_files_with(&RealFs, dir)
+}
+
+/// Like [`find_workspace_files`], looking in `fs`.
+pub fn find_workspace_files_with(fs: &dyn FileSystem, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = fs
+        .read_dir(dir)
+        .map_err(WorkspaceError::io(dir))?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "code-workspace"))
+        .filter(|path| fs.exists(path) && !fs.is_dir(path))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Replaces the `folders` array in the original file `text`, leaving every other
+/// byte untouched. The new array is indented to match the surrounding file.
+///
+/// Returns `None` when the array can't be located unambiguously.
+pub fn replace_folders_in_text(text: &str, folders: &[WorkspaceFolder]) -> Result<Option<String>> {
+    let Some(span) = jsonc::top_level_value_span(text, "folders") else {
+        return Ok(None);
+    };
+    let indent = jsonc::line_indent(text, span.start);
+
+    let array = if text[span.clone()].contains('\n') || !indent.is_empty() {
+        // Nested lines are relative to the key's own indentation
+        to_pretty_json(&folders, indent)?.replace('\n', &format!("\n{}", indent))
+    } else {
+        serde_json::to_string(folders).map_err(anyhow::Error::from)?
+    };
+
+    Ok(Some(format!(
+        "{}{}{}",
+        &text[..span.start],
+        array,
+        &text[span.end..]
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{MemoryFs, Operation};
+    use crate::folders::list_folders;
+    use crate::model::Extensions;
+    use anyhow::Result;
+    use std::io::ErrorKind;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_locate_workspace_file() -> Result<()> {
+        let fs = MemoryFs::new();
+        let dir = Path::new("/project");
+        fs.add_dir(dir);
+        assert!(locate_workspace_file_with(&fs, dir, None).is_err());
+
+        fs.add_file("/project/other.code-workspace", "{}");
+        fs.add_dir("/project/folder.code-workspace");
+        assert_eq!(
+            locate_workspace_file_with(&fs, dir, None)?,
+            dir.join("other.code-workspace")
+        );
+
+        fs.add_file("/project/second.code-workspace", "{}");
+        let err = locate_workspace_file_with(&fs, dir, None).unwrap_err();
+        assert!(err.to_string().contains("second.code-workspace"));
+
+        fs.add_file("/project/project.code-workspace", "{}");
+        assert_eq!(
+            locate_workspace_file_with(&fs, dir, None)?,
+            dir.join("project.code-workspace")
+        );
+
+        let explicit = locate_workspace_file_with(&fs, dir, Some(Path::new("x.code-workspace")))?;
+        assert_eq!(explicit, dir.join("x.code-workspace"));
+
+        fs.fail(Operation::ReadDir, dir, ErrorKind::PermissionDenied);
+        let err = locate_workspace_file_with(&fs, dir, None).unwrap_err();
+        assert!(matches!(err, WorkspaceError::Io { path, .. } if path == dir));
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover_workspace_file() -> Result<()> {
+        let fs = MemoryFs::new();
+        let dir = Path::new("/a/b/c/d/e");
+        fs.add_dir(dir);
+        assert_eq!(discover_workspace_file_with(&fs, dir)?, None);
+
+        // Too far up
+        fs.add_file("/a/far.code-workspace", "{}");
+        fs.add_file("/a/b/c/d/e/here.code-workspace", "{}");
+        assert_eq!(discover_workspace_file_with(&fs, dir)?, None);
+
+        fs.add_file("/a/b/c/team.code-workspace", "{}");
+        assert_eq!(
+            discover_workspace_file_with(&fs, dir)?,
+            Some(PathBuf::from("/a/b/c/team.code-workspace"))
+        );
+        // Only the closest directory with one counts
+        fs.add_file("/a/b/c/other.code-workspace", "{}");
+        fs.add_file("/a/b/c/d/d.code-workspace", "{}");
+        assert_eq!(
+            discover_workspace_file_with(&fs, dir)?,
+            Some(PathBuf::from("/a/b/c/d/d.code-workspace"))
+        );
+        fs.remove("/a/b/c/d/d.code-workspace");
+        let err = discover_workspace_file_with(&fs, dir).unwrap_err();
+        assert!(matches!(err, WorkspaceError::AmbiguousDiscovery(ref found) if found.len() == 2));
+        assert!(err.to_string().contains("other.code-workspace"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_workspace_files_below() -> Result<()> {
+        let fs = MemoryFs::new();
+        for file in [
+            "/work/top.code-workspace",
+            "/work/api/api.code-workspace",
+            "/work/api/src/deep/too-deep.code-workspace",
+            "/work/.cache/hidden.code-workspace",
+            "/work/web/notes.txt",
+        ] {
+            fs.add_file(file, "{}");
+        }
+        fs.add_dir("/work/web/folder.code-workspace");
+
+        let files = find_workspace_files_below_with(&fs, Path::new("/work"), 2)?;
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("/work/api/api.code-workspace"),
+                PathBuf::from("/work/top.code-workspace")
+            ]
+        );
+        assert_eq!(
+            find_workspace_files_below_with(&fs, Path::new("/work"), 3)?.len(),
+            3
+        );
+        assert!(find_workspace_files_below_with(&fs, Path::new("/none"), 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_and_write_failures() -> Result<()> {
+        let fs = MemoryFs::new();
+        let path = Path::new("/project/team.code-workspace");
+        fs.add_file(path, r#"{"folders": [{"path": "api"}]}"#);
+
+        // The file is read, then writing it back fails: the error names the file and
+        // the content is left as it was
+        let mut workspace = read_workspace_with(&fs, path)?;
+        workspace.folders.push(WorkspaceFolder::new("web", "web"));
+        fs.fail(Operation::Write, path, ErrorKind::PermissionDenied);
+        let options = WriteOptions::default();
+        let err = write_workspace_with(&fs, path, &workspace, &options).unwrap_err();
+        assert!(
+            matches!(&err, WorkspaceError::Write { path: failed, source }
+            if failed == path && source.kind() == ErrorKind::PermissionDenied)
+        );
+        let backup = WriteOptions {
+            backup: true,
+            ..Default::default()
+        };
+        assert!(write_workspace_with(&fs, path, &workspace, &backup).is_err());
+        assert_eq!(
+            fs.file(path).as_deref(),
+            Some(r#"{"folders": [{"path": "api"}]}"#)
+        );
+
+        // Unchanged content isn't written, so it doesn't fail either
+        let unchanged = read_workspace_with(&fs, path)?;
+        fs.add_file(path, serde_json::to_string_pretty(&unchanged)?);
+        assert!(!write_workspace_with(&fs, path, &unchanged, &options)?);
+
+        // Changed since it was read: the newer content is compared, so it is written
+        fs.add_file(path, r#"{"folders": []}"#);
+        let err = write_workspace_with(&fs, path, &unchanged, &options).unwrap_err();
+        assert!(matches!(err, WorkspaceError::Write { .. }));
+
+        let other = Path::new("/project/other.code-workspace");
+        fs.add_file(other, "{}");
+        fs.fail(Operation::ReadToString, other, ErrorKind::PermissionDenied);
+        let err = read_workspace_with(&fs, other).unwrap_err();
+        assert!(matches!(err, WorkspaceError::Io { source, .. }
+            if source.kind() == ErrorKind::PermissionDenied));
+        fs.add_file("/project/broken.code-workspace", "{");
+        assert!(matches!(
+            read_workspace_with(&fs, Path::new("/project/broken.code-workspace")),
+            Err(WorkspaceError::ParseWorkspace { .. })
+        ));
+        Ok(())
+    }
+
+    /// Shaped like a file VS Code saved itself: tab indentation, folders with and
+    /// without names, a remote folder and keys in the order VS Code writes them.
+    const VSCODE_FILE: &str = r#"{
+	"folders": [
+		{
+			"path": "api"
+		},
+		{
+			"path": "../shared/ui",
+			"name": "UI kit"
+		},
+		{
+			"name": "Box",
+			"uri": "vscode-remote://ssh-remote+box/srv/app"
+		}
+	],
+	"remoteAuthority": "ssh-remote+box",
+	"settings": {
+		"files.exclude": {
+			"**/node_modules": true
+		},
+		"editor.formatOnSave": true
+	},
+	"extensions": {
+		"recommendations": [
+			"rust-lang.rust-analyzer"
+		]
+	}
+}"#;
+
+    /// Shaped like a file written by hand: comments, trailing commas and four spaces.
+    const HAND_WRITTEN_FILE: &str = r#"{
+    // Our services
+    "folders": [
+        { "path": "api", "name": "API" },
+        { "path": "web" }, // the frontend
+    ],
+    "settings": {
+        "editor.tabSize": 4,
+    },
+}
+"#;
+
+    #[test]
+    fn test_round_trip_vscode_file() -> Result<()> {
+        let fs = MemoryFs::new();
+        let path = Path::new("/project/team.code-workspace");
+        fs.add_file(path, VSCODE_FILE);
+
+        let mut workspace = read_workspace_with(&fs, path)?;
+        assert_eq!(workspace.folders.len(), 3);
+        assert_eq!(workspace.folders[0].name, "");
+        assert_eq!(workspace.remote_authority(), Some("ssh-remote+box"));
+        // Written back unchanged, the file is byte for byte the same and left alone
+        let options = WriteOptions::default();
+        assert!(!write_workspace_with(&fs, path, &workspace, &options)?);
+        fs.fail(Operation::Write, path, ErrorKind::PermissionDenied);
+        assert!(!write_workspace_with(&fs, path, &workspace, &options)?);
+
+        let fs = MemoryFs::new();
+        fs.add_file(path, VSCODE_FILE);
+        workspace.folders.push(WorkspaceFolder::new("web", "Web"));
+        assert!(write_workspace_with(&fs, path, &workspace, &options)?);
+        let written = fs.file(path).unwrap();
+        assert!(written.contains("\n\t\t{\n\t\t\t\"path\": \"web\",\n\t\t\t\"name\": \"Web\""));
+        assert_eq!(read_workspace_with(&fs, path)?, workspace);
+
+        let two_spaces = WriteOptions {
+            preserve_indent: false,
+            ..Default::default()
+        };
+        assert!(write_workspace_with(&fs, path, &workspace, &two_spaces)?);
+        assert!(fs
+            .file(path)
+            .unwrap()
+            .starts_with("{\n  \"folders\": [\n    {"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip_hand_written_file() -> Result<()> {
+        let fs = MemoryFs::new();
+        let path = Path::new("/project/team.code-workspace");
+        fs.add_file(path, HAND_WRITTEN_FILE);
+        let mut workspace = read_workspace_with(&fs, path)?;
+        assert_eq!(workspace.folders[1].name, "");
+        workspace.folders.remove(0);
+
+        // Only the folders are rewritten; the comments elsewhere stay
+        let folders_only = WriteOptions {
+            folders_only: true,
+            backup: true,
+            ..Default::default()
+        };
+        assert!(write_workspace_with(&fs, path, &workspace, &folders_only)?);
+        let written = fs.file(path).unwrap();
+        assert!(written.starts_with("{\n    // Our services\n    \"folders\": [\n        {"));
+        assert!(written.contains("\"editor.tabSize\": 4,\n    },\n}\n"));
+        assert!(!written.contains("API"));
+        assert_eq!(read_workspace_with(&fs, path)?, workspace);
+        assert_eq!(
+            fs.file("/project/team.code-workspace.bak").as_deref(),
+            Some(HAND_WRITTEN_FILE)
+        );
+
+        // A whole rewrite loses the comments but keeps the indentation
+        workspace.settings = None;
+        assert!(write_workspace_with(
+            &fs,
+            path,
+            &workspace,
+            &WriteOptions::default()
+        )?);
+        let written = fs.file(path).unwrap();
+        assert_eq!(
+            written,
+            "{\n    \"folders\": [\n        {\n            \"path\": \"web\"\n        }\n    ]\n}"
+        );
+        assert_eq!(
+            backup_path(path),
+            Path::new("/project/team.code-workspace.bak")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_indent() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_dir("/project");
+        let path = Path::new("/project/new.code-workspace");
+        let mut workspace = WorkspaceFile::default();
+        workspace.folders.push(WorkspaceFolder::new("api", "API"));
+        let write = |indent, final_newline| -> Result<String> {
+            fs.remove(path);
+            let options = WriteOptions {
+                indent,
+                final_newline,
+                ..Default::default()
+            };
+            write_workspace_with(&fs, path, &workspace, &options)?;
+            Ok(fs.file(path).unwrap())
+        };
+
+        assert_eq!(
+            write(None, false)?,
+            "{\n  \"folders\": [\n    {\n      \"path\": \"api\",\n      \"name\": \"API\"\n    }\n  ]\n}"
+        );
+        assert_eq!(write(Some(Indent::Spaces(2)), false)?, write(None, false)?);
+        assert_eq!(
+            write(Some(Indent::Spaces(4)), true)?,
+            "{\n    \"folders\": [\n        {\n            \"path\": \"api\",\n            \"name\": \"API\"\n        }\n    ]\n}\n"
+        );
+        assert_eq!(
+            write(Some(Indent::Tabs), false)?,
+            "{\n\t\"folders\": [\n\t\t{\n\t\t\t\"path\": \"api\",\n\t\t\t\"name\": \"API\"\n\t\t}\n\t]\n}"
+        );
+
+        // Asked for, an indentation wins over the existing file's, so it is a change
+        let tabs = WriteOptions {
+            indent: Some(Indent::Tabs),
+            ..Default::default()
+        };
+        assert!(!write_workspace_with(&fs, path, &workspace, &tabs)?);
+        let options = WriteOptions::default();
+        assert!(!write_workspace_with(&fs, path, &workspace, &options)?);
+        let four = WriteOptions {
+            indent: Some(Indent::Spaces(4)),
+            ..Default::default()
+        };
+        assert!(write_workspace_with(&fs, path, &workspace, &four)?);
+        assert!(fs.file(path).unwrap().starts_with("{\n    \"folders\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_folders_in_text_preserves_other_bytes() -> Result<()> {
+        let text = "{\n    \"settings\": { \"a\":1.50 },\n    \"folders\": [\n        {\"path\": \"x\", \"name\": \"X\"}\n    ],\n    // trailing comment\n    \"z\": \"\\u00e9\"\n}\n";
+        let folders = vec![WorkspaceFolder::new("y", "Y")];
+
+        let updated = replace_folders_in_text(text, &folders)?.unwrap();
+
+        assert_eq!(
+            updated,
+            "{\n    \"settings\": { \"a\":1.50 },\n    \"folders\": [\n        {\n            \"path\": \"y\",\n            \"name\": \"Y\"\n        }\n    ],\n    // trailing comment\n    \"z\": \"\\u00e9\"\n}\n"
+        );
+        assert!(replace_folders_in_text("{}", &folders)?.is_none());
+        Ok(())
+    }
+
+    /// Formatting a full rewrite would normalize: escapes, number forms, tabs, CRLF
+    /// line ends, comments and trailing commas, on both sides of the folders.
+    const REVIEWED_FILE: &str = "{\r\n\t/* reviewed: keep as is */\r\n\t\"settings\": {\"a\\/b\": \"caf\\u00e9\", \"n\": 1.50e3,\t\"x\":-0},\r\n\t\"folders\": [ // generated\r\n\t\t{\"path\":\"old\"},\r\n\t],\r\n\t\"launch\": {\"configurations\": [], \"compounds\": [],},  // trailing\r\n}\r\n";
+
+    #[test]
+    fn test_folders_only_keeps_other_bytes() -> Result<()> {
+        let fs = MemoryFs::new();
+        let path = Path::new("/project/team.code-workspace");
+        fs.add_file(path, REVIEWED_FILE);
+        let mut workspace = read_workspace_with(&fs, path)?;
+        workspace.folders = vec![
+            WorkspaceFolder::new("api", "📦 api"),
+            WorkspaceFolder::new("web", "📦 web"),
+        ];
+        let options = WriteOptions {
+            folders_only: true,
+            ..Default::default()
+        };
+        assert!(write_workspace_with(&fs, path, &workspace, &options)?);
+
+        let written = fs.file(path).unwrap();
+        let before = jsonc::top_level_value_span(REVIEWED_FILE, "folders").unwrap();
+        let after = jsonc::top_level_value_span(&written, "folders").unwrap();
+        assert_eq!(written[..after.start], REVIEWED_FILE[..before.start]);
+        assert_eq!(written[after.end..], REVIEWED_FILE[before.end..]);
+        assert_eq!(read_workspace_with(&fs, path)?, workspace);
+        assert!(!write_workspace_with(&fs, path, &workspace, &options)?);
+
+        // Without a single folders array to replace, the whole file is written
+        let ambiguous = "{\"folders\": [], \"folders\": []}";
+        fs.add_file(path, ambiguous);
+        assert!(write_workspace_with(&fs, path, &workspace, &options)?);
+        assert_eq!(read_workspace_with(&fs, path)?.folders, workspace.folders);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join("ws.code-workspace");
+        fs::write(&path, "old")?;
+
+        write_atomic(&path, b"new")?;
+
+        assert_eq!(fs::read_to_string(&path)?, "new");
+        assert_eq!(fs::read_dir(temp.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_workspace_is_lenient() -> Result<()> {
+        let temp = TempDir::new()?;
+        let file = temp.path().join("ws.code-workspace");
+        fs::create_dir(temp.path().join("api"))?;
+        fs::write(
+            &file,
+            r#"{
+    // Hand-written by a human
+    "folders": [
+        { "path": "api" },
+        { "path": "gone", "name": "Gone", },
+        { "uri": "vscode-remote://ssh-remote+box/srv/app", "name": "Remote" },
+    ],
+}"#,
+        )?;
+
+        let workspace = read_workspace(&file)?;
+        let listing = list_folders(&workspace, temp.path());
+
+        assert_eq!(listing.len(), 3);
+        assert_eq!(listing[0].name, "");
+        assert_eq!(listing[0].exists, Some(true));
+        assert_eq!(listing[1].exists, Some(false));
+        assert_eq!(listing[2].exists, None);
+        assert_eq!(
+            listing[2].uri.as_deref(),
+            Some("vscode-remote://ssh-remote+box/srv/app")
+        );
+
+        let json = serde_json::to_value(&workspace.folders[2])?;
+        assert_eq!(
+            json,
+            serde_json::json!({"name": "Remote", "uri": "vscode-remote://ssh-remote+box/srv/app"})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_validates_against_the_schema() -> Result<()> {
+        let fs = MemoryFs::new();
+        let path = Path::new("/work/team.code-workspace");
+        fs.add_file(path, r#"{"folders": []}"#);
+        let options = WriteOptions {
+            validate: true,
+            ..Default::default()
+        };
+
+        // A folder with neither a path nor a uri serializes as `{}`
+        let mut workspace = WorkspaceFile::default();
+        workspace.folders.push(WorkspaceFolder::new("api", ""));
+        workspace.folders.push(WorkspaceFolder::default());
+        workspace
+            .other
+            .insert("transient".to_string(), serde_json::json!("yes"));
+        let err = write_workspace_with(&fs, path, &workspace, &options).unwrap_err();
+        let WorkspaceError::SchemaViolation { violations, .. } = &err else {
+            panic!("unexpected error: {}", err);
+        };
+        let pointers: Vec<&str> = violations.iter().map(|v| v.pointer.as_str()).collect();
+        assert_eq!(pointers, ["/folders/1", "/transient"]);
+        assert!(err
+            .to_string()
+            .contains("/folders/1: a folder needs a path or a uri"));
+        assert_eq!(fs.read_to_string(path)?, r#"{"folders": []}"#);
+
+        workspace.folders.pop();
+        workspace.other.clear();
+        assert!(write_workspace_with(&fs, path, &workspace, &options)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_keeps_top_level_key_order() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join("remote.code-workspace");
+        fs::write(
+            &path,
+            r#"{"remoteAuthority": "dev-container+abc", "transient": true,
+                "settings": {"a": 1}, "folders": []}"#,
+        )?;
+        let mut workspace = parse_workspace(&fs::read_to_string(&path)?)?;
+        workspace.folders.push(WorkspaceFolder::new("api", "api"));
+        workspace.extensions = Some(Extensions {
+            recommendations: vec!["golang.go".to_string()],
+            ..Default::default()
+        });
+        let options = WriteOptions::default();
+        assert!(write_workspace(&path, &workspace, &options)?);
+
+        let written: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        let keys: Vec<&String> = written.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            vec![
+                "remoteAuthority",
+                "transient",
+                "settings",
+                "folders",
+                "extensions"
+            ]
+        );
+        assert_eq!(parse_workspace(&fs::read_to_string(&path)?)?, workspace);
+        assert!(!write_workspace(&path, &workspace, &options)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! Minimal span-aware scanning of JSON text (comments allowed), used to edit a
+//! single top-level value without reformatting the rest of a file.
+
+use std::ops::Range;
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Scanner {
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    /// Skips whitespace, `//` line comments and `/* */` block comments.
+    fn skip_trivia(&mut self) -> Option<()> {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.pos += 1,
+                Some(b'/') => match self.bytes.get(self.pos + 1) {
+                    Some(b'/') => {
+                        while !matches!(self.peek(), None | Some(b'\n')) {
+                            self.pos += 1;
+                        }
+                    }
+                    Some(b'*') => {
+                        self.pos += 2;
+                        while !self.bytes[self.pos..].starts_with(b"*/") {
+                            self.peek()?;
+                            self.pos += 1;
+                        }
+                        self.pos += 2;
+                    }
+                    _ => return None,
+                },
+                _ => return Some(()),
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.skip_trivia()?;
+        if self.peek()? == byte {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Consumes a string literal and returns its span including the quotes.
+    fn string(&mut self) -> Option<Range<usize>> {
+        let start = self.pos;
+        if self.peek()? != b'"' {
+            return None;
+        }
+        self.pos += 1;
+        loop {
+            match self.peek()? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(start..self.pos);
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Consumes any value, returning its span.
+    fn value(&mut self) -> Option<Range<usize>> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        match self.peek()? {
+            b'"' => {
+                self.string()?;
+            }
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.skip_trivia()?;
+                    match self.peek()? {
+                        byte if byte == close => {
+                            self.pos += 1;
+                            break;
+                        }
+                        b',' | b':' => self.pos += 1,
+                        _ => {
+                            self.value()?;
+                        }
+                    }
+                }
+            }
+            _ => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r' | b'/')
+                ) {
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return None;
+                }
+            }
+        }
+        Some(start..self.pos)
+    }
+}
+
+/// Locates the value of `key` in the top-level object of `text`.
+///
+/// Returns `None` when the text isn't an object, can't be scanned, or the key
+/// is missing or appears more than once.
+pub(crate) fn top_level_value_span(text: &str, key: &str) -> Option<Range<usize>> {
+    let mut scanner = Scanner::new(text);
+    scanner.expect(b'{')?;
+    let mut found = None;
+    loop {
+        scanner.skip_trivia()?;
+        match scanner.peek()? {
+            b'}' => return found,
+            b',' => {
+                scanner.pos += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let key_span = scanner.string()?;
+        let raw_key = &text[key_span];
+        scanner.expect(b':')?;
+        let value_span = scanner.value()?;
+
+        let matches = match serde_json::from_str::<String>(raw_key) {
+            Ok(decoded) => decoded == key,
+            Err(_) => false,
+        };
+        if matches {
+            if found.is_some() {
+                return None;
+            }
+            found = Some(value_span);
+        }
+    }
+}
+
+/// Converts JSONC (JSON with comments and trailing commas, as written by VS Code)
+/// to plain JSON. Comments and trailing commas are replaced by spaces so byte
+/// offsets and line numbers in parse errors still match the original text.
+pub(crate) fn to_json(text: &str) -> String {
+    let mut out = text.as_bytes().to_vec();
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    // Position of the last comma outside strings, cleared by any other token
+    let mut pending_comma: Option<usize> = None;
+
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => {
+                pending_comma = None;
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'"' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+                pos += 1;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    out[pos] = b' ';
+                    pos += 1;
+                }
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                let end = text[pos + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |index| pos + 2 + index + 2);
+                for byte in &mut out[pos..end] {
+                    if *byte != b'\n' {
+                        *byte = b' ';
+                    }
+                }
+                pos = end;
+            }
+            b',' => {
+                pending_comma = Some(pos);
+                pos += 1;
+            }
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    out[comma] = b' ';
+                }
+                pos += 1;
+            }
+            b' ' | b'\t' | b'\n' | b'\r' => pos += 1,
+            _ => {
+                pending_comma = None;
+                pos += 1;
+            }
+        }
+    }
+
+    // Only ASCII bytes outside strings were replaced, so the text is still UTF-8
+    String::from_utf8(out).unwrap_or_else(|_| text.to_string())
+}
+
+/// The leading whitespace of the line containing byte offset `pos`.
+pub(crate) fn line_indent(text: &str, pos: usize) -> &str {
+    let line_start = text[..pos].rfind('\n').map_or(0, |index| index + 1);
+    let line = &text[line_start..pos];
+    let trimmed = line.trim_start_matches([' ', '\t']);
+    &line[..line.len() - trimmed.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_level_value_span() {
+        let text = r#"{
+  // "folders": "in a comment",
+  "settings": {"folders": [1], "s": "}\"]"},
+  /* block */ "folders": [
+    {"path": "a"}
+  ],
+  "n": 12
+}"#;
+        let span = top_level_value_span(text, "folders").unwrap();
+        assert_eq!(&text[span], "[\n    {\"path\": \"a\"}\n  ]");
+        let span = top_level_value_span(text, "n").unwrap();
+        assert_eq!(&text[span], "12");
+        assert!(top_level_value_span(text, "missing").is_none());
+    }
+
+    #[test]
+    fn test_top_level_value_span_rejects_duplicates_and_garbage() {
+        assert!(top_level_value_span(r#"{"folders": [], "folders": []}"#, "folders").is_none());
+        assert!(top_level_value_span(r#"["folders"]"#, "folders").is_none());
+        assert!(top_level_value_span(r#"{"folders": [}"#, "folders").is_none());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let text = "{\n  // comment\n  \"a\": \"// not a comment, \\\" }\",\n  /* block\n */ \"b\": [1, 2,],\n}";
+        let json = to_json(text);
+
+        assert_eq!(json.len(), text.len());
+        assert_eq!(json.lines().count(), text.lines().count());
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["a"], "// not a comment, \" }");
+        assert_eq!(value["b"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_line_indent() {
+        let text = "{\n\t\"a\": 1,\n    \"b\": 2}";
+        assert_eq!(line_indent(text, text.find("\"a\"").unwrap()), "\t");
+        assert_eq!(line_indent(text, text.find("\"b\"").unwrap()), "    ");
+        assert_eq!(line_indent(text, 0), "");
+    }
+}
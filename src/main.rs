@@ -1,38 +1,1448 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use workspace_manager::*;
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn main() -> std::process::ExitCode {
+    let cli = Cli::parse();
+    console::init(cli.color);
+    match run(cli.into_command()) {
+        Ok(code) => code.into(),
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitCode::from_error(&err).into()
+        }
+    }
+}
+
+/// Runs `command`, returning the exit status when it doesn't fail.
+fn run(command: Command) -> Result<ExitCode> {
+    match command {
+        Command::Generate(args) => generate(&args),
+        Command::Init(args) => init(&args),
+        Command::Update(args) => update(&args),
+        Command::RefreshAll(args) => refresh_all(&args),
+        Command::Add(args) => add(&args),
+        Command::Remove(args) => remove(&args),
+        Command::Rename(args) => rename(&args),
+        Command::List(args) => list(&args),
+        Command::Clean(args) => clean(&args),
+        Command::Merge(args) => merge(&args),
+        Command::Diff(args) => return diff(&args),
+        Command::Export(args) => export(&args),
+        Command::Doctor(args) => return doctor(&args),
+        Command::Stats(args) => stats(&args),
+        Command::Restore(args) => restore(&args),
+        Command::Delete(args) => delete(&args),
+        Command::Tasks { command } => tasks(&command),
+        Command::Workspaces { command } => workspaces(&command),
+        Command::Completions { shell } => {
+            generate_completions(shell, &mut io::stdout());
+            Ok(())
+        }
+        Command::Mangen { out_dir } => {
+            let path = generate_man_page(&out_dir)?;
+            println!("Man page written to '{}'", path.display());
+            Ok(())
+        }
+    }?;
+    Ok(ExitCode::Success)
+}
+
+fn init(args: &GenerateArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    // Here --force overwrites the file rather than lifting --max-folders
+    let flags = GenerateArgs {
+        force: false,
+        ..args.clone()
+    };
+    let resolved = flags.resolve(&current_dir)?;
+    let path = resolved.options.scan_path.join(config::PROJECT_CONFIG_FILE);
+    if path.exists() && !args.force {
+        anyhow::bail!(
+            "'{}' already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+    let text = resolved.to_config_toml();
+    write_atomic(&path, text.as_bytes())?;
+
+    let set = text
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .count();
+    println!(
+        "Wrote '{}' with {} setting(s) from the command line",
+        path.display(),
+        set
+    );
+    println!("Next steps:");
+    println!("  - Review it, uncommenting the keys this directory needs");
+    println!("  - Commit it, so everyone scanning this directory gets the same defaults");
+    println!("  - Run workspace-manager to generate the workspace; flags still override it");
+    Ok(())
+}
+
+fn generate(args: &GenerateArgs) -> Result<()> {
+    if args.print_path {
+        console::status_to_stderr();
+    }
+    // The workspace file is written to the current directory
+    let current_dir = env::current_dir()?;
+    let ResolvedOptions {
+        options: mut opts,
+        provenance,
+        mut named_after,
+    } = args.resolve(&current_dir)?;
+    // unless it has none and, not given a name, one is found in a parent directory
+    let mut base_dir = current_dir.clone();
+    if let Some(file) = &opts.workspace_file {
+        if !file.to_string_lossy().ends_with(".code-workspace") {
+            console::warning(format_args!(
+                "'{}' doesn't end in .code-workspace, so VS Code won't open it as a workspace",
+                file.display()
+            ));
+        }
+        let dir = file.parent().unwrap_or(&current_dir);
+        base_dir = dir.canonicalize().with_context(|| {
+            format!(
+                "Directory of --workspace-file '{}' does not exist",
+                dir.display()
+            )
+        })?;
+        // The task runs next to the workspace file
+        let relative = pathdiff::diff_paths(&opts.scan_path, &base_dir)
+            .unwrap_or_else(|| opts.scan_path.clone());
+        let task_args = GenerateArgs {
+            workspace_file: Some(opts.workspace_filename()),
+            path: match relative.to_string_lossy().as_ref() {
+                "" => ".".to_string(),
+                relative => relative.to_string(),
+            },
+            ..args.clone()
+        };
+        opts.task_args = task_args.to_task_args();
+    }
+    if named_after.is_some()
+        && opts.discover
+        && !args.group_by_parent
+        && !current_dir.join(opts.workspace_filename()).exists()
+    {
+        if let Some(file) = discover_workspace_file(&current_dir)? {
+            console::status(format_args!(
+                "Updating '{}' found in a parent directory; pass --no-discover to write a \
+                 new file here",
+                file.display()
+            ));
+            let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+            opts.name = file_name.trim_end_matches(".code-workspace").to_string();
+            base_dir = file.parent().map_or(base_dir, Path::to_path_buf);
+            named_after = None;
+        }
+    }
+    if let Some((source, separator)) = args.paths_source() {
+        let candidates = match source {
+            "-" => read_candidates(io::stdin().lock(), separator, &current_dir)?,
+            file => {
+                let file = fs::File::open(current_dir.join(file))
+                    .with_context(|| format!("Failed to open '{}'", file))?;
+                read_candidates(io::BufReader::new(file), separator, &current_dir)?
+            }
+        };
+        opts.candidates = Some(candidates);
+    }
+    // Choices remembered in the project configuration, which a dry run leaves alone
+    if args.no_task && !args.dry_run {
+        // Remember the choice so later runs don't add the task back
+        let config_path = opts.scan_path.join(config::PROJECT_CONFIG_FILE);
+        if config::remember(&config_path, "no_task", true)? {
+            console::status(format_args!(
+                "Recorded 'no_task = true' in '{}'",
+                config_path.display()
+            ));
+        }
+    }
+    if args.append && !args.dry_run {
+        // Remember the mode so refreshes from VS Code only add folders too
+        let config_path = opts.scan_path.join(config::PROJECT_CONFIG_FILE);
+        if config::remember(&config_path, "append", true)? {
+            console::status(format_args!(
+                "Recorded 'append = true' in '{}'",
+                config_path.display()
+            ));
+        }
+    }
+    if let Some(kind) = args.task_command.filter(|_| !args.dry_run) {
+        // Remember the choice so refreshes from the task keep it
+        let config_path = opts.scan_path.join(config::PROJECT_CONFIG_FILE);
+        let kind = value_name(&kind);
+        if config::remember(&config_path, "task_command", kind.as_str())? {
+            console::status(format_args!(
+                "Recorded 'task_command = \"{}\"' in '{}'",
+                kind,
+                config_path.display()
+            ));
+        }
+    }
+    if args.verbose {
+        for (key, value) in config::environment_overrides() {
+            eprintln!("environment: {}={}", key, value);
+        }
+        for (field, source) in provenance.iter() {
+            eprintln!("{:<15} from {}", field, source);
+        }
+    }
+    if args.group_by_parent {
+        return generate_groups(args, &opts);
+    }
+    // With --dual, the container file is written from the same scan after the host one
+    let (opts, container) = opts.variants();
+    let workspace_filename = opts.workspace_filename();
+    // Find the editor up front so a missing one fails before anything is written
+    let editor = if args.open {
+        Some(editor::discover_editor(
+            args.editor_cmd.as_deref(),
+            &args.editor_priority,
+        )?)
+    } else {
+        None
+    };
+    let (mut workspace, changes) = WorkspaceBuilder::from_options(opts.clone())
+        .base_dir(&base_dir)
+        .build_with_changes()?;
+    if args.dry_run {
+        console::status(&changes);
+        // With --print-path, the files that would be written, the report going to stderr
+        if args.print_path {
+            println!("{}", base_dir.join(&workspace_filename).display());
+            if let Some(container) = &container {
+                println!(
+                    "{}",
+                    base_dir.join(container.workspace_filename()).display()
+                );
+            }
+        }
+        return Ok(());
+    }
+    if opts.reconcile == ReconcileMode::Replace {
+        confirm_removals(&changes.folders_removed, args.yes)?;
+    }
+    if args.interactive {
+        select_interactively(&mut workspace, args.remember)?;
+    }
+
+    // Only worth a note when it isn't named after the current directory as before
+    if let Some(repository) = named_after.filter(|dir| dir.file_name() != current_dir.file_name()) {
+        console::status(format_args!(
+            "Named the workspace '{}' after the git repository in '{}'; pass --name to \
+             choose another",
+            opts.name,
+            repository.display()
+        ));
+    }
+    let mut written = vec![write_generated(&opts, &workspace, &base_dir)?];
+    if let Some(container) = &container {
+        let workspace = WorkspaceBuilder::from_options(container.clone())
+            .base_dir(&base_dir)
+            .build()?;
+        written.push(write_generated(container, &workspace, &base_dir)?);
+    }
+    if args.print_path {
+        for path in &written {
+            println!("{}", path.display());
+        }
+    }
+
+    if let Some(editor) = editor {
+        if editor::is_zed(&editor) {
+            let folders: Vec<PathBuf> = workspace
+                .folders
+                .iter()
+                .filter_map(|folder| folder.resolve(&base_dir))
+                .collect();
+            editor::open_folders(&editor, &folders)?;
+        } else {
+            editor::open_workspace(&editor, &base_dir.join(&workspace_filename))?;
+        }
+    }
+    if args.watch {
+        watch_workspace(&opts, &base_dir, workspace, args.debounce)?;
+    }
+    Ok(())
+}
+
+/// How generated workspaces are written: only their folders with `--surgical`, and
+/// checked against the schema with `--validate`.
+fn write_options(opts: &GenerateOptions) -> WriteOptions {
+    WriteOptions {
+        folders_only: opts.surgical,
+        validate: opts.validate,
+        indent: opts.indent,
+        final_newline: opts.final_newline,
+        ..Default::default()
+    }
+}
+
+/// Writes `workspace` into `dir`, with the files `--emit` asks for, records it in the
+/// registry and returns the path of the workspace file.
+fn write_generated(
+    opts: &GenerateOptions,
+    workspace: &WorkspaceFile,
+    dir: &Path,
+) -> Result<PathBuf> {
+    let workspace_path = dir.join(opts.workspace_filename());
+    let current_dir = env::current_dir()?;
+    let shown = workspace_path
+        .strip_prefix(&current_dir)
+        .unwrap_or(&workspace_path);
+    // Rewriting identical content would still make VS Code reload the workspace,
+    // which matters when the task runs every time the workspace is opened
+    if write_workspace(&workspace_path, workspace, &write_options(opts))? {
+        console::status(format_args!(
+            "Workspace file '{}' updated successfully!",
+            shown.display()
+        ));
+    } else {
+        console::status(format_args!(
+            "Workspace file '{}' is already up to date",
+            shown.display()
+        ));
+    }
+    emit_projects(opts, &workspace_path, workspace)?;
+    // The registry is a convenience; a broken one must not fail generation
+    if let Err(err) = registry::record_generation(&workspace_path, &opts.scan_path) {
+        console::warning(format_args!(
+            "could not update the workspace registry: {:#}",
+            err
+        ));
+    }
+    Ok(workspace_path)
+}
+
+/// Writes a workspace for each grouping directory under the scan path, containing the
+/// directories inside it. Each is generated as if the tool had been run for that group
+/// alone, so its Update Workspace task regenerates just that group.
+fn generate_groups(args: &GenerateArgs, opts: &GenerateOptions) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let groups = group_directories(opts, args.group_depth.unwrap_or(1))?;
+    for group in &groups {
+        let target_dir = match &args.output_dir {
+            Some(dir) => current_dir.join(dir),
+            None => group.clone(),
+        };
+        fs::create_dir_all(&target_dir)
+            .with_context(|| format!("Failed to create {}", target_dir.display()))?;
+        let target_dir = target_dir.canonicalize()?;
+        let relative = pathdiff::diff_paths(group, &target_dir).unwrap_or_else(|| group.clone());
+        let relative = match relative.to_string_lossy().as_ref() {
+            "" => ".".to_string(),
+            relative => relative.to_string(),
+        };
+
+        let mut group_args = GenerateArgs {
+            group_by_parent: false,
+            group_depth: None,
+            output_dir: None,
+            name: Some(
+                group
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+            ),
+            path: group.to_string_lossy().into_owned(),
+            ..args.clone()
+        };
+        if args.output_dir.is_some() {
+            // The current-directory folder would be the output directory, not the group
+            group_args.exclude_current = true;
+            group_args.no_exclude_current = false;
+        }
+        let mut group_opts = group_args.resolve(&target_dir)?.options;
+        // The task runs next to the workspace file
+        group_args.path = relative;
+        group_opts.task_args = group_args.to_task_args();
+
+        let workspace = WorkspaceBuilder::from_options(group_opts.clone())
+            .base_dir(&target_dir)
+            .build()?;
+        let path = write_generated(&group_opts, &workspace, &target_dir)?;
+        if args.print_path {
+            println!("{}", path.display());
+        }
+    }
+    console::status(format_args!(
+        "Generated {} workspace file(s) for the groups in '{}'",
+        groups.len(),
+        opts.scan_path.display()
+    ));
+    Ok(())
+}
+
+/// Writes the project files requested with `--emit` next to `workspace_path`.
+fn emit_projects(
+    opts: &GenerateOptions,
+    workspace_path: &Path,
+    workspace: &WorkspaceFile,
+) -> Result<()> {
+    for target in &opts.emit {
+        match target {
+            EmitTarget::Sublime => {
+                let (path, written) = sublime::write_project(workspace_path, workspace)?;
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if written {
+                    console::status(format_args!(
+                        "Sublime Text project '{}' updated successfully!",
+                        name
+                    ));
+                } else {
+                    console::status(format_args!(
+                        "Sublime Text project '{}' is already up to date",
+                        name
+                    ));
+                }
+            }
+            EmitTarget::Zed => {
+                let (path, written) = zed::write_script(workspace_path, workspace)?;
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if written {
+                    console::status(format_args!(
+                        "Zed launcher '{}' updated successfully!",
+                        name
+                    ));
+                } else {
+                    console::status(format_args!(
+                        "Zed launcher '{}' is already up to date",
+                        name
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Asks before `--replace` removes `removed`, unless `yes`. Without a terminal to ask
+/// on, nothing is removed without `yes`.
+fn confirm_removals(removed: &[FolderChange], yes: bool) -> Result<()> {
+    if removed.is_empty() || yes {
+        return Ok(());
+    }
+    let out = console::stderr();
+    eprintln!("--replace removes {} folder(s):", removed.len());
+    for folder in removed {
+        eprintln!("  {} ({})", out.name(&folder.name), folder.path);
+    }
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!("Not running in a terminal; pass --yes to remove them");
+    }
+    let confirmed = dialoguer::Confirm::new()
+        .with_prompt("Remove them?")
+        .default(false)
+        .interact()
+        .context("Confirmation was cancelled")?;
+    if !confirmed {
+        anyhow::bail!("Nothing was written");
+    }
+    Ok(())
+}
+
+/// Lets the user uncheck folders of a freshly generated workspace.
+fn select_interactively(workspace: &mut WorkspaceFile, remember: bool) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!(
+            "--interactive needs a terminal; run without it or from an interactive shell"
+        );
+    }
+    // The selection is drawn on standard error
+    let out = console::stderr();
+    let items: Vec<String> = workspace
+        .folders
+        .iter()
+        .map(|folder| format!("{} ({})", out.name(&folder.name), folder.path))
+        .collect();
+    let defaults = vec![true; items.len()];
+    let selected = dialoguer::MultiSelect::new()
+        .with_prompt("Folders to include (space toggles, enter confirms)")
+        .items(&items)
+        .defaults(&defaults)
+        .interact()
+        .context("Folder selection was cancelled")?;
+
+    let deselected: Vec<usize> = (0..items.len())
+        .filter(|index| !selected.contains(index))
+        .collect();
+    let removed = exclude_folders(workspace, &deselected, remember);
+    if remember && !removed.is_empty() {
+        console::status(format_args!(
+            "{} deselected folders will be left out when regenerating",
+            removed.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Regenerates the workspace in `dir` whenever the scanned directories change.
+fn watch_workspace(
+    opts: &GenerateOptions,
+    dir: &Path,
+    mut current: WorkspaceFile,
+    debounce: f64,
+) -> Result<()> {
+    let workspace_path = dir.join(opts.workspace_filename());
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst))?;
+
+    console::status(format_args!(
+        "Watching '{}' for changes (Ctrl-C to stop)",
+        opts.scan_path.display()
+    ));
+    let debounce = Duration::from_secs_f64(debounce.max(0.0));
+    watch::watch(&opts.scan_path, &workspace_path, debounce, &stop, || {
+        let workspace = WorkspaceBuilder::from_options(opts.clone())
+            .base_dir(dir)
+            .build()?;
+        let diff = current.diff(&workspace);
+        if diff.folders_added.is_empty() && diff.folders_removed.is_empty() {
+            return Ok(());
+        }
+        write_workspace(&workspace_path, &workspace, &write_options(opts))?;
+        emit_projects(opts, &workspace_path, &workspace)?;
+        console::status(format_args!(
+            "Regenerated '{}': {} folders added, {} removed",
+            opts.workspace_filename(),
+            diff.folders_added.len(),
+            diff.folders_removed.len()
+        ));
+        let out = console::status_style();
+        for folder in &diff.folders_added {
+            let line = format!("  + {} ({})", out.name(&folder.name), folder.path);
+            console::status(out.diff_line(&line));
+        }
+        for folder in &diff.folders_removed {
+            let line = format!("  - {} ({})", out.name(&folder.name), folder.path);
+            console::status(out.diff_line(&line));
+        }
+        current = workspace;
+        Ok(())
+    })?;
+    console::status("Stopped watching");
+    Ok(())
+}
+
+fn update(args: &UpdateArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = match (&args.file, &args.name) {
+        (Some(file), _) => current_dir.join(file),
+        (None, Some(name)) => current_dir.join(format!("{}.code-workspace", name)),
+        (None, None) => pick_workspace_file(&current_dir)?,
+    };
+    update_workspace_file(&workspace_path)
+}
+
+/// Regenerates `workspace_path` with the options stored in its Update Workspace task.
+fn update_workspace_file(workspace_path: &Path) -> Result<()> {
     let current_dir = env::current_dir()?;
-    let base_path = Path::new(&args.path).canonicalize()?;
+    let workspace = read_workspace(workspace_path)?;
+    let task_args = workspace.generation_args().with_context(|| {
+        format!(
+            "'{}' has no '{}' task to take the options from",
+            workspace_path.display(),
+            workspace.managed_task_label()
+        )
+    })?;
+    let mut generate_args = GenerateArgs::from_task_args(&task_args)?;
 
-    let workspace_name = args.name.clone().unwrap_or_else(|| {
-        current_dir
-            .file_name()
+    // Task arguments are relative to the directory holding the workspace file
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+    env::set_current_dir(workspace_dir)
+        .with_context(|| format!("Failed to enter {}", workspace_dir.display()))?;
+    if generate_args.name.is_none() {
+        // Named after the file itself, not whatever a run without a name would pick
+        let stem = workspace_path
+            .file_stem()
             .unwrap_or_default()
-            .to_string_lossy()
+            .to_string_lossy();
+        generate_args.name = Some(stem.into_owned());
+    }
+    generate(&generate_args)
+}
+
+/// Regenerates every workspace file under `--root` that has an Update Workspace task,
+/// going on past those that fail, and reports what happened to each.
+fn refresh_all(args: &RefreshAllArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let root = current_dir.join(&args.root);
+    let files = find_workspace_files_below(&root, args.max_depth)?;
+    let shown = |file: &Path| {
+        file.strip_prefix(&root)
+            .unwrap_or(file)
+            .display()
             .to_string()
-    });
+    };
+
+    let (mut changed, mut unchanged, mut failed, mut unmanaged) = (0, 0, 0, vec![]);
+    for file in &files {
+        let managed = read_workspace(file).map(|workspace| workspace.generation_args().is_some());
+        if let Ok(false) = managed {
+            unmanaged.push(shown(file));
+            continue;
+        }
+        println!("==> {}", shown(file));
+        let before = fs::read(file).ok();
+        let result = managed
+            .map_err(anyhow::Error::from)
+            .and_then(|_| update_workspace_file(file));
+        // Each file is regenerated from its own directory
+        env::set_current_dir(&current_dir)?;
+        match result {
+            Ok(()) if fs::read(file).ok() == before => unchanged += 1,
+            Ok(()) => changed += 1,
+            Err(err) => {
+                eprintln!("Error: failed to refresh '{}': {:#}", shown(file), err);
+                failed += 1;
+            }
+        }
+    }
 
-    let workspace_filename = format!("{}.code-workspace", workspace_name);
-    let workspace = create_workspace(
-        &base_path,
-        &workspace_name,
-        args.exclude_current,
-        args.update_task,
-        &args,
+    println!(
+        "{} workspace file(s) under '{}': {} changed, {} unchanged, {} failed, {} unmanaged",
+        files.len(),
+        root.display(),
+        changed,
+        unchanged,
+        failed,
+        unmanaged.len()
+    );
+    if !unmanaged.is_empty() {
+        println!("Unmanaged, without an Update Workspace task to take the options from:");
+        for file in &unmanaged {
+            println!("  {}", file);
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} managed workspace files could not be refreshed",
+            failed,
+            files.len() - unmanaged.len()
+        );
+    }
+    Ok(())
+}
+
+/// Lets the user choose among the workspace files in `dir`.
+fn pick_workspace_file(dir: &Path) -> Result<PathBuf> {
+    let mut files = find_workspace_files(dir)?;
+    if files.is_empty() {
+        anyhow::bail!("No .code-workspace file found in {}", dir.display());
+    }
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!("Not running in a terminal; name the workspace file to update");
+    }
+    let items: Vec<String> = files
+        .iter()
+        .map(|file| {
+            file.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    let index = dialoguer::FuzzySelect::new()
+        .with_prompt("Workspace file to update")
+        .items(&items)
+        .default(0)
+        .interact()
+        .context("Workspace selection was cancelled")?;
+    Ok(files.remove(index))
+}
+
+fn tasks(command: &TasksCommand) -> Result<()> {
+    match command {
+        TasksCommand::Remove(args) => remove_tasks(args),
+    }
+}
+
+fn remove_tasks(args: &TaskRemoveArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let files = match args.files.as_slice() {
+        [] => vec![locate_workspace_file(&current_dir, None)?],
+        files => files.iter().map(|file| current_dir.join(file)).collect(),
+    };
+    for path in &files {
+        let mut workspace = read_workspace(path)?;
+        match strip_managed_task(&mut workspace, args.label.as_deref()) {
+            Some(task) => {
+                write_workspace(path, &workspace, &WriteOptions::default())?;
+                let section = if workspace.tasks.is_none() {
+                    " and the now empty tasks section"
+                } else {
+                    ""
+                };
+                println!(
+                    "Removed the '{}' task{} from '{}'",
+                    task.label,
+                    section,
+                    path.display()
+                );
+            }
+            None => println!("No managed task in '{}'", path.display()),
+        }
+    }
+    Ok(())
+}
+
+fn workspaces(command: &WorkspacesCommand) -> Result<()> {
+    let registry_path =
+        registry::registry_path().context("Could not determine the data directory")?;
+    let mut registry = registry::Registry::load(&registry_path)?;
+
+    match command {
+        WorkspacesCommand::List { json } => {
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&registry.workspaces)?);
+                return Ok(());
+            }
+            if registry.workspaces.is_empty() {
+                println!("No workspaces registered yet");
+            }
+            let now = registry::now();
+            for entry in &registry.workspaces {
+                let missing = if entry.exists() { "" } else { "  [missing]" };
+                println!(
+                    "{}  (scans {}, updated {}){}",
+                    entry.workspace_file.display(),
+                    entry.scan_path.display(),
+                    format_age(now.saturating_sub(entry.updated_at)),
+                    missing
+                );
+            }
+            if registry.workspaces.iter().any(|entry| !entry.exists()) {
+                println!("Run 'workspace-manager workspaces prune' to forget missing files");
+            }
+        }
+        WorkspacesCommand::Refresh(args) => {
+            let files: Vec<PathBuf> = if args.all {
+                registry
+                    .workspaces
+                    .iter()
+                    .map(|entry| entry.workspace_file.clone())
+                    .collect()
+            } else {
+                let current_dir = env::current_dir()?;
+                args.files
+                    .iter()
+                    .map(|file| current_dir.join(file))
+                    .collect()
+            };
+            let mut failed = 0;
+            for file in &files {
+                if !file.is_file() {
+                    console::warning(format_args!(
+                        "skipping '{}', which no longer exists",
+                        file.display()
+                    ));
+                    continue;
+                }
+                if let Err(err) = update_workspace_file(file) {
+                    eprintln!("Error: failed to refresh '{}': {:#}", file.display(), err);
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!(
+                    "{} of {} workspaces could not be refreshed",
+                    failed,
+                    files.len()
+                );
+            }
+        }
+        WorkspacesCommand::Prune => {
+            let removed = registry.remove_missing();
+            registry.save(&registry_path)?;
+            for entry in &removed {
+                println!("Forgot '{}'", entry.workspace_file.display());
+            }
+            println!(
+                "{} missing workspaces removed from the registry",
+                removed.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A coarse human-readable age, e.g. `5 minutes ago`.
+fn format_age(seconds: u64) -> String {
+    let (value, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        value,
+        unit,
+        if value == 1 { "" } else { "s" }
+    )
+}
+
+fn add(args: &AddArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.workspace_file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+
+    let folder = current_dir.join(&args.folder);
+    let folder = folder.canonicalize().unwrap_or(folder);
+
+    let mut workspace = read_workspace(&workspace_path)?;
+    let added = add_folder(
+        &mut workspace,
+        workspace_dir,
+        &folder,
+        args.name.as_deref(),
+        args.force,
     )?;
+    write_workspace(&workspace_path, &workspace, &WriteOptions::default())?;
+
+    println!(
+        "Added '{}' ({}) to '{}'",
+        added.name,
+        added.path,
+        workspace_path.display()
+    );
+    Ok(())
+}
+
+fn remove(args: &RemoveArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.workspace_file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+
+    let selector = match (&args.name, &args.path) {
+        (_, Some(path)) => path_selector(&current_dir, workspace_dir, path),
+        (Some(name), None) => FolderSelector::Name(name.clone()),
+        (None, None) => anyhow::bail!("Specify a folder name or --path"),
+    };
+
+    let mut workspace = read_workspace(&workspace_path)?;
+    let removed = remove_folders(&mut workspace, &selector)?;
+    if removed.is_empty() {
+        anyhow::bail!("No matching folder in '{}'", workspace_path.display());
+    }
+    if workspace.folders.is_empty() && !args.force {
+        anyhow::bail!(
+            "Removing this would leave '{}' without folders, which VS Code handles poorly (use --force to remove it anyway)",
+            workspace_path.display()
+        );
+    }
+
+    // Tasks generated for the removed folders go with them
+    let options = WriteOptions {
+        folders_only: folder_tasks::prune_folder_tasks(&mut workspace) == 0,
+        ..Default::default()
+    };
+    write_workspace(&workspace_path, &workspace, &options)?;
 
-    let workspace_json = serde_json::to_string_pretty(&workspace)?;
-    fs::write(&workspace_filename, workspace_json)?;
+    let out = console::stdout();
+    for folder in &removed {
+        println!("Removed '{}' ({})", out.name(&folder.name), folder.path);
+    }
+    if workspace.folders.is_empty() {
+        console::warning(format_args!(
+            "'{}' has no folders left",
+            workspace_path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn rename(args: &RenameArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.workspace_file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+
+    let (selector, new_name) = match (args.names.as_slice(), &args.path) {
+        ([new_name], Some(path)) => (path_selector(&current_dir, workspace_dir, path), new_name),
+        ([old_name, new_name], None) => (FolderSelector::Name(old_name.clone()), new_name),
+        (_, Some(_)) => anyhow::bail!("With --path only the new name is expected"),
+        (_, None) => anyhow::bail!("Expected the current name and the new name"),
+    };
+
+    let mut workspace = read_workspace(&workspace_path)?;
+    let (old_name, new_name) =
+        rename_folder(&mut workspace, &selector, new_name, args.keep_prefix)?;
+    write_workspace(&workspace_path, &workspace, &WriteOptions::default())?;
 
+    let out = console::stdout();
     println!(
-        "Workspace file '{}' updated successfully!",
-        workspace_filename
+        "Renamed '{}' to '{}'",
+        out.name(&old_name),
+        out.name(&new_name)
+    );
+    Ok(())
+}
+
+/// Matches `path` as given and relative to the workspace file's directory.
+fn path_selector(current_dir: &Path, workspace_dir: &Path, path: &Path) -> FolderSelector {
+    let mut paths = vec![path.to_string_lossy().to_string()];
+    if let Some(relative) = pathdiff::diff_paths(current_dir.join(path), workspace_dir) {
+        paths.push(relative.to_string_lossy().to_string());
+    }
+    FolderSelector::Path(paths)
+}
+
+fn list(args: &ListArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+    let workspace = read_workspace(&workspace_path)?;
+    let listing = list_folders(&workspace, workspace_dir);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&listing)?);
+        return Ok(());
+    }
+
+    let out = console::stdout();
+    if args.tree {
+        let tree = folder_tree(&listing, args.depth.map(|depth| depth as usize));
+        let file_name = workspace_path.file_name().unwrap_or_default();
+        println!("{}", file_name.to_string_lossy());
+        print_tree(&tree, "", &out);
+        return Ok(());
+    }
+
+    let location = |folder: &FolderListing| folder.uri.clone().unwrap_or(folder.path.clone());
+
+    let width = listing
+        .iter()
+        .map(|folder| out.name(&folder.name).chars().count())
+        .max()
+        .unwrap_or(0);
+    for folder in &listing {
+        println!(
+            "{:width$}  {}{}",
+            out.name(&folder.name),
+            location(folder),
+            folder_status(folder),
+            width = width
+        );
+    }
+    Ok(())
+}
+
+/// What `list` says of whether `folder` exists, after two spaces when anything.
+fn folder_status(folder: &FolderListing) -> &'static str {
+    match folder.exists {
+        Some(true) => "",
+        Some(false) => "  (missing)",
+        None => "  (uri, not checked)",
+    }
+}
+
+/// Prints `nodes` below a line drawn with `prefix`, one line per directory with the
+/// folders at it, in box-drawing characters or, when `out` can't show them, ASCII.
+fn print_tree(nodes: &[FolderTreeNode], prefix: &str, out: &console::Style) {
+    let (branch, last, pipe) = match out.box_drawing {
+        true => ("├── ", "└── ", "│   "),
+        false => ("|-- ", "`-- ", "|   "),
+    };
+    for (index, node) in nodes.iter().enumerate() {
+        let is_last = index + 1 == nodes.len();
+        let mut line = format!(
+            "{}{}{}",
+            prefix,
+            if is_last { last } else { branch },
+            node.label
+        );
+        if node.folders.is_empty() {
+            line.push('/');
+        }
+        for folder in &node.folders {
+            line.push_str("  ");
+            line.push_str(&out.name(&folder.name));
+            if !folder.project_types.is_empty() {
+                line.push_str(&format!(" [{}]", folder.project_types.join(", ")));
+            }
+            line.push_str(folder_status(folder));
+        }
+        if node.collapsed > 0 {
+            line.push_str(&format!("  (+{} folder(s) below)", node.collapsed));
+        }
+        println!("{}", line);
+        let prefix = format!("{}{}", prefix, if is_last { "    " } else { pipe });
+        print_tree(&node.children, &prefix, out);
+    }
+}
+
+fn clean(args: &CleanArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+
+    let mut workspace = read_workspace(&workspace_path)?;
+    let removed = remove_missing_folders(&mut workspace, workspace_dir);
+    if removed.is_empty() {
+        println!("No missing folders in '{}'", workspace_path.display());
+        return Ok(());
+    }
+
+    let verb = if args.dry_run {
+        "Would remove"
+    } else {
+        "Removed"
+    };
+    let out = console::stdout();
+    for folder in &removed {
+        println!("{} '{}' ({})", verb, out.name(&folder.name), folder.path);
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let options = WriteOptions {
+        folders_only: true,
+        ..Default::default()
+    };
+    write_workspace(&workspace_path, &workspace, &options)?;
+    Ok(())
+}
+
+fn merge(args: &MergeArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let output = current_dir.join(&args.output);
+    let output_dir = output
+        .parent()
+        .unwrap_or(&current_dir)
+        .canonicalize()
+        .with_context(|| format!("Output directory for '{}' does not exist", output.display()))?;
+    let preferred = match &args.prefer {
+        Some(prefer) => Some(current_dir.join(prefer).canonicalize()?),
+        None => None,
+    };
+
+    let mut merged: Option<WorkspaceFile> = None;
+    let mut container_root: Option<(Option<String>, &Path)> = None;
+    for file in &args.files {
+        let path = current_dir
+            .join(file)
+            .canonicalize()
+            .with_context(|| format!("Workspace file '{}' not found", file.display()))?;
+        let mut workspace = read_workspace(&path)?;
+        // Host and container paths don't mix: the result would work in neither place
+        let root = workspace.metadata().container_root;
+        match &container_root {
+            None => container_root = Some((root, file)),
+            Some((first_root, first)) if *first_root != root => {
+                let describe = |root: &Option<String>| match root {
+                    Some(root) => format!("container paths under '{}'", root),
+                    None => "host paths".to_string(),
+                };
+                anyhow::bail!(
+                    "'{}' uses {} but '{}' uses {}; merge files of one kind",
+                    first.display(),
+                    describe(first_root),
+                    file.display(),
+                    describe(&root)
+                );
+            }
+            Some(_) => {}
+        }
+        rebase_folders(
+            &mut workspace,
+            path.parent().unwrap_or(&current_dir),
+            &output_dir,
+        );
+
+        let Some(merged) = merged.as_mut() else {
+            merged = Some(workspace);
+            continue;
+        };
+        let (strategy, settings) = if preferred.as_ref() == Some(&path) {
+            (MergeStrategy::Theirs, SettingsMerge::Theirs)
+        } else {
+            (MergeStrategy::Ours, SettingsMerge::Ours)
+        };
+        let report = merged
+            .merge(
+                &workspace,
+                strategy,
+                args.settings_merge.unwrap_or(settings),
+            )
+            .with_context(|| format!("Failed to merge '{}'", file.display()))?;
+        for folder in &report.duplicate_folders {
+            console::warning(format_args!(
+                "folder '{}' appears in several inputs",
+                folder
+            ));
+        }
+        for label in &report.duplicate_tasks {
+            console::warning(format_args!("task '{}' appears in several inputs", label));
+        }
+        for key in &report.conflicts {
+            console::warning(format_args!("conflicting values for '{}'", key));
+        }
+    }
+
+    let merged = merged.unwrap_or_default();
+    write_workspace(&output, &merged, &WriteOptions::default())?;
+    println!(
+        "Merged {} files into '{}' ({} folders)",
+        args.files.len(),
+        output.display(),
+        merged.folders.len()
+    );
+    Ok(())
+}
+
+fn diff(args: &DiffArgs) -> Result<ExitCode> {
+    let current_dir = env::current_dir()?;
+    let old_path = current_dir.join(&args.old);
+    let new_path = current_dir.join(&args.new);
+    let old = read_workspace(&old_path)?;
+    let mut new = read_workspace(&new_path)?;
+    // Compare folder paths as seen from the old file's directory
+    rebase_folders(
+        &mut new,
+        new_path.parent().unwrap_or(&current_dir),
+        old_path.parent().unwrap_or(&current_dir),
     );
+
+    let diff = old.diff(&new);
+    let code = match diff.is_empty() {
+        true => ExitCode::Success,
+        false => ExitCode::CheckFailed,
+    };
+    if args.json {
+        let mut value = serde_json::to_value(&diff)?;
+        value["status"] = (code as u8).into();
+        println!("{}", serde_json::to_string_pretty(&value)?);
+    } else {
+        let out = console::stdout();
+        for line in diff.to_string().lines() {
+            println!("{}", out.diff_line(&out.name(line)));
+        }
+    }
+    Ok(code)
+}
+
+fn export(args: &ExportArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+    let workspace = read_workspace(&workspace_path)?;
+    let output = |content: &str| -> Result<()> {
+        match &args.output {
+            Some(path) => Ok(write_atomic(path, content.as_bytes())?),
+            None => Ok(io::stdout().write_all(content.as_bytes())?),
+        }
+    };
+
+    if args.markdown {
+        let probes = stats::probe_folders(&workspace, workspace_dir, stats::PROBE_TIMEOUT);
+        let title = workspace_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let text = markdown::render_markdown(&title, &workspace, &probes, SystemTime::now());
+        return output(&text);
+    }
+    let field = if args.names {
+        ExportField::Name
+    } else if args.relative {
+        ExportField::RelativePath
+    } else {
+        ExportField::AbsolutePath
+    };
+    let (values, skipped) = export_folders(&workspace, workspace_dir, field, args.include_uris);
+    for folder in skipped {
+        console::warning(format_args!(
+            "skipping uri folder '{}' (use --include-uris to print it)",
+            folder.name
+        ));
+    }
+
+    output(&format_export(&values, args.format)?)
+}
+
+fn stats(args: &StatsArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+    let workspace = read_workspace(&workspace_path)?;
+    let timeout = Duration::from_secs(args.timeout);
+    let stats = stats::workspace_stats(&workspace, workspace_dir, timeout);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    let mut types: Vec<String> = stats
+        .project_types
+        .iter()
+        .map(|(name, count)| format!("{}: {}", name, count))
+        .collect();
+    types.push(format!("unknown: {}", stats.unknown));
+    println!("Folders: {}", stats.folders);
+    println!("Project types: {}", types.join(", "));
+    println!("Missing: {}", stats.missing);
+    if stats.uri_folders > 0 {
+        println!("Uri folders (not checked): {}", stats.uri_folders);
+    }
+    if stats.timed_out > 0 {
+        println!(
+            "Timed out: {} (not probed within {}s)",
+            stats.timed_out, args.timeout
+        );
+    }
+    let task = workspace.managed_task_label();
+    match stats.managed_task {
+        true => println!("Managed task: yes ('{}')", task),
+        false => println!("Managed task: no"),
+    }
+    Ok(())
+}
+
+fn restore(args: &RestoreArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+    let workspace = read_workspace(&workspace_path)?;
+    let plan = restore::plan_restore(&workspace, workspace_dir);
+
+    for path in &plan.no_remote {
+        println!("Skipped '{}': no remote recorded", path);
+    }
+    if args.dry_run {
+        for (path, reason) in &plan.blocked {
+            console::warning(format_args!("would not clone into '{}': {}", path, reason));
+        }
+        for job in &plan.clones {
+            println!("Would clone {} into '{}'", job.url, job.path);
+        }
+    } else {
+        for (path, reason) in &plan.blocked {
+            eprintln!("Error: not cloning into '{}': {}", path, reason);
+        }
+        let results =
+            restore::clone_folders(&plan.clones, &restore::SystemRunner, args.jobs as usize);
+        for (job, result) in plan.clones.iter().zip(&results) {
+            match result {
+                Ok(()) => println!("Cloned {} into '{}'", job.url, job.path),
+                Err(err) => eprintln!(
+                    "Error: failed to clone {} into '{}': {:#}",
+                    job.url, job.path, err
+                ),
+            }
+        }
+        let failed = results.iter().filter(|result| result.is_err()).count() + plan.blocked.len();
+        if failed > 0 {
+            anyhow::bail!(
+                "{} of {} missing folder(s) could not be restored",
+                failed,
+                plan.clones.len() + plan.blocked.len()
+            );
+        }
+    }
+    if plan.clones.is_empty() && plan.blocked.is_empty() && plan.no_remote.is_empty() {
+        println!("No missing folders in '{}'", workspace_path.display());
+    }
     Ok(())
 }
+
+/// The workspace file `delete` is given: a path when it names an existing file, has a
+/// directory or ends in `.code-workspace`, otherwise the name of one in `dir`.
+fn delete_target(dir: &Path, target: &str) -> Result<PathBuf> {
+    let path = dir.join(target);
+    let is_path = path.is_file()
+        || target.ends_with(".code-workspace")
+        || Path::new(target).components().count() > 1;
+    let path = match is_path {
+        true => path,
+        false => dir.join(format!("{}.code-workspace", target)),
+    };
+    if path
+        .extension()
+        .is_none_or(|extension| extension != "code-workspace")
+    {
+        anyhow::bail!(
+            "Refusing to delete '{}', which is not a .code-workspace file",
+            path.display()
+        );
+    }
+    // The registry has the path the file was written to, below a canonical directory
+    let parent = path.parent().and_then(|parent| parent.canonicalize().ok());
+    Ok(match (parent, path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path,
+    })
+}
+
+fn delete(args: &DeleteArgs) -> Result<()> {
+    let current_dir = env::current_dir()?;
+    let path = delete_target(&current_dir, &args.target)?;
+    let shown = path
+        .strip_prefix(&current_dir)
+        .unwrap_or(&path)
+        .display()
+        .to_string();
+
+    // The file and the registry are handled independently, so one failing still lets
+    // the other be cleaned up
+    let mut failures = vec![];
+    if !path.exists() {
+        console::warning(format_args!("'{}' does not exist", shown));
+    } else if args.clean_task_only {
+        match remove_managed_task(&path) {
+            Ok(Some(task)) => println!("Removed the '{}' task from '{}'", task.label, shown),
+            Ok(None) => println!("No managed task in '{}'", shown),
+            Err(err) => failures.push(format!(
+                "failed to remove the task from '{}': {:#}",
+                shown, err
+            )),
+        }
+    } else {
+        confirm_deletion(&shown, args.yes)?;
+        match fs::remove_file(&path) {
+            Ok(()) => println!("Deleted '{}'", shown),
+            Err(err) => failures.push(format!("failed to delete '{}': {}", shown, err)),
+        }
+    }
+
+    match registry::forget_workspace(&path) {
+        Ok(true) => println!("Removed '{}' from the workspace registry", shown),
+        Ok(false) => {}
+        Err(err) => failures.push(format!(
+            "could not update the workspace registry: {:#}",
+            err
+        )),
+    }
+    if !failures.is_empty() {
+        for failure in &failures[1..] {
+            eprintln!("Error: {}", failure);
+        }
+        anyhow::bail!("{}", failures[0]);
+    }
+    Ok(())
+}
+
+/// Removes the managed task of the workspace file at `path`, returning it.
+fn remove_managed_task(path: &Path) -> Result<Option<Task>> {
+    let mut workspace = read_workspace(path)?;
+    let task = strip_managed_task(&mut workspace, None);
+    if task.is_some() {
+        write_workspace(path, &workspace, &WriteOptions::default())?;
+    }
+    Ok(task)
+}
+
+/// Asks before `delete` removes the workspace file `shown`, unless `yes`. Without a
+/// terminal to ask on, nothing is deleted without `yes`.
+fn confirm_deletion(shown: &str, yes: bool) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        anyhow::bail!(
+            "Not running in a terminal; pass --yes to delete '{}'",
+            shown
+        );
+    }
+    let confirmed = dialoguer::Confirm::new()
+        .with_prompt(format!("Delete '{}'?", shown))
+        .default(false)
+        .interact()
+        .context("Confirmation was cancelled")?;
+    if !confirmed {
+        anyhow::bail!("Nothing was deleted");
+    }
+    Ok(())
+}
+
+/// Points the managed task of the workspace file at `path`, read as `content`, at this
+/// tool, as `--task-command` resolves for its options, when the binary it runs no
+/// longer exists, and returns the content as it is afterwards.
+fn repair_task(path: &Path, content: String) -> Result<String> {
+    let Ok(mut workspace) = parse_workspace(&content) else {
+        // Reported by the checks
+        return Ok(content);
+    };
+    if validate::stale_task_command(&workspace).is_none() {
+        return Ok(content);
+    }
+    let kind = configured_task_command(&workspace, path).unwrap_or_else(|err| {
+        console::warning(format_args!(
+            "Could not resolve the options of the task, so it runs the default command: {:#}",
+            err
+        ));
+        TaskCommand::default()
+    });
+    let Some(stale) = repair_task_command(&mut workspace, kind) else {
+        return Ok(content);
+    };
+    write_workspace(path, &workspace, &WriteOptions::default())?;
+    eprintln!(
+        "Repaired the '{}' task: it ran '{}', which no longer exists, and now runs '{}'",
+        workspace.managed_task_label(),
+        stale,
+        task_command(kind)
+    );
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// The `--task-command` of `workspace`, the file at `path`: its task arguments resolved
+/// with the configuration that applies to them, as `update` would.
+fn configured_task_command(workspace: &WorkspaceFile, path: &Path) -> Result<TaskCommand> {
+    let task_args = workspace.generation_args().unwrap_or_default();
+    let mut args = GenerateArgs::from_task_args(&task_args)?;
+    // Task arguments are relative to the directory holding the workspace file
+    let dir = path.parent().unwrap_or(Path::new("."));
+    args.path = dir.join(&args.path).to_string_lossy().into_owned();
+    Ok(args.resolve(dir)?.options.task_command)
+}
+
+fn doctor(args: &DoctorArgs) -> Result<ExitCode> {
+    let current_dir = env::current_dir()?;
+    let workspace_path = locate_workspace_file(&current_dir, args.file.as_deref())?;
+    let workspace_dir = workspace_path.parent().unwrap_or(&current_dir);
+    let mut content = fs::read_to_string(&workspace_path)
+        .with_context(|| format!("Failed to read {}", workspace_path.display()))?;
+    if args.fix {
+        content = repair_task(&workspace_path, content)?;
+    }
+
+    let findings = validate::diagnose(&content, workspace_dir);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&findings)?);
+    } else if findings.is_empty() {
+        println!("No problems found in '{}'", workspace_path.display());
+    } else {
+        let out = console::stdout();
+        for finding in &findings {
+            let severity = match finding.severity {
+                validate::Severity::Error => out.paint("error", console::Color::Red),
+                validate::Severity::Warning => out.paint("warning", console::Color::Yellow),
+                validate::Severity::Info => "info".into(),
+            };
+            println!("{}[{}]: {}", severity, finding.code, finding.message);
+        }
+    }
+
+    if findings.iter().any(|finding| finding.code == "parse-error") {
+        Ok(ExitCode::MalformedWorkspace)
+    } else if validate::has_errors(&findings) {
+        Ok(ExitCode::CheckFailed)
+    } else {
+        Ok(ExitCode::Success)
+    }
+}
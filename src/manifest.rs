@@ -0,0 +1,174 @@
+//! Folder manifests read by `--folders-file`: checked-in lists of the directories a
+//! workspace has, curated in review rather than found by a scan.
+
+use crate::console;
+use crate::error::Result;
+use crate::filesystem::FileSystem;
+use crate::jsonc;
+use crate::options::MissingFolders;
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A directory listed in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The directory as listed; relative paths are relative to the manifest
+    pub path: PathBuf,
+    /// Display name given by a JSON manifest, kept like a name chosen with `rename`
+    pub name: Option<String>,
+}
+
+/// An entry of a JSON manifest.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonEntry {
+    path: String,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Parses a manifest: a JSON array of `{"path": ..., "name": ...}` objects, comments
+/// allowed, when `json`, and otherwise one path per line, leaving out blank lines and
+/// lines starting with `#`.
+pub fn parse_manifest(content: &str, json: bool) -> Result<Vec<ManifestEntry>> {
+    if json {
+        let entries: Vec<JsonEntry> = serde_json::from_str(&jsonc::to_json(content))
+            .context("Expected a JSON array of {\"path\", \"name\"} objects")?;
+        return Ok(entries
+            .into_iter()
+            .map(|entry| ManifestEntry {
+                path: PathBuf::from(entry.path),
+                name: entry.name,
+            })
+            .collect());
+    }
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| ManifestEntry {
+            path: PathBuf::from(line),
+            name: None,
+        })
+        .collect())
+}
+
+/// The directories listed in the manifest at `path`, read from `fs`, in order and
+/// with canonical paths. Its format follows its extension: `.json` for JSON, any
+/// other for lines. Listed paths that aren't directories are left out with a
+/// warning naming them all, or fail the read when `missing` asks to.
+pub fn read_manifest_with(
+    fs: &dyn FileSystem,
+    path: &Path,
+    missing: MissingFolders,
+) -> Result<Vec<ManifestEntry>> {
+    let content = fs
+        .read_to_string(path)
+        .with_context(|| format!("Failed to read folder manifest '{}'", path.display()))?;
+    let json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let entries = parse_manifest(&content, json)
+        .with_context(|| format!("Invalid folder manifest '{}'", path.display()))?;
+    let manifest_dir = fs
+        .canonicalize(path)
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+
+    let mut found: Vec<ManifestEntry> = vec![];
+    let mut not_found = vec![];
+    for entry in entries {
+        match fs.canonicalize(&manifest_dir.join(&entry.path)) {
+            Ok(dir) if fs.is_dir(&dir) => {
+                if !found.iter().any(|found| found.path == dir) {
+                    found.push(ManifestEntry { path: dir, ..entry });
+                }
+            }
+            _ => not_found.push(format!("\n  {}", entry.path.display())),
+        }
+    }
+    if !not_found.is_empty() {
+        let message = format!(
+            "{} folder(s) listed in '{}' are not directories:{}",
+            not_found.len(),
+            path.display(),
+            not_found.concat()
+        );
+        match missing {
+            MissingFolders::Warn => console::warning(format_args!("{}", message)),
+            MissingFolders::Error => return Err(anyhow::anyhow!(message).into()),
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFs;
+    use anyhow::Result;
+
+    #[test]
+    fn test_parse_manifest() -> Result<()> {
+        let lines = "# Services\napi\n\n  web  \n# web-old\n../shared/lib\n";
+        let paths: Vec<PathBuf> = parse_manifest(lines, false)?
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("api"),
+                Path::new("web"),
+                Path::new("../shared/lib")
+            ]
+        );
+
+        let json = r#"[
+            // The main service
+            {"path": "api", "name": "Backend"},
+            {"path": "web"}
+        ]"#;
+        let entries = parse_manifest(json, true)?;
+        assert_eq!(entries[0].name.as_deref(), Some("Backend"));
+        assert_eq!(entries[1].name, None);
+        assert!(parse_manifest(r#"{"path": "api"}"#, true).is_err());
+        assert!(parse_manifest(r#"[{"dir": "api"}]"#, true).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_manifest() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_dir("/repo/services/api");
+        fs.add_dir("/repo/services/web");
+        fs.add_file("/repo/services/README.md", "");
+        fs.add_file(
+            "/repo/folders.txt",
+            "services/web\nservices/api\nservices/README.md\nservices/gone\n/repo/services/api\n",
+        );
+
+        let entries =
+            read_manifest_with(&fs, Path::new("/repo/folders.txt"), MissingFolders::Warn)?;
+        let paths: Vec<&Path> = entries.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("/repo/services/web"),
+                Path::new("/repo/services/api")
+            ]
+        );
+
+        let err = read_manifest_with(&fs, Path::new("/repo/folders.txt"), MissingFolders::Error)
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(message.starts_with("2 folder(s)"), "{}", message);
+        assert!(message.contains("services/gone"), "{}", message);
+        assert!(
+            read_manifest_with(&fs, Path::new("/repo/none.txt"), MissingFolders::Warn).is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! The Markdown inventory written by `export --markdown`: a table of the folders of a
+//! workspace with the kind of project, git remote and branch of each, for wikis and
+//! other places people rather than editors read. Rendering takes folders already
+//! probed by [`probe_folders`](crate::stats::probe_folders), so it never touches the
+//! disk itself.
+
+use crate::model::{normalize_folder_path, WorkspaceFile, WorkspaceFolder};
+use crate::stats::FolderProbe;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A Markdown document titled `title` with one table row per folder of `workspace`,
+/// using `probes`, one per folder as [`probe_folders`](crate::stats::probe_folders)
+/// gives them, and a footer saying the table was generated at `generated_at`.
+///
+/// A folder's remote is the one recorded in the metadata section, or else the one
+/// its probe found. Pipes and line breaks in values are escaped, so no folder name
+/// can break the table.
+pub fn render_markdown(
+    title: &str,
+    workspace: &WorkspaceFile,
+    probes: &[Option<FolderProbe>],
+    generated_at: SystemTime,
+) -> String {
+    let recorded = workspace.metadata().folders;
+    let mut text = format!("# {}\n\n", escape(title));
+    text.push_str("| Folder | Path | Type | Remote | Branch |\n");
+    text.push_str("| --- | --- | --- | --- | --- |\n");
+    for (index, folder) in workspace.folders.iter().enumerate() {
+        let probe = probes.get(index).and_then(Option::as_ref);
+        let remote = recorded
+            .get(&normalize_folder_path(&folder.path))
+            .and_then(|folder| folder.remote.clone())
+            .or_else(|| probe.and_then(|probe| probe.remote.clone()));
+        let cells = [
+            display_name(folder),
+            folder.uri.clone().unwrap_or_else(|| folder.path.clone()),
+            project_type(folder, probe),
+            remote.unwrap_or_default(),
+            probe
+                .and_then(|probe| probe.branch.clone())
+                .unwrap_or_default(),
+        ];
+        let cells: Vec<String> = cells.iter().map(|cell| escape(cell)).collect();
+        text.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    text.push_str(&format!(
+        "\n_Generated by {} {} on {}_\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        format_utc(generated_at)
+    ));
+    text
+}
+
+/// The name VS Code shows for `folder`: its own, or else the last part of its path.
+fn display_name(folder: &WorkspaceFolder) -> String {
+    if !folder.name.is_empty() {
+        return folder.name.clone();
+    }
+    let path = normalize_folder_path(&folder.path);
+    path.rsplit('/').next().unwrap_or(&path).to_string()
+}
+
+fn project_type(folder: &WorkspaceFolder, probe: Option<&FolderProbe>) -> String {
+    match probe {
+        None if folder.uri.is_some() => "uri".to_string(),
+        None => "not probed".to_string(),
+        Some(probe) if !probe.exists => "missing".to_string(),
+        Some(probe) if probe.ecosystems.is_empty() => "unknown".to_string(),
+        Some(probe) => {
+            let names: Vec<&str> = probe.ecosystems.iter().map(|e| e.name()).collect();
+            names.join(", ")
+        }
+    }
+}
+
+/// `value` as the content of a table cell: pipes escaped and line breaks as spaces.
+fn escape(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace("\r\n", " ")
+        .replace(['\n', '\r'], " ")
+}
+
+/// `time` as `YYYY-MM-DD HH:MM UTC`.
+fn format_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folder_tasks::Ecosystem;
+    use crate::io::parse_workspace;
+    use anyhow::Result;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_markdown() -> Result<()> {
+        let workspace = parse_workspace(
+            r#"{
+                "folders": [
+                    {"path": "services/api", "name": "📦 api"},
+                    {"path": "web", "name": "front | back"},
+                    {"path": "./docs/"},
+                    {"path": "gone"},
+                    {"path": "slow"},
+                    {"uri": "vscode-remote://ssh-remote+host/srv", "name": "srv"}
+                ],
+                "workspaceManager": {"folders": {
+                    "services/api": {"remote": "git@example.com:org/api.git"}
+                }}
+            }"#,
+        )?;
+        let found = |ecosystems: Vec<Ecosystem>, branch: Option<&str>, remote: Option<&str>| {
+            Some(FolderProbe {
+                exists: true,
+                ecosystems,
+                branch: branch.map(String::from),
+                remote: remote.map(String::from),
+            })
+        };
+        let probes = [
+            found(vec![Ecosystem::Cargo], Some("main"), Some("ignored")),
+            found(
+                vec![Ecosystem::Npm, Ecosystem::Python],
+                Some("feature/a|b"),
+                Some("https://example.com/org/web.git"),
+            ),
+            found(vec![], None, None),
+            Some(FolderProbe {
+                exists: false,
+                ecosystems: vec![],
+                branch: None,
+                remote: None,
+            }),
+            None,
+            None,
+        ];
+        let generated_at = UNIX_EPOCH + Duration::from_secs(1_760_523_000);
+
+        let text = render_markdown("team | platform", &workspace, &probes, generated_at);
+        let expected = format!(
+            "# team \\| platform\n\
+             \n\
+             | Folder | Path | Type | Remote | Branch |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | 📦 api | services/api | cargo | git@example.com:org/api.git | main |\n\
+             | front \\| back | web | npm, python | https://example.com/org/web.git | feature/a\\|b |\n\
+             | docs | ./docs/ | unknown |  |  |\n\
+             | gone | gone | missing |  |  |\n\
+             | slow | slow | not probed |  |  |\n\
+             | srv | vscode-remote://ssh-remote+host/srv | uri |  |  |\n\
+             \n\
+             _Generated by workspace-manager {} on 2025-10-15 10:10 UTC_\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        assert_eq!(text, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_utc() {
+        let at = |secs| format_utc(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01 00:00 UTC");
+        assert_eq!(at(951_868_800), "2000-03-01 00:00 UTC");
+        assert_eq!(at(951_782_399), "2000-02-28 23:59 UTC");
+        assert_eq!(at(1_709_208_000), "2024-02-29 12:00 UTC");
+    }
+}
@@ -0,0 +1,1727 @@
+//! The `.code-workspace` file format: folders, tasks, launch configurations and the
+//! tool's own metadata, with merging and semantic comparison of whole files.
+
+use crate::error::Result;
+use crate::options::SettingsMerge;
+use crate::tasks::UPDATE_TASK_LABEL;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A task of the workspace `tasks` list, as VS Code reads it.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Task {
+    pub label: String,
+    // Compound tasks that only list dependsOn have no type, command or args
+    #[serde(rename = "type", default, skip_serializing_if = "String::is_empty")]
+    pub task_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<TaskGroup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presentation: Option<TaskPresentation>,
+    /// A matcher name, a matcher object or a list of either; `[]` for none
+    #[serde(rename = "problemMatcher", skip_serializing_if = "Option::is_none")]
+    pub problem_matcher: Option<serde_json::Value>,
+    #[serde(rename = "runOptions", skip_serializing_if = "Option::is_none")]
+    pub run_options: Option<TaskRunOptions>,
+    // Fields such as dependsOn or options on tasks the user wrote
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The `group` of a task: a plain kind such as `"build"`, or an object.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum TaskGroup {
+    Kind(String),
+    Detailed {
+        kind: String,
+        /// `true`, or a glob of the files the task is the default for
+        #[serde(rename = "isDefault", skip_serializing_if = "Option::is_none")]
+        is_default: Option<serde_json::Value>,
+        #[serde(flatten)]
+        other: serde_json::Map<String, serde_json::Value>,
+    },
+}
+
+/// The `extensions` section: extensions VS Code suggests installing.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Extensions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recommendations: Vec<String>,
+    // unwantedRecommendations and whatever else VS Code adds
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The `launch` section: debug configurations, shaped like `.vscode/launch.json`.
+/// Configurations and compounds are kept as written; only their `name` is read.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Launch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub configurations: Option<Vec<serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compounds: Option<Vec<serde_json::Value>>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Launch {
+    /// Merges `other` into `self`, matching configurations and compounds by name.
+    /// Entries and keys missing here are added; those that differ are replaced when
+    /// `replace` is set. Returns the differing ones, e.g. `configurations.Debug`.
+    pub fn merge(&mut self, other: &Launch, replace: bool) -> Vec<String> {
+        let mut conflicts = vec![];
+        match (&self.version, &other.version) {
+            (None, _) => self.version = other.version.clone(),
+            (Some(own), Some(their)) if own != their => {
+                conflicts.push("version".to_string());
+                if replace {
+                    self.version = other.version.clone();
+                }
+            }
+            _ => {}
+        }
+        for (section, own, their) in [
+            (
+                "configurations",
+                &mut self.configurations,
+                &other.configurations,
+            ),
+            ("compounds", &mut self.compounds, &other.compounds),
+        ] {
+            let Some(their) = their else {
+                continue;
+            };
+            let own = own.get_or_insert_with(Vec::new);
+            for entry in their {
+                let name = entry.get("name");
+                match own
+                    .iter_mut()
+                    .find(|existing| name.is_some() && existing.get("name") == name)
+                {
+                    Some(existing) if existing != entry => {
+                        let name = name.and_then(|name| name.as_str()).unwrap_or_default();
+                        conflicts.push(format!("{}.{}", section, name));
+                        if replace {
+                            *existing = entry.clone();
+                        }
+                    }
+                    Some(_) => {}
+                    None => own.push(entry.clone()),
+                }
+            }
+        }
+        for (key, value) in &other.other {
+            match self.other.get_mut(key) {
+                None => {
+                    self.other.insert(key.clone(), value.clone());
+                }
+                Some(existing) if existing != value => {
+                    conflicts.push(key.clone());
+                    if replace {
+                        *existing = value.clone();
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        conflicts
+    }
+}
+
+/// When and how often VS Code runs a task.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct TaskRunOptions {
+    /// `default`, or `folderOpen` to run the task when the workspace is opened
+    #[serde(rename = "runOn", skip_serializing_if = "Option::is_none")]
+    pub run_on: Option<String>,
+    // instanceLimit, reevaluateOnRerun and the like
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// How VS Code shows the terminal of a task.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct TaskPresentation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reveal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panel: Option<String>,
+    // focus, clear, showReuseMessage and the like
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The `tasks` section of a workspace file.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Tasks {
+    // Kept as written; VS Code only understands 2.0.0 but that is for doctor to report
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+    // Sections such as inputs or options shared by all tasks
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// An entry of the workspace `folders` list: a local path or a uri, with a display
+/// name. Keys other extensions add to the entry are kept in `other`; two entries are
+/// equal when they agree on path, name and uri, whatever those keys hold.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct WorkspaceFolder {
+    // Empty for folders that are given by uri instead
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    // Hand-written workspace files may omit the display name; it stays omitted
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl PartialEq for WorkspaceFolder {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.name == other.name && self.uri == other.uri
+    }
+}
+
+impl WorkspaceFolder {
+    pub fn new(path: impl Into<String>, name: impl Into<String>) -> Self {
+        WorkspaceFolder {
+            path: path.into(),
+            name: name.into(),
+            uri: None,
+            other: serde_json::Map::new(),
+        }
+    }
+
+    /// Where the folder lives on disk, resolved against `workspace_dir`.
+    /// `None` for uri folders, which may point anywhere.
+    pub fn resolve(&self, workspace_dir: &Path) -> Option<PathBuf> {
+        if self.uri.is_some() || self.path.is_empty() {
+            return None;
+        }
+        Some(workspace_dir.join(&self.path))
+    }
+}
+
+/// A `.code-workspace` file. Sections this tool doesn't model are kept in `other`.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct WorkspaceFile {
+    pub folders: Vec<WorkspaceFolder>,
+    /// Workspace settings, kept as written apart from the keys changed by `--set`
+    /// and `--unset`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tasks: Option<Tasks>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Extensions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub launch: Option<Launch>,
+    // Add a catch-all field for other sections
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Top-level key holding this tool's own bookkeeping inside a workspace file.
+pub const METADATA_KEY: &str = "workspaceManager";
+
+/// Top-level key naming the remote host of a workspace opened through Remote-SSH,
+/// Dev Containers or WSL, e.g. `ssh-remote+build-box`.
+pub const REMOTE_AUTHORITY_KEY: &str = "remoteAuthority";
+
+/// Bookkeeping stored under [`METADATA_KEY`].
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagerMetadata {
+    /// Normalized paths of folders whose display name was chosen by the user and
+    /// must survive regeneration
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_names: Vec<String>,
+    /// Normalized paths of scanned directories the user deselected, which
+    /// regeneration must not add back
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_paths: Vec<String>,
+    /// Normalized paths of folders added with `add`, which regeneration keeps unless
+    /// `--replace` asks otherwise
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_paths: Vec<String>,
+    /// Label of the managed task when it is not [`UPDATE_TASK_LABEL`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_label: Option<String>,
+    /// Arguments of the managed task when it is a shell task, whose command line
+    /// can't be parsed back reliably
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_args: Option<Vec<String>>,
+    /// Labels of the tasks generated per folder by `--folder-tasks`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folder_tasks: Vec<String>,
+    /// `files.exclude` keys written by `--hide-nested`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden_paths: Vec<String>,
+    /// `rust-analyzer.linkedProjects` entries written by `--rust-analyzer-links`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linked_projects: Vec<String>,
+    /// Root of the container paths the folders are written with, by `--container-root`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_root: Option<String>,
+    /// Extension recommendations added by `--recommend-extensions`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recommended_extensions: Vec<String>,
+    /// Globs written by `--exclude-heavy-dirs`, by the setting they were written to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub excluded_globs: BTreeMap<String, Vec<String>>,
+    /// Branches appended to display names by `--show-branch`, by normalized folder path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub branches: BTreeMap<String, String>,
+    /// Normalized paths of folders marked dirty by `--git-status annotate`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dirty_paths: Vec<String>,
+    /// What is known of each folder, by normalized folder path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub folders: BTreeMap<String, FolderMetadata>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// What the metadata section records of one folder, under `folders`.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderMetadata {
+    /// URL of the `origin` remote of the folder's git repository, which `restore`
+    /// clones when the folder is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+impl WorkspaceFile {
+    /// The remote host the workspace is opened on, if any.
+    pub fn remote_authority(&self) -> Option<&str> {
+        self.other.get(REMOTE_AUTHORITY_KEY)?.as_str()
+    }
+
+    /// The tool's metadata section, or an empty one when missing or unreadable.
+    pub fn metadata(&self) -> ManagerMetadata {
+        self.other
+            .get(METADATA_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Label of the task managed by this tool, as recorded in the metadata section.
+    pub fn managed_task_label(&self) -> String {
+        self.metadata()
+            .task_label
+            .unwrap_or_else(|| UPDATE_TASK_LABEL.to_string())
+    }
+
+    /// The managed Update Workspace task, if the file has one.
+    pub fn update_task(&self) -> Option<&Task> {
+        let label = self.managed_task_label();
+        self.tasks
+            .as_ref()?
+            .tasks
+            .iter()
+            .find(|task| task.label == label)
+    }
+
+    /// The arguments the file was generated with, as recorded for its managed task.
+    pub fn generation_args(&self) -> Option<Vec<String>> {
+        let task = self.update_task()?;
+        Some(
+            self.metadata()
+                .task_args
+                .unwrap_or_else(|| task.args.clone()),
+        )
+    }
+
+    /// Stores `metadata`, dropping the section entirely when it is empty.
+    pub fn set_metadata(&mut self, metadata: ManagerMetadata) {
+        if metadata == ManagerMetadata::default() {
+            self.other.remove(METADATA_KEY);
+        } else if let Ok(value) = serde_json::to_value(metadata) {
+            self.other.insert(METADATA_KEY.to_string(), value);
+        }
+    }
+}
+
+/// Which side wins when [`WorkspaceFile::merge`] finds a conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// Keep the value already in `self`
+    #[default]
+    Ours,
+    /// Take the value from the workspace being merged in
+    Theirs,
+}
+
+/// What [`WorkspaceFile::merge`] had to resolve.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    /// Normalized paths present in both workspaces
+    pub duplicate_folders: Vec<String>,
+    /// Labels of tasks present in both workspaces
+    pub duplicate_tasks: Vec<String>,
+    /// Keys with different values on each side, e.g. `settings.editor.tabSize`
+    pub conflicts: Vec<String>,
+}
+
+impl WorkspaceFile {
+    /// Merges `other` into `self`. Both must use folder paths relative to the same
+    /// directory (see [`rebase_folders`]).
+    ///
+    /// - folders are unioned by normalized path; a folder without a name takes the
+    ///   other one's, and differing names are reported as `folders.<path>.name`;
+    /// - tasks are concatenated, with one task kept per label;
+    /// - settings are merged by [`merge_settings`] following `settings`, and differing
+    ///   values are reported as `settings.<key>.<nested key>`;
+    /// - extension recommendations are unioned;
+    /// - launch configurations and compounds are matched by name;
+    /// - any other section is merged by its top-level keys.
+    ///
+    /// Whenever both sides disagree outside the settings, `strategy` picks the winner,
+    /// and the disagreement is listed in the returned [`MergeReport`]. Fails, leaving
+    /// `self` unchanged, only when `settings` is [`SettingsMerge::Error`] and the
+    /// settings disagree.
+    pub fn merge(
+        &mut self,
+        other: &WorkspaceFile,
+        strategy: MergeStrategy,
+        settings: SettingsMerge,
+    ) -> Result<MergeReport> {
+        let mut report = MergeReport::default();
+        // Settings go first, so that failing on them leaves `self` unchanged
+        let mut settings_conflicts = vec![];
+        if let Some(their) = &other.settings {
+            let mut own = self.settings.clone().unwrap_or_default();
+            settings_conflicts = merge_settings(&mut own, their, "settings", settings)?;
+            self.settings = Some(own);
+        }
+        let theirs = strategy == MergeStrategy::Theirs;
+
+        for folder in &other.folders {
+            let key = folder_key(folder);
+            match self.folders.iter_mut().find(|own| folder_key(own) == key) {
+                Some(own) => {
+                    if own.name.is_empty() {
+                        own.name = folder.name.clone();
+                    } else if !folder.name.is_empty() && own.name != folder.name {
+                        report.conflicts.push(format!("folders.{}.name", key));
+                        if theirs {
+                            own.name = folder.name.clone();
+                        }
+                    }
+                    report.duplicate_folders.push(key);
+                }
+                None => self.folders.push(folder.clone()),
+            }
+        }
+
+        if let Some(other_tasks) = &other.tasks {
+            let tasks = self.tasks.get_or_insert_with(|| Tasks {
+                version: other_tasks.version.clone(),
+                ..Default::default()
+            });
+            for (key, value) in &other_tasks.other {
+                match tasks.other.get_mut(key) {
+                    None => {
+                        tasks.other.insert(key.clone(), value.clone());
+                    }
+                    Some(own) if own != value => {
+                        report.conflicts.push(format!("tasks.{}", key));
+                        if theirs {
+                            *own = value.clone();
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
+            for task in &other_tasks.tasks {
+                match tasks.tasks.iter_mut().find(|own| own.label == task.label) {
+                    Some(own) => {
+                        report.duplicate_tasks.push(task.label.clone());
+                        if theirs {
+                            *own = task.clone();
+                        }
+                    }
+                    None => tasks.tasks.push(task.clone()),
+                }
+            }
+        }
+
+        if let Some(their) = &other.extensions {
+            let own = self.extensions.get_or_insert_with(Extensions::default);
+            for extension in &their.recommendations {
+                if !own.recommendations.contains(extension) {
+                    own.recommendations.push(extension.clone());
+                }
+            }
+            for (key, value) in &their.other {
+                match own.other.get_mut(key) {
+                    None => {
+                        own.other.insert(key.clone(), value.clone());
+                    }
+                    Some(existing) if existing != value => {
+                        report.conflicts.push(format!("extensions.{}", key));
+                        if theirs {
+                            *existing = value.clone();
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        report.conflicts.append(&mut settings_conflicts);
+
+        if let Some(their) = &other.launch {
+            let own = self.launch.get_or_insert_with(Launch::default);
+            for name in own.merge(their, theirs) {
+                report.conflicts.push(format!("launch.{}", name));
+            }
+        }
+
+        for (key, value) in &other.other {
+            match (self.other.get_mut(key), value) {
+                (None, _) => {
+                    self.other.insert(key.clone(), value.clone());
+                }
+                (Some(own), value) if own != value => {
+                    report.conflicts.push(key.clone());
+                    if theirs {
+                        *own = value.clone();
+                    }
+                }
+                (Some(_), _) => {}
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Merges the settings object `their` into `own` key by key, descending into objects
+/// present on both sides, and returns the keys whose values differ, named under
+/// `prefix` like `settings.files.exclude`. `strategy` decides what a differing key
+/// ends up as:
+///
+/// - [`SettingsMerge::Ours`] keeps the value in `own`;
+/// - [`SettingsMerge::Theirs`] takes the value in `their`;
+/// - [`SettingsMerge::UnionDeep`] appends the elements of an array in `their` that its
+///   counterpart in `own` lacks, and otherwise keeps the value in `own`, so only keys
+///   of different scalars or of different types are returned;
+/// - [`SettingsMerge::Error`] fails listing the keys, leaving `own` unchanged.
+pub fn merge_settings(
+    own: &mut serde_json::Map<String, serde_json::Value>,
+    their: &serde_json::Map<String, serde_json::Value>,
+    prefix: &str,
+    strategy: SettingsMerge,
+) -> Result<Vec<String>> {
+    let mut merged = own.clone();
+    let mut conflicts = vec![];
+    merge_objects(&mut merged, their, prefix, strategy, &mut conflicts);
+    if strategy == SettingsMerge::Error && !conflicts.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} setting(s) have different values on each side:\n  {}",
+            conflicts.len(),
+            conflicts.join("\n  ")
+        )
+        .into());
+    }
+    *own = merged;
+    Ok(conflicts)
+}
+
+fn merge_objects(
+    own: &mut serde_json::Map<String, serde_json::Value>,
+    their: &serde_json::Map<String, serde_json::Value>,
+    prefix: &str,
+    strategy: SettingsMerge,
+    conflicts: &mut Vec<String>,
+) {
+    use serde_json::Value;
+
+    for (key, value) in their {
+        let path = format!("{}.{}", prefix, key);
+        match (own.get_mut(key), value) {
+            (None, _) => {
+                own.insert(key.clone(), value.clone());
+            }
+            (Some(Value::Object(own)), Value::Object(their)) => {
+                merge_objects(own, their, &path, strategy, conflicts);
+            }
+            (Some(Value::Array(own)), Value::Array(their))
+                if strategy == SettingsMerge::UnionDeep =>
+            {
+                for element in their {
+                    if !own.contains(element) {
+                        own.push(element.clone());
+                    }
+                }
+            }
+            (Some(existing), _) if existing != value => {
+                conflicts.push(path);
+                if strategy == SettingsMerge::Theirs {
+                    *existing = value.clone();
+                }
+            }
+            (Some(_), _) => {}
+        }
+    }
+}
+
+/// A folder whose display name changed while its path stayed the same.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FolderRename {
+    pub path: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// A folder with the same display name that now points somewhere else.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FolderMove {
+    pub name: String,
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// A value that was added (`old` is `None`), removed (`new` is `None`) or changed.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ValueChange {
+    pub key: String,
+    pub old: Option<serde_json::Value>,
+    pub new: Option<serde_json::Value>,
+}
+
+/// Semantic differences between two workspace files, independent of key order
+/// and formatting. Folders are matched by normalized path, tasks by label.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct WorkspaceDiff {
+    pub folders_added: Vec<WorkspaceFolder>,
+    pub folders_removed: Vec<WorkspaceFolder>,
+    pub folders_renamed: Vec<FolderRename>,
+    pub folders_moved: Vec<FolderMove>,
+    /// Individual keys of the settings section
+    pub settings: Vec<ValueChange>,
+    /// Tasks, keyed by label
+    pub tasks: Vec<ValueChange>,
+    /// Any other top-level section, compared as a whole
+    pub sections: Vec<ValueChange>,
+}
+
+impl WorkspaceDiff {
+    /// Whether both workspaces have the same content.
+    pub fn is_empty(&self) -> bool {
+        *self == WorkspaceDiff::default()
+    }
+}
+
+/// One line per difference, `+` for additions, `-` for removals and `~` for changes,
+/// or `No differences`.
+impl fmt::Display for WorkspaceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No differences");
+        }
+        let location = |folder: &WorkspaceFolder| folder.uri.clone().unwrap_or(folder.path.clone());
+        let mut lines = vec![];
+        for folder in &self.folders_added {
+            lines.push(format!("+ folder '{}' ({})", folder.name, location(folder)));
+        }
+        for folder in &self.folders_removed {
+            lines.push(format!("- folder '{}' ({})", folder.name, location(folder)));
+        }
+        for rename in &self.folders_renamed {
+            lines.push(format!(
+                "~ folder {} renamed '{}' -> '{}'",
+                rename.path, rename.old_name, rename.new_name
+            ));
+        }
+        for moved in &self.folders_moved {
+            lines.push(format!(
+                "~ folder '{}' moved {} -> {}",
+                moved.name, moved.old_path, moved.new_path
+            ));
+        }
+        for (kind, changes) in [
+            ("setting", &self.settings),
+            ("task", &self.tasks),
+            ("section", &self.sections),
+        ] {
+            for change in changes {
+                match (&change.old, &change.new) {
+                    (None, Some(new)) => {
+                        lines.push(format!("+ {} '{}' = {}", kind, change.key, new))
+                    }
+                    (Some(old), None) => {
+                        lines.push(format!("- {} '{}' (was {})", kind, change.key, old))
+                    }
+                    (Some(old), Some(new)) => {
+                        lines.push(format!("~ {} '{}': {} -> {}", kind, change.key, old, new))
+                    }
+                    (None, None) => {}
+                }
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// What happened to the managed Update Workspace task when a workspace was generated.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskAction {
+    Created,
+    Updated,
+    Removed,
+    #[default]
+    Unchanged,
+}
+
+/// A folder of a [`ChangeReport`]: its normalized path, or uri, and display name.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FolderChange {
+    pub path: String,
+    pub name: String,
+}
+
+impl From<&WorkspaceFolder> for FolderChange {
+    fn from(folder: &WorkspaceFolder) -> Self {
+        FolderChange {
+            path: folder_key(folder),
+            name: folder.name.clone(),
+        }
+    }
+}
+
+/// What generating a workspace changed compared to the existing file, for
+/// summaries, `--json` output and confirmation prompts. Folders are matched by
+/// path, so a folder that moved is removed at one path and added at the other.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct ChangeReport {
+    pub folders_added: Vec<FolderChange>,
+    pub folders_removed: Vec<FolderChange>,
+    /// Folders at the same path with the same name
+    pub folders_kept: Vec<FolderChange>,
+    pub folders_renamed: Vec<FolderRename>,
+    pub task_action: TaskAction,
+    /// Keys of the settings section that were added, removed or changed
+    pub settings_touched: Vec<String>,
+    /// Extension recommendations that were added
+    pub extensions_added: Vec<String>,
+}
+
+impl ChangeReport {
+    /// Compares the `existing` workspace, `None` when there was no file, with the
+    /// generated one. Both must use folder paths relative to the same directory.
+    pub fn new(existing: Option<&WorkspaceFile>, generated: &WorkspaceFile) -> Self {
+        let empty = WorkspaceFile::default();
+        let existing_file = existing.unwrap_or(&empty);
+        let mut report = ChangeReport::default();
+        for old in &existing_file.folders {
+            let key = folder_key(old);
+            match generated.folders.iter().find(|new| folder_key(new) == key) {
+                Some(new) if new.name != old.name => report.folders_renamed.push(FolderRename {
+                    path: key,
+                    old_name: old.name.clone(),
+                    new_name: new.name.clone(),
+                }),
+                Some(_) => report.folders_kept.push(old.into()),
+                None => report.folders_removed.push(old.into()),
+            }
+        }
+        for new in &generated.folders {
+            let key = folder_key(new);
+            if !existing_file
+                .folders
+                .iter()
+                .any(|old| folder_key(old) == key)
+            {
+                report.folders_added.push(new.into());
+            }
+        }
+
+        report.task_action = match (existing_file.update_task(), generated.update_task()) {
+            (None, Some(_)) => TaskAction::Created,
+            (Some(_), None) => TaskAction::Removed,
+            (Some(old), Some(new)) if old != new => TaskAction::Updated,
+            _ => TaskAction::Unchanged,
+        };
+        let settings = |workspace: &WorkspaceFile| workspace.settings.clone().unwrap_or_default();
+        report.settings_touched = diff_maps(&settings(existing_file), &settings(generated))
+            .into_iter()
+            .map(|change| change.key)
+            .collect();
+        let recommendations = |workspace: &WorkspaceFile| {
+            workspace
+                .extensions
+                .as_ref()
+                .map(|extensions| extensions.recommendations.clone())
+                .unwrap_or_default()
+        };
+        let recommended = recommendations(existing_file);
+        report.extensions_added = recommendations(generated)
+            .into_iter()
+            .filter(|extension| !recommended.contains(extension))
+            .collect();
+        report
+    }
+
+    /// Whether generating changed no folder, the task, any setting or the recommended
+    /// extensions.
+    pub fn is_empty(&self) -> bool {
+        self.folders_added.is_empty()
+            && self.folders_removed.is_empty()
+            && self.folders_renamed.is_empty()
+            && self.task_action == TaskAction::Unchanged
+            && self.settings_touched.is_empty()
+            && self.extensions_added.is_empty()
+    }
+}
+
+impl fmt::Display for TaskAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TaskAction::Created => "created",
+            TaskAction::Updated => "updated",
+            TaskAction::Removed => "removed",
+            TaskAction::Unchanged => "unchanged",
+        })
+    }
+}
+
+/// One line per change, in the style of the `diff` command, then the number of
+/// folders kept as they were.
+impl fmt::Display for ChangeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for folder in &self.folders_added {
+            writeln!(f, "+ folder '{}' ({})", folder.name, folder.path)?;
+        }
+        for folder in &self.folders_removed {
+            writeln!(f, "- folder '{}' ({})", folder.name, folder.path)?;
+        }
+        for rename in &self.folders_renamed {
+            writeln!(
+                f,
+                "~ folder {} renamed '{}' -> '{}'",
+                rename.path, rename.old_name, rename.new_name
+            )?;
+        }
+        if self.task_action != TaskAction::Unchanged {
+            writeln!(f, "~ update task {}", self.task_action)?;
+        }
+        for key in &self.settings_touched {
+            writeln!(f, "~ setting '{}'", key)?;
+        }
+        for extension in &self.extensions_added {
+            writeln!(f, "+ extension '{}'", extension)?;
+        }
+        match (self.is_empty(), self.folders_kept.len()) {
+            (true, kept) => write!(f, "No changes ({} folders unchanged)", kept),
+            (false, 1) => write!(f, "1 folder unchanged"),
+            (false, kept) => write!(f, "{} folders unchanged", kept),
+        }
+    }
+}
+
+fn diff_maps(
+    old: &serde_json::Map<String, serde_json::Value>,
+    new: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<ValueChange> {
+    let mut changes = vec![];
+    for (key, old_value) in old {
+        match new.get(key) {
+            Some(new_value) if new_value == old_value => {}
+            new_value => changes.push(ValueChange {
+                key: key.clone(),
+                old: Some(old_value.clone()),
+                new: new_value.cloned(),
+            }),
+        }
+    }
+    for (key, new_value) in new {
+        if !old.contains_key(key) {
+            changes.push(ValueChange {
+                key: key.clone(),
+                old: None,
+                new: Some(new_value.clone()),
+            });
+        }
+    }
+    changes
+}
+
+impl WorkspaceFile {
+    /// Compares `self` (old) with `other` (new). Both must use folder paths relative
+    /// to the same directory (see [`rebase_folders`]).
+    pub fn diff(&self, other: &WorkspaceFile) -> WorkspaceDiff {
+        let mut diff = WorkspaceDiff::default();
+
+        for old in &self.folders {
+            match other
+                .folders
+                .iter()
+                .find(|new| folder_key(new) == folder_key(old))
+            {
+                Some(new) if new.name != old.name => diff.folders_renamed.push(FolderRename {
+                    path: folder_key(old),
+                    old_name: old.name.clone(),
+                    new_name: new.name.clone(),
+                }),
+                Some(_) => {}
+                None => diff.folders_removed.push(old.clone()),
+            }
+        }
+        for new in &other.folders {
+            if !self
+                .folders
+                .iter()
+                .any(|old| folder_key(old) == folder_key(new))
+            {
+                diff.folders_added.push(new.clone());
+            }
+        }
+        // A removal and an addition sharing a display name is the same folder moved
+        diff.folders_removed.retain(|old| {
+            let moved = diff
+                .folders_added
+                .iter()
+                .position(|new| !old.name.is_empty() && new.name == old.name);
+            match moved {
+                Some(index) => {
+                    let new = diff.folders_added.remove(index);
+                    diff.folders_moved.push(FolderMove {
+                        name: old.name.clone(),
+                        old_path: folder_key(old),
+                        new_path: folder_key(&new),
+                    });
+                    false
+                }
+                None => true,
+            }
+        });
+
+        let settings = |workspace: &WorkspaceFile| workspace.settings.clone().unwrap_or_default();
+        diff.settings = diff_maps(&settings(self), &settings(other));
+
+        let tasks = |workspace: &WorkspaceFile| {
+            let mut map = serde_json::Map::new();
+            for task in workspace.tasks.iter().flat_map(|tasks| &tasks.tasks) {
+                if let Ok(value) = serde_json::to_value(task) {
+                    map.insert(task.label.clone(), value);
+                }
+            }
+            map
+        };
+        diff.tasks = diff_maps(&tasks(self), &tasks(other));
+
+        let sections = |workspace: &WorkspaceFile| {
+            let mut map = workspace.other.clone();
+            if let Some(extensions) = &workspace.extensions {
+                if let Ok(value) = serde_json::to_value(extensions) {
+                    map.insert("extensions".to_string(), value);
+                }
+            }
+            if let Some(launch) = &workspace.launch {
+                if let Ok(value) = serde_json::to_value(launch) {
+                    map.insert("launch".to_string(), value);
+                }
+            }
+            map
+        };
+        diff.sections = diff_maps(&sections(self), &sections(other));
+
+        diff
+    }
+}
+
+/// Identity of a folder for merging: its uri, or its normalized path.
+pub(crate) fn folder_key(folder: &WorkspaceFolder) -> String {
+    match &folder.uri {
+        Some(uri) => uri.clone(),
+        None => normalize_folder_path(&folder.path),
+    }
+}
+
+/// Rewrites relative folder paths written against `from_dir` so they resolve to the
+/// same directories from `to_dir`. Absolute paths and uri folders are unchanged.
+pub fn rebase_folders(workspace: &mut WorkspaceFile, from_dir: &Path, to_dir: &Path) {
+    for folder in &mut workspace.folders {
+        if folder.uri.is_some() || folder.path.is_empty() || Path::new(&folder.path).is_absolute() {
+            continue;
+        }
+        let target = from_dir.join(&folder.path);
+        if let Some(relative) = pathdiff::diff_paths(&target, to_dir) {
+            folder.path = normalize_folder_path(&relative.to_string_lossy());
+        }
+    }
+}
+
+/// The entries of `folders` that are one folder written in several ways, such as
+/// `./services` and `services/`, as groups of indices in order. Paths are compared
+/// normalized and uris as written; folders listed once, and entries with neither a
+/// path nor a uri, are left out.
+pub fn duplicate_folders(folders: &[WorkspaceFolder]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(String, Vec<usize>)> = vec![];
+    for (index, folder) in folders.iter().enumerate() {
+        if folder.uri.is_none() && folder.path.is_empty() {
+            continue;
+        }
+        let key = folder_key(folder);
+        match groups.iter_mut().find(|(other, _)| *other == key) {
+            Some((_, indices)) => indices.push(index),
+            None => groups.push((key, vec![index])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, indices)| indices)
+        .filter(|indices| indices.len() > 1)
+        .collect()
+}
+
+/// Two folders of a workspace, one inside the other, by their index in its folders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NestedFolder {
+    pub outer: usize,
+    pub inner: usize,
+}
+
+/// The pairs of `folders` where one is inside the other, in the order of the inner
+/// folder. Relative paths are resolved against `workspace_dir` and normalized, then
+/// passed to `canonicalize`, which may resolve symlinks and returns `None` to keep
+/// the normalized path. Folders given by uri and the `.` root, which contains
+/// everything by construction, are left out, as are duplicates of one directory.
+pub fn nested_folders(
+    folders: &[WorkspaceFolder],
+    workspace_dir: &Path,
+    canonicalize: impl Fn(&Path) -> Option<PathBuf>,
+) -> Vec<NestedFolder> {
+    let root = normalize_folder_path(&workspace_dir.to_string_lossy());
+    let resolve = |path: &str| {
+        let normalized = normalize_folder_path(&workspace_dir.join(path).to_string_lossy());
+        match canonicalize(Path::new(&normalized)) {
+            Some(canonical) => normalize_folder_path(&canonical.to_string_lossy()),
+            None => normalized,
+        }
+    };
+    let paths: Vec<Option<String>> = folders
+        .iter()
+        .map(
+            |folder| match folder.uri.is_none() && !folder.path.is_empty() {
+                true => Some(resolve(&folder.path)).filter(|path| *path != root),
+                false => None,
+            },
+        )
+        .collect();
+
+    let mut nested = vec![];
+    for (inner, inner_path) in paths.iter().enumerate() {
+        let Some(inner_path) = inner_path else {
+            continue;
+        };
+        for (outer, outer_path) in paths.iter().enumerate() {
+            let inside = outer_path.as_ref().is_some_and(|outer_path| {
+                let prefix = format!("{}/", outer_path.trim_end_matches('/'));
+                inner_path.starts_with(&prefix)
+            });
+            if inside {
+                nested.push(NestedFolder { outer, inner });
+            }
+        }
+    }
+    nested
+}
+
+/// Lexically normalizes a folder path for comparison: separators become `/`,
+/// `.` components and repeated or trailing separators are dropped and `..` is
+/// resolved where possible, without looking at the disk. Every comparison of folder
+/// paths goes through it, so that one folder written two ways is one folder.
+pub fn normalize_folder_path(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut parts: Vec<&str> = vec![];
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|last| *last != "..") => {
+                parts.pop();
+            }
+            // Nothing is above the root
+            ".." if absolute => {}
+            _ => parts.push(part),
+        }
+    }
+
+    let joined = parts.join("/");
+    match (absolute, joined.is_empty()) {
+        (true, _) => format!("/{}", joined),
+        (false, true) => ".".to_string(),
+        (false, false) => joined,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{parse_workspace, read_workspace};
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_nested_folders() {
+        let folders: Vec<WorkspaceFolder> = [
+            ".",
+            "./services",
+            "services/api/../api/",
+            "/work/team/services/web",
+            "../team/services/web/src",
+            "tools",
+            "./tools/",
+            "toolsmith",
+            "",
+        ]
+        .into_iter()
+        .map(|path| WorkspaceFolder::new(path, ""))
+        .collect();
+        let pairs = |folders: &[WorkspaceFolder]| -> Vec<(usize, usize)> {
+            nested_folders(folders, Path::new("/work/team"), |_| None)
+                .into_iter()
+                .map(|pair| (pair.outer, pair.inner))
+                .collect()
+        };
+        // The "." root contains everything and doesn't count, nor do duplicates such
+        // as the two spellings of tools, or folders that only share a prefix
+        assert_eq!(pairs(&folders), vec![(1, 2), (1, 3), (1, 4), (3, 4)]);
+
+        let mut uri = WorkspaceFolder::new("", "remote");
+        uri.uri = Some("vscode-remote://ssh-remote+box/work/team/services/x".into());
+        assert_eq!(pairs(&[folders[1].clone(), uri]), vec![]);
+
+        // Canonical paths are compared, e.g. with a symlink resolved
+        let linked = [
+            WorkspaceFolder::new("services", ""),
+            WorkspaceFolder::new("api-link", ""),
+        ];
+        let nested = nested_folders(&linked, Path::new("/work/team"), |path| {
+            (path == Path::new("/work/team/api-link"))
+                .then(|| PathBuf::from("/work/team/services/api"))
+        });
+        assert_eq!(nested, vec![NestedFolder { outer: 0, inner: 1 }]);
+    }
+
+    #[test]
+    fn test_merge_workspace_files() -> Result<()> {
+        let temp = TempDir::new()?;
+        let frontend_dir = temp.path().join("frontend");
+        let backend_dir = temp.path().join("backend");
+        fs::create_dir_all(&frontend_dir)?;
+        fs::create_dir_all(&backend_dir)?;
+        fs::write(
+            frontend_dir.join("frontend.code-workspace"),
+            r#"{
+    "folders": [
+        {"path": ".", "name": "🏗️ frontend"},
+        {"path": "../shared", "name": "📦 shared"}
+    ],
+    "settings": {"editor.tabSize": 2, "eslint.enable": true},
+    "tasks": {"version": "2.0.0", "tasks": [
+        {"label": "Build", "type": "shell", "command": "npm", "args": ["run", "build"]}
+    ]}
+}"#,
+        )?;
+        fs::write(
+            backend_dir.join("backend.code-workspace"),
+            r#"{
+    "folders": [
+        {"path": ".", "name": "🏗️ backend"},
+        {"path": "../shared/", "name": "Shared protos"}
+    ],
+    "settings": {"editor.tabSize": 4, "rust-analyzer.check.command": "clippy"},
+    "tasks": {"version": "2.0.0", "tasks": [
+        {"label": "Build", "type": "shell", "command": "cargo", "args": ["build"]},
+        {"label": "Test", "type": "shell", "command": "cargo", "args": ["test"]}
+    ]}
+}"#,
+        )?;
+
+        let mut merged = read_workspace(&frontend_dir.join("frontend.code-workspace"))?;
+        rebase_folders(&mut merged, &frontend_dir, temp.path());
+        let mut backend = read_workspace(&backend_dir.join("backend.code-workspace"))?;
+        rebase_folders(&mut backend, &backend_dir, temp.path());
+
+        let report = merged.merge(&backend, MergeStrategy::Ours, SettingsMerge::Ours)?;
+
+        let folders: Vec<(&str, &str)> = merged
+            .folders
+            .iter()
+            .map(|f| (f.path.as_str(), f.name.as_str()))
+            .collect();
+        assert_eq!(
+            folders,
+            vec![
+                ("frontend", "🏗️ frontend"),
+                ("shared", "📦 shared"),
+                ("backend", "🏗️ backend"),
+            ]
+        );
+        assert_eq!(report.duplicate_folders, vec!["shared"]);
+        assert_eq!(report.duplicate_tasks, vec!["Build"]);
+        assert_eq!(
+            report.conflicts,
+            vec!["folders.shared.name", "settings.editor.tabSize"]
+        );
+
+        let settings = merged.settings.as_ref().unwrap();
+        assert_eq!(settings["editor.tabSize"], 2);
+        assert_eq!(settings["rust-analyzer.check.command"], "clippy");
+        let tasks = merged.tasks.unwrap().tasks;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].command, "npm");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_prefers_theirs() -> Result<()> {
+        let mut ours: WorkspaceFile = serde_json::from_str(
+            r#"{"folders": [{"path": "api", "name": "API"}], "settings": {"a": 1}, "launch": {"version": "0.2.0"}}"#,
+        )?;
+        let theirs: WorkspaceFile = serde_json::from_str(
+            r#"{"folders": [{"path": "./api", "name": "Backend"}], "settings": {"a": 2}, "launch": {"version": "0.3.0"}}"#,
+        )?;
+
+        let report = ours.merge(&theirs, MergeStrategy::Theirs, SettingsMerge::Theirs)?;
+
+        assert_eq!(ours.folders.len(), 1);
+        assert_eq!(ours.folders[0].name, "Backend");
+        assert_eq!(ours.settings.as_ref().unwrap()["a"], 2);
+        assert_eq!(ours.launch.unwrap().version.as_deref(), Some("0.3.0"));
+        assert_eq!(
+            report.conflicts,
+            vec!["folders.api.name", "settings.a", "launch.version"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_rules() -> Result<()> {
+        use MergeStrategy::{Ours, Theirs};
+        // (ours, theirs, strategy, expected result, expected conflicts)
+        let cases = [
+            (
+                r#"{"folders": [{"path": "api", "name": "API"}]}"#,
+                r#"{"folders": [{"path": "./api/", "name": "Backend"}]}"#,
+                Ours,
+                r#"{"folders": [{"path": "api", "name": "API"}]}"#,
+                vec!["folders.api.name"],
+            ),
+            (
+                r#"{"folders": [{"path": "api", "name": "API"}]}"#,
+                r#"{"folders": [{"path": "./api/", "name": "Backend"}]}"#,
+                Theirs,
+                r#"{"folders": [{"path": "api", "name": "Backend"}]}"#,
+                vec!["folders.api.name"],
+            ),
+            (
+                r#"{"folders": [{"path": "api"}]}"#,
+                r#"{"folders": [{"path": "api", "name": "API"}, {"path": "web"}]}"#,
+                Ours,
+                r#"{"folders": [{"path": "api", "name": "API"}, {"path": "web"}]}"#,
+                vec![],
+            ),
+            (
+                r#"{"folders": [], "settings": {"a": {"b": 1, "c": {"d": true}}}}"#,
+                r#"{"folders": [], "settings": {"a": {"b": 2, "c": {"e": "x"}}, "f": 3}}"#,
+                Ours,
+                r#"{"folders": [], "settings": {"a": {"b": 1, "c": {"d": true, "e": "x"}}, "f": 3}}"#,
+                vec!["settings.a.b"],
+            ),
+            (
+                r#"{"folders": [], "settings": {"a": {"b": 1, "c": {"d": true}}}}"#,
+                r#"{"folders": [], "settings": {"a": {"b": 2, "c": {"d": false}}}}"#,
+                Theirs,
+                r#"{"folders": [], "settings": {"a": {"b": 2, "c": {"d": false}}}}"#,
+                vec!["settings.a.b", "settings.a.c.d"],
+            ),
+            (
+                r#"{"folders": [], "settings": {"a": {"b": 1}}}"#,
+                r#"{"folders": [], "settings": {"a": 5}}"#,
+                Theirs,
+                r#"{"folders": [], "settings": {"a": 5}}"#,
+                vec!["settings.a"],
+            ),
+            (
+                r#"{"folders": [], "remoteAuthority": "wsl", "x": {"y": 1}}"#,
+                r#"{"folders": [], "remoteAuthority": "ssh", "x": {"z": 2}}"#,
+                Ours,
+                r#"{"folders": [], "remoteAuthority": "wsl", "x": {"y": 1}}"#,
+                vec!["remoteAuthority", "x"],
+            ),
+        ];
+
+        for (ours, theirs, strategy, expected, conflicts) in cases {
+            let mut merged: WorkspaceFile = serde_json::from_str(ours)?;
+            let settings = match strategy {
+                Ours => SettingsMerge::Ours,
+                Theirs => SettingsMerge::Theirs,
+            };
+            let report = merged.merge(&serde_json::from_str(theirs)?, strategy, settings)?;
+            let expected: WorkspaceFile = serde_json::from_str(expected)?;
+            assert_eq!(merged, expected, "{} + {} ({:?})", ours, theirs, strategy);
+            assert_eq!(report.conflicts, conflicts, "{} + {}", ours, theirs);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_settings() -> Result<()> {
+        use SettingsMerge::{Error, Ours, Theirs, UnionDeep};
+        let object = |json: &str| -> Result<serde_json::Map<String, serde_json::Value>> {
+            Ok(serde_json::from_str(json)?)
+        };
+        // (ours, theirs, strategy, expected result, expected conflicts)
+        let cases = [
+            // Nested objects are merged on both sides, whatever the strategy
+            (
+                r#"{"a": {"b": 1, "c": {"d": true}}}"#,
+                r#"{"a": {"b": 2, "c": {"e": "x"}}, "f": 3}"#,
+                Ours,
+                r#"{"a": {"b": 1, "c": {"d": true, "e": "x"}}, "f": 3}"#,
+                vec!["s.a.b"],
+            ),
+            (
+                r#"{"a": {"b": 1, "c": {"d": true}}}"#,
+                r#"{"a": {"b": 2, "c": {"e": "x"}}, "f": 3}"#,
+                Theirs,
+                r#"{"a": {"b": 2, "c": {"d": true, "e": "x"}}, "f": 3}"#,
+                vec!["s.a.b"],
+            ),
+            (
+                r#"{"a": {"b": 1, "c": {"d": true}}}"#,
+                r#"{"a": {"b": 2, "c": {"e": "x"}}, "f": 3}"#,
+                UnionDeep,
+                r#"{"a": {"b": 1, "c": {"d": true, "e": "x"}}, "f": 3}"#,
+                vec!["s.a.b"],
+            ),
+            // Arrays are whole values, unless unioned without duplicates
+            (
+                r#"{"a": [1, 2], "b": {"c": ["x"]}}"#,
+                r#"{"a": [2, 3], "b": {"c": ["x"]}}"#,
+                Ours,
+                r#"{"a": [1, 2], "b": {"c": ["x"]}}"#,
+                vec!["s.a"],
+            ),
+            (
+                r#"{"a": [1, 2], "b": {"c": ["x"]}}"#,
+                r#"{"a": [2, 3], "b": {"c": ["x"]}}"#,
+                Theirs,
+                r#"{"a": [2, 3], "b": {"c": ["x"]}}"#,
+                vec!["s.a"],
+            ),
+            (
+                r#"{"a": [1, 2], "b": {"c": ["x"]}}"#,
+                r#"{"a": [2, 3, 3], "b": {"c": ["y", "x"]}}"#,
+                UnionDeep,
+                r#"{"a": [1, 2, 3], "b": {"c": ["x", "y"]}}"#,
+                vec![],
+            ),
+            // Objects in arrays are elements compared whole
+            (
+                r#"{"rulers": [{"column": 80}, {"column": 100, "color": "red"}]}"#,
+                r#"{"rulers": [{"column": 100}, {"column": 80}]}"#,
+                UnionDeep,
+                r#"{"rulers": [{"column": 80}, {"column": 100, "color": "red"}, {"column": 100}]}"#,
+                vec![],
+            ),
+            // Different types under one key are conflicts, even for a union
+            (
+                r#"{"a": "text", "b": [1], "c": {"d": 1}}"#,
+                r#"{"a": {"nested": true}, "b": {"e": 1}, "c": null}"#,
+                UnionDeep,
+                r#"{"a": "text", "b": [1], "c": {"d": 1}}"#,
+                vec!["s.a", "s.b", "s.c"],
+            ),
+            (
+                r#"{"a": "text", "b": [1], "c": {"d": 1}}"#,
+                r#"{"a": {"nested": true}, "b": {"e": 1}, "c": null}"#,
+                Theirs,
+                r#"{"a": {"nested": true}, "b": {"e": 1}, "c": null}"#,
+                vec!["s.a", "s.b", "s.c"],
+            ),
+            // Without differences nothing fails
+            (
+                r#"{"a": {"b": 1}, "c": [1]}"#,
+                r#"{"a": {"b": 1, "d": 2}, "e": true}"#,
+                Error,
+                r#"{"a": {"b": 1, "d": 2}, "c": [1], "e": true}"#,
+                vec![],
+            ),
+        ];
+
+        for (ours, theirs, strategy, expected, conflicts) in cases {
+            let mut merged = object(ours)?;
+            let found = merge_settings(&mut merged, &object(theirs)?, "s", strategy)?;
+            let message = format!("{} + {} ({:?})", ours, theirs, strategy);
+            assert_eq!(merged, object(expected)?, "{}", message);
+            assert_eq!(found, conflicts, "{}", message);
+        }
+
+        let mut own = object(r#"{"a": {"b": 1}, "c": "x", "d": [1]}"#)?;
+        let their = object(r#"{"a": {"b": 2}, "c": {"e": 1}, "d": [2], "f": 3}"#)?;
+        let message = merge_settings(&mut own, &their, "settings", Error)
+            .unwrap_err()
+            .to_string();
+        assert!(message.starts_with("3 setting(s)"), "{}", message);
+        for key in ["settings.a.b", "settings.c", "settings.d"] {
+            assert!(message.contains(key), "{}", message);
+        }
+        assert_eq!(own, object(r#"{"a": {"b": 1}, "c": "x", "d": [1]}"#)?);
+
+        // A failed merge of whole files leaves them as they were
+        let mut workspace: WorkspaceFile =
+            serde_json::from_str(r#"{"folders": [{"path": "api"}], "settings": {"a": 1}}"#)?;
+        let other: WorkspaceFile =
+            serde_json::from_str(r#"{"folders": [{"path": "web"}], "settings": {"a": 2}}"#)?;
+        let before = workspace.clone();
+        assert!(workspace.merge(&other, MergeStrategy::Ours, Error).is_err());
+        assert_eq!(workspace, before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_workspace_files() -> Result<()> {
+        let old: WorkspaceFile = serde_json::from_str(
+            r#"{
+                "folders": [
+                    {"path": "./api", "name": "📦 api"},
+                    {"path": "web", "name": "📦 web"},
+                    {"path": "docs", "name": "📦 docs"},
+                    {"path": "old/tools", "name": "Tools"}
+                ],
+                "settings": {"a": 1, "b": {"nested": true}},
+                "tasks": {"version": "2.0.0", "tasks": [
+                    {"label": "Build", "type": "shell", "command": "make", "args": []}
+                ]},
+                "launch": {"version": "0.2.0"}
+            }"#,
+        )?;
+        let new: WorkspaceFile = serde_json::from_str(
+            r#"{
+                "launch": {"version": "0.2.0"},
+                "settings": {"b": {"nested": true}, "a": 2, "c": "x"},
+                "folders": [
+                    {"path": "web", "name": "Frontend"},
+                    {"path": "api/", "name": "📦 api"},
+                    {"path": "new/tools", "name": "Tools"},
+                    {"path": "cli", "name": "📦 cli"}
+                ]
+            }"#,
+        )?;
+
+        let diff = old.diff(&new);
+
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.folders_added,
+            vec![WorkspaceFolder::new("cli", "📦 cli")]
+        );
+        assert_eq!(
+            diff.folders_removed,
+            vec![WorkspaceFolder::new("docs", "📦 docs")]
+        );
+        assert_eq!(diff.folders_renamed.len(), 1);
+        assert_eq!(diff.folders_renamed[0].new_name, "Frontend");
+        assert_eq!(diff.folders_moved[0].new_path, "new/tools");
+        let keys = |changes: &[ValueChange]| -> Vec<String> {
+            changes.iter().map(|c| c.key.clone()).collect()
+        };
+        assert_eq!(keys(&diff.settings), vec!["a", "c"]);
+        assert_eq!(keys(&diff.tasks), vec!["Build"]);
+        assert!(diff.tasks[0].new.is_none());
+        assert!(diff.sections.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_ignores_order_and_formatting() -> Result<()> {
+        let old = parse_workspace(
+            r#"{"folders": [{"path": "a", "name": "A"}], "settings": {"x": 1, "y": 2}}"#,
+        )?;
+        let new = parse_workspace(
+            "{\n  // reformatted\n  \"settings\": {\"y\": 2, \"x\": 1},\n  \"folders\": [{\"name\": \"A\", \"path\": \"./a/\"}],\n}",
+        )?;
+
+        assert!(old.diff(&new).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_pairs() -> Result<()> {
+        let task = |command: &str| {
+            format!(
+                r#"{{"version": "2.0.0", "tasks": [
+                    {{"label": "Build", "type": "shell", "command": "{}"}}
+                ]}}"#,
+                command
+            )
+        };
+        // (old, new, expected description of the difference)
+        let cases = [
+            // Cosmetic rewrites of paths, keys and formatting
+            (
+                r#"{"folders": [{"path": "./api", "name": "API"}]}"#.to_string(),
+                r#"{"folders": [{"name": "API", "path": "api/"}]}"#.to_string(),
+                "No differences",
+            ),
+            (
+                r#"{"folders": [{"path": "tools/cli", "name": "CLI"}]}"#.to_string(),
+                r#"{"folders": [{"path": ".\\tools\\cli\\", "name": "CLI"}]}"#.to_string(),
+                "No differences",
+            ),
+            (
+                r#"{"folders": [{"path": "a/../b", "name": "B"}], "settings": {"x": 1}}"#
+                    .to_string(),
+                "{\n\t// moved around\n\t\"settings\": {\"x\": 1,},\n\t\"folders\": [{\"path\": \"b\", \"name\": \"B\"}],\n}"
+                    .to_string(),
+                "No differences",
+            ),
+            // Folders
+            (
+                r#"{"folders": [{"path": "api", "name": "API"}]}"#.to_string(),
+                r#"{"folders": [{"path": "api", "name": "API"}, {"path": "web", "name": "Web"}]}"#
+                    .to_string(),
+                "+ folder 'Web' (web)",
+            ),
+            (
+                r#"{"folders": [{"path": "api", "name": "API"}, {"uri": "ssh://host/srv", "name": "Srv"}]}"#
+                    .to_string(),
+                r#"{"folders": [{"path": "api", "name": "API"}]}"#.to_string(),
+                "- folder 'Srv' (ssh://host/srv)",
+            ),
+            (
+                r#"{"folders": [{"path": "api", "name": "API"}]}"#.to_string(),
+                r#"{"folders": [{"path": "./api", "name": "Backend"}]}"#.to_string(),
+                "~ folder api renamed 'API' -> 'Backend'",
+            ),
+            (
+                r#"{"folders": [{"path": "old/api", "name": "API"}]}"#.to_string(),
+                r#"{"folders": [{"path": "new/api", "name": "API"}]}"#.to_string(),
+                "~ folder 'API' moved old/api -> new/api",
+            ),
+            (
+                r#"{"folders": [{"path": "old/api"}]}"#.to_string(),
+                r#"{"folders": [{"path": "new/api"}]}"#.to_string(),
+                "+ folder '' (new/api)\n- folder '' (old/api)",
+            ),
+            // Settings, compared key by key
+            (
+                r#"{"folders": [], "settings": {"a": 1, "b": {"c": true}}}"#.to_string(),
+                r#"{"folders": [], "settings": {"b": {"c": false}, "d": "x"}}"#.to_string(),
+                "- setting 'a' (was 1)\n~ setting 'b': {\"c\":true} -> {\"c\":false}\n\
+                 + setting 'd' = \"x\"",
+            ),
+            (
+                r#"{"folders": []}"#.to_string(),
+                r#"{"folders": [], "settings": {}}"#.to_string(),
+                "No differences",
+            ),
+            // Tasks, matched by label
+            (
+                format!(r#"{{"folders": [], "tasks": {}}}"#, task("make")),
+                format!(r#"{{"folders": [], "tasks": {}}}"#, task("cargo")),
+                "~ task 'Build': {\"label\":\"Build\",\"type\":\"shell\",\"command\":\"make\"} \
+                 -> {\"label\":\"Build\",\"type\":\"shell\",\"command\":\"cargo\"}",
+            ),
+            (
+                r#"{"folders": []}"#.to_string(),
+                format!(r#"{{"folders": [], "tasks": {}}}"#, task("make")),
+                "+ task 'Build' = {\"label\":\"Build\",\"type\":\"shell\",\"command\":\"make\"}",
+            ),
+            // Other sections, compared as a whole
+            (
+                r#"{"folders": [], "remoteAuthority": "wsl+Ubuntu"}"#.to_string(),
+                r#"{"folders": [], "remoteAuthority": "ssh-remote+box"}"#.to_string(),
+                "~ section 'remoteAuthority': \"wsl+Ubuntu\" -> \"ssh-remote+box\"",
+            ),
+            (
+                r#"{"folders": [], "extensions": {"recommendations": ["a.b"]}}"#.to_string(),
+                r#"{"folders": []}"#.to_string(),
+                "- section 'extensions' (was {\"recommendations\":[\"a.b\"]})",
+            ),
+        ];
+
+        for (old, new, expected) in cases {
+            let diff = parse_workspace(&old)?.diff(&parse_workspace(&new)?);
+            assert_eq!(diff.to_string(), expected, "{} -> {}", old, new);
+            assert_eq!(diff.is_empty(), expected == "No differences");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_change_report() -> Result<()> {
+        let task = |args: &str| {
+            format!(
+                r#""tasks": {{"version": "2.0.0", "tasks": [
+                    {{"label": "{}", "type": "process", "command": "wm", "args": [{}]}}
+                ]}}"#,
+                UPDATE_TASK_LABEL, args
+            )
+        };
+        let old = parse_workspace(&format!(
+            r#"{{
+                "folders": [
+                    {{"path": ".", "name": "🏗️ team"}},
+                    {{"path": "api", "name": "Backend"}},
+                    {{"path": "docs", "name": "📦 docs"}}
+                ],
+                "settings": {{"a": 1, "b": 2}},
+                {}
+            }}"#,
+            task(r#""--name", "team""#)
+        ))?;
+
+        // A new file: everything is added
+        let report = ChangeReport::new(None, &old);
+        assert_eq!(report.folders_added.len(), 3);
+        assert_eq!(report.task_action, TaskAction::Created);
+        assert_eq!(report.settings_touched, vec!["a", "b"]);
+        assert!(report.folders_kept.is_empty());
+
+        // Regenerating the same file changes nothing
+        let report = ChangeReport::new(Some(&old), &old);
+        assert!(report.is_empty());
+        assert_eq!(report.folders_kept.len(), 3);
+        assert_eq!(report.to_string(), "No changes (3 folders unchanged)");
+
+        let new = parse_workspace(&format!(
+            r#"{{
+                "folders": [
+                    {{"path": "./", "name": "🏗️ team"}},
+                    {{"path": "api/", "name": "📦 api"}},
+                    {{"path": "web", "name": "📦 web"}}
+                ],
+                "settings": {{"b": 2, "c": 3}},
+                "extensions": {{"recommendations": ["rust-lang.rust-analyzer"]}},
+                {}
+            }}"#,
+            task(r#""--name", "team", "--sort", "name""#)
+        ))?;
+        let report = ChangeReport::new(Some(&old), &new);
+        assert_eq!(
+            report.folders_added,
+            vec![FolderChange {
+                path: "web".into(),
+                name: "📦 web".into()
+            }]
+        );
+        assert_eq!(report.folders_removed[0].path, "docs");
+        assert_eq!(report.folders_kept[0].path, ".");
+        assert_eq!(report.folders_renamed[0].old_name, "Backend");
+        assert_eq!(report.task_action, TaskAction::Updated);
+        assert_eq!(report.settings_touched, vec!["a", "c"]);
+        assert_eq!(
+            report.to_string(),
+            "+ folder '📦 web' (web)\n\
+             - folder '📦 docs' (docs)\n\
+             ~ folder api renamed 'Backend' -> '📦 api'\n\
+             ~ update task updated\n\
+             ~ setting 'a'\n\
+             ~ setting 'c'\n\
+             + extension 'rust-lang.rust-analyzer'\n\
+             1 folder unchanged"
+        );
+        let json = serde_json::to_value(&report)?;
+        assert_eq!(json["task_action"], "updated");
+        assert_eq!(json["folders_added"][0]["name"], "📦 web");
+
+        // Dropping the task and the settings
+        let mut bare = new.clone();
+        bare.tasks = None;
+        bare.settings = None;
+        let report = ChangeReport::new(Some(&new), &bare);
+        assert_eq!(report.task_action, TaskAction::Removed);
+        assert_eq!(report.settings_touched, vec!["b", "c"]);
+        assert!(report.extensions_added.is_empty());
+        assert_eq!(report.folders_kept.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_folder_path() {
+        let cases = [
+            ("services", "services"),
+            ("./services", "services"),
+            ("services/", "services"),
+            ("./services/", "services"),
+            ("services//api", "services/api"),
+            ("./services/./api/", "services/api"),
+            ("services\\api", "services/api"),
+            (".\\services\\api\\", "services/api"),
+            ("a/b/../c", "a/c"),
+            ("services/api/../api", "services/api"),
+            ("a/..", "."),
+            ("a/../..", ".."),
+            ("../shared", "../shared"),
+            ("../../shared/./lib/", "../../shared/lib"),
+            ("./../shared", "../shared"),
+            ("", "."),
+            (".", "."),
+            ("./", "."),
+            ("./.", "."),
+            ("/abs/./path", "/abs/path"),
+            ("/abs/path/", "/abs/path"),
+            ("//abs//path", "/abs/path"),
+            ("/abs/../path", "/path"),
+            ("/..", "/"),
+            ("/../abs", "/abs"),
+            ("/", "/"),
+            ("C:\\work\\..\\api", "C:/api"),
+        ];
+        for (path, normalized) in cases {
+            assert_eq!(normalize_folder_path(path), normalized, "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_folders() {
+        let mut folders: Vec<WorkspaceFolder> = [
+            "./services",
+            "api",
+            "services/",
+            "./services/api",
+            "services/api/../../services",
+            "",
+            "",
+            "services/api/",
+        ]
+        .into_iter()
+        .map(|path| WorkspaceFolder::new(path, ""))
+        .collect();
+        for _ in 0..2 {
+            let mut uri = WorkspaceFolder::new("", "remote");
+            uri.uri = Some("vscode-remote://ssh-remote+box/srv".to_string());
+            folders.push(uri);
+        }
+        assert_eq!(
+            duplicate_folders(&folders),
+            vec![vec![0, 2, 4], vec![3, 7], vec![8, 9]]
+        );
+        assert_eq!(duplicate_folders(&folders[..2]), Vec::<Vec<usize>>::new());
+    }
+}
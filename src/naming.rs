@@ -0,0 +1,237 @@
+//! [`FolderNamer`], how scanned folders get their display names, with the namers the
+//! command line options map onto and others for use from code.
+
+use crate::folder_tasks::Ecosystem;
+use crate::jsonc;
+use crate::options::{GenerateOptions, NameStyle};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// What a [`FolderNamer`] knows about the folder it names, besides its directory.
+#[derive(Debug, Clone, Copy)]
+pub struct NamingContext<'a> {
+    /// The directory that was scanned
+    pub scan_path: &'a Path,
+    /// The directory of the folder relative to the scan path
+    pub relative_path: &'a Path,
+    /// The kind of project in the directory, from its manifest
+    pub ecosystem: Option<Ecosystem>,
+    /// The display name of the folder in the existing workspace file, if it's there
+    pub existing_name: Option<&'a str>,
+}
+
+/// Chooses the display names of scanned folders. A [`WorkspaceBuilder`] tries its
+/// namers in order and uses the first name given, falling back to the name the
+/// options describe.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use std::path::Path;
+/// use workspace_manager::naming::{FolderNamer, NamingContext};
+/// use workspace_manager::{SortOrder, WorkspaceBuilder};
+///
+/// /// Names from a service catalog, keyed by directory name.
+/// #[derive(Debug)]
+/// struct Catalog(HashMap<String, String>);
+///
+/// impl FolderNamer for Catalog {
+///     fn name(&self, dir: &Path, _ctx: &NamingContext) -> Option<String> {
+///         let dir = dir.file_name()?.to_str()?;
+///         self.0.get(dir).map(|service| format!("🛰️ {}", service))
+///     }
+/// }
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let projects = tempfile::TempDir::new()?;
+/// std::fs::create_dir(projects.path().join("api"))?;
+/// std::fs::create_dir(projects.path().join("web"))?;
+///
+/// let catalog = Catalog(HashMap::from([("api".into(), "Billing API".into())]));
+/// let workspace = WorkspaceBuilder::new(projects.path())
+///     .exclude_current(true)
+///     .sort(SortOrder::Name)
+///     .namer(catalog)
+///     .build()?;
+/// let names: Vec<&str> = workspace.folders.iter().map(|f| f.name.as_str()).collect();
+/// assert_eq!(names, ["🛰️ Billing API", "📦 web"]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`WorkspaceBuilder`]: crate::WorkspaceBuilder
+pub trait FolderNamer: std::fmt::Debug + Send + Sync {
+    /// The display name of the folder for the scanned directory `dir`, or `None` to
+    /// leave it to the next namer.
+    fn name(&self, dir: &Path, ctx: &NamingContext) -> Option<String>;
+}
+
+/// Names made of a prefix and the directory name or relative path, as the command
+/// line does with `--name-style`, `--folder-prefix` and `--no-emoji`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleNamer {
+    pub style: NameStyle,
+    pub prefix: String,
+}
+
+impl FolderNamer for StyleNamer {
+    fn name(&self, dir: &Path, ctx: &NamingContext) -> Option<String> {
+        let name = self.style.folder_name(dir, ctx.scan_path);
+        Some(format!("{}{}", self.prefix, name))
+    }
+}
+
+/// Names made of a prefix and the package name in the `Cargo.toml` or `package.json`
+/// of the directory. Directories without a named package are left to the next namer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageNamer {
+    pub prefix: String,
+}
+
+impl FolderNamer for PackageNamer {
+    fn name(&self, dir: &Path, ctx: &NamingContext) -> Option<String> {
+        let package = package_name(dir, ctx.ecosystem?)?;
+        Some(format!("{}{}", self.prefix, package))
+    }
+}
+
+/// Names from a template where `{name}` is the directory name, `{path}` its path
+/// relative to the scan path and `{kind}` its ecosystem, e.g. `cargo`, or nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateNamer {
+    pub template: String,
+}
+
+impl FolderNamer for TemplateNamer {
+    fn name(&self, dir: &Path, ctx: &NamingContext) -> Option<String> {
+        let path = ctx
+            .relative_path
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Some(
+            self.template
+                .replace(
+                    "{name}",
+                    &NameStyle::Basename.folder_name(dir, ctx.scan_path),
+                )
+                .replace("{path}", &path)
+                .replace("{kind}", ctx.ecosystem.map(Ecosystem::name).unwrap_or("")),
+        )
+    }
+}
+
+/// The prefix of generated folder names: `--folder-prefix`, or the emoji unless
+/// `--no-emoji`.
+pub(crate) fn name_prefix(opts: &GenerateOptions) -> &str {
+    match (&opts.folder_prefix, opts.no_emoji) {
+        (Some(prefix), _) => prefix.as_str(),
+        (None, true) => "",
+        (None, false) => "📦 ",
+    }
+}
+
+/// The namer the command line options describe, used after any given to the builder.
+pub fn default_namer(opts: &GenerateOptions) -> Arc<dyn FolderNamer> {
+    Arc::new(StyleNamer {
+        style: opts.name_style,
+        prefix: name_prefix(opts).to_string(),
+    })
+}
+
+/// The name the first of `namers` gives `dir`, or its directory name when none does.
+pub fn folder_name(namers: &[Arc<dyn FolderNamer>], dir: &Path, ctx: &NamingContext) -> String {
+    namers
+        .iter()
+        .find_map(|namer| namer.name(dir, ctx))
+        .unwrap_or_else(|| NameStyle::Basename.folder_name(dir, ctx.scan_path))
+}
+
+/// The name of the package in `dir`, from its manifest for `ecosystem`.
+fn package_name(dir: &Path, ecosystem: Ecosystem) -> Option<String> {
+    match ecosystem {
+        Ecosystem::Cargo => {
+            let content = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+            let manifest: toml::Table = content.parse().ok()?;
+            Some(manifest.get("package")?.get("name")?.as_str()?.to_string())
+        }
+        Ecosystem::Npm => {
+            let content = fs::read_to_string(dir.join("package.json")).ok()?;
+            let manifest: serde_json::Value =
+                serde_json::from_str(&jsonc::to_json(&content)).ok()?;
+            Some(manifest.get("name")?.as_str()?.to_string())
+        }
+        Ecosystem::Go | Ecosystem::Python => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    /// Names only the directory it was given.
+    #[derive(Debug)]
+    struct Fixed(&'static str, &'static str);
+
+    impl FolderNamer for Fixed {
+        fn name(&self, dir: &Path, _ctx: &NamingContext) -> Option<String> {
+            (dir.file_name()? == self.0).then(|| self.1.to_string())
+        }
+    }
+
+    #[test]
+    fn test_namer_chain_order() -> Result<()> {
+        let temp = TempDir::new()?;
+        let root = temp.path();
+        for dir in ["api", "web", "tools", "docs"] {
+            fs::create_dir(root.join(dir))?;
+        }
+        fs::write(
+            root.join("api/Cargo.toml"),
+            "[package]\nname = \"billing-api\"\n",
+        )?;
+        fs::write(root.join("web/package.json"), r#"{"name": "@team/web"}"#)?;
+        fs::write(root.join("tools/Cargo.toml"), "[workspace]\n")?;
+
+        let namers: Vec<Arc<dyn FolderNamer>> = vec![
+            Arc::new(Fixed("docs", "Handbook")),
+            Arc::new(Fixed("api", "Billing")),
+            Arc::new(PackageNamer {
+                prefix: "📦 ".to_string(),
+            }),
+            Arc::new(TemplateNamer {
+                template: "{name} ({kind})".to_string(),
+            }),
+        ];
+        let name = |dir: &str| {
+            let relative = Path::new(dir);
+            let ctx = NamingContext {
+                scan_path: root,
+                relative_path: relative,
+                ecosystem: Ecosystem::detect(&root.join(dir)),
+                existing_name: None,
+            };
+            folder_name(&namers, &root.join(dir), &ctx)
+        };
+
+        // The first namer to give a name wins, even when a later one would name it too
+        assert_eq!(name("docs"), "Handbook");
+        assert_eq!(name("api"), "Billing");
+        assert_eq!(name("web"), "📦 @team/web");
+        // A Cargo workspace without a package falls through to the template
+        assert_eq!(name("tools"), "tools (cargo)");
+
+        // Without any namer giving a name, the directory name is used
+        let ctx = NamingContext {
+            scan_path: root,
+            relative_path: Path::new("web"),
+            ecosystem: None,
+            existing_name: Some("Frontend"),
+        };
+        assert_eq!(folder_name(&namers[..2], &root.join("web"), &ctx), "web");
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+//! Packages of npm, yarn and pnpm workspaces, read from the root `package.json` and
+//! `pnpm-workspace.yaml` without running a package manager.
+
+use crate::cargo::lexical;
+use crate::{jsonc, WorkspaceMember};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The packages of the node workspace rooted at `root`, in the order its patterns list
+/// them, or nothing when `root` is not a workspace root. Directories the patterns match
+/// that have no `package.json` are skipped.
+pub fn workspace_packages(root: &Path) -> Result<Vec<WorkspaceMember>> {
+    let patterns = workspace_patterns(root)?;
+    Ok(expand_globs(root, &patterns)
+        .into_iter()
+        .filter_map(|dir| {
+            let content = fs::read_to_string(dir.join("package.json")).ok()?;
+            let package = serde_json::from_str::<Value>(&jsonc::to_json(&content))
+                .ok()
+                .and_then(|manifest| Some(manifest.get("name")?.as_str()?.to_string()))
+                .unwrap_or_else(|| {
+                    dir.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned()
+                });
+            Some(WorkspaceMember { dir, package })
+        })
+        .collect())
+}
+
+/// The workspace globs of `root`: the `workspaces` of its `package.json` (a list, or
+/// yarn's `{"packages": [...]}`) followed by the `packages` of `pnpm-workspace.yaml`.
+fn workspace_patterns(root: &Path) -> Result<Vec<String>> {
+    let mut patterns = vec![];
+    let manifest_path = root.join("package.json");
+    if let Ok(content) = fs::read_to_string(&manifest_path) {
+        let manifest: Value = serde_json::from_str(&jsonc::to_json(&content))
+            .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+        let workspaces = match &manifest["workspaces"] {
+            Value::Object(yarn) => yarn.get("packages").cloned().unwrap_or_default(),
+            workspaces => workspaces.clone(),
+        };
+        if let Value::Array(items) = workspaces {
+            patterns.extend(
+                items
+                    .iter()
+                    .filter_map(|item| Some(item.as_str()?.to_string())),
+            );
+        }
+    }
+    if let Ok(content) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        patterns.extend(pnpm_packages(&content));
+    }
+    Ok(patterns)
+}
+
+/// The `packages` list of a `pnpm-workspace.yaml`, in block (`- item`) or flow
+/// (`[a, b]`) style. Other keys are ignored.
+fn pnpm_packages(content: &str) -> Vec<String> {
+    let unquote = |item: &str| {
+        let item = item.trim();
+        match item.chars().next() {
+            Some(quote @ ('\'' | '"')) => item[1..].split(quote).next().unwrap_or_default(),
+            _ => item.split(" #").next().unwrap_or_default().trim(),
+        }
+        .to_string()
+    };
+    let mut packages = vec![];
+    let mut in_packages = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = false;
+            if let Some(value) = trimmed.strip_prefix("packages:") {
+                let value = value.trim();
+                match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                    Some(flow) => packages
+                        .extend(flow.split(',').map(unquote).filter(|item| !item.is_empty())),
+                    None => in_packages = value.is_empty(),
+                }
+            }
+            continue;
+        }
+        if in_packages {
+            if let Some(item) = trimmed.strip_prefix('-') {
+                packages.push(unquote(item));
+            }
+        }
+    }
+    packages
+}
+
+/// The directories under `root` matched by `patterns`, relative to `root`, in pattern
+/// order and without duplicates. A pattern starting with `!` removes the directories it
+/// matches, wherever it appears. Matches outside `root` are skipped.
+pub fn expand_globs(root: &Path, patterns: &[String]) -> Vec<PathBuf> {
+    let root = lexical(root);
+    let negations: Vec<glob::Pattern> = patterns
+        .iter()
+        .filter_map(|pattern| pattern.strip_prefix('!'))
+        .filter_map(|pattern| glob::Pattern::new(pattern.trim_start_matches("./")).ok())
+        .collect();
+
+    let mut dirs: Vec<PathBuf> = vec![];
+    for pattern in patterns.iter().filter(|pattern| !pattern.starts_with('!')) {
+        let Ok(paths) = glob::glob(&root.join(pattern).to_string_lossy()) else {
+            continue;
+        };
+        for dir in paths.filter_map(|path| path.ok()) {
+            let dir = lexical(&dir);
+            let Ok(relative) = dir.strip_prefix(&root) else {
+                continue;
+            };
+            let relative = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if relative.is_empty()
+                || !dir.is_dir()
+                || negations.iter().any(|negation| negation.matches(&relative))
+                || dirs.contains(&dir)
+            {
+                continue;
+            }
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn tree(dirs: &[&str]) -> Result<TempDir> {
+        let temp = TempDir::new()?;
+        for dir in dirs {
+            fs::create_dir_all(temp.path().join(dir))?;
+        }
+        Ok(temp)
+    }
+
+    fn relative(root: &Path, dirs: Vec<PathBuf>) -> Vec<String> {
+        dirs.iter()
+            .map(|dir| {
+                dir.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_globs() -> Result<()> {
+        let temp = tree(&[
+            "packages/ui",
+            "packages/legacy-ui",
+            "packages/legacy-api",
+            "apps/web",
+            "apps/docs",
+            "tools",
+        ])?;
+        let root = temp.path();
+        let patterns = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let dirs = expand_globs(root, &patterns(&["packages/*", "apps/web", "apps/*"]));
+        assert_eq!(
+            relative(root, dirs),
+            vec![
+                "packages/legacy-api",
+                "packages/legacy-ui",
+                "packages/ui",
+                "apps/web",
+                "apps/docs"
+            ]
+        );
+
+        // Negations apply wherever they appear
+        let dirs = expand_globs(
+            root,
+            &patterns(&["!packages/legacy-*", "packages/*", "./tools", "../*"]),
+        );
+        assert_eq!(relative(root, dirs), vec!["packages/ui", "tools"]);
+
+        // Recursive patterns
+        let dirs = expand_globs(root, &patterns(&["apps/**", "!apps"]));
+        assert_eq!(relative(root, dirs), vec!["apps/docs", "apps/web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pnpm_packages() {
+        let content = r#"
+# the monorepo
+packages:
+  - 'packages/*'
+  - "apps/**"   # every app
+  - '!packages/legacy-*'
+catalog:
+  react: ^18
+"#;
+        assert_eq!(
+            pnpm_packages(content),
+            vec!["packages/*", "apps/**", "!packages/legacy-*"]
+        );
+        assert_eq!(
+            pnpm_packages("packages: ['a/*', \"b\"]\n"),
+            vec!["a/*", "b"]
+        );
+    }
+
+    #[test]
+    fn test_workspace_packages() -> Result<()> {
+        let temp = tree(&[
+            "packages/ui",
+            "packages/legacy-ui",
+            "packages/notes",
+            "apps/web",
+        ])?;
+        let root = temp.path();
+        fs::write(
+            root.join("package.json"),
+            r#"{"name": "repo", "workspaces": {"packages": ["packages/*"]}}"#,
+        )?;
+        fs::write(
+            root.join("pnpm-workspace.yaml"),
+            "packages:\n  - apps/*\n  - '!packages/legacy-*'\n",
+        )?;
+        fs::write(
+            root.join("packages/ui/package.json"),
+            r#"{"name": "@repo/ui"}"#,
+        )?;
+        fs::write(
+            root.join("packages/legacy-ui/package.json"),
+            r#"{"name": "old"}"#,
+        )?;
+        fs::write(root.join("apps/web/package.json"), "{}")?;
+
+        let packages = workspace_packages(root)?;
+        let names: Vec<&str> = packages.iter().map(|p| p.package.as_str()).collect();
+        assert_eq!(names, vec!["@repo/ui", "web"]);
+        assert!(workspace_packages(&root.join("apps/web"))?.is_empty());
+        Ok(())
+    }
+}
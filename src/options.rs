@@ -0,0 +1,2371 @@
+//! Generation options: the values of `generate` resolved against configuration files
+//! and the environment, the choices they offer, and the arguments written back into
+//! the Update Workspace task.
+
+use crate::cli::{Cli, Command, GenerateArgs};
+use crate::config;
+use crate::console;
+use crate::error::{Result, WorkspaceError};
+use crate::filter::{compile_regexes, TimeSpec};
+use crate::io::Indent;
+use crate::scan::check_depths;
+use crate::tasks::UPDATE_TASK_LABEL;
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Collects command-line arguments, one argument per flag and per value.
+#[derive(Default)]
+struct TaskArgs(Vec<String>);
+
+impl TaskArgs {
+    fn flag(&mut self, flag: &str, set: bool) {
+        if set {
+            self.0.push(flag.to_string());
+        }
+    }
+
+    fn value(&mut self, flag: &str, value: Option<&str>) {
+        if let Some(value) = value {
+            self.0.extend([flag.to_string(), value.to_string()]);
+        }
+    }
+}
+
+/// Generation options together with where each value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedOptions {
+    pub options: GenerateOptions,
+    pub provenance: config::Provenance,
+    /// The directory the workspace is named after when no name was given
+    pub named_after: Option<PathBuf>,
+}
+
+impl ResolvedOptions {
+    /// A commented project configuration, as written by `init`, setting the values
+    /// given on the command line and showing every other key commented out. Resolving
+    /// with it and no flags gives the same options, except for a `git-activity` sort,
+    /// which only applies when given on the command line.
+    pub fn to_config_toml(&self) -> String {
+        config::scaffold(|key| {
+            // One provenance entry covers both spellings of these settings
+            let field = match key {
+                "tabs" => "indent",
+                "emoji" => "no_emoji",
+                key => key,
+            };
+            match self.provenance.get(field) {
+                Some(config::Source::CommandLine) => self.config_value(key),
+                _ => None,
+            }
+        })
+    }
+
+    /// The value of the configuration key `key` in these options, or `None` when it is
+    /// unset or has no value of its own here.
+    fn config_value(&self, key: &str) -> Option<toml_edit::Value> {
+        let opts = &self.options;
+        let strings = |values: &[String]| values.iter().collect::<toml_edit::Value>();
+        let path = |path: &Option<PathBuf>| -> Option<toml_edit::Value> {
+            Some(path.as_ref()?.to_string_lossy().as_ref().into())
+        };
+        let number = |number: usize| i64::try_from(number).ok().map(toml_edit::Value::from);
+        let choice = |name: String| Some(toml_edit::Value::from(name));
+        match key {
+            "name" => Some(opts.name.as_str().into()),
+            "exclude_current" => Some(opts.exclude_current.into()),
+            "discover" => Some(opts.discover.into()),
+            "folders_file" => path(&opts.folders_file),
+            "merge_scan" => Some(opts.merge_scan.into()),
+            "missing_folders" => choice(value_name(&opts.missing_folders)),
+            "exclude" => Some(strings(&opts.exclude)),
+            "exclude_name" => Some(strings(&opts.exclude_name)),
+            "exclude_name_ci" => Some(opts.exclude_name_ci.into()),
+            "exclude_regex" => Some(strings(&opts.exclude_regex)),
+            "include_regex" => Some(strings(&opts.include_regex)),
+            "exclude_in" => Some(
+                opts.exclude_in
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect(),
+            ),
+            "include_hidden" => Some(opts.include_hidden.into()),
+            "markers" => Some(strings(&opts.markers)),
+            "contains" => Some(strings(&opts.contains)),
+            "contains_recursive" => number(opts.contains_depth?),
+            "newer_than" => Some(opts.newer_than?.to_string().into()),
+            "older_than" => Some(opts.older_than?.to_string().into()),
+            "max_depth" => number(opts.max_depth?),
+            "min_depth" => number(opts.min_depth?),
+            "max_entries" => number(opts.max_entries?),
+            "warn_size" => number(opts.warn_size?),
+            "min_folders" => number(opts.min_folders),
+            "allow_empty" => Some((opts.min_folders == 0).into()),
+            "warn_folders" => number(opts.warn_folders),
+            "max_folders" => number(opts.max_folders?),
+            "sort" => match opts.sort {
+                SortOrder::GitActivity => None,
+                sort => choice(value_name(&sort)),
+            },
+            "name_style" => choice(value_name(&opts.name_style)),
+            "folder_prefix" => Some(opts.folder_prefix.as_deref()?.into()),
+            "no_emoji" => Some(opts.no_emoji.into()),
+            "show_branch" => Some(opts.show_branch.into()),
+            "worktrees" => opts.worktrees.map(|value| value_name(&value).into()),
+            "git_status" => opts.git_status.map(|value| value_name(&value).into()),
+            "collapse_nested" => opts.collapse_nested.map(|value| value_name(&value).into()),
+            "canonical_dedupe" => Some(opts.canonical_dedupe.into()),
+            "fix_overlaps" => Some(opts.fix_overlaps.into()),
+            "hide_nested" => Some(opts.hide_nested.into()),
+            "absorb_workspaces" => Some(opts.absorb_workspaces.into()),
+            "cargo_members" => Some(opts.cargo_members.into()),
+            "node_packages" => Some(opts.node_packages.into()),
+            "submodules" => Some(opts.submodules.into()),
+            "uninitialized_submodules" => choice(value_name(&opts.uninitialized_submodules)),
+            "record_remotes" => Some(opts.record_remotes.into()),
+            "no_task" => Some(opts.no_task.into()),
+            "task_label" => Some(opts.task_label.as_deref()?.into()),
+            "task_reveal" => choice(value_name(&opts.task_reveal)),
+            "task_panel" => choice(value_name(&opts.task_panel)),
+            "task_group" => opts.task_group.map(|value| value_name(&value).into()),
+            "task_command" => choice(value_name(&opts.task_command)),
+            "task_type" => choice(value_name(&opts.task_type)),
+            "task_shell_command" => Some(opts.task_shell_command.as_deref()?.into()),
+            "task_run_on_open" => Some(opts.task_run_on_open.into()),
+            "folder_tasks" => Some(opts.folder_tasks.into()),
+            "rust_analyzer_links" => Some(opts.rust_analyzer_links.into()),
+            "recommend_extensions" => Some(opts.recommend_extensions.into()),
+            "exclude_heavy_dirs" => Some(opts.exclude_heavy_dirs.into()),
+            "launch_template" => path(&opts.launch_template),
+            "template" => path(&opts.template),
+            "copy_settings_from" => path(&opts.copy_settings_from),
+            "copy_extensions_from" => path(&opts.copy_extensions_from),
+            "settings_merge" => choice(value_name(&opts.settings_merge)),
+            "respect_remote_authority" => Some(opts.respect_remote_authority.into()),
+            "container_root" => Some(opts.container_root.as_deref()?.into()),
+            "dual" => Some(opts.dual.into()),
+            "relative_to" => path(&opts.relative_to),
+            "emit" => Some(opts.emit.iter().map(value_name).collect()),
+            "append" => Some((opts.reconcile == ReconcileMode::Append).into()),
+            "surgical" => Some(opts.surgical.into()),
+            "validate" => Some(opts.validate.into()),
+            "indent" => match opts.indent? {
+                Indent::Spaces(spaces) => number(spaces),
+                Indent::Tabs => None,
+            },
+            "tabs" => (opts.indent? == Indent::Tabs).then(|| true.into()),
+            "final_newline" => Some(opts.final_newline.into()),
+            "cache" => Some(opts.cache.is_some().into()),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a `--set KEY=VALUE` argument. The value is parsed as JSON, so `true`, `4`
+/// or `["a"]` keep their type, and anything else is taken as a string.
+pub fn parse_setting(setting: &str) -> Result<(String, serde_json::Value)> {
+    let (key, value) = setting
+        .split_once('=')
+        .filter(|(key, _)| !key.trim().is_empty())
+        .with_context(|| format!("Invalid setting '{}': expected KEY=VALUE", setting))?;
+    let value = serde_json::from_str(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+    Ok((key.trim().to_string(), value))
+}
+
+/// The directory a workspace without a name is named after: the top of the git
+/// repository `scan_path` is in, the closest directory upwards for which
+/// `is_repo_root` holds, or else `base_dir`, where the file is written.
+pub fn default_name_dir<'a>(
+    scan_path: &'a Path,
+    base_dir: &'a Path,
+    is_repo_root: impl Fn(&Path) -> bool,
+) -> &'a Path {
+    scan_path
+        .ancestors()
+        .find(|dir| is_repo_root(dir))
+        .unwrap_or(base_dir)
+}
+
+/// The name of the directory `dir`, empty for a root.
+pub(crate) fn dir_name(dir: &Path) -> String {
+    dir.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Fails unless `name` can name the workspace file: it must not be empty, `.`, `..`,
+/// or contain a path separator.
+pub(crate) fn check_name(name: &str) -> Result<()> {
+    if matches!(name, "" | "." | "..") || name.contains(['/', '\\', '\0']) {
+        return Err(WorkspaceError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// The name of the workspace in the file at `path`: its file name without the
+/// `.code-workspace` extension.
+fn workspace_file_name(path: &Path) -> String {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    file_name.trim_end_matches(".code-workspace").to_string()
+}
+
+/// The value of an on/off flag pair: `None` unless one of them was given. The
+/// pair overrides each other, so at most one is set.
+fn switch(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+impl GenerateArgs {
+    /// Resolves the parsed flags into [`GenerateOptions`], canonicalizing the scan path
+    /// and deriving the workspace name from `base_dir`, the directory the workspace
+    /// file goes in, when none is given.
+    pub fn to_options(&self, base_dir: &Path) -> Result<GenerateOptions> {
+        Ok(self.resolve(base_dir)?.options)
+    }
+
+    /// Resolves the options from, in increasing order of precedence: built-in
+    /// defaults, the global configuration, the [`config::PROJECT_CONFIG_FILE`] in the
+    /// scan root, the selected profile of each, `WORKSPACE_MANAGER_*` variables and
+    /// the flags that were actually given. Without a name, the workspace is named
+    /// after `base_dir`, the directory the workspace file goes in.
+    pub fn resolve(&self, base_dir: &Path) -> Result<ResolvedOptions> {
+        let scan_path = Path::new(&self.path)
+            .canonicalize()
+            .map_err(|_| WorkspaceError::ScanPathNotFound(PathBuf::from(&self.path)))?;
+        let global = match config::global_config_path() {
+            Some(path) => load_config(&path)?,
+            None => None,
+        };
+        let project = load_config(&scan_path.join(config::PROJECT_CONFIG_FILE))?;
+        let env = config::Config::from_env(|key| env::var(key).ok())?;
+
+        let layers = config::ConfigLayers::build(global, project, self.profile.as_deref(), env)?;
+        self.resolve_with(scan_path, base_dir, &layers)
+    }
+
+    /// Like [`GenerateArgs::resolve`], with the scan path already resolved and the
+    /// configuration already stacked.
+    pub fn resolve_with(
+        &self,
+        scan_path: PathBuf,
+        base_dir: &Path,
+        layers: &config::ConfigLayers,
+    ) -> Result<ResolvedOptions> {
+        let mut provenance = config::Provenance::default();
+        let list = |values: &[String]| (!values.is_empty()).then(|| values.to_vec());
+
+        let mut named_after = None;
+        let configured = layers.resolve(&mut provenance, "name", self.name.clone(), |c| {
+            c.name.clone()
+        });
+        let name = match (configured, &self.workspace_file) {
+            (Some(name), _) => name,
+            // The root folder entry is labeled like the file it is in
+            (None, Some(file)) => workspace_file_name(Path::new(file)),
+            (None, None) => {
+                let dir = default_name_dir(&scan_path, base_dir, |dir| dir.join(".git").exists());
+                named_after = Some(dir.to_path_buf());
+                dir_name(dir)
+            }
+        };
+        check_name(&name)?;
+        let exclude = layers
+            .resolve(&mut provenance, "exclude", list(&self.exclude), |c| {
+                c.exclude.clone()
+            })
+            .unwrap_or_default();
+        for pattern in &exclude {
+            glob::Pattern::new(pattern).map_err(|source| WorkspaceError::InvalidPattern {
+                pattern: pattern.clone(),
+                source,
+            })?;
+        }
+        let exclude_name = layers
+            .resolve(
+                &mut provenance,
+                "exclude_name",
+                list(&self.exclude_name),
+                |c| c.exclude_name.clone(),
+            )
+            .unwrap_or_default();
+        let exclude_regex = layers
+            .resolve(
+                &mut provenance,
+                "exclude_regex",
+                list(&self.exclude_regex),
+                |c| c.exclude_regex.clone(),
+            )
+            .unwrap_or_default();
+        let include_regex = layers
+            .resolve(
+                &mut provenance,
+                "include_regex",
+                list(&self.include_regex),
+                |c| c.include_regex.clone(),
+            )
+            .unwrap_or_default();
+        let exclude_in = layers
+            .resolve(&mut provenance, "exclude_in", list(&self.exclude_in), |c| {
+                c.exclude_in.clone()
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        compile_regexes(&exclude_regex)?;
+        compile_regexes(&include_regex)?;
+        let exclude_name_ci = layers
+            .resolve(
+                &mut provenance,
+                "exclude_name_ci",
+                switch(self.exclude_name_ci, self.no_exclude_name_ci),
+                |c| c.exclude_name_ci,
+            )
+            .unwrap_or(false);
+        let settings = self
+            .set
+            .iter()
+            .map(|setting| parse_setting(setting))
+            .collect::<Result<Vec<_>>>()?;
+        let exclude_current = layers
+            .resolve(
+                &mut provenance,
+                "exclude_current",
+                switch(self.exclude_current, self.no_exclude_current),
+                |c| c.exclude_current,
+            )
+            .unwrap_or(false);
+        let include_hidden = layers
+            .resolve(
+                &mut provenance,
+                "include_hidden",
+                switch(self.include_hidden, self.no_include_hidden),
+                |c| c.include_hidden,
+            )
+            .unwrap_or(false);
+        let folder_prefix = layers.resolve(
+            &mut provenance,
+            "folder_prefix",
+            self.folder_prefix.clone(),
+            |c| c.folder_prefix.clone(),
+        );
+        let mut sort = layers
+            .resolve(&mut provenance, "sort", self.sort, |c| c.sort)
+            .unwrap_or_default();
+        // The order changes with every commit, so it must not come back on refreshes
+        if sort == SortOrder::GitActivity && self.sort != Some(sort) {
+            console::warning(format_args!(
+                "ignoring sort = \"git-activity\" from the {}; it only applies when \
+                 --sort git-activity is given on the command line",
+                provenance.get("sort").unwrap_or(config::Source::Default)
+            ));
+            sort = SortOrder::default();
+        }
+        let name_style = layers
+            .resolve(&mut provenance, "name_style", self.name_style, |c| {
+                c.name_style
+            })
+            .unwrap_or_default();
+        let worktrees = layers.resolve(&mut provenance, "worktrees", self.worktrees, |c| {
+            c.worktrees
+        });
+        let git_status = layers.resolve(&mut provenance, "git_status", self.git_status, |c| {
+            c.git_status
+        });
+        let collapse_nested = layers.resolve(
+            &mut provenance,
+            "collapse_nested",
+            self.collapse_nested,
+            |c| c.collapse_nested,
+        );
+        let canonical_dedupe = layers
+            .resolve(
+                &mut provenance,
+                "canonical_dedupe",
+                switch(self.canonical_dedupe, false),
+                |c| c.canonical_dedupe,
+            )
+            .unwrap_or(false);
+        let fix_overlaps = layers
+            .resolve(
+                &mut provenance,
+                "fix_overlaps",
+                switch(self.fix_overlaps, false),
+                |c| c.fix_overlaps,
+            )
+            .unwrap_or(false);
+        let markers = layers
+            .resolve(&mut provenance, "markers", list(&self.markers), |c| {
+                c.markers.clone()
+            })
+            .unwrap_or_default();
+        let contains = layers
+            .resolve(&mut provenance, "contains", list(&self.contains), |c| {
+                c.contains.clone()
+            })
+            .unwrap_or_default();
+        let contains_depth = layers.resolve(
+            &mut provenance,
+            "contains_recursive",
+            self.contains_recursive,
+            |c| c.contains_recursive,
+        );
+        let newer_than = layers.resolve(&mut provenance, "newer_than", self.newer_than, |c| {
+            c.newer_than
+        });
+        let older_than = layers.resolve(&mut provenance, "older_than", self.older_than, |c| {
+            c.older_than
+        });
+        let max_depth = layers.resolve(&mut provenance, "max_depth", self.max_depth, |c| {
+            c.max_depth
+        });
+        let min_depth = layers.resolve(&mut provenance, "min_depth", self.min_depth, |c| {
+            c.min_depth
+        });
+        check_depths(min_depth.unwrap_or(1), max_depth.unwrap_or(1))?;
+        let allow_empty = layers
+            .resolve(
+                &mut provenance,
+                "allow_empty",
+                switch(self.allow_empty, false),
+                |c| c.allow_empty,
+            )
+            .unwrap_or(false);
+        let min_folders = layers
+            .resolve(&mut provenance, "min_folders", self.min_folders, |c| {
+                c.min_folders
+            })
+            .unwrap_or(1);
+        let warn_folders = layers
+            .resolve(&mut provenance, "warn_folders", self.warn_folders, |c| {
+                c.warn_folders
+            })
+            .unwrap_or(DEFAULT_WARN_FOLDERS);
+        let max_folders = layers.resolve(&mut provenance, "max_folders", self.max_folders, |c| {
+            c.max_folders
+        });
+        let max_entries = layers.resolve(&mut provenance, "max_entries", self.max_entries, |c| {
+            c.max_entries
+        });
+        let warn_size = layers.resolve(&mut provenance, "warn_size", self.warn_size, |c| {
+            c.warn_size
+        });
+        let emit = (!self.emit.is_empty()).then(|| self.emit.clone());
+        let emit = layers
+            .resolve(&mut provenance, "emit", emit, |c| c.emit.clone())
+            .unwrap_or_default();
+        let no_task = layers
+            .resolve(
+                &mut provenance,
+                "no_task",
+                switch(self.no_task, self.task),
+                |c| c.no_task,
+            )
+            .unwrap_or(false)
+            // The task couldn't pipe the directories in again
+            || self.reads_stdin();
+        let task_label = layers.resolve(
+            &mut provenance,
+            "task_label",
+            self.task_label.clone(),
+            |c| c.task_label.clone(),
+        );
+        let task_reveal = layers
+            .resolve(&mut provenance, "task_reveal", self.task_reveal, |c| {
+                c.task_reveal
+            })
+            .unwrap_or_default();
+        let task_panel = layers
+            .resolve(&mut provenance, "task_panel", self.task_panel, |c| {
+                c.task_panel
+            })
+            .unwrap_or_default();
+        let task_group = layers.resolve(&mut provenance, "task_group", self.task_group, |c| {
+            c.task_group
+        });
+        let task_command = layers
+            .resolve(&mut provenance, "task_command", self.task_command, |c| {
+                c.task_command
+            })
+            .unwrap_or_default();
+        let task_type = layers
+            .resolve(&mut provenance, "task_type", self.task_type, |c| {
+                c.task_type
+            })
+            .unwrap_or_default();
+        let task_shell_command = layers.resolve(
+            &mut provenance,
+            "task_shell_command",
+            self.task_shell_command.clone(),
+            |c| c.task_shell_command.clone(),
+        );
+        let task_run_on_open = layers
+            .resolve(
+                &mut provenance,
+                "task_run_on_open",
+                switch(self.task_run_on_open, self.no_task_run_on_open),
+                |c| c.task_run_on_open,
+            )
+            .unwrap_or(false);
+        let folder_tasks = layers
+            .resolve(
+                &mut provenance,
+                "folder_tasks",
+                switch(self.folder_tasks, self.no_folder_tasks),
+                |c| c.folder_tasks,
+            )
+            .unwrap_or(false);
+        let hide_nested = layers
+            .resolve(
+                &mut provenance,
+                "hide_nested",
+                switch(self.hide_nested, self.no_hide_nested),
+                |c| c.hide_nested,
+            )
+            .unwrap_or(false);
+        let show_branch = layers
+            .resolve(
+                &mut provenance,
+                "show_branch",
+                switch(self.show_branch, self.no_show_branch),
+                |c| c.show_branch,
+            )
+            .unwrap_or(false);
+        let cargo_members = layers
+            .resolve(
+                &mut provenance,
+                "cargo_members",
+                switch(self.cargo_members, self.no_cargo_members),
+                |c| c.cargo_members,
+            )
+            .unwrap_or(false);
+        let node_packages = layers
+            .resolve(
+                &mut provenance,
+                "node_packages",
+                switch(self.node_packages, self.no_node_packages),
+                |c| c.node_packages,
+            )
+            .unwrap_or(false);
+        let submodules = layers
+            .resolve(
+                &mut provenance,
+                "submodules",
+                switch(self.submodules, self.no_submodules),
+                |c| c.submodules,
+            )
+            .unwrap_or(false);
+        let uninitialized_submodules = layers
+            .resolve(
+                &mut provenance,
+                "uninitialized_submodules",
+                self.uninitialized_submodules,
+                |c| c.uninitialized_submodules,
+            )
+            .unwrap_or_default();
+        let absorb_workspaces = layers
+            .resolve(
+                &mut provenance,
+                "absorb_workspaces",
+                switch(self.absorb_workspaces, self.no_absorb_workspaces),
+                |c| c.absorb_workspaces,
+            )
+            .unwrap_or(false);
+        let rust_analyzer_links = layers
+            .resolve(
+                &mut provenance,
+                "rust_analyzer_links",
+                switch(self.rust_analyzer_links, self.no_rust_analyzer_links),
+                |c| c.rust_analyzer_links,
+            )
+            .unwrap_or(false);
+        let recommend_extensions = layers
+            .resolve(
+                &mut provenance,
+                "recommend_extensions",
+                switch(self.recommend_extensions, self.no_recommend_extensions),
+                |c| c.recommend_extensions,
+            )
+            .unwrap_or(false);
+        let exclude_heavy_dirs = layers
+            .resolve(
+                &mut provenance,
+                "exclude_heavy_dirs",
+                switch(self.exclude_heavy_dirs, self.no_exclude_heavy_dirs),
+                |c| c.exclude_heavy_dirs,
+            )
+            .unwrap_or(false);
+        let record_remotes = layers
+            .resolve(
+                &mut provenance,
+                "record_remotes",
+                switch(self.record_remotes, self.no_record_remotes),
+                |c| c.record_remotes,
+            )
+            .unwrap_or(false);
+        let respect_remote_authority = layers
+            .resolve(
+                &mut provenance,
+                "respect_remote_authority",
+                switch(
+                    self.respect_remote_authority,
+                    self.no_respect_remote_authority,
+                ),
+                |c| c.respect_remote_authority,
+            )
+            .unwrap_or(false);
+        let container_root = layers.resolve(
+            &mut provenance,
+            "container_root",
+            self.container_root.clone(),
+            |c| c.container_root.clone(),
+        );
+        let dual = layers
+            .resolve(&mut provenance, "dual", self.dual.then_some(true), |c| {
+                c.dual
+            })
+            .unwrap_or(false);
+        if dual && container_root.is_none() {
+            return Err(anyhow::anyhow!(
+                "--dual needs a container root to write the container file with"
+            )
+            .into());
+        }
+        let folders_file = layers
+            .resolve(
+                &mut provenance,
+                "folders_file",
+                self.folders_file.clone(),
+                |c| c.folders_file.clone(),
+            )
+            .map(PathBuf::from);
+        let merge_scan = layers
+            .resolve(
+                &mut provenance,
+                "merge_scan",
+                switch(self.merge_scan, self.no_merge_scan),
+                |c| c.merge_scan,
+            )
+            .unwrap_or(false);
+        let missing_folders = layers
+            .resolve(
+                &mut provenance,
+                "missing_folders",
+                self.missing_folders,
+                |c| c.missing_folders,
+            )
+            .unwrap_or_default();
+        let relative_to = layers.resolve(
+            &mut provenance,
+            "relative_to",
+            self.relative_to.clone(),
+            |c| c.relative_to.clone(),
+        );
+        if relative_to.is_some() && container_root.is_some() {
+            return Err(anyhow::anyhow!(
+                "--relative-to can't be used with --container-root, which writes absolute paths"
+            )
+            .into());
+        }
+        let relative_to = relative_to
+            .map(|dir| {
+                Path::new(&dir).canonicalize().with_context(|| {
+                    format!("Directory for --relative-to '{}' does not exist", dir)
+                })
+            })
+            .transpose()?;
+        let launch_template = layers
+            .resolve(
+                &mut provenance,
+                "launch_template",
+                self.launch_template.clone(),
+                |c| c.launch_template.clone(),
+            )
+            .map(PathBuf::from);
+        let template = layers
+            .resolve(&mut provenance, "template", self.template.clone(), |c| {
+                c.template.clone()
+            })
+            .map(PathBuf::from);
+        let copy_settings_from = layers
+            .resolve(
+                &mut provenance,
+                "copy_settings_from",
+                self.copy_settings_from.clone(),
+                |c| c.copy_settings_from.clone(),
+            )
+            .map(PathBuf::from);
+        let copy_extensions_from = layers
+            .resolve(
+                &mut provenance,
+                "copy_extensions_from",
+                self.copy_extensions_from.clone(),
+                |c| c.copy_extensions_from.clone(),
+            )
+            .map(PathBuf::from);
+        let settings_merge = layers
+            .resolve(
+                &mut provenance,
+                "settings_merge",
+                self.settings_merge,
+                |c| c.settings_merge,
+            )
+            .unwrap_or_default();
+        let extension_map = layers
+            .resolve(&mut provenance, "extensions", None, |c| {
+                c.extensions.clone()
+            })
+            .unwrap_or_default();
+        let no_emoji = layers
+            .resolve(
+                &mut provenance,
+                "no_emoji",
+                switch(self.no_emoji, self.emoji),
+                |c| {
+                    let code_page = console::console_code_page();
+                    c.no_emoji
+                        .or(c.emoji.map(|choice| !choice.in_file(code_page)))
+                },
+            )
+            .unwrap_or(false);
+        let cache = layers
+            .resolve(
+                &mut provenance,
+                "cache",
+                switch(self.cache, self.no_cache),
+                |c| c.cache,
+            )
+            .unwrap_or(false);
+        let surgical = layers
+            .resolve(
+                &mut provenance,
+                "surgical",
+                switch(self.surgical, false),
+                |c| c.surgical,
+            )
+            .unwrap_or(false);
+        let validate = layers
+            .resolve(
+                &mut provenance,
+                "validate",
+                switch(self.validate, false),
+                |c| c.validate,
+            )
+            .unwrap_or(false);
+        let indent = layers.resolve(
+            &mut provenance,
+            "indent",
+            match self.tabs {
+                true => Some(Indent::Tabs),
+                false => self.indent.map(Indent::Spaces),
+            },
+            |c| match c.tabs {
+                Some(true) => Some(Indent::Tabs),
+                _ => c.indent.map(Indent::Spaces),
+            },
+        );
+        let final_newline = layers
+            .resolve(&mut provenance, "final_newline", self.final_newline, |c| {
+                c.final_newline
+            })
+            .unwrap_or(false);
+        let discover = layers
+            .resolve(
+                &mut provenance,
+                "discover",
+                switch(false, self.no_discover),
+                |c| c.discover,
+            )
+            .unwrap_or(true);
+        let append = layers
+            .resolve(
+                &mut provenance,
+                "append",
+                switch(self.append, self.prune),
+                |c| c.append,
+            )
+            .unwrap_or(false);
+        // Replacing drops folders added by hand, so only the flag of this run asks for it
+        let reconcile = match (self.replace, append) {
+            (true, _) => ReconcileMode::Replace,
+            (false, true) => ReconcileMode::Append,
+            (false, false) => ReconcileMode::Prune,
+        };
+        let cache = match cache {
+            true => crate::cache::default_cache_path(&scan_path),
+            false => None,
+        };
+
+        let options = GenerateOptions {
+            scan_path,
+            name,
+            exclude_current,
+            update_task: self.update_task,
+            no_task,
+            task_label,
+            task_reveal,
+            task_panel,
+            task_group,
+            task_command,
+            task_type,
+            task_shell_command,
+            task_run_on_open,
+            folder_tasks,
+            hide_nested,
+            show_branch,
+            rust_analyzer_links,
+            absorb_workspaces,
+            cargo_members,
+            node_packages,
+            submodules,
+            uninitialized_submodules,
+            recommend_extensions,
+            exclude_heavy_dirs,
+            record_remotes,
+            extension_map,
+            launch_template,
+            template,
+            reapply_template: self.reapply_template,
+            settings_merge,
+            copy_settings_from,
+            copy_extensions_from,
+            respect_remote_authority,
+            container_root,
+            relative_to,
+            candidates: None,
+            folders_file,
+            merge_scan,
+            missing_folders,
+            dual,
+            settings,
+            unset: self.unset.clone(),
+            task_args: self.to_task_args(),
+            exclude,
+            exclude_name,
+            exclude_name_ci,
+            exclude_regex,
+            include_regex,
+            exclude_in,
+            include_hidden,
+            folder_prefix,
+            sort,
+            markers,
+            contains,
+            contains_depth,
+            newer_than,
+            older_than,
+            max_depth,
+            min_depth,
+            min_folders: if allow_empty { 0 } else { min_folders },
+            warn_folders,
+            max_folders: max_folders.filter(|_| !self.force),
+            max_entries,
+            warn_size,
+            limit: self.limit,
+            emit,
+            name_style,
+            worktrees,
+            collapse_nested,
+            canonical_dedupe,
+            fix_overlaps,
+            git_status,
+            verbose: self.verbose,
+            no_emoji,
+            cache,
+            surgical,
+            validate,
+            indent,
+            final_newline,
+            reconcile,
+            discover,
+            workspace_file: self.workspace_file.as_ref().map(|file| base_dir.join(file)),
+        };
+        Ok(ResolvedOptions {
+            options,
+            provenance,
+            named_after,
+        })
+    }
+
+    /// Recovers the options of an invocation from the `args` of its Update Workspace
+    /// task. Tasks written by older versions contain only bare flags, newer ones may
+    /// start with the `generate` subcommand.
+    pub fn from_task_args(args: &[String]) -> Result<GenerateArgs> {
+        let cli = Cli::try_parse_from(
+            std::iter::once("workspace-manager").chain(args.iter().map(String::as_str)),
+        )
+        .context("Failed to parse the Update Workspace task arguments")?;
+        match cli.into_command() {
+            Command::Generate(args) => Ok(*args),
+            _ => Err(
+                anyhow::anyhow!("The Update Workspace task does not generate a workspace").into(),
+            ),
+        }
+    }
+
+    /// Where `--paths-from` or `--paths-from0` reads the directories from, `-` for
+    /// stdin, and the byte separating them.
+    pub fn paths_source(&self) -> Option<(&str, u8)> {
+        match (&self.paths_from, &self.paths_from0) {
+            (Some(file), _) => Some((file, b'\n')),
+            (None, Some(file)) => Some((file, b'\0')),
+            (None, None) => None,
+        }
+    }
+
+    /// Whether the directories are read from stdin instead of scanned.
+    pub fn reads_stdin(&self) -> bool {
+        self.paths_source().is_some_and(|(file, _)| file == "-")
+    }
+
+    /// Arguments stored in the Update Workspace task to reproduce this invocation.
+    ///
+    /// Every option that affects the generated file is emitted when it was given, except
+    /// those that only apply to the run they are given on:
+    /// - `--open`, `--watch`, `--interactive`, `--verbose`, `--print-path`, `--dry-run`
+    ///   and their companions, which change how the run goes rather than the file;
+    /// - `--reapply-template`, so a refresh doesn't merge `--template` over edits made
+    ///   since, while new files are still seeded from it;
+    /// - `--limit`, so a refresh adds the folders it left out;
+    /// - `--replace`, which drops folders added with `add` and must never run
+    ///   implicitly.
+    ///
+    /// Each value is a separate argument, so values with spaces survive. No subcommand
+    /// name is emitted, so the task also runs with versions that predate subcommands.
+    pub fn to_task_args(&self) -> Vec<String> {
+        let mut args = TaskArgs::default();
+        args.value("--name", self.name.as_deref());
+        args.value("--workspace-file", self.workspace_file.as_deref());
+        args.flag("--exclude-current", self.exclude_current);
+        args.flag("--no-exclude-current", self.no_exclude_current);
+        args.flag("--update-task", self.update_task);
+        args.flag("--task", self.task);
+        args.value("--task-label", self.task_label.as_deref());
+        args.value(
+            "--task-reveal",
+            self.task_reveal.as_ref().map(value_name).as_deref(),
+        );
+        args.value(
+            "--task-panel",
+            self.task_panel.as_ref().map(value_name).as_deref(),
+        );
+        args.value(
+            "--task-group",
+            self.task_group.as_ref().map(value_name).as_deref(),
+        );
+        args.value(
+            "--task-command",
+            self.task_command.as_ref().map(value_name).as_deref(),
+        );
+        args.value(
+            "--task-type",
+            self.task_type.as_ref().map(value_name).as_deref(),
+        );
+        args.value("--task-shell-command", self.task_shell_command.as_deref());
+        args.flag("--task-run-on-open", self.task_run_on_open);
+        args.flag("--no-task-run-on-open", self.no_task_run_on_open);
+        args.flag("--folder-tasks", self.folder_tasks);
+        args.flag("--no-folder-tasks", self.no_folder_tasks);
+        args.flag("--hide-nested", self.hide_nested);
+        args.flag("--no-hide-nested", self.no_hide_nested);
+        args.flag("--show-branch", self.show_branch);
+        args.flag("--no-show-branch", self.no_show_branch);
+        args.flag("--rust-analyzer-links", self.rust_analyzer_links);
+        args.flag("--no-rust-analyzer-links", self.no_rust_analyzer_links);
+        args.flag("--absorb-workspaces", self.absorb_workspaces);
+        args.flag("--no-absorb-workspaces", self.no_absorb_workspaces);
+        args.flag("--cargo-members", self.cargo_members);
+        args.flag("--no-cargo-members", self.no_cargo_members);
+        args.flag("--node-packages", self.node_packages);
+        args.flag("--no-node-packages", self.no_node_packages);
+        args.flag("--submodules", self.submodules);
+        args.flag("--no-submodules", self.no_submodules);
+        args.value(
+            "--uninitialized-submodules",
+            self.uninitialized_submodules
+                .as_ref()
+                .map(value_name)
+                .as_deref(),
+        );
+        args.flag("--recommend-extensions", self.recommend_extensions);
+        args.flag("--no-recommend-extensions", self.no_recommend_extensions);
+        args.flag("--exclude-heavy-dirs", self.exclude_heavy_dirs);
+        args.flag("--no-exclude-heavy-dirs", self.no_exclude_heavy_dirs);
+        args.flag("--record-remotes", self.record_remotes);
+        args.flag("--no-record-remotes", self.no_record_remotes);
+        args.value("--launch-template", self.launch_template.as_deref());
+        args.value("--template", self.template.as_deref());
+        args.value("--copy-settings-from", self.copy_settings_from.as_deref());
+        args.value(
+            "--copy-extensions-from",
+            self.copy_extensions_from.as_deref(),
+        );
+        args.value(
+            "--settings-merge",
+            self.settings_merge.as_ref().map(value_name).as_deref(),
+        );
+        args.value("--container-root", self.container_root.as_deref());
+        args.flag("--dual", self.dual);
+        args.value("--relative-to", self.relative_to.as_deref());
+        args.value("--folders-file", self.folders_file.as_deref());
+        args.flag("--merge-scan", self.merge_scan);
+        args.flag("--no-merge-scan", self.no_merge_scan);
+        args.value(
+            "--missing-folders",
+            self.missing_folders.as_ref().map(value_name).as_deref(),
+        );
+        // Stdin can't be read again; the task is left out then
+        let not_stdin = |file: &&str| *file != "-";
+        args.value("--paths-from", self.paths_from.as_deref().filter(not_stdin));
+        args.value(
+            "--paths-from0",
+            self.paths_from0.as_deref().filter(not_stdin),
+        );
+        args.flag("--respect-remote-authority", self.respect_remote_authority);
+        args.flag(
+            "--no-respect-remote-authority",
+            self.no_respect_remote_authority,
+        );
+        for pattern in &self.exclude {
+            args.value("--exclude", Some(pattern));
+        }
+        for name in &self.exclude_name {
+            args.value("--exclude-name", Some(name));
+        }
+        for regex in &self.exclude_regex {
+            args.value("--exclude-regex", Some(regex));
+        }
+        for regex in &self.include_regex {
+            args.value("--include-regex", Some(regex));
+        }
+        for file in &self.exclude_in {
+            args.value("--exclude-in", Some(file));
+        }
+        args.flag("--exclude-name-ci", self.exclude_name_ci);
+        args.flag("--no-exclude-name-ci", self.no_exclude_name_ci);
+        args.flag("--include-hidden", self.include_hidden);
+        args.flag("--no-include-hidden", self.no_include_hidden);
+        args.value("--folder-prefix", self.folder_prefix.as_deref());
+        let sort = self.sort.filter(|sort| *sort != SortOrder::GitActivity);
+        args.value("--sort", sort.map(SortOrder::as_str));
+        args.value(
+            "--name-style",
+            self.name_style.as_ref().map(value_name).as_deref(),
+        );
+        args.value(
+            "--worktrees",
+            self.worktrees.as_ref().map(value_name).as_deref(),
+        );
+        args.value(
+            "--collapse-nested",
+            self.collapse_nested.as_ref().map(value_name).as_deref(),
+        );
+        args.flag("--canonical-dedupe", self.canonical_dedupe);
+        args.flag("--fix-overlaps", self.fix_overlaps);
+        args.value(
+            "--git-status",
+            self.git_status.as_ref().map(value_name).as_deref(),
+        );
+        for marker in &self.markers {
+            args.value("--marker", Some(marker));
+        }
+        for pattern in &self.contains {
+            args.value("--contains", Some(pattern));
+        }
+        args.value(
+            "--contains-recursive",
+            self.contains_recursive.map(|n| n.to_string()).as_deref(),
+        );
+        args.value(
+            "--newer-than",
+            self.newer_than.map(|spec| spec.to_string()).as_deref(),
+        );
+        args.value(
+            "--older-than",
+            self.older_than.map(|spec| spec.to_string()).as_deref(),
+        );
+        args.value(
+            "--max-depth",
+            self.max_depth.map(|n| n.to_string()).as_deref(),
+        );
+        args.value(
+            "--min-depth",
+            self.min_depth.map(|n| n.to_string()).as_deref(),
+        );
+        args.value(
+            "--min-folders",
+            self.min_folders.map(|n| n.to_string()).as_deref(),
+        );
+        args.flag("--allow-empty", self.allow_empty);
+        args.value(
+            "--warn-folders",
+            self.warn_folders.map(|n| n.to_string()).as_deref(),
+        );
+        args.value(
+            "--max-folders",
+            self.max_folders.map(|n| n.to_string()).as_deref(),
+        );
+        args.flag("--force", self.force);
+        args.value(
+            "--max-entries",
+            self.max_entries.map(|n| n.to_string()).as_deref(),
+        );
+        args.value(
+            "--warn-size",
+            self.warn_size.map(|n| n.to_string()).as_deref(),
+        );
+        for target in &self.emit {
+            args.value("--emit", Some(&value_name(target)));
+        }
+        args.flag("--no-emoji", self.no_emoji);
+        args.flag("--emoji", self.emoji);
+        args.flag("--cache", self.cache);
+        args.flag("--no-cache", self.no_cache);
+        args.flag("--surgical", self.surgical);
+        args.flag("--validate", self.validate);
+        args.value("--indent", self.indent.map(|n| n.to_string()).as_deref());
+        args.flag("--tabs", self.tabs);
+        args.value(
+            "--final-newline",
+            self.final_newline.map(|b| b.to_string()).as_deref(),
+        );
+        args.flag("--append", self.append);
+        args.flag("--prune", self.prune);
+        for setting in &self.set {
+            args.value("--set", Some(setting));
+        }
+        for key in &self.unset {
+            args.value("--unset", Some(key));
+        }
+        args.value("--profile", self.profile.as_deref());
+        args.value("--path", Some(&self.path));
+        args.0
+    }
+}
+
+/// Project files written alongside the workspace with `--emit`.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmitTarget {
+    /// A Sublime Text `.sublime-project` with the same folders
+    Sublime,
+    /// A script opening every folder in Zed
+    Zed,
+}
+
+/// What the display name of a scanned folder is made from, after the prefix.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NameStyle {
+    /// The directory name, e.g. `api`
+    #[default]
+    Basename,
+    /// The path relative to the scan root with `/` separators, e.g. `clientA/api`
+    Relpath,
+}
+
+/// How scanned directories that are git worktrees are named and filtered.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorktreeMode {
+    /// Name each worktree after its main worktree plus the branch, e.g. `myrepo [hotfix]`
+    Group,
+    /// Append the branch to the display name, e.g. `myrepo-hotfix [hotfix]`
+    Annotate,
+    /// Leave out linked worktrees, keeping only the main one
+    SkipLinked,
+}
+
+/// Which side `--collapse-nested` keeps of two folders where one is inside the other.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CollapseNested {
+    /// Keep the outer folder, dropping those inside it
+    KeepOuter,
+    /// Keep the inner folders, dropping those containing them
+    KeepInner,
+}
+
+/// How regeneration reconciles the folders of an existing workspace file with the
+/// folders generated.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconcileMode {
+    /// Use the generated folders, keeping the existing ones added with `add` that
+    /// generation doesn't have
+    #[default]
+    Prune,
+    /// Keep the existing folders as they are, adding the generated ones they don't
+    /// have at the end, sorted by path among themselves
+    Append,
+    /// Use exactly the generated folders
+    Replace,
+}
+
+/// What `--folders-file` does when the manifest lists paths that aren't directories.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingFolders {
+    /// Leave them out with a warning naming them
+    #[default]
+    Warn,
+    /// Fail without writing anything
+    Error,
+}
+
+/// How the `settings` of two workspace files are combined by `merge`,
+/// `--reapply-template`, `--copy-settings-from` and `--copy-extensions-from`.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SettingsMerge {
+    /// Keep the existing value of a key both sides set
+    #[default]
+    Ours,
+    /// Take the incoming value of a key both sides set
+    Theirs,
+    /// Merge nested objects and combine arrays without duplicates, keeping the
+    /// existing value of other keys both sides set
+    UnionDeep,
+    /// Fail, listing every key both sides set differently
+    Error,
+}
+
+/// What `--git-status` does with git folders that have uncommitted changes.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GitStatusMode {
+    /// Append " ●" to their display names
+    Annotate,
+    /// Include only scanned directories with uncommitted changes
+    OnlyDirty,
+}
+
+/// Number of scanned folders above which generation warns, unless configured.
+pub const DEFAULT_WARN_FOLDERS: usize = 100;
+
+/// What `--submodules` does with a submodule whose directory is still empty.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UninitializedSubmodules {
+    /// Leave it out
+    #[default]
+    Skip,
+    /// Leave it out with a warning
+    Warn,
+    /// Add the empty folder anyway
+    Include,
+}
+
+impl NameStyle {
+    /// The name of the scanned directory `dir` found under `scan_path`.
+    pub fn folder_name(self, dir: &Path, scan_path: &Path) -> String {
+        let basename = || {
+            dir.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned()
+        };
+        match self {
+            NameStyle::Basename => basename(),
+            NameStyle::Relpath => match dir.strip_prefix(scan_path) {
+                Ok(relative) if !relative.as_os_str().is_empty() => relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                _ => basename(),
+            },
+        }
+    }
+}
+
+/// Order of the folders found by a scan.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// The order the directories are read from disk
+    #[default]
+    None,
+    /// Alphabetically by directory name
+    Name,
+    /// Git checkouts first, the most recently committed to first, then the other
+    /// directories by name
+    #[serde(rename = "git-activity")]
+    GitActivity,
+}
+
+impl SortOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::None => "none",
+            SortOrder::Name => "name",
+            SortOrder::GitActivity => "git-activity",
+        }
+    }
+}
+
+/// When VS Code brings the terminal of the managed task to the front.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskReveal {
+    Always,
+    /// Only when the task fails
+    #[default]
+    Silent,
+    Never,
+}
+
+/// Which terminal panel the managed task runs in.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskPanel {
+    /// Shared with other tasks
+    Shared,
+    /// A panel of its own, reused on every run
+    #[default]
+    Dedicated,
+    /// A new panel on every run
+    New,
+}
+
+/// How the managed task refers to this tool.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskCommand {
+    /// The absolute path of the running binary
+    #[default]
+    Absolute,
+    /// Just `workspace-manager`, looked up on PATH, so the file can be shared
+    Portable,
+}
+
+/// How VS Code runs the managed task.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskType {
+    /// Run this tool directly, without a shell
+    #[default]
+    Process,
+    /// Run a command line in the default shell, e.g. to chain further commands
+    Shell,
+}
+
+/// Task group the managed task is listed in.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskGroupKind {
+    Build,
+    Test,
+}
+
+/// The command-line spelling of a value enum, e.g. `dedicated`.
+pub fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// How `export` separates its values.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// A JSON array of strings
+    Json,
+    /// One value per line
+    #[default]
+    Lines,
+    /// NUL-terminated values, for `xargs -0`
+    Nul,
+}
+
+/// What `export` prints for each folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportField {
+    /// Absolute path, resolved against the workspace file's directory
+    #[default]
+    AbsolutePath,
+    /// Path exactly as written in the workspace file
+    RelativePath,
+    /// Display name
+    Name,
+}
+
+/// Options for generating a workspace, independent of how they were parsed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenerateOptions {
+    /// Directory whose subdirectories become workspace folders
+    pub scan_path: PathBuf,
+    /// Workspace name, used for the file name and the root folder entry
+    pub name: String,
+    /// Leave the current directory out of the workspace
+    pub exclude_current: bool,
+    /// Refresh the Update Workspace task in an existing file
+    pub update_task: bool,
+    /// Leave the Update Workspace task out, removing an existing one
+    pub no_task: bool,
+    /// Label of the managed task, [`UPDATE_TASK_LABEL`] when not set
+    pub task_label: Option<String>,
+    /// When VS Code reveals the terminal of the managed task
+    pub task_reveal: TaskReveal,
+    /// Which terminal panel the managed task runs in
+    pub task_panel: TaskPanel,
+    /// Task group the managed task is listed in
+    pub task_group: Option<TaskGroupKind>,
+    /// How the managed task invokes this tool
+    pub task_command: TaskCommand,
+    /// Whether the managed task runs directly or through the shell
+    pub task_type: TaskType,
+    /// Command a shell task runs after regenerating
+    pub task_shell_command: Option<String>,
+    /// Whether VS Code runs the managed task when the workspace is opened
+    pub task_run_on_open: bool,
+    /// Whether to generate build and test tasks for each folder
+    pub folder_tasks: bool,
+    /// Whether to hide scanned folders nested in the current-directory folder
+    pub hide_nested: bool,
+    /// Whether to append the checked-out branch to the names of git folders
+    pub show_branch: bool,
+    /// Whether to list Rust folders in rust-analyzer.linkedProjects
+    pub rust_analyzer_links: bool,
+    /// Whether to add the folders of workspace files inside scanned directories
+    pub absorb_workspaces: bool,
+    /// Whether to add the members of Cargo workspaces
+    pub cargo_members: bool,
+    /// Whether to add the packages of node workspaces
+    pub node_packages: bool,
+    /// Whether to add git submodules
+    pub submodules: bool,
+    /// What to do with submodules that aren't checked out
+    pub uninitialized_submodules: UninitializedSubmodules,
+    /// Whether to recommend extensions for the kinds of project found
+    pub recommend_extensions: bool,
+    /// Whether to exclude the build directories of the kinds of project found from
+    /// search and file watching
+    pub exclude_heavy_dirs: bool,
+    /// Whether to record the origin URL of each git folder in the metadata section
+    pub record_remotes: bool,
+    /// Configured extensions per project type (`cargo`, `npm`, `go`, `python`),
+    /// replacing the built-in lists of the types it names
+    pub extension_map: BTreeMap<String, Vec<String>>,
+    /// File whose debug configurations are merged into the launch section
+    pub launch_template: Option<PathBuf>,
+    /// Workspace file a new workspace starts from, leaving out its folders
+    pub template: Option<PathBuf>,
+    /// Merge `template` into an existing workspace too, its values winning conflicts
+    pub reapply_template: bool,
+    /// How `reapply_template`, `copy_settings_from` and `copy_extensions_from` combine
+    /// the incoming settings with the existing ones
+    pub settings_merge: SettingsMerge,
+    /// Workspace file whose settings section is merged into the workspace
+    pub copy_settings_from: Option<PathBuf>,
+    /// Workspace file whose extensions section is merged into the workspace
+    pub copy_extensions_from: Option<PathBuf>,
+    /// Whether to write folders as URIs on the workspace's remoteAuthority
+    pub respect_remote_authority: bool,
+    /// Directory of the dev container that folders are written under
+    pub container_root: Option<String>,
+    /// Directories to make folders of instead of scanning, as read by
+    /// [`read_candidates`](crate::scan::read_candidates) for `--paths-from`. The
+    /// scan filters don't apply to them
+    pub candidates: Option<Vec<PathBuf>>,
+    /// Manifest listing the directories to make folders of instead of scanning, read
+    /// by [`read_manifest_with`](crate::manifest::read_manifest_with)
+    pub folders_file: Option<PathBuf>,
+    /// Also scan with a `folders_file`, adding the directories found after those
+    /// listed
+    pub merge_scan: bool,
+    /// What to do when `folders_file` lists paths that aren't directories
+    pub missing_folders: MissingFolders,
+    /// Canonical directory folder paths are written relative to, or the directory
+    /// of the workspace file when not set
+    pub relative_to: Option<PathBuf>,
+    /// Whether to write a host and a container file from one scan
+    pub dual: bool,
+    /// Workspace settings to set, in order
+    pub settings: Vec<(String, serde_json::Value)>,
+    /// Workspace settings to remove
+    pub unset: Vec<String>,
+    /// Arguments written into the Update Workspace task
+    pub task_args: Vec<String>,
+    /// Glob patterns of directory names to leave out
+    pub exclude: Vec<String>,
+    /// Exact directory names to leave out
+    pub exclude_name: Vec<String>,
+    /// Compare `exclude_name` ignoring case
+    pub exclude_name_ci: bool,
+    /// Regexes of relative paths to leave out
+    pub exclude_regex: Vec<String>,
+    /// Regexes of relative paths to keep; all are kept when empty
+    pub include_regex: Vec<String>,
+    /// Workspace files whose folders are left out
+    pub exclude_in: Vec<PathBuf>,
+    /// Include directories whose name starts with a dot
+    pub include_hidden: bool,
+    /// Prefix for folder display names, `📦 ` when not set
+    pub folder_prefix: Option<String>,
+    /// Order of the scanned folders
+    pub sort: SortOrder,
+    /// Only include directories containing one of these files
+    pub markers: Vec<String>,
+    /// Only include directories containing a file matching one of these globs
+    pub contains: Vec<String>,
+    /// How many levels `contains` searches, with `--contains-recursive`; only the
+    /// files directly inside each directory when not set
+    pub contains_depth: Option<usize>,
+    /// Only include directories modified since this bound
+    pub newer_than: Option<TimeSpec>,
+    /// Only include directories not modified since this bound
+    pub older_than: Option<TimeSpec>,
+    /// How many levels below the scan path to look, or only the directories
+    /// directly inside it when not set
+    pub max_depth: Option<usize>,
+    /// How many levels below the scan path directories start to be included, or
+    /// from the directories directly inside it when not set
+    pub min_depth: Option<usize>,
+    /// Fewest folders the scan must find, or 0 to accept an empty scan
+    pub min_folders: usize,
+    /// Number of folders above which the scan prints a warning, or 0 to never warn
+    pub warn_folders: usize,
+    /// Most folders the scan may find, or no limit when not set
+    pub max_folders: Option<usize>,
+    /// Most entries a directory may have directly inside it to be scanned, or no
+    /// limit when not set
+    pub max_entries: Option<usize>,
+    /// Megabytes of files above which a folder gets a warning, or no warning when not
+    /// set
+    pub warn_size: Option<usize>,
+    /// Most folders to add that the existing workspace file doesn't have, leaving out
+    /// the rest, or all of them when not set
+    pub limit: Option<usize>,
+    /// Other project files to write with the same folders
+    pub emit: Vec<EmitTarget>,
+    /// What folder display names are made from
+    pub name_style: NameStyle,
+    /// How to treat git worktrees, or like any other directory when not set
+    pub worktrees: Option<WorktreeMode>,
+    /// Which of two nested folders to keep, or only warn about them when not set
+    pub collapse_nested: Option<CollapseNested>,
+    /// Whether to keep one folder of those that are the same directory through
+    /// symlinks
+    pub canonical_dedupe: bool,
+    /// Whether to merge the folders listed several times with differently written
+    /// paths, and apply `collapse_nested` to the folders kept from the existing file
+    pub fix_overlaps: bool,
+    /// Whether to mark or filter git folders by uncommitted changes
+    pub git_status: Option<GitStatusMode>,
+    /// Report repositories whose status couldn't be checked
+    pub verbose: bool,
+    /// Leave the emoji out of generated display names
+    pub no_emoji: bool,
+    /// File the scan is cached in, to be reused while the scanned directories don't
+    /// change, or no cache when not set
+    pub cache: Option<PathBuf>,
+    /// Rewrite only the folders array of an existing workspace file
+    pub surgical: bool,
+    /// Check the written content against the workspace file schema
+    pub validate: bool,
+    /// Indentation of the written JSON, or that of the existing file when not set
+    pub indent: Option<Indent>,
+    /// End the written file with a newline
+    pub final_newline: bool,
+    /// How the folders of an existing workspace file are reconciled with those
+    /// generated
+    pub reconcile: ReconcileMode,
+    /// Whether the command line, given no name, updates the workspace file of a
+    /// parent directory rather than writing a new one; see
+    /// [`discover_workspace_file`](crate::io::discover_workspace_file)
+    pub discover: bool,
+    /// Workspace file to load and update instead of `<name>.code-workspace`, which
+    /// then only names the root folder entry. The file is written into the base
+    /// directory under its file name, so callers pass its directory as the base
+    pub workspace_file: Option<PathBuf>,
+}
+
+impl GenerateOptions {
+    /// File name of the workspace, e.g. `name.code-workspace`, or that of
+    /// [`workspace_file`](Self::workspace_file) when given.
+    pub fn workspace_filename(&self) -> String {
+        if let Some(file) = &self.workspace_file {
+            return file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+        }
+        match (&self.container_root, self.dual) {
+            (Some(_), true) => format!("{}.container.code-workspace", self.name),
+            _ => format!("{}.code-workspace", self.name),
+        }
+    }
+
+    /// With `dual`, the options for the file with host paths and for the one with
+    /// container paths; otherwise these options alone.
+    pub fn variants(&self) -> (GenerateOptions, Option<GenerateOptions>) {
+        if !self.dual || self.container_root.is_none() {
+            return (self.clone(), None);
+        }
+        let host = GenerateOptions {
+            container_root: None,
+            dual: false,
+            ..self.clone()
+        };
+        (host, Some(self.clone()))
+    }
+
+    /// Label of the managed task.
+    pub fn task_label(&self) -> &str {
+        self.task_label.as_deref().unwrap_or(UPDATE_TASK_LABEL)
+    }
+}
+
+/// Reads a configuration file, warning about keys that are not recognized.
+fn load_config(path: &Path) -> anyhow::Result<Option<config::Config>> {
+    let config = config::Config::load(path)?;
+    for key in config.iter().flat_map(config::Config::unknown_keys) {
+        console::warning(format_args!(
+            "ignoring unknown key '{}' in {}",
+            key,
+            path.display()
+        ));
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_project_config_provides_defaults() -> Result<()> {
+        let temp = TempDir::new()?;
+        let config = config::Config::parse(
+            r#"
+            name = "from-config"
+            exclude = ["build"]
+            include_hidden = true
+            folder_prefix = "🦀 "
+            sort = "name"
+            "#,
+        )?;
+
+        let layers = config::ConfigLayers::build(None, Some(config), None, Default::default())?;
+
+        let cli = Cli::try_parse_from(["workspace-manager"])?;
+        let ResolvedOptions {
+            options: opts,
+            provenance,
+            ..
+        } = cli
+            .generate
+            .resolve_with(temp.path().to_path_buf(), temp.path(), &layers)?;
+        assert_eq!(opts.name, "from-config");
+        assert_eq!(opts.exclude, vec!["build"]);
+        assert!(opts.include_hidden);
+        assert_eq!(opts.folder_prefix.as_deref(), Some("🦀 "));
+        assert_eq!(opts.sort, SortOrder::Name);
+        assert_eq!(provenance.get("name"), Some(config::Source::ProjectConfig));
+        assert_eq!(provenance.get("markers"), Some(config::Source::Default));
+        // Values from the config stay out of the task, which finds the config again
+        assert_eq!(opts.task_args, vec!["--path", "."]);
+
+        let cli = Cli::try_parse_from([
+            "workspace-manager",
+            "-n",
+            "cli",
+            "--exclude",
+            "dist",
+            "--sort",
+            "none",
+            "--folder-prefix=",
+        ])?;
+        let ResolvedOptions {
+            options: opts,
+            provenance: provenance_cli,
+            ..
+        } = cli
+            .generate
+            .resolve_with(temp.path().to_path_buf(), temp.path(), &layers)?;
+        assert_eq!(opts.name, "cli");
+        assert_eq!(opts.exclude, vec!["dist"]);
+        assert!(opts.include_hidden);
+        assert_eq!(opts.folder_prefix.as_deref(), Some(""));
+        assert_eq!(opts.sort, SortOrder::None);
+        assert_eq!(
+            provenance_cli.get("name"),
+            Some(config::Source::CommandLine)
+        );
+        assert_eq!(
+            provenance_cli.get("include_hidden"),
+            Some(config::Source::ProjectConfig)
+        );
+        assert_eq!(
+            opts.task_args,
+            vec![
+                "--name",
+                "cli",
+                "--exclude",
+                "dist",
+                "--folder-prefix",
+                "",
+                "--sort",
+                "none",
+                "--path",
+                "."
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_emoji_setting() -> Result<()> {
+        let temp = TempDir::new()?;
+        let no_emoji = |global: &str, project: &str, args: &[&str]| -> Result<bool> {
+            let layers = config::ConfigLayers::build(
+                Some(config::Config::parse(global)?),
+                Some(config::Config::parse(project)?),
+                None,
+                Default::default(),
+            )?;
+            let cli = Cli::try_parse_from([&["workspace-manager"], args].concat())?;
+            let dir = temp.path();
+            let resolved = cli.generate.resolve_with(dir.to_path_buf(), dir, &layers)?;
+            Ok(resolved.options.no_emoji)
+        };
+        assert!(!no_emoji("", "", &[])?);
+        assert!(no_emoji("", "emoji = \"never\"", &[])?);
+        assert!(!no_emoji("", "emoji = \"never\"", &["--emoji"])?);
+        // The project's choice wins over the user's, whichever key either uses
+        assert!(!no_emoji("no_emoji = true", "emoji = \"always\"", &[])?);
+        assert!(no_emoji("emoji = \"always\"", "no_emoji = true", &[])?);
+        // Outside Windows there is no legacy code page to avoid
+        #[cfg(not(windows))]
+        assert!(!no_emoji("emoji = \"auto\"", "", &[])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_activity_sort() -> Result<()> {
+        let temp = TempDir::new()?;
+        let resolve = |project: &str, args: &[&str]| -> Result<(SortOrder, Vec<String>)> {
+            let layers = config::ConfigLayers::build(
+                None,
+                Some(config::Config::parse(project)?),
+                None,
+                Default::default(),
+            )?;
+            let cli = Cli::try_parse_from([&["workspace-manager"], args].concat())?;
+            let dir = temp.path();
+            let resolved = cli.generate.resolve_with(dir.to_path_buf(), dir, &layers)?;
+            Ok((resolved.options.sort, cli.generate.to_task_args()))
+        };
+        // Only on the run that asks for it, never on refreshes
+        let (sort, task_args) = resolve("", &["--sort", "git-activity"])?;
+        assert_eq!(sort, SortOrder::GitActivity);
+        assert!(!task_args.contains(&"--sort".to_string()));
+        let (sort, _) = resolve("sort = \"git-activity\"", &[])?;
+        assert_eq!(sort, SortOrder::None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_depth_setting() -> Result<()> {
+        let temp = TempDir::new()?;
+        let depths = |project: &str, args: &[&str]| -> Result<(usize, usize)> {
+            let layers = config::ConfigLayers::build(
+                None,
+                Some(config::Config::parse(project)?),
+                None,
+                Default::default(),
+            )?;
+            let cli = Cli::try_parse_from([&["workspace-manager"], args].concat())?;
+            let dir = temp.path();
+            let resolved = cli.generate.resolve_with(dir.to_path_buf(), dir, &layers)?;
+            let scan = crate::scan::ScanOptions::from(&resolved.options);
+            Ok((scan.min_depth, scan.max_depth))
+        };
+        assert_eq!(depths("", &[])?, (1, 1));
+        assert_eq!(depths("max_depth = 3\nmin_depth = 2", &[])?, (2, 3));
+        assert_eq!(depths("max_depth = 3", &["--min-depth", "3"])?, (3, 3));
+        // Checked once all the layers are stacked, whichever set each bound
+        let err = depths("min_depth = 2", &[]).unwrap_err();
+        let err = err.downcast_ref::<WorkspaceError>();
+        assert!(matches!(
+            err,
+            Some(WorkspaceError::InvalidDepth { min: 2, max: 1 })
+        ));
+        assert!(depths("min_depth = 2", &["--max-depth", "2"]).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_defaults_to_base_dir() -> Result<()> {
+        let temp = TempDir::new()?;
+        let base_dir = temp.path().join("team-workspace");
+        let cli = Cli::try_parse_from(["workspace-manager"])?;
+        let opts = cli.generate.resolve_with(
+            temp.path().to_path_buf(),
+            &base_dir,
+            &config::ConfigLayers::default(),
+        )?;
+        assert_eq!(opts.options.name, "team-workspace");
+
+        // An explicit workspace file keeps its name, whatever --name labels
+        let resolve = |args: &[&str]| -> Result<GenerateOptions> {
+            let cli = Cli::try_parse_from([&["workspace-manager"], args].concat())?;
+            let layers = config::ConfigLayers::default();
+            Ok(cli
+                .generate
+                .resolve_with(temp.path().to_path_buf(), &base_dir, &layers)?
+                .options)
+        };
+        let opts = resolve(&["--workspace-file", "../ws/team-alpha.code-workspace"])?;
+        assert_eq!(opts.name, "team-alpha");
+        assert_eq!(opts.workspace_filename(), "team-alpha.code-workspace");
+        assert_eq!(
+            opts.workspace_file,
+            Some(base_dir.join("../ws/team-alpha.code-workspace"))
+        );
+        let opts = resolve(&["--workspace-file", "alpha.json", "-n", "Alpha"])?;
+        assert_eq!(opts.name, "Alpha");
+        assert_eq!(opts.workspace_filename(), "alpha.json");
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_name_dir() {
+        let repos = [
+            Path::new("/src/monorepo"),
+            Path::new("/src/monorepo/vendor/lib"),
+        ];
+        let is_repo_root = |dir: &Path| repos.contains(&dir);
+        let name_dir = |scan_path: &'static str| {
+            default_name_dir(Path::new(scan_path), Path::new("/out"), is_repo_root)
+        };
+        assert_eq!(
+            name_dir("/src/monorepo/tools/scripts"),
+            Path::new("/src/monorepo")
+        );
+        assert_eq!(name_dir("/src/monorepo"), Path::new("/src/monorepo"));
+        // The closest repository wins, as for a submodule
+        assert_eq!(name_dir("/src/monorepo/vendor/lib/src"), repos[1]);
+        assert_eq!(name_dir("/src/other"), Path::new("/out"));
+    }
+
+    #[test]
+    fn test_profile_is_recorded_in_task() -> Result<()> {
+        let temp = TempDir::new()?;
+        let project = config::Config::parse("[profile.work]\nname = \"work\"")?;
+        let layers = config::ConfigLayers::build(
+            None,
+            Some(project.clone()),
+            Some("work"),
+            Default::default(),
+        )?;
+
+        let cli = Cli::try_parse_from(["workspace-manager", "--profile", "work"])?;
+        let opts = cli
+            .generate
+            .resolve_with(temp.path().to_path_buf(), temp.path(), &layers)?
+            .options;
+        assert_eq!(opts.name, "work");
+        assert_eq!(opts.task_args, vec!["--profile", "work", "--path", "."]);
+
+        let unknown =
+            config::ConfigLayers::build(None, Some(project), Some("home"), Default::default());
+        assert!(unknown.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_option_precedence() -> Result<()> {
+        use config::Source::*;
+
+        struct Case {
+            global: &'static str,
+            project: &'static str,
+            profile: Option<&'static str>,
+            env: &'static [(&'static str, &'static str)],
+            args: &'static [&'static str],
+            field: &'static str,
+            value: &'static str,
+            source: config::Source,
+        }
+        let cases = [
+            Case {
+                global: "",
+                project: "",
+                profile: None,
+                env: &[],
+                args: &[],
+                field: "exclude_current",
+                value: "false",
+                source: Default,
+            },
+            Case {
+                global: "exclude_current = true",
+                project: "",
+                profile: None,
+                env: &[],
+                args: &[],
+                field: "exclude_current",
+                value: "true",
+                source: GlobalConfig,
+            },
+            Case {
+                global: "exclude_current = true",
+                project: "exclude_current = false",
+                profile: None,
+                env: &[],
+                args: &[],
+                field: "exclude_current",
+                value: "false",
+                source: ProjectConfig,
+            },
+            Case {
+                global: "",
+                project: "exclude_current = true",
+                profile: None,
+                env: &[],
+                args: &["--no-exclude-current"],
+                field: "exclude_current",
+                value: "false",
+                source: CommandLine,
+            },
+            Case {
+                global: "",
+                project: "",
+                profile: None,
+                env: &[],
+                args: &["--no-exclude-current", "-e"],
+                field: "exclude_current",
+                value: "true",
+                source: CommandLine,
+            },
+            Case {
+                global: "",
+                project: "exclude_current = true",
+                profile: None,
+                env: &[("WORKSPACE_MANAGER_EXCLUDE_CURRENT", "no")],
+                args: &[],
+                field: "exclude_current",
+                value: "false",
+                source: Environment,
+            },
+            Case {
+                global: "include_hidden = true",
+                project: "",
+                profile: None,
+                env: &[],
+                args: &["--no-include-hidden"],
+                field: "include_hidden",
+                value: "false",
+                source: CommandLine,
+            },
+            Case {
+                global: "no_emoji = true",
+                project: "",
+                profile: None,
+                env: &[],
+                args: &["--emoji"],
+                field: "no_emoji",
+                value: "false",
+                source: CommandLine,
+            },
+            Case {
+                global: "sort = \"name\"",
+                project: "[profile.p]\nsort = \"none\"",
+                profile: Some("p"),
+                env: &[],
+                args: &[],
+                field: "sort",
+                value: "None",
+                source: ProjectProfile,
+            },
+            Case {
+                global: "[profile.p]\nsort = \"name\"",
+                project: "sort = \"none\"",
+                profile: Some("p"),
+                env: &[],
+                args: &[],
+                field: "sort",
+                value: "None",
+                source: ProjectConfig,
+            },
+            Case {
+                global: "",
+                project: "sort = \"name\"",
+                profile: None,
+                env: &[("WORKSPACE_MANAGER_SORT", "none")],
+                args: &[],
+                field: "sort",
+                value: "None",
+                source: Environment,
+            },
+            Case {
+                global: "",
+                project: "exclude = [\"a\"]",
+                profile: None,
+                env: &[("WORKSPACE_MANAGER_EXCLUDE", "b:c")],
+                args: &[],
+                field: "exclude",
+                value: "[\"b\", \"c\"]",
+                source: Environment,
+            },
+            Case {
+                global: "",
+                project: "",
+                profile: None,
+                env: &[("WORKSPACE_MANAGER_EXCLUDE", "b")],
+                args: &["--exclude", "d"],
+                field: "exclude",
+                value: "[\"d\"]",
+                source: CommandLine,
+            },
+            Case {
+                global: "name = \"g\"",
+                project: "",
+                profile: None,
+                env: &[],
+                args: &[],
+                field: "name",
+                value: "\"g\"",
+                source: GlobalConfig,
+            },
+            Case {
+                global: "",
+                project: "task_command = \"portable\"",
+                profile: None,
+                env: &[],
+                args: &[],
+                field: "task_command",
+                value: "Portable",
+                source: ProjectConfig,
+            },
+            Case {
+                global: "",
+                project: "task_command = \"portable\"",
+                profile: None,
+                env: &[],
+                args: &["--task-command", "absolute"],
+                field: "task_command",
+                value: "Absolute",
+                source: CommandLine,
+            },
+        ];
+
+        let temp = TempDir::new()?;
+        for (index, case) in cases.iter().enumerate() {
+            let env = config::Config::from_env(|key| {
+                case.env
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            })?;
+            let layers = config::ConfigLayers::build(
+                Some(config::Config::parse(case.global)?),
+                Some(config::Config::parse(case.project)?),
+                case.profile,
+                env,
+            )?;
+            let cli = Cli::try_parse_from(
+                std::iter::once("workspace-manager").chain(case.args.iter().copied()),
+            )?;
+            let resolved =
+                cli.generate
+                    .resolve_with(temp.path().to_path_buf(), temp.path(), &layers)?;
+            let opts = &resolved.options;
+            let value = match case.field {
+                "exclude_current" => opts.exclude_current.to_string(),
+                "include_hidden" => opts.include_hidden.to_string(),
+                "no_emoji" => opts.no_emoji.to_string(),
+                "sort" => format!("{:?}", opts.sort),
+                "task_command" => format!("{:?}", opts.task_command),
+                "exclude" => format!("{:?}", opts.exclude),
+                "name" => format!("{:?}", opts.name),
+                other => panic!("no accessor for {}", other),
+            };
+            assert_eq!(value, case.value, "case {}", index);
+            assert_eq!(
+                resolved.provenance.get(case.field),
+                Some(case.source),
+                "case {}",
+                index
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_setting() -> Result<()> {
+        let parsed = |setting: &str| parse_setting(setting).map(|(_, value)| value);
+        assert_eq!(
+            parse_setting("files.trimTrailingWhitespace=true")?,
+            (
+                "files.trimTrailingWhitespace".to_string(),
+                serde_json::json!(true)
+            )
+        );
+        assert_eq!(
+            parsed("editor.rulers=[80, 100]")?,
+            serde_json::json!([80, 100])
+        );
+        assert_eq!(
+            parsed("editor.fontFamily=Fira Code")?,
+            serde_json::json!("Fira Code")
+        );
+        assert_eq!(parsed("a.b=x=y")?, serde_json::json!("x=y"));
+        assert_eq!(parsed("a.b=")?, serde_json::json!(""));
+        assert_eq!(parsed(r#"a.b="42""#)?, serde_json::json!("42"));
+        assert!(parse_setting("no-equals").is_err());
+        assert!(parse_setting("=value").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_args_from_task_args() -> Result<()> {
+        let to_strings = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        // Written by the first versions, before --name and subcommands existed
+        let args = GenerateArgs::from_task_args(&to_strings(&["--path", "/src/projects"]))?;
+        assert_eq!(args.path, "/src/projects");
+        assert_eq!(args.name, None);
+        assert!(!args.exclude_current);
+
+        let old = to_strings(&["--name", "ws", "--exclude-current", "--path", "."]);
+        let args = GenerateArgs::from_task_args(&old)?;
+        assert_eq!(args.name.as_deref(), Some("ws"));
+        assert!(args.exclude_current);
+        assert_eq!(args.to_task_args(), old);
+
+        let args = GenerateArgs::from_task_args(&to_strings(&["generate", "-p", "x"]))?;
+        assert_eq!(args.path, "x");
+        assert_eq!(args.debounce, 1.5);
+
+        assert!(GenerateArgs::from_task_args(&to_strings(&["--bogus"])).is_err());
+        assert!(GenerateArgs::from_task_args(&to_strings(&["list"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_task_args_round_trip() -> Result<()> {
+        let to_strings = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let invocation = to_strings(&[
+            "--name",
+            "my ws",
+            "--workspace-file",
+            "my ws.code-workspace",
+            "--no-exclude-current",
+            "-u",
+            "--task",
+            "--task-label",
+            "Refresh Folders",
+            "--task-reveal",
+            "never",
+            "--task-panel",
+            "new",
+            "--task-group",
+            "build",
+            "--task-command",
+            "portable",
+            "--task-run-on-open",
+            "--exclude",
+            "tmp *",
+            "--exclude",
+            "build",
+            "--exclude-regex",
+            r"-\d{4}-\d{2}-\d{2}$",
+            "--include-regex",
+            "^(api|web)/[^/]+$",
+            "--include-hidden",
+            "--folder-prefix",
+            "- ",
+            "--sort",
+            "name",
+            "--marker",
+            "Cargo.toml",
+            "--contains",
+            "*.tf",
+            "--contains-recursive",
+            "--newer-than",
+            "30d",
+            "--older-than",
+            "2024-01-01",
+            "--max-depth",
+            "3",
+            "--min-depth",
+            "2",
+            "--paths-from",
+            "dirs.txt",
+            "--copy-settings-from",
+            "../team.code-workspace",
+            "--settings-merge",
+            "theirs",
+            "--template",
+            "base.code-workspace",
+            "--launch-template",
+            ".vscode/launch.json",
+            "--reapply-template",
+            "--limit",
+            "5",
+            "--replace",
+            "--emoji",
+            "--indent",
+            "4",
+            "--final-newline",
+            "true",
+            "--profile",
+            "work",
+            "--path",
+            "/src/my projects",
+            "--open",
+            "--editor-cmd",
+            "codium",
+            "--verbose",
+            "--print-path",
+        ]);
+        let args = GenerateArgs::from_task_args(&invocation)?;
+        let task_args = args.to_task_args();
+        assert!(task_args.contains(&"tmp *".to_string()));
+        assert_eq!(args.contains_recursive, Some(3));
+        assert_eq!((args.max_depth, args.min_depth), (Some(3), Some(2)));
+        // Regexes are argv entries, kept as they are, even when starting with a dash
+        assert!(task_args.contains(&r"-\d{4}-\d{2}-\d{2}$".to_string()));
+        assert!(task_args.contains(&"--update-task".to_string()));
+        assert!(!task_args.contains(&"--open".to_string()));
+        assert!(task_args.contains(&"--template".to_string()));
+        assert!(task_args.contains(&"--launch-template".to_string()));
+        assert!(!task_args.contains(&"--reapply-template".to_string()));
+        assert!(!task_args.contains(&"--limit".to_string()));
+        assert!(!task_args.contains(&"--replace".to_string()));
+
+        // Everything but the single-run options survives the trip through the task
+        let reparsed = GenerateArgs::from_task_args(&task_args)?;
+        let expected = GenerateArgs {
+            open: false,
+            editor_cmd: None,
+            verbose: false,
+            print_path: false,
+            reapply_template: false,
+            limit: None,
+            replace: false,
+            ..args
+        };
+        assert_eq!(reparsed, expected);
+        assert_eq!(reparsed.to_task_args(), task_args);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_toml_round_trip() -> Result<()> {
+        let temp = TempDir::new()?;
+        let dir = temp.path();
+        let resolve = |args: &[&str], layers: &config::ConfigLayers| -> Result<ResolvedOptions> {
+            let cli = Cli::try_parse_from([&["workspace-manager"], args].concat())?;
+            Ok(cli.generate.resolve_with(dir.to_path_buf(), dir, layers)?)
+        };
+        let invocations: [&[&str]; 3] = [
+            &["--exclude", "build", "--marker", "Cargo.toml", "--no-emoji"],
+            &[
+                "-n",
+                "team",
+                "--exclude-current",
+                "--exclude-name",
+                "target",
+                "--exclude-regex",
+                "^archive/",
+                "--folder-prefix=",
+                "--sort",
+                "name",
+                "--name-style",
+                "relpath",
+                "--worktrees",
+                "skip-linked",
+                "--contains",
+                "*.tf",
+                "--contains-recursive",
+                "--newer-than",
+                "30d",
+                "--max-depth",
+                "3",
+                "--min-depth",
+                "2",
+                "--allow-empty",
+                "--max-folders",
+                "50",
+                "--max-entries",
+                "100",
+                "--task-label",
+                "Refresh",
+                "--task-reveal",
+                "never",
+                "--task-group",
+                "test",
+                "--task-command",
+                "portable",
+                "--task-type",
+                "shell",
+                "--task-shell-command",
+                "git add -A",
+                "--folder-tasks",
+                "--emit",
+                "zed",
+                "--append",
+                "--tabs",
+                "--final-newline",
+                "true",
+                "--emoji",
+            ],
+            &[
+                "--no-task",
+                "--indent",
+                "4",
+                "--no-discover",
+                "--submodules",
+                "--copy-extensions-from",
+                "team.code-workspace",
+                "--settings-merge",
+                "union-deep",
+            ],
+        ];
+        for args in invocations {
+            let flags = resolve(args, &config::ConfigLayers::default())?;
+            let text = flags.to_config_toml();
+            let project = config::Config::parse(&text)?;
+            let layers =
+                config::ConfigLayers::build(None, Some(project), None, Default::default())?;
+            let from_config = resolve(&[], &layers)?;
+            let expected = GenerateOptions {
+                task_args: from_config.options.task_args.clone(),
+                ..flags.options
+            };
+            assert_eq!(from_config.options, expected, "{}", text);
+        }
+
+        // Only what was given is set; the rest stays commented out
+        let text = resolve(invocations[0], &config::ConfigLayers::default())?.to_config_toml();
+        let set: Vec<&str> = text
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        assert_eq!(
+            set,
+            [
+                "exclude = [\"build\"]",
+                "markers = [\"Cargo.toml\"]",
+                "no_emoji = true"
+            ]
+        );
+        Ok(())
+    }
+}
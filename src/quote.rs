@@ -0,0 +1,127 @@
+//! Quoting of command-line arguments for the shell that runs a `"type": "shell"`
+//! task: a POSIX shell everywhere but Windows, where VS Code defaults to PowerShell.
+
+/// Quoting rules of a shell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// sh, bash, zsh and friends
+    Posix,
+    /// Windows PowerShell and PowerShell 7
+    PowerShell,
+}
+
+impl Dialect {
+    /// The dialect of the shell VS Code uses by default on this platform.
+    pub fn native() -> Dialect {
+        if cfg!(windows) {
+            Dialect::PowerShell
+        } else {
+            Dialect::Posix
+        }
+    }
+
+    /// Quotes `arg` so the shell passes it through as a single, unchanged argument.
+    /// Arguments made only of characters without special meaning are left as they are.
+    pub fn quote(self, arg: &str) -> String {
+        if !arg.is_empty() && arg.chars().all(|c| is_plain(self, c)) {
+            return arg.to_string();
+        }
+        // Both shells treat everything between single quotes literally; they only
+        // differ in how a single quote itself is written
+        let escaped_quote = match self {
+            Dialect::Posix => r#"'"'"'"#,
+            Dialect::PowerShell => "''",
+        };
+        let mut quoted = String::with_capacity(arg.len() + 2);
+        quoted.push('\'');
+        for c in arg.chars() {
+            match c {
+                '\'' => quoted.push_str(escaped_quote),
+                // PowerShell also accepts typographic quotes as delimiters
+                '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}'
+                    if self == Dialect::PowerShell =>
+                {
+                    quoted.push(c);
+                    quoted.push(c);
+                }
+                _ => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        quoted
+    }
+
+    /// The command line that invokes `command` with `args`. PowerShell needs the call
+    /// operator to run a quoted program path.
+    pub fn command_line<S: AsRef<str>>(self, command: &str, args: &[S]) -> String {
+        let program = self.quote(command);
+        let mut line = match self {
+            Dialect::PowerShell if program.starts_with('\'') => format!("& {}", program),
+            _ => program,
+        };
+        for arg in args {
+            line.push(' ');
+            line.push_str(&self.quote(arg.as_ref()));
+        }
+        line
+    }
+}
+
+fn is_plain(dialect: Dialect, c: char) -> bool {
+    match dialect {
+        Dialect::Posix => c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c),
+        // `@` splats and `,` builds arrays in PowerShell; `\` is a path separator
+        Dialect::PowerShell => c.is_ascii_alphanumeric() || "-_./=:\\+%".contains(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_posix() {
+        let posix = Dialect::Posix;
+        assert_eq!(posix.quote("--path"), "--path");
+        assert_eq!(
+            posix.quote("/usr/local/bin/workspace-manager"),
+            "/usr/local/bin/workspace-manager"
+        );
+        assert_eq!(posix.quote(""), "''");
+        assert_eq!(posix.quote("my projects"), "'my projects'");
+        assert_eq!(posix.quote("$HOME; rm -rf *"), "'$HOME; rm -rf *'");
+        assert_eq!(posix.quote("it's"), r#"'it'"'"'s'"#);
+        assert_eq!(posix.quote("📦 "), "'📦 '");
+    }
+
+    #[test]
+    fn test_quote_powershell() {
+        let ps = Dialect::PowerShell;
+        assert_eq!(
+            ps.quote(r"C:\Tools\workspace-manager.exe"),
+            r"C:\Tools\workspace-manager.exe"
+        );
+        assert_eq!(ps.quote(r"C:\My Projects"), r"'C:\My Projects'");
+        assert_eq!(ps.quote("it's"), "'it''s'");
+        assert_eq!(ps.quote("$env:HOME"), "'$env:HOME'");
+        assert_eq!(ps.quote("a,b"), "'a,b'");
+        assert_eq!(ps.quote(""), "''");
+    }
+
+    #[test]
+    fn test_command_line() {
+        let args = ["--name", "my ws", "--path", "."];
+        assert_eq!(
+            Dialect::Posix.command_line("/opt/my tools/wm", &args),
+            "'/opt/my tools/wm' --name 'my ws' --path ."
+        );
+        assert_eq!(
+            Dialect::PowerShell.command_line(r"C:\Program Files\wm.exe", &args),
+            r"& 'C:\Program Files\wm.exe' --name 'my ws' --path ."
+        );
+        assert_eq!(
+            Dialect::PowerShell.command_line("wm", &args[..2]),
+            "wm --name 'my ws'"
+        );
+    }
+}
@@ -0,0 +1,183 @@
+//! Registry of generated workspace files, kept in the user's data directory so
+//! they can be listed and refreshed from anywhere.
+
+use crate::write_atomic;
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Overrides the registry location, e.g. for tests or sandboxed setups.
+pub const REGISTRY_ENV: &str = "WORKSPACE_MANAGER_REGISTRY";
+
+/// A workspace file written by `generate` or `update`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryEntry {
+    /// Absolute path of the workspace file
+    pub workspace_file: PathBuf,
+    /// Absolute path of the directory that was scanned
+    pub scan_path: PathBuf,
+    /// Seconds since the Unix epoch of the last generation
+    pub updated_at: u64,
+}
+
+impl RegistryEntry {
+    /// Whether the workspace file is still on disk.
+    pub fn exists(&self) -> bool {
+        self.workspace_file.is_file()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Registry {
+    #[serde(default)]
+    pub workspaces: Vec<RegistryEntry>,
+}
+
+/// Where the registry lives: `$WORKSPACE_MANAGER_REGISTRY`, otherwise
+/// `registry.json` in the platform data directory
+/// (`~/.local/share/workspace-manager` on Linux).
+pub fn registry_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(REGISTRY_ENV).filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    ProjectDirs::from("", "", "workspace-manager").map(|dirs| dirs.data_dir().join("registry.json"))
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+impl Registry {
+    /// Reads the registry at `path`; a missing file is an empty registry.
+    pub fn load(path: &Path) -> Result<Registry> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Registry::default())
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()))
+            }
+        };
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse registry {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        Ok(write_atomic(path, content.as_bytes())?)
+    }
+
+    /// Adds or updates the entry for `workspace_file`.
+    pub fn record(&mut self, workspace_file: &Path, scan_path: &Path, updated_at: u64) {
+        let entry = RegistryEntry {
+            workspace_file: workspace_file.to_path_buf(),
+            scan_path: scan_path.to_path_buf(),
+            updated_at,
+        };
+        match self
+            .workspaces
+            .iter_mut()
+            .find(|existing| existing.workspace_file == workspace_file)
+        {
+            Some(existing) => *existing = entry,
+            None => self.workspaces.push(entry),
+        }
+    }
+
+    /// Drops the entry for `workspace_file`, returning whether there was one.
+    pub fn forget(&mut self, workspace_file: &Path) -> bool {
+        let before = self.workspaces.len();
+        self.workspaces
+            .retain(|entry| entry.workspace_file != workspace_file);
+        self.workspaces.len() != before
+    }
+
+    /// Drops entries whose workspace file no longer exists, returning them.
+    pub fn remove_missing(&mut self) -> Vec<RegistryEntry> {
+        let (kept, missing) = self.workspaces.drain(..).partition(|entry| entry.exists());
+        self.workspaces = kept;
+        missing
+    }
+}
+
+/// Records a generated workspace file in the registry at [`registry_path`].
+pub fn record_generation(workspace_file: &Path, scan_path: &Path) -> Result<()> {
+    let path = registry_path().context("Could not determine the data directory")?;
+    let mut registry = Registry::load(&path)?;
+    registry.record(workspace_file, scan_path, now());
+    registry.save(&path)
+}
+
+/// Drops `workspace_file` from the registry at [`registry_path`], returning whether
+/// it was registered. The registry is only rewritten when it changes.
+pub fn forget_workspace(workspace_file: &Path) -> Result<bool> {
+    let path = registry_path().context("Could not determine the data directory")?;
+    let mut registry = Registry::load(&path)?;
+    if !registry.forget(workspace_file) {
+        return Ok(false);
+    }
+    registry.save(&path)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_registry_round_trip() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join("data").join("registry.json");
+        assert_eq!(Registry::load(&path)?, Registry::default());
+
+        let existing = temp.path().join("a.code-workspace");
+        fs::write(&existing, "{}")?;
+        let gone = temp.path().join("gone.code-workspace");
+
+        let mut registry = Registry::default();
+        registry.record(&existing, temp.path(), 1);
+        registry.record(&gone, temp.path(), 2);
+        registry.record(&existing, temp.path(), 3);
+        registry.save(&path)?;
+
+        let mut loaded = Registry::load(&path)?;
+        assert_eq!(loaded.workspaces.len(), 2);
+        assert_eq!(loaded.workspaces[0].updated_at, 3);
+
+        let missing = loaded.remove_missing();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].workspace_file, gone);
+        assert_eq!(loaded.workspaces.len(), 1);
+
+        assert!(!loaded.forget(&gone));
+        assert!(loaded.forget(&existing));
+        assert!(loaded.workspaces.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupt_registry_is_an_error() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join("registry.json");
+        fs::write(&path, "{not json")?;
+        let err = Registry::load(&path).unwrap_err();
+        assert!(err.to_string().contains("registry.json"));
+        Ok(())
+    }
+}
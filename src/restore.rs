@@ -0,0 +1,269 @@
+//! Cloning the repositories of a workspace's missing folders on a new machine, from
+//! the `origin` URLs recorded per folder in the metadata section. Git is run through
+//! a [`CommandRunner`], so tests can stand in for it.
+
+use crate::model::{normalize_folder_path, WorkspaceFile};
+use anyhow::Context;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Runs external commands. [`SystemRunner`] runs them for real.
+pub trait CommandRunner: Sync {
+    /// Runs `command` to completion, capturing its output.
+    fn run(&self, command: &mut Command) -> io::Result<Output>;
+}
+
+/// Runs commands as child processes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, command: &mut Command) -> io::Result<Output> {
+        command.output()
+    }
+}
+
+/// A missing folder to clone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloneJob {
+    /// The folder path as written in the workspace file
+    pub path: String,
+    /// Where the repository is cloned to
+    pub dir: PathBuf,
+    /// The recorded `origin` URL
+    pub url: String,
+}
+
+/// What `restore` would do for the folders of a workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestorePlan {
+    /// Missing folders with a recorded remote
+    pub clones: Vec<CloneJob>,
+    /// Paths of missing folders without a recorded remote, which are skipped
+    pub no_remote: Vec<String>,
+    /// Paths of missing folders taken by something that isn't an empty directory,
+    /// with what is in the way; nothing is cloned there
+    pub blocked: Vec<(String, String)>,
+}
+
+/// Plans the clones that bring back the missing folders of `workspace`, resolved
+/// against `workspace_dir`. A folder is missing when its directory doesn't exist or
+/// is empty; uri folders are never missing.
+pub fn plan_restore(workspace: &WorkspaceFile, workspace_dir: &Path) -> RestorePlan {
+    let recorded = workspace.metadata().folders;
+    let mut plan = RestorePlan::default();
+    for folder in &workspace.folders {
+        let Some(dir) = folder.resolve(workspace_dir) else {
+            continue;
+        };
+        if dir.is_dir() && !is_empty_dir(&dir) {
+            continue;
+        }
+        let remote = recorded
+            .get(&normalize_folder_path(&folder.path))
+            .and_then(|folder| folder.remote.clone());
+        match remote {
+            None => plan.no_remote.push(folder.path.clone()),
+            Some(_) if dir.exists() && !dir.is_dir() => plan
+                .blocked
+                .push((folder.path.clone(), "a file is in the way".to_string())),
+            Some(url) => plan.clones.push(CloneJob {
+                path: folder.path.clone(),
+                dir,
+                url,
+            }),
+        }
+    }
+    plan
+}
+
+fn is_empty_dir(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// Clones `job` with `git clone`, refusing when its directory isn't empty by then,
+/// e.g. because another folder of the workspace was cloned there. A failed clone's
+/// error has git's own message.
+pub fn clone_folder(job: &CloneJob, runner: &dyn CommandRunner) -> anyhow::Result<()> {
+    if job.dir.exists() && !(job.dir.is_dir() && is_empty_dir(&job.dir)) {
+        anyhow::bail!(
+            "'{}' exists and is not an empty directory",
+            job.dir.display()
+        );
+    }
+    if let Some(parent) = job.dir.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut command = Command::new("git");
+    command.arg("clone").arg("--").arg(&job.url).arg(&job.dir);
+    let output = runner.run(&mut command).context("Failed to run git")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("git clone failed ({}): {}", output.status, stderr.trim());
+    }
+    Ok(())
+}
+
+/// [`clone_folder`] for each of `jobs`, `parallel` at a time, with the results in the
+/// order of `jobs`.
+pub fn clone_folders(
+    jobs: &[CloneJob],
+    runner: &dyn CommandRunner,
+    parallel: usize,
+) -> Vec<anyhow::Result<()>> {
+    let workers = parallel.clamp(1, jobs.len().max(1));
+    let next = AtomicUsize::new(0);
+    let mut done: Vec<(usize, anyhow::Result<()>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(index) else {
+                            break done;
+                        };
+                        done.push((index, clone_folder(job, runner)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+    done.sort_by_key(|(index, _)| *index);
+    done.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::parse_workspace;
+    use anyhow::Result;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    /// Records the commands it is given and, like git, creates the directory named by
+    /// the last argument, failing for URLs containing `broken`.
+    #[derive(Default)]
+    struct FakeGit {
+        commands: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl CommandRunner for FakeGit {
+        fn run(&self, command: &mut Command) -> io::Result<Output> {
+            let args: Vec<String> = command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect();
+            self.commands.lock().unwrap().push(args.clone());
+            let failed = args.iter().any(|arg| arg.contains("broken"));
+            if !failed {
+                fs::create_dir_all(Path::new(args.last().unwrap()).join(".git"))?;
+            }
+            Ok(Output {
+                status: exit_status(if failed { 128 } else { 0 }),
+                stdout: vec![],
+                stderr: match failed {
+                    true => b"fatal: repository 'broken' not found\n".to_vec(),
+                    false => vec![],
+                },
+            })
+        }
+    }
+
+    #[cfg(unix)]
+    fn exit_status(code: i32) -> std::process::ExitStatus {
+        use std::os::unix::process::ExitStatusExt;
+        std::process::ExitStatus::from_raw(code << 8)
+    }
+
+    #[cfg(windows)]
+    fn exit_status(code: i32) -> std::process::ExitStatus {
+        use std::os::windows::process::ExitStatusExt;
+        std::process::ExitStatus::from_raw(code as u32)
+    }
+
+    fn workspace() -> Result<WorkspaceFile> {
+        Ok(parse_workspace(
+            r#"{
+                "folders": [
+                    {"path": "api"}, {"path": "./services/web"}, {"path": "docs"},
+                    {"path": "present"}, {"path": "empty"}, {"path": "file"},
+                    {"path": "gone"}
+                ],
+                "workspaceManager": {"folders": {
+                    "api": {"remote": "git@example.com:org/api.git"},
+                    "services/web": {"remote": "https://example.com/org/web.git"},
+                    "present": {"remote": "git@example.com:org/present.git"},
+                    "empty": {"remote": "git@example.com:org/empty.git"},
+                    "file": {"remote": "git@example.com:org/file.git"},
+                    "gone": {"remote": "git@example.com:org/broken.git"}
+                }}
+            }"#,
+        )?)
+    }
+
+    #[test]
+    fn test_plan_restore() -> Result<()> {
+        let temp = TempDir::new()?;
+        fs::create_dir_all(temp.path().join("present").join(".git"))?;
+        fs::create_dir(temp.path().join("empty"))?;
+        fs::write(temp.path().join("file"), "")?;
+
+        let plan = plan_restore(&workspace()?, temp.path());
+        let paths: Vec<&str> = plan.clones.iter().map(|job| job.path.as_str()).collect();
+        assert_eq!(paths, ["api", "./services/web", "empty", "gone"]);
+        assert_eq!(plan.clones[1].url, "https://example.com/org/web.git");
+        assert_eq!(plan.clones[1].dir, temp.path().join("./services/web"));
+        assert_eq!(plan.no_remote, ["docs"]);
+        assert_eq!(plan.blocked.len(), 1);
+        assert_eq!(plan.blocked[0].0, "file");
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone_folders() -> Result<()> {
+        let temp = TempDir::new()?;
+        fs::create_dir_all(temp.path().join("present").join(".git"))?;
+        fs::create_dir(temp.path().join("empty"))?;
+        fs::write(temp.path().join("file"), "")?;
+        let plan = plan_restore(&workspace()?, temp.path());
+        let git = FakeGit::default();
+
+        let results = clone_folders(&plan.clones, &git, 2);
+        assert!(results[..3].iter().all(|result| result.is_ok()));
+        let err = results[3].as_ref().unwrap_err().to_string();
+        assert!(
+            err.contains("fatal: repository 'broken' not found"),
+            "{}",
+            err
+        );
+        assert!(temp.path().join("services/web/.git").is_dir());
+        let commands = git.commands.lock().unwrap();
+        assert_eq!(commands.len(), 4);
+        assert!(commands.contains(&vec![
+            "clone".to_string(),
+            "--".to_string(),
+            "git@example.com:org/api.git".to_string(),
+            temp.path().join("api").to_string_lossy().into_owned(),
+        ]));
+        drop(commands);
+
+        // A directory filled since the plan was made is left alone
+        let result = clone_folder(&plan.clones[0], &git);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("not an empty directory"));
+        assert_eq!(git.commands.lock().unwrap().len(), 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,1977 @@
+//! Finding the directories that become workspace folders: the scan of the scan path
+//! with its filters, grouping directories, and the members of nested workspaces.
+
+use crate::console;
+use crate::error::{Result, WorkspaceError};
+use crate::filesystem::{DirEntry, FileSystem, RealFs};
+use crate::filter::{FilterDecision, FolderFilter, ReferencedFilter, ScanContext, TimeSpec};
+use crate::io::{find_workspace_files_with, parse_workspace};
+use crate::model::{normalize_folder_path, rebase_folders, WorkspaceFolder};
+use crate::options::{GenerateOptions, GitStatusMode, SortOrder, WorktreeMode};
+use crate::{cargo, git, node};
+use anyhow::Context;
+use std::cell::OnceCell;
+use std::fmt;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Whether the name of `path` starts with a dot.
+pub fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with('.'))
+        .unwrap_or(false)
+}
+
+/// How [`scan`] discovers directories. The default finds the directories directly
+/// inside the base directory whose name doesn't start with a dot, as generation does
+/// without any filter flags.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// How many levels below the base directory to look: 1 for its direct
+    /// subdirectories, 2 to also look inside those, and so on
+    pub max_depth: usize,
+    /// How many levels below the base directory directories start to be found: 1
+    /// for its direct subdirectories, 2 to only find the directories inside those,
+    /// and so on. Shallower directories are still looked inside. The base
+    /// directory, depth 0, is never found itself
+    pub min_depth: usize,
+    /// Include directories whose name starts with a dot, and look inside them
+    pub include_hidden: bool,
+    /// Treat symlinks to directories like directories. Each directory is still
+    /// looked inside once, so links can't make the scan loop
+    pub follow_symlinks: bool,
+    /// Glob patterns of directory names to leave out, without looking inside them
+    pub excludes: Vec<String>,
+    /// Exact directory names to leave out, without looking inside them
+    pub exclude_names: Vec<String>,
+    /// Compare [`exclude_names`](Self::exclude_names) ignoring case
+    pub exclude_names_ignore_case: bool,
+    /// Glob patterns of directory names to keep; all are kept when empty. Directories
+    /// that don't match are still looked inside
+    pub includes: Vec<String>,
+    /// Regexes of paths relative to the base directory, with `/` between
+    /// components, to leave out without looking inside them
+    pub exclude_regexes: Vec<String>,
+    /// Regexes of relative paths to keep, like [`includes`](Self::includes)
+    pub include_regexes: Vec<String>,
+    /// Leave out directories with more entries directly inside them than this,
+    /// without looking inside them
+    pub max_entries: Option<usize>,
+    /// Only keep directories containing one of these files. Directories without
+    /// one are still looked inside
+    pub markers: Vec<String>,
+    /// Only keep directories containing a file whose name matches one of these glob
+    /// patterns. Directories without one are still looked inside
+    pub contains: Vec<String>,
+    /// How many levels of a directory [`contains`](Self::contains) looks in: 1 for
+    /// the files directly inside it, more to search its subdirectories too
+    pub contains_depth: usize,
+    /// Only keep directories modified since this bound, by the newest modification time
+    /// of the directory and the entries directly inside it. The others are still
+    /// looked inside
+    pub newer_than: Option<TimeSpec>,
+    /// Only keep directories not modified since this bound, like
+    /// [`newer_than`](Self::newer_than)
+    pub older_than: Option<TimeSpec>,
+    /// Leave out directories ignored by the `.gitignore` files of the base directory
+    /// and the directories below it
+    pub respect_gitignore: bool,
+    /// Order of the directories found
+    pub sort: SortOrder,
+    /// Further filters, asked after those of the other options
+    pub filters: Vec<Arc<dyn FolderFilter>>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            max_depth: 1,
+            min_depth: 1,
+            include_hidden: false,
+            follow_symlinks: true,
+            excludes: vec![],
+            exclude_names: vec![],
+            exclude_names_ignore_case: false,
+            includes: vec![],
+            exclude_regexes: vec![],
+            include_regexes: vec![],
+            max_entries: None,
+            markers: vec![],
+            contains: vec![],
+            contains_depth: 1,
+            newer_than: None,
+            older_than: None,
+            respect_gitignore: false,
+            sort: SortOrder::None,
+            filters: vec![],
+        }
+    }
+}
+
+impl From<&GenerateOptions> for ScanOptions {
+    fn from(opts: &GenerateOptions) -> Self {
+        ScanOptions {
+            max_depth: opts.max_depth.unwrap_or(1),
+            min_depth: opts.min_depth.unwrap_or(1),
+            include_hidden: opts.include_hidden,
+            excludes: opts.exclude.clone(),
+            exclude_names: opts.exclude_name.clone(),
+            exclude_names_ignore_case: opts.exclude_name_ci,
+            exclude_regexes: opts.exclude_regex.clone(),
+            include_regexes: opts.include_regex.clone(),
+            max_entries: opts.max_entries,
+            markers: opts.markers.clone(),
+            contains: opts.contains.clone(),
+            contains_depth: opts.contains_depth.unwrap_or(1),
+            newer_than: opts.newer_than,
+            older_than: opts.older_than,
+            sort: opts.sort,
+            ..Default::default()
+        }
+    }
+}
+
+/// Why [`scan`] left a directory out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Its name starts with a dot
+    Hidden,
+    /// Its name matches this exclude pattern
+    Excluded(String),
+    /// Its name is this excluded name
+    ExcludedName(String),
+    /// Its name matches none of the include patterns
+    NotIncluded,
+    /// Its relative path matches this exclude regex
+    ExcludedByRegex(String),
+    /// Its relative path matches none of the include regexes
+    NotIncludedByRegex,
+    /// It has more entries directly inside it than this `--max-entries` limit
+    TooManyEntries(usize),
+    /// It contains none of the marker files
+    NoMarker,
+    /// It contains no file matching the `--contains` patterns
+    NoMatchingFile,
+    /// It wasn't modified since this `--newer-than` bound
+    NotModifiedWithin(TimeSpec),
+    /// It was modified since this `--older-than` bound
+    ModifiedWithin(TimeSpec),
+    /// It is a symlink and symlinks aren't followed
+    Symlink,
+    /// A `.gitignore` file ignores it
+    Gitignored,
+    /// It is less deep below the base directory than [`ScanOptions::min_depth`]
+    TooShallow,
+    /// It couldn't be looked inside, so nothing below it was found; holds the error
+    Unreadable(String),
+    /// It is a folder of this workspace file, given with `--exclude-in`
+    Referenced(PathBuf),
+    /// A [`FolderFilter`] left it out; holds its reason
+    Filtered(String),
+}
+
+/// The reason as shown by `--verbose`, e.g. `excluded by 'build*'`.
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Hidden => write!(f, "hidden"),
+            SkipReason::Excluded(pattern) => write!(f, "excluded by '{}'", pattern),
+            SkipReason::ExcludedName(name) => write!(f, "excluded by name '{}'", name),
+            SkipReason::NotIncluded => write!(f, "matches no include pattern"),
+            SkipReason::ExcludedByRegex(regex) => write!(f, "excluded by regex '{}'", regex),
+            SkipReason::NotIncludedByRegex => write!(f, "matches no include regex"),
+            SkipReason::TooManyEntries(max) => {
+                write!(f, "more than {} entries (--max-entries)", max)
+            }
+            SkipReason::NoMarker => write!(f, "contains no marker file"),
+            SkipReason::NoMatchingFile => write!(f, "contains no file matching --contains"),
+            SkipReason::NotModifiedWithin(spec) => {
+                write!(f, "not modified within --newer-than {}", spec)
+            }
+            SkipReason::ModifiedWithin(spec) => write!(f, "modified within --older-than {}", spec),
+            SkipReason::Symlink => write!(f, "symlink not followed"),
+            SkipReason::Gitignored => write!(f, "ignored by .gitignore"),
+            SkipReason::TooShallow => write!(f, "shallower than --min-depth"),
+            SkipReason::Referenced(file) => write!(f, "already in {}", file.display()),
+            SkipReason::Unreadable(err) => write!(f, "unreadable: {}", err),
+            SkipReason::Filtered(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// A directory [`scan`] left out or couldn't look inside, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+/// What [`scan`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOutcome {
+    /// The directories found, parents before the directories inside them
+    pub directories: Vec<PathBuf>,
+    /// The directories left out or not looked inside, in the order they were met
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Finds the directories below `base` that `opts` selects. Directories that are
+/// left out are reported in [`ScanOutcome::skipped`]; only failing to list `base`
+/// itself, or an invalid pattern, is an error.
+pub fn scan(base: &Path, opts: &ScanOptions) -> Result<ScanOutcome> {
+    scan_with(&RealFs, base, opts)
+}
+
+/// Like [`scan`], looking in `fs`.
+pub fn scan_with(fs: &dyn FileSystem, base: &Path, opts: &ScanOptions) -> Result<ScanOutcome> {
+    scan_until(fs, base, opts, &AtomicBool::new(false))
+}
+
+/// Like [`scan_with`], stopping with what was found so far once `stop` is set.
+pub(crate) fn scan_until(
+    fs: &dyn FileSystem,
+    base: &Path,
+    opts: &ScanOptions,
+    stop: &AtomicBool,
+) -> Result<ScanOutcome> {
+    let mut outcome = ScanOutcome::default();
+    let mut found = ScanIter::new(fs, base, opts).record_skipped(true);
+    while let Some(item) = found.next().filter(|_| !stop.load(Ordering::Relaxed)) {
+        match item {
+            Ok(dir) => outcome.directories.push(dir.path),
+            Err(WorkspaceError::Io { path, source }) if path != base => {
+                outcome.skipped.push(SkippedEntry {
+                    path,
+                    reason: SkipReason::Unreadable(source.to_string()),
+                });
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    outcome.skipped.append(&mut found.take_skipped());
+    if opts.sort == SortOrder::Name {
+        outcome.directories.sort();
+    }
+    Ok(outcome)
+}
+
+/// Like [`scan`], yielding the directories one at a time as they are found, so a
+/// caller can show progress or stop early. Only the directories still to be looked
+/// inside and the entries of the one being listed are kept in memory.
+///
+/// Directories come in the order they are read from disk, parents before the
+/// directories inside them, whatever the sort order of `opts`. A directory that
+/// can't be listed is yielded as an [`WorkspaceError::Io`] item and the scan goes
+/// on; an invalid pattern or a missing `base` is the only item.
+pub fn scan_iter(base: &Path, opts: &ScanOptions) -> impl Iterator<Item = Result<ScannedDir>> {
+    ScanIter::new(&RealFs, base, opts)
+}
+
+/// Like [`scan_iter`], looking in `fs`.
+pub fn scan_iter_with<'a>(
+    fs: &'a dyn FileSystem,
+    base: &Path,
+    opts: &ScanOptions,
+) -> impl Iterator<Item = Result<ScannedDir>> + 'a {
+    ScanIter::new(fs, base, opts)
+}
+
+/// A directory found by [`scan_iter`], with what made it match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannedDir {
+    pub path: PathBuf,
+    /// 1 for the directories directly inside the base directory
+    pub depth: usize,
+    /// What made the filters keep it, e.g. `included by 'api*'` or
+    /// `marker 'Cargo.toml'`, in the order the filters were asked
+    pub reasons: Vec<String>,
+}
+
+/// The iterator behind [`scan_iter`] and [`scan`].
+struct ScanIter<'a> {
+    fs: &'a dyn FileSystem,
+    base: PathBuf,
+    opts: ScanOptions,
+    filters: Vec<Arc<dyn FolderFilter>>,
+    /// Yielded before anything else: an invalid pattern or an unreadable base
+    failed: Option<WorkspaceError>,
+    /// The directory being listed
+    listing: Option<Listing>,
+    /// Directories still to be listed, the next one last
+    pending: Vec<Pending>,
+    /// Whether to keep the entries left out for [`ScanIter::take_skipped`]
+    record_skipped: bool,
+    skipped: Vec<SkippedEntry>,
+}
+
+/// A directory to look inside, with the `.gitignore` rules that apply in it and the
+/// canonical paths of the directories it was reached through, which a link must
+/// not lead back to.
+struct Pending {
+    dir: PathBuf,
+    depth: usize,
+    gitignore: Gitignore,
+    ancestors: Vec<PathBuf>,
+    /// Its entries, when the filters already listed them
+    entries: Option<Vec<DirEntry>>,
+}
+
+struct Listing {
+    entries: std::vec::IntoIter<DirEntry>,
+    dir: Pending,
+    /// Subdirectories to look inside once this listing is done
+    below: Vec<Pending>,
+}
+
+impl<'a> ScanIter<'a> {
+    fn new(fs: &'a dyn FileSystem, base: &Path, opts: &ScanOptions) -> ScanIter<'a> {
+        let mut iter = ScanIter {
+            fs,
+            base: base.to_path_buf(),
+            opts: opts.clone(),
+            filters: vec![],
+            failed: None,
+            listing: None,
+            pending: vec![],
+            record_skipped: false,
+            skipped: vec![],
+        };
+        let prepared = check_depths(opts.min_depth, opts.max_depth)
+            .and_then(|_| opts.all_filters())
+            .and_then(|filters| Ok((filters, read_scan_path(fs, base)?)));
+        match prepared {
+            Ok((filters, entries)) => {
+                let mut gitignore = Gitignore::default();
+                if opts.respect_gitignore {
+                    gitignore.read(fs, base);
+                }
+                let canonical = fs.canonicalize(base).unwrap_or_else(|_| base.to_path_buf());
+                iter.filters = filters;
+                iter.listing = Some(Listing {
+                    entries: entries.into_iter(),
+                    dir: Pending {
+                        dir: base.to_path_buf(),
+                        depth: 0,
+                        gitignore,
+                        ancestors: vec![canonical],
+                        entries: None,
+                    },
+                    below: vec![],
+                });
+            }
+            Err(err) => iter.failed = Some(err),
+        }
+        iter
+    }
+
+    fn record_skipped(mut self, record: bool) -> Self {
+        self.record_skipped = record;
+        self
+    }
+
+    /// The entries left out since the last call, when recording them.
+    fn take_skipped(&mut self) -> Vec<SkippedEntry> {
+        std::mem::take(&mut self.skipped)
+    }
+
+    fn skip(&mut self, path: PathBuf, reason: SkipReason) {
+        if self.record_skipped {
+            self.skipped.push(SkippedEntry { path, reason });
+        }
+    }
+
+    /// Filters `entry` of the current listing, queueing it to be looked inside when
+    /// it is deep enough and no filter excluded it for good. Returns it when every
+    /// filter keeps it.
+    fn visit(&mut self, entry: DirEntry) -> Option<ScannedDir> {
+        let listing = self.listing.as_ref()?;
+        if !entry.is_dir {
+            return None;
+        }
+        let path = entry.path;
+        let depth = listing.dir.depth + 1;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let entries = OnceCell::new();
+        let ctx = ScanContext {
+            fs: self.fs,
+            base: &self.base,
+            depth,
+            name: &name,
+            is_symlink: entry.is_symlink,
+            dir: &path,
+            gitignore: &listing.dir.gitignore,
+            entries: &entries,
+        };
+        let mut reasons = vec![];
+        let mut excluded = None;
+        for filter in &self.filters {
+            match filter.include(&path, &ctx) {
+                FilterDecision::Include(reason) => reasons.extend(reason),
+                FilterDecision::Exclude { reason, descend } => {
+                    excluded = Some((reason, descend));
+                    break;
+                }
+            }
+        }
+        if let Some((reason, false)) = excluded {
+            self.skip(path, reason);
+            return None;
+        }
+
+        let opts = &self.opts;
+        if depth < opts.max_depth {
+            // Only a link can lead somewhere else than below the directory listed
+            let canonical = match (entry.is_symlink, listing.dir.ancestors.last()) {
+                (false, Some(parent)) => parent.join(&*name),
+                _ => self.fs.canonicalize(&path).unwrap_or_else(|_| path.clone()),
+            };
+            // A link back to a directory on the way here would loop
+            if !listing.dir.ancestors.contains(&canonical) {
+                let mut gitignore = listing.dir.gitignore.clone();
+                if opts.respect_gitignore {
+                    gitignore.read(self.fs, &path);
+                }
+                let mut ancestors = listing.dir.ancestors.clone();
+                ancestors.push(canonical);
+                let below = Pending {
+                    dir: path.clone(),
+                    depth,
+                    gitignore,
+                    ancestors,
+                    entries: entries.into_inner().flatten(),
+                };
+                self.listing.as_mut()?.below.push(below);
+            }
+        }
+        match excluded {
+            Some((reason, _)) => {
+                self.skip(path, reason);
+                None
+            }
+            None if depth < opts.min_depth => {
+                self.skip(path, SkipReason::TooShallow);
+                None
+            }
+            None => Some(ScannedDir {
+                path,
+                depth,
+                reasons,
+            }),
+        }
+    }
+}
+
+impl Iterator for ScanIter<'_> {
+    type Item = Result<ScannedDir>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.failed.take() {
+            return Some(Err(err));
+        }
+        loop {
+            let Some(listing) = &mut self.listing else {
+                let mut pending = self.pending.pop()?;
+                let entries = match pending.entries.take() {
+                    Some(entries) => Ok(entries),
+                    None => self.fs.read_dir_entries(&pending.dir),
+                };
+                match entries {
+                    Ok(entries) => {
+                        self.listing = Some(Listing {
+                            entries: entries.into_iter(),
+                            dir: pending,
+                            below: vec![],
+                        })
+                    }
+                    Err(source) => {
+                        return Some(Err(WorkspaceError::Io {
+                            path: pending.dir,
+                            source,
+                        }))
+                    }
+                }
+                continue;
+            };
+            match listing.entries.next() {
+                Some(entry) => {
+                    if let Some(found) = self.visit(entry) {
+                        return Some(Ok(found));
+                    }
+                }
+                None => {
+                    let listing = self.listing.take()?;
+                    // Reversed, so the first subdirectory is listed next
+                    self.pending.extend(listing.below.into_iter().rev());
+                }
+            }
+        }
+    }
+}
+
+/// The directories directly inside `base_path` that aren't hidden, unsorted. Kept
+/// for compatibility; [`scan`] offers the filters of a scan.
+pub fn scan_directories(base_path: &Path) -> Result<Vec<PathBuf>> {
+    Ok(scan(base_path, &ScanOptions::default())?.directories)
+}
+
+/// Lists the scan path `dir`, which is missing rather than unreadable when it doesn't
+/// exist.
+fn read_scan_path(fs: &dyn FileSystem, dir: &Path) -> Result<Vec<DirEntry>> {
+    fs.read_dir_entries(dir)
+        .map_err(|source| match source.kind() {
+            std::io::ErrorKind::NotFound => WorkspaceError::ScanPathNotFound(dir.to_path_buf()),
+            _ => WorkspaceError::Io {
+                path: dir.to_path_buf(),
+                source,
+            },
+        })
+}
+
+/// Subdirectories of the scan path that become folders, after the hidden,
+/// exclude and marker filters of `opts` and in its sort order.
+pub fn candidate_directories(opts: &GenerateOptions) -> Result<Vec<PathBuf>> {
+    candidate_directories_with(&RealFs, opts)
+}
+
+/// Like [`candidate_directories`], looking in `fs`.
+pub fn candidate_directories_with(
+    fs: &dyn FileSystem,
+    opts: &GenerateOptions,
+) -> Result<Vec<PathBuf>> {
+    Ok(filtered_scan(fs, opts, &[])?.directories)
+}
+
+/// The directories listed in `reader`, separated by `separator`, `b'\n'` or `b'\0'`,
+/// to use as [`GenerateOptions::candidates`]. Relative paths are resolved against
+/// `current_dir`. Paths that don't exist or aren't directories are skipped with a
+/// warning, as are repeats; only failing to read is an error.
+pub fn read_candidates(
+    reader: impl BufRead,
+    separator: u8,
+    current_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    read_candidates_with(&RealFs, reader, separator, current_dir)
+}
+
+/// Like [`read_candidates`], looking in `fs`.
+pub fn read_candidates_with(
+    fs: &dyn FileSystem,
+    reader: impl BufRead,
+    separator: u8,
+    current_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let mut dirs = vec![];
+    for entry in reader.split(separator) {
+        let entry = entry.context("Failed to read the list of directories")?;
+        let entry = String::from_utf8_lossy(&entry);
+        let entry = entry.strip_suffix('\r').unwrap_or(&entry);
+        if entry.is_empty() {
+            continue;
+        }
+        match fs.canonicalize(&current_dir.join(entry)) {
+            Ok(dir) if fs.is_dir(&dir) => {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+            Ok(_) => console::warning(format_args!("skipping '{}': not a directory", entry)),
+            Err(_) => console::warning(format_args!("skipping '{}': not found", entry)),
+        }
+    }
+    Ok(dirs)
+}
+
+/// Fails unless directories `min_depth` levels deep are within `max_depth`.
+pub(crate) fn check_depths(min_depth: usize, max_depth: usize) -> Result<()> {
+    match min_depth > max_depth {
+        true => Err(WorkspaceError::InvalidDepth {
+            min: min_depth,
+            max: max_depth,
+        }),
+        false => Ok(()),
+    }
+}
+
+/// The scan behind [`candidate_directories_with`], asking `filters` after those of
+/// `opts`. With `--verbose`, says why each directory left out was.
+pub(crate) fn filtered_scan(
+    fs: &dyn FileSystem,
+    opts: &GenerateOptions,
+    filters: &[Arc<dyn FolderFilter>],
+) -> Result<ScanOutcome> {
+    let mut all_filters: Vec<Arc<dyn FolderFilter>> = vec![];
+    if !opts.exclude_in.is_empty() {
+        all_filters.push(Arc::new(ReferencedFilter::load(fs, &opts.exclude_in)));
+    }
+    all_filters.extend(filters.iter().cloned());
+    let scan_opts = ScanOptions {
+        filters: all_filters,
+        ..ScanOptions::from(opts)
+    };
+    let outcome = scan_with(fs, &opts.scan_path, &scan_opts)?;
+    if opts.verbose {
+        for skipped in &outcome.skipped {
+            console::diagnostic(format_args!(
+                "Skipped {}: {}",
+                skipped.path.display(),
+                skipped.reason
+            ));
+        }
+    }
+    // Left out for their size rather than for what they are, so always told
+    let crowded: Vec<String> = outcome
+        .skipped
+        .iter()
+        .filter(|skipped| matches!(skipped.reason, SkipReason::TooManyEntries(_)))
+        .map(|skipped| {
+            let path = skipped.path.strip_prefix(&opts.scan_path);
+            format!("\n  {}", path.unwrap_or(&skipped.path).display())
+        })
+        .collect();
+    if let (false, Some(max)) = (crowded.is_empty(), opts.max_entries) {
+        console::warning(format_args!(
+            "left out {} director(ies) with more than {} entries (--max-entries):{}",
+            crowded.len(),
+            max,
+            crowded.concat()
+        ));
+    }
+    Ok(outcome)
+}
+
+/// The rules of the `.gitignore` files met on the way down from the scan root, in
+/// the order they apply: later rules win.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Gitignore {
+    rules: Vec<GitignoreRule>,
+}
+
+#[derive(Debug, Clone)]
+struct GitignoreRule {
+    /// Directory of the `.gitignore` file the rule comes from
+    dir: PathBuf,
+    pattern: glob::Pattern,
+    /// Whether the pattern is matched against the path relative to `dir` rather
+    /// than against the name alone
+    anchored: bool,
+    /// A `!` rule, re-including what earlier rules ignored
+    negated: bool,
+}
+
+impl Gitignore {
+    /// Adds the rules of the `.gitignore` file in `dir`, if there is one. Lines that
+    /// aren't valid patterns are skipped.
+    fn read(&mut self, fs: &dyn FileSystem, dir: &Path) {
+        let Ok(content) = fs.read_to_string(&dir.join(".gitignore")) else {
+            return;
+        };
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let line = line.trim_end_matches('/');
+            let anchored = line.contains('/');
+            let Ok(pattern) = glob::Pattern::new(line.trim_start_matches('/')) else {
+                continue;
+            };
+            self.rules.push(GitignoreRule {
+                dir: dir.to_path_buf(),
+                pattern,
+                anchored,
+                negated,
+            });
+        }
+    }
+
+    /// Whether the rules ignore the directory at `path`.
+    pub(crate) fn ignores(&self, path: &Path) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut ignored = false;
+        for rule in &self.rules {
+            let Ok(relative) = path.strip_prefix(&rule.dir) else {
+                continue;
+            };
+            let matched = match rule.anchored {
+                true => {
+                    let relative = relative
+                        .components()
+                        .map(|part| part.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    rule.pattern.matches_with(&relative, options)
+                }
+                false => rule.pattern.matches_with(&name, options),
+            };
+            if matched {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+/// The error for a scan that found `found` folders, fewer than `--min-folders`, listing
+/// the filters that may have left folders out. `remembered` is the number of paths
+/// the workspace file excludes.
+pub(crate) fn too_few_folders(
+    opts: &GenerateOptions,
+    found: usize,
+    remembered: usize,
+) -> WorkspaceError {
+    let mut filters: Vec<String> = vec![];
+    filters.extend(
+        opts.exclude
+            .iter()
+            .map(|pattern| format!("--exclude '{}'", pattern)),
+    );
+    filters.extend(
+        opts.exclude_name
+            .iter()
+            .map(|name| format!("--exclude-name '{}'", name)),
+    );
+    filters.extend(
+        opts.exclude_regex
+            .iter()
+            .map(|regex| format!("--exclude-regex '{}'", regex)),
+    );
+    filters.extend(
+        opts.include_regex
+            .iter()
+            .map(|regex| format!("--include-regex '{}'", regex)),
+    );
+    filters.extend(
+        opts.exclude_in
+            .iter()
+            .map(|file| format!("--exclude-in '{}'", file.display())),
+    );
+    filters.extend(
+        opts.markers
+            .iter()
+            .map(|marker| format!("--marker '{}'", marker)),
+    );
+    filters.extend(
+        opts.contains
+            .iter()
+            .map(|pattern| format!("--contains '{}'", pattern)),
+    );
+    filters.extend(opts.max_entries.map(|max| format!("--max-entries {}", max)));
+    filters.extend(opts.newer_than.map(|spec| format!("--newer-than {}", spec)));
+    filters.extend(opts.older_than.map(|spec| format!("--older-than {}", spec)));
+    if !opts.include_hidden {
+        filters.push("directories starting with a dot are skipped".to_string());
+    }
+    if opts.git_status == Some(GitStatusMode::OnlyDirty) {
+        filters.push("--git-status only-dirty".to_string());
+    }
+    if opts.worktrees == Some(WorktreeMode::SkipLinked) {
+        filters.push("--worktrees skip-linked".to_string());
+    }
+    if remembered > 0 {
+        filters.push(format!(
+            "{} path(s) excluded in the workspace file",
+            remembered
+        ));
+    }
+    let mut message = format!(
+        "Found {} folder(s) in '{}', fewer than --min-folders {}; nothing was written",
+        found,
+        opts.scan_path.display(),
+        opts.min_folders
+    );
+    if filters.is_empty() {
+        filters.push("none".to_string());
+    }
+    message.push_str("\nActive filters:");
+    for filter in &filters {
+        message.push_str(&format!("\n  {}", filter));
+    }
+    message.push_str("\nUse --allow-empty to write the workspace anyway");
+    WorkspaceError::TooFewFolders { found, message }
+}
+
+/// The directories `depth` levels below the scan path of `opts`, for which
+/// `--group-by-parent` writes a workspace each. Hidden and excluded directories are
+/// skipped as in a scan; markers apply to the folders inside the groups instead.
+pub fn group_directories(opts: &GenerateOptions, depth: u32) -> Result<Vec<PathBuf>> {
+    let mut level = vec![opts.scan_path.clone()];
+    for _ in 0..depth {
+        let mut next = vec![];
+        for dir in &level {
+            next.extend(candidate_directories(&GenerateOptions {
+                scan_path: dir.clone(),
+                markers: vec![],
+                sort: SortOrder::Name,
+                ..opts.clone()
+            })?);
+        }
+        level = next;
+    }
+    Ok(level)
+}
+
+/// A package of a Cargo or node workspace, or a git submodule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMember {
+    pub dir: PathBuf,
+    /// Name of the package, or its directory name when the manifest has none
+    pub package: String,
+}
+
+/// Folders for the members of the Cargo and node workspaces and the git submodules
+/// rooted at `dir`, as enabled in `opts`, named after their packages. A manifest that
+/// can't be parsed is skipped with a warning.
+pub(crate) fn member_folders(
+    dir: &Path,
+    base_path: &Path,
+    prefix: &str,
+    opts: &GenerateOptions,
+) -> Vec<WorkspaceFolder> {
+    let mut members = vec![];
+    let found = [
+        (
+            "Cargo workspace members",
+            opts.cargo_members.then(|| cargo::workspace_members(dir)),
+        ),
+        (
+            "node workspace packages",
+            opts.node_packages.then(|| node::workspace_packages(dir)),
+        ),
+        (
+            "git submodules",
+            opts.submodules
+                .then(|| git::submodule_members(dir, opts.uninitialized_submodules)),
+        ),
+    ];
+    for (kind, found) in found {
+        match found {
+            Some(Ok(found)) => members.extend(found),
+            Some(Err(err)) => console::warning(format_args!("skipping {}: {:#}", kind, err)),
+            None => {}
+        }
+    }
+    members
+        .into_iter()
+        .filter_map(|member| {
+            let relative = pathdiff::diff_paths(&member.dir, base_path)?;
+            Some(WorkspaceFolder::new(
+                normalize_folder_path(&relative.to_string_lossy()),
+                format!("{}{}", prefix, member.package),
+            ))
+        })
+        .collect()
+}
+
+/// The folders of the `.code-workspace` files directly inside `dir`, with paths
+/// rebased onto `base_path`. Files that can't be read or parsed are skipped with a
+/// warning.
+pub(crate) fn absorbed_folders(
+    fs: &dyn FileSystem,
+    dir: &Path,
+    base_path: &Path,
+) -> Vec<WorkspaceFolder> {
+    let Ok(files) = find_workspace_files_with(fs, dir) else {
+        return vec![];
+    };
+
+    let mut folders = vec![];
+    for file in files {
+        let parsed = fs
+            .read_to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(parse_workspace(&content)?));
+        match parsed {
+            Ok(mut workspace) => {
+                rebase_folders(&mut workspace, dir, base_path);
+                folders.extend(workspace.folders);
+            }
+            Err(err) => console::warning(format_args!("skipping '{}': {:#}", file.display(), err)),
+        }
+    }
+    folders
+}
+
+/// The directories among `dirs` that are git checkouts with uncommitted changes,
+/// checked in parallel. Repositories where git fails count as clean, with a note when
+/// `verbose`.
+pub(crate) fn dirty_repositories(dirs: &[PathBuf], verbose: bool) -> Vec<PathBuf> {
+    let repositories: Vec<PathBuf> = dirs
+        .iter()
+        .filter(|dir| git::worktree(dir).is_some())
+        .cloned()
+        .collect();
+    let statuses = git::dirty_statuses(&repositories, git::STATUS_TIMEOUT);
+    repositories
+        .into_iter()
+        .zip(statuses)
+        .filter_map(|(dir, status)| match status {
+            Ok(dirty) => dirty.then_some(dir),
+            Err(err) => {
+                if verbose {
+                    console::diagnostic(format_args!(
+                        "Treating {} as clean: {:#}",
+                        dir.display(),
+                        err
+                    ));
+                }
+                None
+            }
+        })
+        .collect()
+}
+
+/// Sorts `dirs` for `--sort git-activity`: git checkouts first, the one whose HEAD
+/// moved last first, then the others by path. Checkouts whose activity can't be read
+/// sort with the others.
+pub(crate) fn sort_by_git_activity(dirs: &mut Vec<PathBuf>) {
+    let activities = git::last_activities(dirs);
+    let mut sorted: Vec<(Option<SystemTime>, PathBuf)> =
+        activities.into_iter().zip(dirs.drain(..)).collect();
+    sorted.sort_by(|(a, a_dir), (b, b_dir)| b.cmp(a).then_with(|| a_dir.cmp(b_dir)));
+    dirs.extend(sorted.into_iter().map(|(_, dir)| dir));
+}
+
+/// How many levels below a folder `--warn-size` adds up the sizes of files.
+pub const SIZE_PROBE_DEPTH: usize = 3;
+
+/// The total size in bytes of the files in `dir` and in the directories below it,
+/// down to `depth` levels, not following links. Deeper files aren't counted, so this
+/// is a lower bound, and adding up stops as soon as it passes `limit`.
+pub fn estimate_size(dir: &Path, depth: usize, limit: u64) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![(dir.to_path_buf(), 1)];
+    while let Some((dir, level)) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() && level < depth {
+                pending.push((entry.path(), level + 1));
+            } else if file_type.is_file() {
+                total += entry.metadata().map_or(0, |metadata| metadata.len());
+                if total > limit {
+                    return total;
+                }
+            }
+        }
+    }
+    total
+}
+
+/// The folder for `path`, relative to the workspace file in `base_path` and named
+/// after the directory. The scan path itself keeps its path relative to `base_path`,
+/// or `.` when it is outside it.
+pub fn create_workspace_folder(
+    path: &Path,
+    base_path: &Path,
+    scan_path: &Path,
+) -> Result<WorkspaceFolder> {
+    let name = path
+        .file_name()
+        .context("Invalid folder name")?
+        .to_string_lossy()
+        .into_owned();
+
+    let relative_path = if path == scan_path {
+        scan_path
+            .strip_prefix(base_path)
+            .unwrap_or(Path::new("."))
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        pathdiff::diff_paths(path, base_path)
+            .context("Failed to calculate relative path")?
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    Ok(WorkspaceFolder::new(relative_path, format!("📦 {}", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{MemoryFs, Operation};
+    use anyhow::Result;
+    use std::fs;
+    use std::io::ErrorKind;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_create_workspace_folder() -> Result<()> {
+        let temp = TempDir::new()?;
+        let base = TempDir::new()?;
+        let base_path = base.path().to_path_buf();
+        let scan_path = temp.path();
+
+        let test_dir = scan_path.join("nested").join("test_folder");
+        fs::create_dir_all(&test_dir)?;
+
+        let folder = create_workspace_folder(&test_dir, &base_path, scan_path)?;
+
+        let expected_path =
+            pathdiff::diff_paths(&test_dir, &base_path).expect("Failed to get relative path");
+
+        assert_eq!(folder.path, expected_path.to_string_lossy());
+        assert_eq!(folder.name, "📦 test_folder");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_directories() -> Result<()> {
+        let temp = TempDir::new()?;
+        let base = temp.path();
+
+        fs::create_dir(base.join("folder1"))?;
+        fs::create_dir(base.join("folder2"))?;
+        fs::create_dir(base.join(".hidden"))?;
+
+        let dirs = scan_directories(base)?;
+
+        assert_eq!(dirs.len(), 2);
+        assert!(dirs.iter().any(|p| p.ends_with("folder1")));
+        assert!(dirs.iter().any(|p| p.ends_with("folder2")));
+        Ok(())
+    }
+
+    /// A tree of directories under a temporary root, with `file`s given as `dir/name`.
+    fn tree(dirs: &[&str], files: &[&str]) -> Result<TempDir> {
+        let temp = TempDir::new()?;
+        for dir in dirs {
+            fs::create_dir_all(temp.path().join(dir))?;
+        }
+        for file in files {
+            fs::write(temp.path().join(file), "")?;
+        }
+        Ok(temp)
+    }
+
+    /// The directories found under `root` with `opts`, relative and sorted by name.
+    fn scanned(root: &Path, opts: ScanOptions) -> Result<Vec<String>> {
+        let outcome = scan(
+            root,
+            &ScanOptions {
+                sort: SortOrder::Name,
+                ..opts
+            },
+        )?;
+        Ok(relative(root, &outcome.directories))
+    }
+
+    fn relative(root: &Path, dirs: &[PathBuf]) -> Vec<String> {
+        dirs.iter()
+            .map(|dir| {
+                dir.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_defaults() -> Result<()> {
+        let temp = tree(&["web/src", "api", ".cache"], &["README.md"])?;
+        let root = temp.path();
+        assert_eq!(scanned(root, ScanOptions::default())?, vec!["api", "web"]);
+
+        let mut shim = scan_directories(root)?;
+        shim.sort();
+        assert_eq!(relative(root, &shim), vec!["api", "web"]);
+
+        let outcome = scan(root, &ScanOptions::default())?;
+        assert_eq!(
+            outcome.skipped,
+            vec![SkippedEntry {
+                path: root.join(".cache"),
+                reason: SkipReason::Hidden
+            }]
+        );
+        assert!(matches!(
+            scan(&root.join("missing"), &ScanOptions::default()),
+            Err(WorkspaceError::ScanPathNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_max_depth() -> Result<()> {
+        let temp = tree(&["acme/api/src", "acme/web", "solo"], &[])?;
+        let root = temp.path();
+        let depth = |max_depth| {
+            scanned(
+                root,
+                ScanOptions {
+                    max_depth,
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(depth(1)?, vec!["acme", "solo"]);
+        assert_eq!(depth(2)?, vec!["acme", "acme/api", "acme/web", "solo"]);
+        assert_eq!(depth(3)?.len(), 5);
+
+        // Unsorted, parents still come before what is inside them
+        let outcome = scan(
+            root,
+            &ScanOptions {
+                max_depth: 3,
+                ..Default::default()
+            },
+        )?;
+        let order = relative(root, &outcome.directories);
+        let position = |dir: &str| order.iter().position(|d| d == dir).unwrap();
+        assert!(position("acme") < position("acme/api"));
+        assert!(position("acme/api") < position("acme/api/src"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_min_depth() -> Result<()> {
+        let temp = tree(
+            &["acme/api/src", "acme/web/src", "globex/app/src", "solo"],
+            &[],
+        )?;
+        let root = temp.path();
+        let depths = |min_depth, max_depth| {
+            scanned(
+                root,
+                ScanOptions {
+                    min_depth,
+                    max_depth,
+                    ..Default::default()
+                },
+            )
+        };
+        // The client directories are looked inside but only their children are found
+        assert_eq!(depths(2, 2)?, vec!["acme/api", "acme/web", "globex/app"]);
+        assert_eq!(
+            depths(3, 3)?,
+            vec!["acme/api/src", "acme/web/src", "globex/app/src"]
+        );
+        assert_eq!(depths(2, 3)?.len(), 6);
+        // The scan path itself is never found
+        assert_eq!(depths(0, 1)?, depths(1, 1)?);
+        let inverted = ScanOptions {
+            min_depth: 3,
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert!(matches!(
+            scan(root, &inverted),
+            Err(WorkspaceError::InvalidDepth { min: 3, max: 2 })
+        ));
+
+        // Filters see how deep each directory is, shallow ones included
+        #[derive(Debug, Default)]
+        struct Depths(std::sync::Mutex<Vec<(String, usize)>>);
+        impl FolderFilter for Depths {
+            fn include(&self, _dir: &Path, ctx: &ScanContext) -> FilterDecision {
+                let seen = (ctx.name.to_string(), ctx.depth);
+                self.0.lock().unwrap().push(seen);
+                FilterDecision::pass()
+            }
+        }
+        let seen = Arc::new(Depths::default());
+        let opts = ScanOptions {
+            min_depth: 2,
+            max_depth: 2,
+            filters: vec![seen.clone()],
+            ..Default::default()
+        };
+        for dir in scan_iter(root, &opts) {
+            assert_eq!(dir?.depth, 2);
+        }
+        let mut seen = seen.0.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen[0], ("acme".to_string(), 1));
+        assert!(seen.contains(&("app".to_string(), 2)));
+
+        // Excluding a shallow directory leaves out everything inside it
+        let outcome = scan(
+            root,
+            &ScanOptions {
+                min_depth: 2,
+                max_depth: 2,
+                excludes: vec!["globex".into()],
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(outcome.directories.len(), 2);
+        let mut too_shallow: Vec<_> = outcome
+            .skipped
+            .into_iter()
+            .filter(|skipped| skipped.reason == SkipReason::TooShallow)
+            .map(|skipped| skipped.path)
+            .collect();
+        too_shallow.sort();
+        assert_eq!(relative(root, &too_shallow), vec!["acme", "solo"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_hidden_and_patterns() -> Result<()> {
+        let temp = tree(
+            &[".config/nested", "api", "api-old", "web", "build/out"],
+            &[],
+        )?;
+        let root = temp.path();
+
+        let hidden = ScanOptions {
+            include_hidden: true,
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            scanned(root, hidden.clone())?,
+            vec![
+                ".config",
+                ".config/nested",
+                "api",
+                "api-old",
+                "build",
+                "build/out",
+                "web"
+            ]
+        );
+
+        let excludes = ScanOptions {
+            excludes: vec!["*-old".into(), "build".into()],
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert_eq!(scanned(root, excludes.clone())?, vec!["api", "web"]);
+        let outcome = scan(root, &excludes)?;
+        assert!(outcome.skipped.contains(&SkippedEntry {
+            path: root.join("build"),
+            reason: SkipReason::Excluded("build".into())
+        }));
+
+        // Includes select what is kept, but directories that don't match are looked inside
+        let includes = ScanOptions {
+            includes: vec!["a*".into(), "out".into()],
+            max_depth: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            scanned(root, includes.clone())?,
+            vec!["api", "api-old", "build/out"]
+        );
+        assert!(scan(root, &includes)?.skipped.contains(&SkippedEntry {
+            path: root.join("web"),
+            reason: SkipReason::NotIncluded
+        }));
+
+        // Excludes win over includes
+        let both = ScanOptions {
+            excludes: vec!["*-old".into()],
+            ..includes
+        };
+        assert_eq!(scanned(root, both)?, vec!["api", "build/out"]);
+
+        let invalid = ScanOptions {
+            includes: vec!["[".into()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            scan(root, &invalid),
+            Err(WorkspaceError::InvalidPattern { pattern, .. }) if pattern == "["
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_exclude_names() -> Result<()> {
+        let temp = tree(&["api", "api-old", "web", "Build/out", "[x]"], &[])?;
+        let root = temp.path();
+        let names = |names: &[&str], ignore_case: bool| ScanOptions {
+            exclude_names: names.iter().map(|name| name.to_string()).collect(),
+            exclude_names_ignore_case: ignore_case,
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        // Names match whole, with the case given, and aren't patterns
+        let exact = names(&["api", "build", "[x]"], false);
+        assert_eq!(
+            scanned(root, exact.clone())?,
+            vec!["Build", "Build/out", "api-old", "web"]
+        );
+        let ignoring_case = names(&["api", "build"], true);
+        assert_eq!(
+            scanned(root, ignoring_case.clone())?,
+            vec!["[x]", "api-old", "web"]
+        );
+        let outcome = scan(root, &ignoring_case)?;
+        let skipped = SkippedEntry {
+            path: root.join("Build"),
+            reason: SkipReason::ExcludedName("build".into()),
+        };
+        assert!(outcome.skipped.contains(&skipped));
+        assert_eq!(skipped.reason.to_string(), "excluded by name 'build'");
+
+        // Alongside exclude patterns, either leaves a directory out
+        let with_globs = ScanOptions {
+            excludes: vec!["*-old".into()],
+            ..exact.clone()
+        };
+        assert_eq!(
+            scanned(root, with_globs)?,
+            vec!["Build", "Build/out", "web"]
+        );
+
+        // And over includes, names win as patterns do
+        let with_includes = ScanOptions {
+            includes: vec!["a*".into(), "out".into()],
+            ..names(&["api", "out"], false)
+        };
+        assert_eq!(scanned(root, with_includes)?, vec!["api-old"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_regexes() -> Result<()> {
+        let temp = tree(
+            &[
+                "api",
+                "archive-2023-11-05",
+                "clients/acme",
+                "clients/acme-2024-01-02",
+                "web",
+            ],
+            &[],
+        )?;
+        let root = temp.path();
+        let regexes = |excludes: &[&str], includes: &[&str]| ScanOptions {
+            exclude_regexes: excludes.iter().map(|regex| regex.to_string()).collect(),
+            include_regexes: includes.iter().map(|regex| regex.to_string()).collect(),
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        // A date suffix, at any depth
+        let dated = regexes(&[r"-\d{4}-\d{2}-\d{2}$"], &[]);
+        assert_eq!(
+            scanned(root, dated.clone())?,
+            vec!["api", "clients", "clients/acme", "web"]
+        );
+        assert!(scan(root, &dated)?.skipped.contains(&SkippedEntry {
+            path: root.join("archive-2023-11-05"),
+            reason: SkipReason::ExcludedByRegex(r"-\d{4}-\d{2}-\d{2}$".into())
+        }));
+
+        // Matched against the relative path with '/', so anchors see the parents
+        assert_eq!(
+            scanned(root, regexes(&["^clients/"], &[]))?,
+            vec!["api", "archive-2023-11-05", "clients", "web"]
+        );
+        // Directories that match no include regex are still looked inside
+        let clients = regexes(&[], &["^clients/[a-z]+$", "^w"]);
+        assert_eq!(scanned(root, clients.clone())?, vec!["clients/acme", "web"]);
+        assert!(scan(root, &clients)?.skipped.contains(&SkippedEntry {
+            path: root.join("clients"),
+            reason: SkipReason::NotIncludedByRegex
+        }));
+
+        // Glob filters come first: an excluded directory isn't looked inside for the
+        // regexes to match, and an include pattern narrows what they are asked about
+        let with_globs = ScanOptions {
+            excludes: vec!["clients".into()],
+            ..clients.clone()
+        };
+        assert_eq!(scanned(root, with_globs)?, vec!["web"]);
+        let with_includes = ScanOptions {
+            includes: vec!["a*".into()],
+            ..clients
+        };
+        assert_eq!(scanned(root, with_includes)?, vec!["clients/acme"]);
+
+        let invalid = regexes(&["[0-9"], &[]);
+        let err = scan(root, &invalid).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid regex '[0-9'");
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_contains() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_file("/root/infra/main.tf", "");
+        fs.add_file("/root/modules/vpc/vpc.tf", "");
+        fs.add_file("/root/app/src/deploy/main.tf", "");
+        // Hidden directories aren't searched, however deep the search goes
+        fs.add_file("/root/docs/.terraform/cache.tf", "");
+        fs.add_file("/root/docs/readme.md", "");
+        fs.add_file("/root/web/package.json", "");
+        let root = Path::new("/root");
+        let scanned = |opts: &ScanOptions| -> Result<Vec<String>> {
+            let opts = ScanOptions {
+                sort: SortOrder::Name,
+                ..opts.clone()
+            };
+            Ok(relative(root, &scan_with(&fs, root, &opts)?.directories))
+        };
+        let terraform = ScanOptions {
+            contains: vec!["*.tf".into()],
+            ..Default::default()
+        };
+        assert_eq!(scanned(&terraform)?, vec!["infra"]);
+        assert!(scan_with(&fs, root, &terraform)?
+            .skipped
+            .contains(&SkippedEntry {
+                path: root.join("docs"),
+                reason: SkipReason::NoMatchingFile
+            }));
+
+        // Directories without a match are still looked inside
+        let deep = ScanOptions {
+            max_depth: 2,
+            ..terraform.clone()
+        };
+        assert_eq!(scanned(&deep)?, vec!["infra", "modules/vpc"]);
+
+        // Searching deeper finds files only reachable below subdirectories
+        let recursive = |depth| ScanOptions {
+            contains_depth: depth,
+            ..terraform.clone()
+        };
+        assert_eq!(scanned(&recursive(2))?, vec!["infra", "modules"]);
+        assert_eq!(scanned(&recursive(3))?, vec!["app", "infra", "modules"]);
+        assert_eq!(scanned(&recursive(9))?, vec!["app", "infra", "modules"]);
+        let found: Vec<_> = scan_iter_with(&fs, root, &recursive(3)).collect::<Result<_, _>>()?;
+        let app = found.iter().find(|dir| dir.path.ends_with("app")).unwrap();
+        assert_eq!(
+            app.reasons,
+            vec!["contains 'src/deploy/main.tf' matching '*.tf'"]
+        );
+
+        // Any pattern may match
+        let either = ScanOptions {
+            contains: vec!["*.tf".into(), "package.*".into()],
+            ..Default::default()
+        };
+        assert_eq!(scanned(&either)?, vec!["infra", "web"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_markers() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_file("/root/api/Cargo.toml", "");
+        fs.add_file("/root/web/package.json", "");
+        fs.add_file("/root/clients/acme/Cargo.toml", "");
+        fs.add_dir("/root/notes");
+        let root = Path::new("/root");
+        let scanned = |opts: &ScanOptions| -> Result<Vec<String>> {
+            let opts = ScanOptions {
+                sort: SortOrder::Name,
+                ..opts.clone()
+            };
+            Ok(relative(root, &scan_with(&fs, root, &opts)?.directories))
+        };
+        let markers = ScanOptions {
+            markers: vec!["Cargo.toml".into(), "package.json".into()],
+            ..Default::default()
+        };
+        assert_eq!(scanned(&markers)?, vec!["api", "web"]);
+
+        // Directories without a marker are still looked inside
+        let deep = ScanOptions {
+            max_depth: 2,
+            ..markers
+        };
+        assert_eq!(scanned(&deep)?, vec!["api", "clients/acme", "web"]);
+        let skipped = scan_with(&fs, root, &deep)?.skipped;
+        assert!(skipped.contains(&SkippedEntry {
+            path: root.join("notes"),
+            reason: SkipReason::NoMarker
+        }));
+
+        // A directory that can't be listed is skipped, whoever runs the test
+        fs.fail(
+            Operation::ReadDir,
+            "/root/clients",
+            ErrorKind::PermissionDenied,
+        );
+        let outcome = scan_with(&fs, root, &deep)?;
+        assert_eq!(relative(root, &outcome.directories), vec!["api", "web"]);
+        assert!(outcome
+            .skipped
+            .iter()
+            .any(|entry| entry.path == root.join("clients")
+                && matches!(entry.reason, SkipReason::Unreadable(_))));
+        fs.fail(Operation::ReadDir, root, ErrorKind::PermissionDenied);
+        assert!(matches!(
+            scan_with(&fs, root, &deep),
+            Err(WorkspaceError::Io { path, .. }) if path == root
+        ));
+        assert!(matches!(
+            scan_with(&fs, Path::new("/missing"), &deep),
+            Err(WorkspaceError::ScanPathNotFound(_))
+        ));
+        Ok(())
+    }
+
+    /// A [`MemoryFs`] counting the lookups a scan makes.
+    #[derive(Debug, Default)]
+    struct Counting {
+        fs: MemoryFs,
+        lookups: std::sync::Mutex<Vec<&'static str>>,
+    }
+
+    impl Counting {
+        fn count(&self, lookup: &'static str) {
+            self.lookups.lock().unwrap().push(lookup);
+        }
+
+        /// How many times each lookup was made since the last call.
+        fn take(&self) -> std::collections::HashMap<&'static str, usize> {
+            let mut counts = std::collections::HashMap::new();
+            for lookup in self.lookups.lock().unwrap().drain(..) {
+                *counts.entry(lookup).or_default() += 1;
+            }
+            counts
+        }
+    }
+
+    impl FileSystem for Counting {
+        fn read_to_string(&self, path: &Path) -> std::io::Result<String> {
+            self.fs.read_to_string(path)
+        }
+        fn write(&self, path: &Path, content: &[u8]) -> std::io::Result<()> {
+            self.fs.write(path, content)
+        }
+        fn exists(&self, path: &Path) -> bool {
+            self.count("exists");
+            self.fs.exists(path)
+        }
+        fn is_dir(&self, path: &Path) -> bool {
+            self.count("is_dir");
+            self.fs.is_dir(path)
+        }
+        fn is_symlink(&self, path: &Path) -> bool {
+            self.count("is_symlink");
+            self.fs.is_symlink(path)
+        }
+        fn read_dir(&self, path: &Path) -> std::io::Result<Vec<PathBuf>> {
+            self.fs.read_dir(path)
+        }
+        fn read_dir_entries(&self, path: &Path) -> std::io::Result<Vec<DirEntry>> {
+            self.count("read_dir");
+            self.fs.read_dir_entries(path)
+        }
+        fn canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+            self.count("canonicalize");
+            self.fs.canonicalize(path)
+        }
+        fn current_dir(&self) -> std::io::Result<PathBuf> {
+            self.fs.current_dir()
+        }
+    }
+
+    #[test]
+    fn test_scan_looks_up_each_directory_once() -> Result<()> {
+        let fs = Counting::default();
+        for i in 0..20 {
+            fs.fs.add_file(format!("/root/app{}/src/main.rs", i), "");
+            fs.fs.add_file(format!("/root/app{}/Cargo.toml", i), "");
+            fs.fs.add_dir(format!("/root/.hidden{}/inner", i));
+        }
+        let root = Path::new("/root");
+        let opts = ScanOptions {
+            max_depth: 2,
+            markers: vec!["Cargo.toml".into(), "package.json".into(), "go.mod".into()],
+            ..Default::default()
+        };
+        let outcome = scan_with(&fs, root, &opts)?;
+        assert_eq!(outcome.directories.len(), 20);
+
+        // The root, the apps and their sources are each listed once, for the markers
+        // and the scan alike; hidden directories are never listed, and nothing is
+        // looked up one by one but the scan path, resolved once
+        let counts = fs.take();
+        assert_eq!(counts.get("read_dir"), Some(&41));
+        assert_eq!(counts.get("canonicalize"), Some(&1));
+        assert_eq!(counts.len(), 2, "{:?}", counts);
+
+        // A single marker is looked up rather than listing the directory for it
+        let single = ScanOptions {
+            max_depth: 1,
+            markers: vec!["Cargo.toml".into()],
+            ..Default::default()
+        };
+        scan_with(&fs, root, &single)?;
+        let counts = fs.take();
+        assert_eq!(counts.get("read_dir"), Some(&1));
+        assert_eq!(counts.get("exists"), Some(&20));
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_size() -> Result<()> {
+        let temp = TempDir::new()?;
+        let deep = temp.path().join("a").join("b").join("c");
+        fs::create_dir_all(&deep)?;
+        fs::write(temp.path().join("top.bin"), vec![0; 100])?;
+        fs::write(temp.path().join("a").join("b").join("mid.bin"), vec![0; 10])?;
+        fs::write(deep.join("deep.bin"), vec![0; 1000])?;
+
+        assert_eq!(estimate_size(temp.path(), 3, u64::MAX), 110);
+        assert_eq!(estimate_size(temp.path(), 4, u64::MAX), 1110);
+        assert_eq!(estimate_size(temp.path(), 1, u64::MAX), 100);
+        // Adding up stops once past the limit, before looking any deeper
+        assert_eq!(estimate_size(temp.path(), 4, 50), 100);
+        assert_eq!(estimate_size(&temp.path().join("missing"), 3, u64::MAX), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_by_git_activity() -> Result<()> {
+        let temp = TempDir::new()?;
+        let repo = |name: &str, secs: Option<u64>| -> Result<PathBuf> {
+            let dir = temp.path().join(name);
+            fs::create_dir_all(dir.join(".git/refs/heads"))?;
+            fs::write(dir.join(".git/HEAD"), "ref: refs/heads/main\n")?;
+            if let Some(secs) = secs {
+                let head = dir.join(".git/refs/heads/main");
+                fs::write(&head, "0123456789abcdef0123\n")?;
+                let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+                fs::File::options()
+                    .write(true)
+                    .open(&head)?
+                    .set_modified(time)?;
+            }
+            Ok(dir)
+        };
+        let mut dirs = vec![
+            temp.path().join("notes"),
+            repo("old", Some(1_000))?,
+            repo("empty", None)?,
+            repo("new", Some(2_000))?,
+            temp.path().join("docs"),
+        ];
+        sort_by_git_activity(&mut dirs);
+        let names: Vec<_> = dirs.iter().map(|dir| dir.file_name().unwrap()).collect();
+        // A repository without commits sorts with the other directories
+        assert_eq!(names, ["new", "old", "docs", "empty", "notes"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_gitignore() -> Result<()> {
+        let temp = tree(
+            &[
+                "api/target",
+                "api/src",
+                "web/dist",
+                "web/node_modules",
+                "docs",
+                "logs/keep",
+            ],
+            &[],
+        )?;
+        let root = temp.path();
+        fs::write(
+            root.join(".gitignore"),
+            "# build output\ntarget/\n/docs\nlogs/*\n!logs/keep\n",
+        )?;
+        fs::write(root.join("web/.gitignore"), "dist\n")?;
+        let opts = ScanOptions {
+            max_depth: 2,
+            respect_gitignore: true,
+            excludes: vec!["node_modules".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            scanned(root, opts.clone())?,
+            vec!["api", "api/src", "logs", "logs/keep", "web"]
+        );
+        let ignored: Vec<PathBuf> = scan(root, &opts)?
+            .skipped
+            .into_iter()
+            .filter(|entry| entry.reason == SkipReason::Gitignored)
+            .map(|entry| entry.path)
+            .collect();
+        assert_eq!(ignored.len(), 3);
+        assert!(ignored.contains(&root.join("web/dist")));
+
+        // Ignored by default
+        let all = ScanOptions {
+            respect_gitignore: false,
+            ..opts
+        };
+        assert_eq!(scanned(root, all)?.len(), 8);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_symlinks() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let temp = tree(&["projects/api", "shared/lib"], &[])?;
+        let root = temp.path().join("projects");
+        symlink(temp.path().join("shared"), root.join("shared"))?;
+        // A link back up must not make a deep scan loop
+        symlink(&root, root.join("api/loop"))?;
+
+        let opts = ScanOptions {
+            max_depth: 4,
+            ..Default::default()
+        };
+        assert_eq!(
+            scanned(&root, opts.clone())?,
+            vec!["api", "api/loop", "shared", "shared/lib"]
+        );
+
+        let no_links = ScanOptions {
+            follow_symlinks: false,
+            ..opts
+        };
+        assert_eq!(scanned(&root, no_links.clone())?, vec!["api"]);
+        let skipped = scan(&root, &no_links)?.skipped;
+        assert!(skipped
+            .iter()
+            .all(|entry| entry.reason == SkipReason::Symlink));
+        assert_eq!(skipped.len(), 2);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_unreadable() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tree(&["locked/inner", "open/inner"], &[])?;
+        let locked = temp.path().join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))?;
+        let readable = fs::read_dir(&locked).is_ok();
+        let outcome = scan(
+            temp.path(),
+            &ScanOptions {
+                max_depth: 2,
+                sort: SortOrder::Name,
+                ..Default::default()
+            },
+        );
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))?;
+        if readable {
+            // Running as root, permissions don't apply
+            return Ok(());
+        }
+        let outcome = outcome?;
+        assert_eq!(
+            relative(temp.path(), &outcome.directories),
+            vec!["locked", "open", "open/inner"]
+        );
+        assert!(matches!(
+            &outcome.skipped[..],
+            [SkippedEntry {
+                reason: SkipReason::Unreadable(_),
+                ..
+            }]
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_iter_reasons() -> Result<()> {
+        let temp = tree(
+            &["api/src", "web", "docs"],
+            &["api/Cargo.toml", "web/package.json"],
+        )?;
+        let root = temp.path();
+        let opts = ScanOptions {
+            max_depth: 2,
+            includes: vec!["a*".into(), "w*".into(), "src".into()],
+            markers: vec!["Cargo.toml".into(), "package.json".into()],
+            ..Default::default()
+        };
+        let mut found = scan_iter(root, &opts).collect::<Result<Vec<_>, _>>()?;
+        found.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            found,
+            vec![
+                ScannedDir {
+                    path: root.join("api"),
+                    depth: 1,
+                    reasons: vec!["included by 'a*'".into(), "marker 'Cargo.toml'".into()],
+                },
+                ScannedDir {
+                    path: root.join("web"),
+                    depth: 1,
+                    reasons: vec!["included by 'w*'".into(), "marker 'package.json'".into()],
+                },
+            ]
+        );
+
+        // Without filters nothing is recorded, and parents come before their children
+        let opts = ScanOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let found = scan_iter(root, &opts).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(found.len(), 4);
+        assert!(found.iter().all(|dir| dir.reasons.is_empty()));
+        let api = found.iter().position(|dir| dir.path == root.join("api"));
+        let src = found
+            .iter()
+            .position(|dir| dir.path == root.join("api/src"));
+        assert!(api < src);
+        assert_eq!(found[src.unwrap()].depth, 2);
+
+        let missing = root.join("missing");
+        let mut failed = scan_iter(&missing, &opts);
+        assert!(matches!(
+            failed.next(),
+            Some(Err(WorkspaceError::ScanPathNotFound(_)))
+        ));
+        assert!(failed.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_iter_stops_early() -> Result<()> {
+        let dirs: Vec<String> = (0..20).map(|i| format!("dir{i}/inner")).collect();
+        let dirs: Vec<&str> = dirs.iter().map(String::as_str).collect();
+        let temp = tree(&dirs, &[])?;
+        let opts = ScanOptions {
+            max_depth: 3,
+            ..Default::default()
+        };
+        let first = scan_iter(temp.path(), &opts)
+            .take(3)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(first.len(), 3);
+
+        // Dropping a scan part way through closes what it has open; repeating it
+        // many times would run out of file descriptors otherwise
+        for i in 0..2000 {
+            let mut found = scan_iter(temp.path(), &opts);
+            for _ in 0..i % 30 {
+                assert!(found.next().is_some_and(|dir| dir.is_ok()));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_iter_yields_errors() -> Result<()> {
+        let temp = tree(&["first/inner", "second/inner"], &[])?;
+        let root = temp.path();
+        let opts = ScanOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let mut found = scan_iter(root, &opts);
+        let mut seen = vec![];
+        // Both top-level directories are found before either is looked inside;
+        // removing one then makes listing it fail without ending the scan
+        for _ in 0..2 {
+            seen.push(found.next().unwrap()?.path);
+        }
+        fs::remove_dir_all(&seen[0])?;
+        let rest: Vec<_> = found.collect();
+        assert!(matches!(
+            &rest[0],
+            Err(WorkspaceError::Io { path, .. }) if *path == seen[0]
+        ));
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[1].as_ref().unwrap().path, seen[1].join("inner"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_candidate_directories_filters() -> Result<()> {
+        let temp = TempDir::new()?;
+        for dir in ["web", "api", ".cache", "build", "tmp-1"] {
+            fs::create_dir(temp.path().join(dir))?;
+        }
+        fs::write(temp.path().join("api").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join(".cache").join("Cargo.toml"), "")?;
+        let names = |opts: &GenerateOptions| -> Result<Vec<String>> {
+            Ok(candidate_directories(opts)?
+                .iter()
+                .map(|dir| dir.file_name().unwrap().to_string_lossy().into_owned())
+                .collect())
+        };
+
+        let mut opts = GenerateOptions {
+            scan_path: temp.path().to_path_buf(),
+            exclude: vec!["build".into(), "tmp-*".into()],
+            sort: SortOrder::Name,
+            ..Default::default()
+        };
+        assert_eq!(names(&opts)?, vec!["api", "web"]);
+
+        opts.include_hidden = true;
+        assert_eq!(names(&opts)?, vec![".cache", "api", "web"]);
+
+        opts.markers = vec!["Cargo.toml".into()];
+        assert_eq!(names(&opts)?, vec![".cache", "api"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_directories() -> Result<()> {
+        let temp = TempDir::new()?;
+        let dirs = [
+            "globex/tool",
+            "acme/web",
+            "acme/api",
+            "archive/old",
+            ".hidden/x",
+        ];
+        for dir in dirs.into_iter().chain(["eu/initech/app"]) {
+            fs::create_dir_all(temp.path().join(dir))?;
+        }
+        let mut opts = GenerateOptions {
+            scan_path: temp.path().to_path_buf(),
+            exclude: vec!["archive".into()],
+            markers: vec!["Cargo.toml".into()],
+            ..Default::default()
+        };
+        let relative = |dirs: Vec<PathBuf>| -> Vec<String> {
+            dirs.iter()
+                .map(|dir| {
+                    dir.strip_prefix(temp.path())
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect()
+        };
+
+        // Markers are for the folders inside the groups, not for the groups
+        assert_eq!(
+            relative(group_directories(&opts, 1)?),
+            vec!["acme", "eu", "globex"]
+        );
+        opts.include_hidden = true;
+        let nested = relative(group_directories(&opts, 2)?);
+        let expected = vec![
+            ".hidden/x",
+            "acme/api",
+            "acme/web",
+            "eu/initech",
+            "globex/tool",
+        ];
+        assert_eq!(nested, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+//! Checks of workspace documents against the JSON Schema of `.code-workspace` files
+//! vendored in `workspace.schema.json`, so what is written is something VS Code
+//! accepts. Only the keywords that schema uses are implemented: `type`, `enum`,
+//! `required`, `properties`, `additionalProperties`, `items` and `anyOf`, with VS
+//! Code's `errorMessage` for the message of a failed `anyOf`.
+
+use crate::jsonc;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// The vendored schema of workspace files.
+pub const WORKSPACE_SCHEMA: &str = include_str!("workspace.schema.json");
+
+static SCHEMA: OnceLock<Value> = OnceLock::new();
+
+/// A value of a document that doesn't match the schema.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON pointer to the value, e.g. `/folders/0/name`; empty for the whole document
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pointer.as_str() {
+            "" => write!(f, "document: {}", self.message),
+            pointer => write!(f, "{}: {}", pointer, self.message),
+        }
+    }
+}
+
+/// Checks the workspace file `content`, which may have comments, against the schema.
+/// Content that isn't JSON is a single violation of the whole document.
+pub fn validate_document(content: &str) -> Vec<SchemaViolation> {
+    match serde_json::from_str(&jsonc::to_json(content)) {
+        Ok(document) => validate_value(&document),
+        Err(err) => vec![violation("", format!("not JSON: {}", err))],
+    }
+}
+
+/// Checks a parsed workspace document against the schema, returning every violation
+/// in document order.
+pub fn validate_value(document: &Value) -> Vec<SchemaViolation> {
+    let schema = SCHEMA.get_or_init(|| {
+        serde_json::from_str(WORKSPACE_SCHEMA).expect("the vendored schema is JSON")
+    });
+    let mut violations = vec![];
+    check(schema, document, "", &mut violations);
+    violations
+}
+
+fn check(schema: &Value, value: &Value, pointer: &str, violations: &mut Vec<SchemaViolation>) {
+    // `true` and schemas without keywords accept anything
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            types => types.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|expected| has_type(value, expected)) {
+            // The other keywords describe a value of the expected type
+            violations.push(violation(
+                pointer,
+                format!(
+                    "expected {}, found {}",
+                    types.join(" or "),
+                    type_name(value)
+                ),
+            ));
+            return;
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            violations.push(violation(
+                pointer,
+                format!("expected one of {}", allowed.join(", ")),
+            ));
+        }
+    }
+    if let Some(Value::Array(alternatives)) = schema.get("anyOf") {
+        let matches = |alternative: &Value| {
+            let mut found = vec![];
+            check(alternative, value, pointer, &mut found);
+            found.is_empty()
+        };
+        if !alternatives.iter().any(matches) {
+            let message = schema.get("errorMessage").and_then(Value::as_str);
+            violations.push(violation(
+                pointer,
+                message.unwrap_or("matches none of the allowed shapes"),
+            ));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(key) {
+                        violations.push(violation(
+                            pointer,
+                            format!("missing required property '{}'", key),
+                        ));
+                    }
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, value) in object {
+                let pointer = child(pointer, key);
+                match (
+                    properties.and_then(|p| p.get(key)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(property), _) => check(property, value, &pointer, violations),
+                    (None, Some(Value::Bool(false))) => {
+                        violations.push(violation(&pointer, "unknown property"))
+                    }
+                    (None, Some(additional)) => check(additional, value, &pointer, violations),
+                    (None, None) => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item) = schema.get("items") {
+                for (index, value) in items.iter().enumerate() {
+                    check(item, value, &child(pointer, &index.to_string()), violations);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn violation(pointer: &str, message: impl Into<String>) -> SchemaViolation {
+    SchemaViolation {
+        pointer: pointer.to_string(),
+        message: message.into(),
+    }
+}
+
+/// The pointer to `key` of the value at `pointer`, escaped as RFC 6901 asks.
+fn child(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        expected => type_name(value) == expected,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pointers(violations: &[SchemaViolation]) -> Vec<&str> {
+        violations.iter().map(|v| v.pointer.as_str()).collect()
+    }
+
+    #[test]
+    fn test_vendored_schema() {
+        let schema: Value = serde_json::from_str(WORKSPACE_SCHEMA).unwrap();
+        assert_eq!(schema["required"], json!(["folders"]));
+    }
+
+    #[test]
+    fn test_valid_documents() {
+        let documents = [
+            json!({"folders": []}),
+            json!({
+                "folders": [
+                    {"path": ".", "name": "📦 root"},
+                    {"path": "api"},
+                    {"uri": "vscode-remote://ssh-remote+host/srv", "name": "srv"}
+                ],
+                "settings": {"files.exclude": {"**/target": true}},
+                "tasks": {"version": "2.0.0", "tasks": [
+                    {"label": "Build", "type": "shell", "command": "make", "args": ["-j4"]},
+                    {"label": "All", "dependsOn": ["Build"]}
+                ]},
+                "launch": {"version": "0.2.0", "configurations": [], "compounds": [
+                    {"name": "Both", "configurations": ["api", "web"]}
+                ]},
+                "extensions": {"recommendations": ["rust-lang.rust-analyzer"]},
+                "remoteAuthority": "wsl+Ubuntu",
+                "workspaceManager": {"customNames": ["api"]}
+            }),
+        ];
+        for document in documents {
+            assert_eq!(validate_value(&document), vec![], "{}", document);
+        }
+        assert_eq!(
+            validate_document("{\"folders\": [], // a comment\n}"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_invalid_document() {
+        let document = json!({
+            "folders": [
+                {"path": "api", "name": 3},
+                {},
+                {"name": "No path"},
+                "web"
+            ],
+            "settings": [],
+            "tasks": {"tasks": [{"label": "Build", "dependsOn": 1}]},
+            "launch": {"compounds": [{"name": "Both"}]},
+            "extensions": {"recommendations": ["a", null]},
+            "transient": "yes"
+        });
+        let violations = validate_value(&document);
+        assert_eq!(
+            pointers(&violations),
+            vec![
+                "/folders/0/name",
+                "/folders/1",
+                "/folders/2",
+                "/folders/3",
+                "/settings",
+                "/tasks/tasks/0/dependsOn",
+                "/launch/compounds/0",
+                "/extensions/recommendations/1",
+                "/transient",
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "/folders/0/name: expected string, found number"
+        );
+        assert_eq!(violations[1].message, "a folder needs a path or a uri");
+        assert_eq!(
+            violations[6].message,
+            "missing required property 'configurations'"
+        );
+
+        let missing = validate_value(&json!({"settings": {}}));
+        assert_eq!(
+            missing[0].to_string(),
+            "document: missing required property 'folders'"
+        );
+        assert_eq!(
+            validate_value(&json!([]))[0].message,
+            "expected object, found array"
+        );
+        assert_eq!(pointers(&validate_document("not json")), vec![""]);
+    }
+
+    #[test]
+    fn test_pointer_escaping() {
+        let schema = json!({"additionalProperties": false});
+        let mut violations = vec![];
+        check(&schema, &json!({"a/b~c": 1}), "/x", &mut violations);
+        assert_eq!(pointers(&violations), vec!["/x/a~1b~0c"]);
+        assert_eq!(violations[0].message, "unknown property");
+
+        let schema = json!({"type": "integer", "enum": [1, 2]});
+        let mut violations = vec![];
+        check(&schema, &json!(3), "", &mut violations);
+        assert_eq!(violations[0].message, "expected one of 1, 2");
+        violations.clear();
+        check(&schema, &json!(1.5), "", &mut violations);
+        assert_eq!(violations[0].message, "expected integer, found number");
+    }
+}
@@ -0,0 +1,273 @@
+//! The overview printed by `stats`: how many folders a workspace has, of which kinds
+//! of project, how many are missing, and whether it has its managed task. Folders are
+//! probed on several threads at once, and those that don't answer in time, such as
+//! directories on an unreachable network share, are counted rather than waited for.
+//! `export --markdown` lists the same probes per folder.
+
+use crate::folder_tasks::Ecosystem;
+use crate::git;
+use crate::model::WorkspaceFile;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long `stats` waits for the folders to be probed, by default.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many folders are probed at once. Probes mostly wait on the file system, so
+/// this is more than the number of cores.
+const PROBE_THREADS: usize = 16;
+
+/// The summary of a workspace printed by `stats`.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStats {
+    /// All folders, uri folders included
+    pub folders: usize,
+    /// Existing folders per kind of project, e.g. `cargo`; a folder with several
+    /// manifests counts for each
+    pub project_types: BTreeMap<String, usize>,
+    /// Existing folders without a manifest of a known kind of project
+    pub unknown: usize,
+    /// Folders whose directory doesn't exist
+    pub missing: usize,
+    /// Folders given by uri, which aren't probed
+    pub uri_folders: usize,
+    /// Folders whose probe didn't finish in time
+    pub timed_out: usize,
+    /// Whether the workspace has its managed task
+    pub managed_task: bool,
+}
+
+/// What probing the directory of a folder found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderProbe {
+    /// Whether the directory exists
+    pub exists: bool,
+    /// The kinds of project it holds
+    pub ecosystems: Vec<Ecosystem>,
+    /// The branch checked out, or the abbreviated commit when HEAD is detached, when
+    /// the directory is the top of a git worktree
+    pub branch: Option<String>,
+    /// The URL of its `origin` remote, read from the git config
+    pub remote: Option<String>,
+}
+
+fn probe(dir: &Path) -> FolderProbe {
+    let exists = dir.is_dir();
+    if !exists {
+        return FolderProbe {
+            exists,
+            ecosystems: vec![],
+            branch: None,
+            remote: None,
+        };
+    }
+    FolderProbe {
+        exists,
+        ecosystems: Ecosystem::detect_all(dir),
+        branch: git::worktree(dir).map(|worktree| worktree.head),
+        remote: git::origin_url(dir),
+    }
+}
+
+/// Probes the folders of `workspace`, resolved against `workspace_dir`, for at most
+/// `timeout` in all. One entry per folder, `None` for uri folders and for those whose
+/// probe didn't finish in time.
+pub fn probe_folders(
+    workspace: &WorkspaceFile,
+    workspace_dir: &Path,
+    timeout: Duration,
+) -> Vec<Option<FolderProbe>> {
+    let (indices, dirs): (Vec<usize>, Vec<PathBuf>) = workspace
+        .folders
+        .iter()
+        .enumerate()
+        .filter_map(|(index, folder)| Some((index, folder.resolve(workspace_dir)?)))
+        .unzip();
+    let mut probes = vec![None; workspace.folders.len()];
+    for (index, result) in indices
+        .into_iter()
+        .zip(in_parallel_with_timeout(dirs, timeout, probe))
+    {
+        probes[index] = result;
+    }
+    probes
+}
+
+/// Summarizes `workspace`, probing its folders, resolved against `workspace_dir`,
+/// for at most `timeout` in all.
+pub fn workspace_stats(
+    workspace: &WorkspaceFile,
+    workspace_dir: &Path,
+    timeout: Duration,
+) -> WorkspaceStats {
+    let mut stats = WorkspaceStats {
+        folders: workspace.folders.len(),
+        managed_task: workspace.update_task().is_some(),
+        ..Default::default()
+    };
+    let probes = probe_folders(workspace, workspace_dir, timeout);
+    for (folder, result) in workspace.folders.iter().zip(probes) {
+        match result {
+            None if folder.resolve(workspace_dir).is_none() => stats.uri_folders += 1,
+            None => stats.timed_out += 1,
+            Some(probe) if !probe.exists => stats.missing += 1,
+            Some(probe) if probe.ecosystems.is_empty() => stats.unknown += 1,
+            Some(probe) => {
+                for ecosystem in probe.ecosystems {
+                    *stats
+                        .project_types
+                        .entry(ecosystem.name().to_string())
+                        .or_default() += 1;
+                }
+            }
+        }
+    }
+    stats
+}
+
+/// `check` for each of `dirs` on several threads, in the order of `dirs`; `None` for
+/// those not checked within `timeout`. The threads aren't waited for, so a check
+/// stuck on a hung file system can't hold up the caller.
+fn in_parallel_with_timeout<T: Send + 'static>(
+    dirs: Vec<PathBuf>,
+    timeout: Duration,
+    check: fn(&Path) -> T,
+) -> Vec<Option<T>> {
+    let deadline = Instant::now() + timeout;
+    let workers = PROBE_THREADS.min(dirs.len());
+    let dirs = Arc::new(dirs);
+    let next = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..workers {
+        let (dirs, next, sender) = (Arc::clone(&dirs), Arc::clone(&next), sender.clone());
+        thread::spawn(move || loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(dir) = dirs.get(index) else {
+                break;
+            };
+            // The receiver is gone once the time is up
+            if sender.send((index, check(dir))).is_err() {
+                break;
+            }
+        });
+    }
+    drop(sender);
+
+    let mut results: Vec<Option<T>> = dirs.iter().map(|_| None).collect();
+    let mut pending = dirs.len();
+    while pending > 0 {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(left) {
+            Ok((index, result)) => {
+                results[index] = Some(result);
+                pending -= 1;
+            }
+            // Out of time, or every worker has stopped
+            Err(_) => break,
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::parse_workspace;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_workspace_stats() -> Result<()> {
+        let temp = TempDir::new()?;
+        for dir in ["api", "cli", "web", "docs"] {
+            fs::create_dir(temp.path().join(dir))?;
+        }
+        fs::write(temp.path().join("api").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("cli").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("web").join("package.json"), "{}")?;
+        fs::write(temp.path().join("web").join("pyproject.toml"), "")?;
+        let workspace = parse_workspace(
+            r#"{"folders": [
+                {"path": "api"}, {"path": "cli"}, {"path": "web"}, {"path": "docs"},
+                {"path": "gone"},
+                {"uri": "vscode-remote://ssh-remote+host/srv"}
+            ]}"#,
+        )?;
+
+        let stats = workspace_stats(&workspace, temp.path(), PROBE_TIMEOUT);
+        let expected = WorkspaceStats {
+            folders: 6,
+            project_types: BTreeMap::from([
+                ("cargo".to_string(), 2),
+                ("npm".to_string(), 1),
+                ("python".to_string(), 1),
+            ]),
+            unknown: 1,
+            missing: 1,
+            uri_folders: 1,
+            timed_out: 0,
+            managed_task: false,
+        };
+        assert_eq!(stats, expected);
+        assert_eq!(
+            serde_json::to_value(&stats)?["projectTypes"]["cargo"],
+            serde_json::json!(2)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_probe_folders() -> Result<()> {
+        let temp = TempDir::new()?;
+        fs::create_dir_all(temp.path().join("api").join(".git"))?;
+        fs::write(temp.path().join("api/.git/HEAD"), "ref: refs/heads/main\n")?;
+        fs::write(
+            temp.path().join("api/.git/config"),
+            "[remote \"origin\"]\n\turl = git@example.com:org/api.git\n",
+        )?;
+        fs::write(temp.path().join("api").join("Cargo.toml"), "")?;
+        fs::create_dir(temp.path().join("docs"))?;
+        let workspace = parse_workspace(
+            r#"{"folders": [
+                {"uri": "vscode-remote://ssh-remote+host/srv"},
+                {"path": "api"}, {"path": "docs"}, {"path": "gone"}
+            ]}"#,
+        )?;
+
+        let probes = probe_folders(&workspace, temp.path(), PROBE_TIMEOUT);
+        assert_eq!(probes.len(), 4);
+        assert_eq!(probes[0], None);
+        let api = probes[1].as_ref().unwrap();
+        assert_eq!(api.ecosystems, [Ecosystem::Cargo]);
+        assert_eq!(api.branch.as_deref(), Some("main"));
+        assert_eq!(api.remote.as_deref(), Some("git@example.com:org/api.git"));
+        let docs = probes[2].as_ref().unwrap();
+        assert!(docs.exists && docs.branch.is_none() && docs.remote.is_none());
+        assert!(!probes[3].as_ref().unwrap().exists);
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_parallel_with_timeout() {
+        fn slow(dir: &Path) -> bool {
+            if dir.ends_with("slow") {
+                thread::sleep(Duration::from_secs(5));
+            }
+            true
+        }
+        let dirs: Vec<PathBuf> = ["a", "slow", "b", "c"].iter().map(PathBuf::from).collect();
+        let started = Instant::now();
+        let results = in_parallel_with_timeout(dirs, Duration::from_millis(300), slow);
+        assert!(started.elapsed() < Duration::from_secs(4));
+        // The others are done by whichever workers aren't stuck
+        assert_eq!(results, [Some(true), None, Some(true), Some(true)]);
+        assert!(in_parallel_with_timeout(vec![], PROBE_TIMEOUT, slow).is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//! Sublime Text project files written next to a workspace by `--emit sublime`. They
+//! list the same folders as the workspace; everything else in an existing project file,
+//! such as `settings` and `build_systems`, is kept as it is.
+
+use crate::{jsonc, normalize_folder_path, write_atomic, WorkspaceFile};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SublimeProject {
+    #[serde(default)]
+    pub folders: Vec<SublimeFolder>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct SublimeFolder {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // folder_exclude_patterns, follow_symlinks and the like
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The project file written for the workspace file at `workspace_path`.
+pub fn project_path(workspace_path: &Path) -> PathBuf {
+    workspace_path.with_extension("sublime-project")
+}
+
+/// The project for the folders of `workspace`, keeping the rest of `existing`.
+/// Folders already in `existing` keep their own keys. Sublime Text can't open uri
+/// folders, so they are left out.
+pub fn sublime_project(
+    workspace: &WorkspaceFile,
+    existing: Option<SublimeProject>,
+) -> SublimeProject {
+    let mut project = existing.unwrap_or_default();
+    let previous = std::mem::take(&mut project.folders);
+    for folder in &workspace.folders {
+        if folder.uri.is_some() || folder.path.is_empty() {
+            continue;
+        }
+        let path = normalize_folder_path(&folder.path);
+        let other = previous
+            .iter()
+            .find(|existing| normalize_folder_path(&existing.path) == path)
+            .map(|existing| existing.other.clone())
+            .unwrap_or_default();
+        project.folders.push(SublimeFolder {
+            path: folder.path.clone(),
+            name: (!folder.name.is_empty()).then(|| folder.name.clone()),
+            other,
+        });
+    }
+    project
+}
+
+/// Writes the project file of the workspace file at `workspace_path` unless it already
+/// has this content. Returns the path of the project file and whether it was written.
+/// Folder paths stay valid because both files live in the same directory.
+pub fn write_project(workspace_path: &Path, workspace: &WorkspaceFile) -> Result<(PathBuf, bool)> {
+    let path = project_path(workspace_path);
+    let existing = match fs::read_to_string(&path) {
+        // Project files allow comments and trailing commas too
+        Ok(content) => Some(
+            serde_json::from_str(&jsonc::to_json(&content))
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+        ),
+        Err(_) => None,
+    };
+    let content = serde_json::to_string_pretty(&sublime_project(workspace, existing))?;
+    if fs::read(&path).is_ok_and(|existing| existing == content.as_bytes()) {
+        return Ok((path, false));
+    }
+    write_atomic(&path, content.as_bytes())?;
+    Ok((path, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_workspace, WorkspaceFolder};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_project_keeps_other_keys() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let workspace_path = temp.path().join("team.code-workspace");
+        let mut workspace = parse_workspace(
+            r#"{"folders": [
+                {"path": ".", "name": "🏗️ team"},
+                {"path": "api", "name": "svc-api"},
+                {"uri": "vscode-remote://ssh-remote+box/srv", "name": "remote"}
+            ]}"#,
+        )?;
+        fs::write(
+            project_path(&workspace_path),
+            r#"{
+                // hand-written
+                "folders": [{"path": "./api", "folder_exclude_patterns": ["target"]}],
+                "settings": {"tab_size": 4},
+                "build_systems": [{"name": "make", "shell_cmd": "make"}],
+            }"#,
+        )?;
+
+        let (path, written) = write_project(&workspace_path, &workspace)?;
+        assert!(written);
+        assert_eq!(path, temp.path().join("team.sublime-project"));
+        let project: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        assert_eq!(
+            project["folders"],
+            serde_json::json!([
+                {"path": ".", "name": "🏗️ team"},
+                {"path": "api", "name": "svc-api", "folder_exclude_patterns": ["target"]}
+            ])
+        );
+        assert_eq!(project["settings"]["tab_size"], 4);
+        assert_eq!(project["build_systems"][0]["name"], "make");
+        assert!(!write_project(&workspace_path, &workspace)?.1);
+
+        // Folders follow the workspace
+        workspace.folders.remove(1);
+        workspace
+            .folders
+            .push(WorkspaceFolder::new("web", "📦 web"));
+        write_project(&workspace_path, &workspace)?;
+        let project: SublimeProject = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        let paths: Vec<&str> = project.folders.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec![".", "web"]);
+        assert!(project.folders[1].other.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,590 @@
+//! The Update Workspace task that regenerates a workspace from inside VS Code, kept
+//! alongside any tasks the user wrote.
+
+use crate::cli::GenerateArgs;
+use crate::model::{Task, TaskGroup, TaskPresentation, TaskRunOptions, Tasks, WorkspaceFile};
+use crate::options::{value_name, GenerateOptions, TaskCommand, TaskPanel, TaskReveal, TaskType};
+use crate::{quote, validate};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// `existing` tasks, or an empty `2.0.0` list, with `new_task` appended in place of any
+/// task with its label and of earlier managed tasks.
+pub fn merge_tasks(existing: Option<Tasks>, new_task: Task) -> Tasks {
+    let mut tasks = existing.unwrap_or_else(|| Tasks {
+        version: "2.0.0".to_string(),
+        ..Default::default()
+    });
+
+    // Remove any existing task with the managed label, and earlier copies of the
+    // managed task that ended up under another label
+    tasks
+        .tasks
+        .retain(|task| task.label != new_task.label && !runs_workspace_manager(task));
+
+    // Add the new update workspace task
+    tasks.tasks.push(new_task);
+
+    tasks
+}
+
+/// Label of the task that re-runs this tool from inside VS Code.
+pub const UPDATE_TASK_LABEL: &str = "Update Workspace";
+
+/// Command of the managed task with `--task-command portable`.
+pub const PORTABLE_COMMAND: &str = "workspace-manager";
+
+/// Whether `task` was written by this tool: a process task whose command is the
+/// running binary or any executable named `workspace-manager`, wherever it was
+/// installed, and whose arguments generate a workspace. Tasks running another
+/// subcommand, such as a `doctor` task of the user's, tasks that only mention the tool
+/// in their arguments, and shell tasks, whose command line may do anything, don't
+/// count.
+pub fn runs_workspace_manager(task: &Task) -> bool {
+    if task.task_type != "process" {
+        return false;
+    }
+    let command = Path::new(&task.command);
+    let is_tool = env::current_exe().is_ok_and(|exe| exe == command)
+        || command
+            .file_stem()
+            .is_some_and(|stem| stem == PORTABLE_COMMAND);
+    is_tool && GenerateArgs::from_task_args(&task.args).is_ok()
+}
+
+/// The command the managed task runs for `kind`.
+pub fn task_command(kind: TaskCommand) -> String {
+    match kind {
+        TaskCommand::Absolute => env::current_exe()
+            .unwrap_or_else(|_| PathBuf::from(PORTABLE_COMMAND))
+            .to_string_lossy()
+            .to_string(),
+        TaskCommand::Portable => PORTABLE_COMMAND.to_string(),
+    }
+}
+
+/// The managed task, labelled `label`, that re-runs this tool with `task_args`.
+///
+/// It runs quietly in a panel of its own and has an empty problem matcher, so
+/// VS Code neither steals focus nor asks which matcher to use.
+pub fn create_update_task(label: &str, task_args: &[String]) -> Task {
+    Task {
+        label: label.to_string(),
+        task_type: "process".to_string(),
+        command: task_command(TaskCommand::default()),
+        args: task_args.to_vec(),
+        presentation: Some(TaskPresentation {
+            reveal: Some(value_name(&TaskReveal::default())),
+            panel: Some(value_name(&TaskPanel::default())),
+            ..Default::default()
+        }),
+        problem_matcher: Some(serde_json::Value::Array(vec![])),
+        ..Default::default()
+    }
+}
+
+/// The managed task with the presentation and group chosen in `opts`.
+pub fn managed_task(opts: &GenerateOptions) -> Task {
+    let mut task = create_update_task(opts.task_label(), &opts.task_args);
+    task.command = task_command(opts.task_command);
+    if opts.task_type == TaskType::Shell {
+        // A single command line, quoted here, so further commands can be chained
+        let mut line = quote::Dialect::native().command_line(&task.command, &task.args);
+        if let Some(extra) = opts.task_shell_command.as_deref().filter(|c| !c.is_empty()) {
+            line = format!("{} && {}", line, extra);
+        }
+        task.task_type = "shell".to_string();
+        task.command = line;
+        task.args = vec![];
+    }
+    task.presentation = Some(TaskPresentation {
+        reveal: Some(value_name(&opts.task_reveal)),
+        panel: Some(value_name(&opts.task_panel)),
+        ..Default::default()
+    });
+    task.group = opts
+        .task_group
+        .map(|kind| TaskGroup::Kind(value_name(&kind)));
+    if opts.task_run_on_open {
+        task.run_options = Some(TaskRunOptions {
+            run_on: Some("folderOpen".to_string()),
+            ..Default::default()
+        });
+    }
+    task
+}
+
+/// Removes the managed task labelled `label`, leaving other tasks alone. The
+/// tasks section is dropped when nothing else is left in it.
+pub fn remove_update_task(tasks: Option<Tasks>, label: &str) -> Option<Tasks> {
+    let mut tasks = tasks?;
+    tasks.tasks.retain(|task| task.label != label);
+    (!tasks.tasks.is_empty() || !tasks.other.is_empty()).then_some(tasks)
+}
+
+/// Removes the managed task of `workspace`, or the task labelled `label`, and
+/// forgets what the metadata section recorded about it. Returns the removed task.
+pub fn strip_managed_task(workspace: &mut WorkspaceFile, label: Option<&str>) -> Option<Task> {
+    let label = label.map_or_else(|| workspace.managed_task_label(), str::to_string);
+    let removed = workspace
+        .tasks
+        .as_ref()?
+        .tasks
+        .iter()
+        .find(|task| task.label == label)
+        .cloned();
+    workspace.tasks = remove_update_task(workspace.tasks.take(), &label);
+    if label == workspace.managed_task_label() {
+        let mut metadata = workspace.metadata();
+        metadata.task_label = None;
+        metadata.task_args = None;
+        workspace.set_metadata(metadata);
+    }
+    removed
+}
+
+/// The tasks of a new workspace: only the managed task for `opts`.
+pub fn create_workspace_task(opts: &GenerateOptions) -> Tasks {
+    merge_tasks(None, managed_task(opts))
+}
+
+/// Writes the managed task into `workspace`, replacing the previous one even when
+/// its label changed, and records the label in the metadata section. Tasks that
+/// merely share a label with a previous managed task are left alone.
+pub fn update_managed_task(workspace: &mut WorkspaceFile, opts: &GenerateOptions) {
+    let label = opts.task_label();
+    let mut metadata = workspace.metadata();
+    let mut tasks = workspace.tasks.take();
+    if let Some(previous) = metadata
+        .task_label
+        .as_deref()
+        .filter(|previous| *previous != label)
+    {
+        tasks = remove_update_task(tasks, previous);
+    }
+    workspace.tasks = Some(merge_tasks(tasks, managed_task(opts)));
+
+    metadata.task_label = (label != UPDATE_TASK_LABEL).then(|| label.to_string());
+    metadata.task_args = (opts.task_type == TaskType::Shell).then(|| opts.task_args.clone());
+    workspace.set_metadata(metadata);
+}
+
+/// Points the managed task of `workspace` at the command for `kind` when the binary
+/// it runs no longer exists, keeping its arguments and everything else about it.
+/// Returns the command it ran before.
+pub fn repair_task_command(workspace: &mut WorkspaceFile, kind: TaskCommand) -> Option<String> {
+    let stale = validate::stale_task_command(workspace)?.to_string();
+    let label = workspace.managed_task_label();
+    let task = workspace
+        .tasks
+        .as_mut()?
+        .tasks
+        .iter_mut()
+        .find(|task| task.label == label)?;
+    task.command = task_command(kind);
+    Some(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{parse_workspace, read_workspace, write_workspace_if_changed};
+    use crate::model::METADATA_KEY;
+    use crate::options::TaskGroupKind;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_remove_update_task_keeps_other_tasks() {
+        let build = Task {
+            label: "Build".to_string(),
+            task_type: "shell".to_string(),
+            command: "make".to_string(),
+            args: vec![],
+            ..Default::default()
+        };
+        let tasks = merge_tasks(
+            Some(Tasks {
+                version: "2.0.0".to_string(),
+                tasks: vec![build.clone()],
+                ..Default::default()
+            }),
+            create_update_task(UPDATE_TASK_LABEL, &[]),
+        );
+        assert_eq!(tasks.tasks.len(), 2);
+
+        let remaining = remove_update_task(Some(tasks), UPDATE_TASK_LABEL).unwrap();
+        assert_eq!(remaining.tasks, vec![build]);
+        let only_managed = create_workspace_task(&GenerateOptions::default());
+        assert_eq!(
+            remove_update_task(Some(only_managed), UPDATE_TASK_LABEL),
+            None
+        );
+        assert_eq!(remove_update_task(None, UPDATE_TASK_LABEL), None);
+    }
+
+    #[test]
+    fn test_custom_task_label_preserves_user_task() {
+        let user_task = Task {
+            label: UPDATE_TASK_LABEL.to_string(),
+            task_type: "shell".to_string(),
+            command: "./scripts/update.sh".to_string(),
+            args: vec![],
+            ..Default::default()
+        };
+        let mut workspace = WorkspaceFile {
+            tasks: Some(Tasks {
+                version: "2.0.0".to_string(),
+                tasks: vec![user_task.clone()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut opts = GenerateOptions {
+            task_label: Some("Refresh Folders".to_string()),
+            ..Default::default()
+        };
+
+        update_managed_task(&mut workspace, &opts);
+        let labels = |ws: &WorkspaceFile| -> Vec<String> {
+            ws.tasks
+                .iter()
+                .flat_map(|t| &t.tasks)
+                .map(|t| t.label.clone())
+                .collect()
+        };
+        assert_eq!(
+            labels(&workspace),
+            vec![UPDATE_TASK_LABEL, "Refresh Folders"]
+        );
+        assert_eq!(workspace.tasks.as_ref().unwrap().tasks[0], user_task);
+        assert_eq!(workspace.managed_task_label(), "Refresh Folders");
+        assert_eq!(workspace.update_task().unwrap().label, "Refresh Folders");
+
+        // Renaming the managed task replaces the old one instead of orphaning it
+        opts.task_label = Some("Sync Folders".to_string());
+        update_managed_task(&mut workspace, &opts);
+        assert_eq!(labels(&workspace), vec![UPDATE_TASK_LABEL, "Sync Folders"]);
+        assert_eq!(workspace.tasks.as_ref().unwrap().tasks[0], user_task);
+    }
+
+    #[test]
+    fn test_update_task_keeps_fields_of_other_tasks() -> Result<()> {
+        let mut workspace = parse_workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {
+                    "label": "Build",
+                    "type": "shell",
+                    "command": "cargo",
+                    "args": ["build"],
+                    "group": {"kind": "build", "isDefault": true},
+                    "problemMatcher": ["$rustc"],
+                    "presentation": {"reveal": "always", "panel": "shared"}
+                },
+                {"label": "All", "dependsOn": ["Build"], "dependsOrder": "sequence"},
+                {"label": "Update Workspace", "type": "process", "command": "old", "args": []}
+            ]}}"#,
+        )?;
+        let before = serde_json::to_value(&workspace.tasks.as_ref().unwrap().tasks[0])?;
+
+        update_managed_task(&mut workspace, &GenerateOptions::default());
+        let tasks = &workspace.tasks.as_ref().unwrap().tasks;
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(
+            serde_json::to_value(&tasks[1])?,
+            serde_json::json!({"label": "All", "dependsOn": ["Build"], "dependsOrder": "sequence"})
+        );
+        let after = serde_json::to_value(&tasks[0])?;
+        assert_eq!(after, before);
+        assert_eq!(after["group"]["isDefault"], true);
+        assert_eq!(after["problemMatcher"], serde_json::json!(["$rustc"]));
+        assert_eq!(after["presentation"]["panel"], "shared");
+        Ok(())
+    }
+
+    #[test]
+    fn test_managed_task_presentation() -> Result<()> {
+        let task = serde_json::to_value(managed_task(&GenerateOptions::default()))?;
+        assert_eq!(
+            task["presentation"],
+            serde_json::json!({"reveal": "silent", "panel": "dedicated"})
+        );
+        assert_eq!(task["problemMatcher"], serde_json::json!([]));
+        assert!(task.get("group").is_none());
+        assert!(Path::new(task["command"].as_str().unwrap()).is_absolute());
+
+        let opts = GenerateOptions {
+            task_reveal: TaskReveal::Never,
+            task_panel: TaskPanel::Shared,
+            task_group: Some(TaskGroupKind::Build),
+            task_command: TaskCommand::Portable,
+            ..Default::default()
+        };
+        let task = serde_json::to_value(managed_task(&opts))?;
+        assert_eq!(task["command"], PORTABLE_COMMAND);
+        assert_eq!(task["presentation"]["reveal"], "never");
+        assert_eq!(task["presentation"]["panel"], "shared");
+        assert_eq!(task["group"], "build");
+        Ok(())
+    }
+
+    #[test]
+    fn test_task_run_on_open() -> Result<()> {
+        let temp = TempDir::new()?;
+        let path = temp.path().join("test.code-workspace");
+        let mut opts = GenerateOptions {
+            task_run_on_open: true,
+            ..Default::default()
+        };
+        let mut workspace = WorkspaceFile::default();
+        update_managed_task(&mut workspace, &opts);
+        assert!(write_workspace_if_changed(&path, &workspace)?);
+
+        let written = read_workspace(&path)?;
+        assert_eq!(written, workspace);
+        let task = serde_json::to_value(written.update_task().unwrap())?;
+        assert_eq!(
+            task["runOptions"],
+            serde_json::json!({"runOn": "folderOpen"})
+        );
+
+        // The task regenerating the file on open must not rewrite it when nothing changed
+        let mut regenerated = written.clone();
+        update_managed_task(&mut regenerated, &opts);
+        assert!(!write_workspace_if_changed(&path, &regenerated)?);
+
+        opts.task_run_on_open = false;
+        update_managed_task(&mut regenerated, &opts);
+        assert!(regenerated.update_task().unwrap().run_options.is_none());
+        assert!(write_workspace_if_changed(&path, &regenerated)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shell_task_replaces_process_task() {
+        let mut opts = GenerateOptions {
+            task_command: TaskCommand::Portable,
+            task_args: vec!["--name".to_string(), "my ws".to_string()],
+            ..Default::default()
+        };
+        let mut workspace = WorkspaceFile::default();
+        update_managed_task(&mut workspace, &opts);
+        let task = workspace.update_task().unwrap();
+        assert_eq!(task.task_type, "process");
+        assert_eq!(task.args, vec!["--name", "my ws"]);
+
+        opts.task_type = TaskType::Shell;
+        opts.task_shell_command = Some("git add *.code-workspace".to_string());
+        update_managed_task(&mut workspace, &opts);
+        let tasks = &workspace.tasks.as_ref().unwrap().tasks;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_type, "shell");
+        assert!(tasks[0].args.is_empty());
+        let quoted = quote::Dialect::native().quote("my ws");
+        assert_eq!(
+            tasks[0].command,
+            format!(
+                "workspace-manager --name {} && git add *.code-workspace",
+                quoted
+            )
+        );
+        assert_eq!(
+            workspace.generation_args().unwrap(),
+            vec!["--name", "my ws"]
+        );
+
+        opts.task_type = TaskType::Process;
+        update_managed_task(&mut workspace, &opts);
+        let tasks = &workspace.tasks.as_ref().unwrap().tasks;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_type, "process");
+        assert_eq!(tasks[0].command, PORTABLE_COMMAND);
+        assert_eq!(workspace.metadata().task_args, None);
+    }
+
+    #[test]
+    fn test_strip_managed_task() -> Result<()> {
+        let mut workspace = parse_workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Build", "type": "shell", "command": "make"},
+                {"label": "Refresh", "type": "process", "command": "wm"}
+            ]}, "workspaceManager": {"taskLabel": "Refresh"}}"#,
+        )?;
+        let removed = strip_managed_task(&mut workspace, None).unwrap();
+        assert_eq!(removed.label, "Refresh");
+        assert_eq!(workspace.tasks.as_ref().unwrap().tasks.len(), 1);
+        assert!(workspace.other.get(METADATA_KEY).is_none());
+        assert!(strip_managed_task(&mut workspace, None).is_none());
+
+        // The section goes once nothing is left in it, unless it has other keys
+        assert_eq!(
+            strip_managed_task(&mut workspace, Some("Build"))
+                .unwrap()
+                .label,
+            "Build"
+        );
+        assert!(workspace.tasks.is_none());
+        let mut workspace = parse_workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "inputs": [], "tasks": [
+                {"label": "Update Workspace", "type": "process", "command": "wm"}
+            ]}}"#,
+        )?;
+        assert!(strip_managed_task(&mut workspace, None).is_some());
+        assert!(workspace.tasks.as_ref().unwrap().tasks.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_task_keeps_tasks_section_keys() -> Result<()> {
+        let mut workspace = parse_workspace(
+            r#"{"folders": [], "tasks": {
+                "version": "2.0.0-beta",
+                "options": {"cwd": "${workspaceFolder}"},
+                "tasks": [],
+                "inputs": [{"id": "target", "type": "promptString", "description": "Target"}]
+            }}"#,
+        )?;
+
+        update_managed_task(&mut workspace, &GenerateOptions::default());
+        let tasks = serde_json::to_value(workspace.tasks.as_ref().unwrap())?;
+        assert_eq!(tasks["version"], "2.0.0-beta");
+        assert_eq!(tasks["options"]["cwd"], "${workspaceFolder}");
+        assert_eq!(tasks["inputs"][0]["id"], "target");
+        assert_eq!(tasks["tasks"][0]["label"], UPDATE_TASK_LABEL);
+
+        // Without the managed task the section survives for the sake of its inputs
+        let remaining = remove_update_task(workspace.tasks.take(), UPDATE_TASK_LABEL).unwrap();
+        assert!(remaining.tasks.is_empty());
+        assert!(remaining.other.contains_key("inputs"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_tasks() {
+        let existing = Tasks {
+            version: "2.0.0".to_string(),
+            tasks: vec![Task {
+                label: "Existing Task".to_string(),
+                task_type: "shell".to_string(),
+                command: "echo".to_string(),
+                args: vec!["hello".to_string()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let new_task = Task {
+            label: "Update Workspace".to_string(),
+            task_type: "process".to_string(),
+            command: "workspace-manager".to_string(),
+            args: vec![],
+            ..Default::default()
+        };
+
+        let merged = merge_tasks(Some(existing), new_task);
+
+        assert_eq!(merged.tasks.len(), 2);
+        assert!(merged.tasks.iter().any(|t| t.label == "Existing Task"));
+        assert!(merged.tasks.iter().any(|t| t.label == "Update Workspace"));
+    }
+
+    #[test]
+    fn test_merge_tasks_collapses_copies_of_the_managed_task() -> Result<()> {
+        let existing = parse_workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Refresh", "type": "process", "command": "/home/me/.cargo/bin/workspace-manager", "args": ["--path", "."]},
+                {"label": "Update Workspace", "type": "process", "command": "workspace-manager", "args": []},
+                {"label": "Windows", "type": "process", "command": "workspace-manager.exe"},
+                {"label": "Doctor", "type": "process", "command": "workspace-manager", "args": ["doctor"]},
+                {"label": "Inventory", "type": "process", "command": "workspace-manager", "args": ["export", "--markdown"]},
+                {"label": "Help", "type": "process", "command": "man", "args": ["workspace-manager"]},
+                {"label": "Chained", "type": "shell", "command": "workspace-manager && git add -A"},
+                {"label": "Fork", "type": "process", "command": "/usr/bin/workspace-manager-fork"},
+                {"label": "Build", "type": "shell", "command": "cargo", "args": ["build"]}
+            ]}}"#,
+        )?
+        .tasks;
+        let current = Task {
+            label: "Elsewhere".to_string(),
+            task_type: "process".to_string(),
+            command: env::current_exe()?.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        let mut existing = existing.unwrap();
+        existing.tasks.push(current);
+
+        let merged = merge_tasks(Some(existing), create_update_task(UPDATE_TASK_LABEL, &[]));
+        let labels: Vec<&str> = merged
+            .tasks
+            .iter()
+            .map(|task| task.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Doctor",
+                "Inventory",
+                "Help",
+                "Chained",
+                "Fork",
+                "Build",
+                UPDATE_TASK_LABEL
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_task_lookup() -> Result<()> {
+        let workspace = parse_workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Build", "type": "shell", "command": "make", "args": []},
+                {"label": "Update Workspace", "type": "process", "command": "wm", "args": ["--path", "."]}
+            ]}}"#,
+        )?;
+        assert_eq!(workspace.update_task().unwrap().args, vec!["--path", "."]);
+        assert!(WorkspaceFile::default().update_task().is_none());
+        Ok(())
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_repair_task_command() -> Result<()> {
+        let mut workspace = parse_workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Deploy", "type": "process", "command": "/nonexistent/deploy"},
+                {"label": "Update Workspace", "type": "process",
+                 "command": "/nonexistent/bin/workspace-manager", "args": ["--name", "team"]}
+            ]}}"#,
+        )?;
+        let stale = repair_task_command(&mut workspace, TaskCommand::Portable);
+        assert_eq!(stale.as_deref(), Some("/nonexistent/bin/workspace-manager"));
+        let task = workspace.update_task().unwrap();
+        assert_eq!(task.command, PORTABLE_COMMAND);
+        assert_eq!(task.args, vec!["--name", "team"]);
+        assert_eq!(
+            workspace.tasks.as_ref().unwrap().tasks[0].command,
+            "/nonexistent/deploy"
+        );
+        // Once repaired there is nothing left to do
+        assert_eq!(
+            repair_task_command(&mut workspace, TaskCommand::Portable),
+            None
+        );
+
+        // A user task under the managed label is left alone
+        let mut workspace = parse_workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Update Workspace", "type": "process", "command": "/nonexistent/deploy"}
+            ]}}"#,
+        )?;
+        assert_eq!(
+            repair_task_command(&mut workspace, TaskCommand::Absolute),
+            None
+        );
+        assert_eq!(
+            workspace.update_task().unwrap().command,
+            "/nonexistent/deploy"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+//! Checks behind the `doctor` subcommand. Each check is independent and returns
+//! the findings it produced, so callers can run any subset.
+
+use crate::model::folder_key;
+use crate::schema;
+use crate::tasks::runs_workspace_manager;
+use crate::{
+    duplicate_folders, nested_folders, normalize_folder_path, parse_workspace, WorkspaceFile,
+    WorkspaceFolder,
+};
+use serde::Serialize;
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A single problem found in a workspace file.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Short, stable identifier of the check, e.g. `missing-path`
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(severity: Severity, code: &'static str, message: impl Into<String>) -> Self {
+        Finding {
+            severity,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Whether any finding is an error.
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.severity == Severity::Error)
+}
+
+/// Parses the file content (JSONC is accepted).
+pub fn check_parse(content: &str) -> Result<WorkspaceFile, Finding> {
+    parse_workspace(content)
+        .map_err(|err| Finding::new(Severity::Error, "parse-error", err.to_string()))
+}
+
+fn describe(index: usize, folder: &WorkspaceFolder) -> String {
+    match folder.name.as_str() {
+        "" => format!("folder #{}", index + 1),
+        name => format!("folder #{} '{}'", index + 1, name),
+    }
+}
+
+/// Every folder must have exactly one of `path` or a `scheme://` style `uri`.
+pub fn check_folder_entries(workspace: &WorkspaceFile) -> Vec<Finding> {
+    let mut findings = vec![];
+    for (index, folder) in workspace.folders.iter().enumerate() {
+        let problem = match (folder.path.is_empty(), &folder.uri) {
+            (true, None) => Some("has neither a path nor a uri".to_string()),
+            (false, Some(_)) => Some("has both a path and a uri".to_string()),
+            (true, Some(uri)) if !uri.contains("://") => {
+                Some(format!("has an invalid uri '{}'", uri))
+            }
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            findings.push(Finding::new(
+                Severity::Error,
+                "invalid-folder",
+                format!("{} {}", describe(index, folder), problem),
+            ));
+        }
+        if folder.name.is_empty() {
+            findings.push(Finding::new(
+                Severity::Info,
+                "unnamed-folder",
+                format!("{} has no display name", describe(index, folder)),
+            ));
+        }
+    }
+    findings
+}
+
+/// Folder paths should exist, relative paths being resolved against `workspace_dir`.
+pub fn check_paths_exist(workspace: &WorkspaceFile, workspace_dir: &Path) -> Vec<Finding> {
+    workspace
+        .folders
+        .iter()
+        .enumerate()
+        .filter_map(|(index, folder)| {
+            let path = folder.resolve(workspace_dir)?;
+            (!path.is_dir()).then(|| {
+                Finding::new(
+                    Severity::Warning,
+                    "missing-path",
+                    format!(
+                        "{} points to '{}', which does not exist",
+                        describe(index, folder),
+                        folder.path
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+/// The same folder should appear only once, however its path is written, and folders
+/// should not be nested inside each other. The `.` root folder contains everything by
+/// design and is exempt. Nesting is reported once for folders listed several times.
+pub fn check_duplicates(workspace: &WorkspaceFile) -> Vec<Finding> {
+    let folders = &workspace.folders;
+    let duplicates = duplicate_folders(folders);
+    let mut findings = vec![];
+    for group in &duplicates {
+        let spellings: Vec<String> = group
+            .iter()
+            .map(|index| format!("'{}'", folders[*index].path))
+            .collect();
+        findings.push(Finding::new(
+            Severity::Error,
+            "duplicate-path",
+            format!(
+                "'{}' is listed {} times, as {}; --fix-overlaps merges them",
+                folder_key(&folders[group[0]]),
+                group.len(),
+                spellings.join(", ")
+            ),
+        ));
+    }
+
+    let repeated = |index: usize| duplicates.iter().any(|group| group[1..].contains(&index));
+    for pair in nested_folders(folders, Path::new(""), |_| None) {
+        if repeated(pair.outer) || repeated(pair.inner) {
+            continue;
+        }
+        findings.push(Finding::new(
+            Severity::Warning,
+            "overlapping-paths",
+            format!(
+                "'{}' is nested inside '{}'",
+                normalize_folder_path(&folders[pair.inner].path),
+                normalize_folder_path(&folders[pair.outer].path)
+            ),
+        ));
+    }
+    findings
+}
+
+/// Looks up `command` the way a shell would: paths are checked directly, bare
+/// names are searched on `PATH`.
+pub fn find_executable(command: &str) -> Option<PathBuf> {
+    let path = Path::new(command);
+    if path.components().count() > 1 {
+        return is_executable(path).then(|| path.to_path_buf());
+    }
+    let search = env::var_os("PATH")?;
+    env::split_paths(&search).find_map(|dir| {
+        let candidate = dir.join(command);
+        if is_executable(&candidate) {
+            return Some(candidate);
+        }
+        // Windows launchers such as VS Code's `code.cmd` are found by extension
+        let extensions: &[&str] = if cfg!(windows) {
+            &["exe", "cmd", "bat"]
+        } else {
+            &[]
+        };
+        extensions
+            .iter()
+            .map(|extension| dir.join(format!("{}.{}", command, extension)))
+            .find(|candidate| is_executable(candidate))
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The Update Workspace task's command should point at an existing executable.
+/// Shell tasks run a whole command line, which is left to the shell.
+pub fn check_managed_task(workspace: &WorkspaceFile) -> Vec<Finding> {
+    workspace
+        .update_task()
+        .into_iter()
+        .filter(|task| task.task_type != "shell" && find_executable(&task.command).is_none())
+        .map(|task| {
+            Finding::new(
+                Severity::Error,
+                "task-command-missing",
+                format!(
+                    "the '{}' task runs '{}', which is not an executable (regenerate or run \
+                     doctor --fix to point it at this binary, or use --task-command \
+                     portable to rely on PATH)",
+                    task.label, task.command
+                ),
+            )
+        })
+        .collect()
+}
+
+/// The command of the managed task when it is an absolute path that no longer
+/// exists or isn't executable, as happens when the binary it was generated with moved
+/// or was reinstalled. Only a task with the recorded label that runs this tool counts,
+/// so a user task that happens to share the label is never taken for it.
+pub fn stale_task_command(workspace: &WorkspaceFile) -> Option<&str> {
+    let task = workspace
+        .update_task()
+        .filter(|task| runs_workspace_manager(task))?;
+    let command = &task.command;
+    (Path::new(command).is_absolute() && find_executable(command).is_none())
+        .then_some(command.as_str())
+}
+
+/// VS Code only understands version 2.0.0 of the tasks format.
+pub fn check_tasks_version(workspace: &WorkspaceFile) -> Vec<Finding> {
+    match &workspace.tasks {
+        Some(tasks) if tasks.version != "2.0.0" => vec![Finding::new(
+            Severity::Warning,
+            "tasks-version",
+            format!("tasks version is '{}', expected '2.0.0'", tasks.version),
+        )],
+        _ => vec![],
+    }
+}
+
+/// The content must match the VS Code workspace file schema; each violation is named
+/// by the JSON pointer of the offending value.
+pub fn check_schema(content: &str) -> Vec<Finding> {
+    schema::validate_document(content)
+        .into_iter()
+        .map(|violation| Finding::new(Severity::Error, "schema", violation.to_string()))
+        .collect()
+}
+
+/// Runs every check against the content of a workspace file in `workspace_dir`.
+pub fn diagnose(content: &str, workspace_dir: &Path) -> Vec<Finding> {
+    let workspace = match check_parse(content) {
+        Ok(workspace) => workspace,
+        Err(finding) => return vec![finding],
+    };
+
+    let mut findings = check_schema(content);
+    findings.extend(check_folder_entries(&workspace));
+    findings.extend(check_paths_exist(&workspace, workspace_dir));
+    findings.extend(check_duplicates(&workspace));
+    findings.extend(check_managed_task(&workspace));
+    findings.extend(check_tasks_version(&workspace));
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn workspace(json: &str) -> WorkspaceFile {
+        parse_workspace(json).unwrap()
+    }
+
+    fn codes(findings: &[Finding]) -> Vec<&'static str> {
+        findings.iter().map(|f| f.code).collect()
+    }
+
+    #[test]
+    fn test_check_parse() {
+        assert!(check_parse(
+            r#"{"folders": [], // ok
+        }"#
+        )
+        .is_ok());
+        let finding = check_parse(r#"{"folders": [}"#).unwrap_err();
+        assert_eq!(finding.code, "parse-error");
+        assert_eq!(finding.severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_check_folder_entries() {
+        let ws = workspace(
+            r#"{"folders": [
+                {"path": "ok", "name": "Ok"},
+                {"name": "Empty"},
+                {"path": "p", "uri": "file:///x", "name": "Both"},
+                {"uri": "not-a-uri", "name": "Bad"},
+                {"path": "unnamed"}
+            ]}"#,
+        );
+        let findings = check_folder_entries(&ws);
+        assert_eq!(
+            codes(&findings),
+            vec![
+                "invalid-folder",
+                "invalid-folder",
+                "invalid-folder",
+                "unnamed-folder"
+            ]
+        );
+        assert!(findings[0].message.contains("'Empty'"));
+    }
+
+    #[test]
+    fn test_check_paths_exist() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        fs::create_dir(temp.path().join("here"))?;
+        let ws = workspace(
+            r#"{"folders": [{"path": "here"}, {"path": "gone"}, {"uri": "file:///gone"}]}"#,
+        );
+        let findings = check_paths_exist(&ws, temp.path());
+        assert_eq!(codes(&findings), vec!["missing-path"]);
+        assert!(findings[0].message.contains("gone"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_duplicates() {
+        let ws = workspace(
+            r#"{"folders": [
+                {"path": "."}, {"path": "services"}, {"path": "./services/"},
+                {"path": "services/api"}, {"path": "servicesx"}
+            ]}"#,
+        );
+        let findings = check_duplicates(&ws);
+        assert_eq!(
+            codes(&findings),
+            vec!["duplicate-path", "overlapping-paths"]
+        );
+        assert!(findings[0]
+            .message
+            .starts_with("'services' is listed 2 times, as 'services', './services/'"));
+        assert!(findings[1]
+            .message
+            .contains("'services/api' is nested inside 'services'"));
+    }
+
+    #[test]
+    fn test_check_managed_task() {
+        let ws = workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Update Workspace", "type": "process", "command": "/nonexistent/workspace-manager", "args": []},
+                {"label": "Other", "type": "process", "command": "/nonexistent/other", "args": []}
+            ]}}"#,
+        );
+        assert_eq!(
+            codes(&check_managed_task(&ws)),
+            vec!["task-command-missing"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_task_command() {
+        let ws = workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Update Workspace", "type": "process", "command": "/nonexistent/workspace-manager"}
+            ]}}"#,
+        );
+        assert_eq!(
+            stale_task_command(&ws),
+            Some("/nonexistent/workspace-manager")
+        );
+        // Bare names are looked up on PATH when the task runs, not here
+        let ws = workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Update Workspace", "type": "process", "command": "workspace-manager"}
+            ]}}"#,
+        );
+        assert_eq!(stale_task_command(&ws), None);
+        // Nor is a task of the user's that shares the label
+        let ws = workspace(
+            r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": [
+                {"label": "Update Workspace", "type": "process", "command": "/nonexistent/deploy"}
+            ]}}"#,
+        );
+        assert_eq!(stale_task_command(&ws), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_executable() {
+        assert!(find_executable("sh").is_some());
+        assert!(find_executable("/bin/sh").is_some());
+        assert!(find_executable("definitely-not-a-command-here").is_none());
+    }
+
+    #[test]
+    fn test_check_tasks_version() {
+        let ws = workspace(r#"{"folders": [], "tasks": {"version": "0.1.0", "tasks": []}}"#);
+        assert_eq!(codes(&check_tasks_version(&ws)), vec!["tasks-version"]);
+        let ws = workspace(r#"{"folders": [], "tasks": {"version": "2.0.0", "tasks": []}}"#);
+        assert!(check_tasks_version(&ws).is_empty());
+    }
+
+    #[test]
+    fn test_diagnose() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let findings = diagnose(
+            r#"{"folders": [{"path": "gone", "name": "Gone"}]}"#,
+            temp.path(),
+        );
+        assert_eq!(codes(&findings), vec!["missing-path"]);
+        assert!(!has_errors(&findings));
+
+        let findings = diagnose(r#"{"folders": [], "remoteAuthority": 22}"#, temp.path());
+        assert_eq!(codes(&findings), vec!["schema"]);
+        assert_eq!(
+            findings[0].message,
+            "/remoteAuthority: expected string, found number"
+        );
+        assert!(has_errors(&findings));
+
+        let findings = diagnose("not json", temp.path());
+        assert_eq!(codes(&findings), vec!["parse-error"]);
+        assert!(has_errors(&findings));
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+//! Watch mode: regenerate the workspace when directories appear or disappear.
+
+use crate::console;
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// How often the loop wakes up to check for Ctrl-C and pending changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Whether `event` may change the folder set. Access events and anything caused by
+/// writing `workspace_file` (including its temporary file) are ignored, so our own
+/// writes don't trigger another regeneration.
+pub fn is_relevant_event(event: &Event, workspace_file: &Path) -> bool {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+    let file_name = workspace_file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_prefix = format!(".{}.", file_name);
+
+    event.paths.is_empty()
+        || event.paths.iter().any(|path| {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            name != file_name && !name.starts_with(&temp_prefix)
+        })
+}
+
+/// Watches the direct children of `scan_path` and calls `regenerate` once events
+/// have been quiet for `debounce`. Returns when `stop` is set or when `scan_path`
+/// disappears.
+pub fn watch(
+    scan_path: &Path,
+    workspace_file: &Path,
+    debounce: Duration,
+    stop: &AtomicBool,
+    mut regenerate: impl FnMut() -> Result<()>,
+) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher
+        .watch(scan_path, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {}", scan_path.display()))?;
+
+    let mut pending_since: Option<Instant> = None;
+    while !stop.load(Ordering::SeqCst) {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) if is_relevant_event(&event, workspace_file) => {
+                pending_since = Some(Instant::now());
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(err)) => console::warning(format_args!("watch error: {}", err)),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        if pending_since.is_some_and(|since| since.elapsed() >= debounce) {
+            pending_since = None;
+            if !scan_path.is_dir() {
+                anyhow::bail!("Scan path {} was removed, stopping", scan_path.display());
+            }
+            if let Err(err) = regenerate() {
+                console::warning(format_args!("regeneration failed: {:#}", err));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+    use std::path::PathBuf;
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_is_relevant_event() {
+        let file = Path::new("/work/ws.code-workspace");
+        let create = EventKind::Create(CreateKind::Folder);
+
+        assert!(is_relevant_event(&event(create, "/work/api"), file));
+        assert!(!is_relevant_event(
+            &event(
+                EventKind::Modify(ModifyKind::Any),
+                "/work/ws.code-workspace"
+            ),
+            file
+        ));
+        assert!(!is_relevant_event(
+            &event(create, "/work/.ws.code-workspace.1234.tmp"),
+            file
+        ));
+        assert!(!is_relevant_event(
+            &event(EventKind::Access(AccessKind::Any), "/work/api"),
+            file
+        ));
+    }
+}
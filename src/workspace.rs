@@ -0,0 +1,2574 @@
+//! Assembling a workspace from a scan: the folders with their names, merged with an
+//! existing file, and the settings, tasks and other sections generation keeps in sync.
+
+use crate::cache::cached_directories;
+use crate::console;
+use crate::error::{Result, WorkspaceError};
+use crate::filesystem::{FileSystem, RealFs};
+use crate::filter::FolderFilter;
+use crate::folders::merge_duplicate_folders;
+use crate::io::read_workspace_with;
+use crate::manifest::{read_manifest_with, ManifestEntry};
+use crate::model::{
+    folder_key, nested_folders, normalize_folder_path, ChangeReport, Extensions, FolderMetadata,
+    Launch, MergeStrategy, WorkspaceFile, WorkspaceFolder, METADATA_KEY,
+};
+use crate::naming::{default_namer, folder_name, name_prefix, FolderNamer, NamingContext};
+use crate::options::{
+    CollapseNested, GenerateOptions, GitStatusMode, ReconcileMode, SettingsMerge, SortOrder,
+    WorktreeMode,
+};
+use crate::scan::{
+    absorbed_folders, create_workspace_folder, dirty_repositories, estimate_size, filtered_scan,
+    member_folders, sort_by_git_activity, too_few_folders, SIZE_PROBE_DEPTH,
+};
+use crate::tasks::{repair_task_command, strip_managed_task, task_command, update_managed_task};
+use crate::{folder_tasks, git, jsonc};
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Applies `--unset` and then `--set` to the settings of `workspace`, dropping the
+/// section when it ends up empty and did not exist before.
+pub fn apply_settings(workspace: &mut WorkspaceFile, opts: &GenerateOptions) {
+    if opts.settings.is_empty() && opts.unset.is_empty() {
+        return;
+    }
+    let existed = workspace.settings.is_some();
+    let settings = workspace.settings.get_or_insert_with(serde_json::Map::new);
+    for key in &opts.unset {
+        settings.shift_remove(key);
+    }
+    for (key, value) in &opts.settings {
+        settings.insert(key.clone(), value.clone());
+    }
+    if settings.is_empty() && !existed {
+        workspace.settings = None;
+    }
+}
+
+/// Reconciles the `files.exclude` entries that hide scanned folders inside the `.`
+/// root folder, so they only appear as roots of their own. Entries written earlier
+/// are recorded in the metadata section and replaced; keys the user wrote are never
+/// changed. With `enabled` false, or without a `.` folder, the entries are removed.
+pub fn sync_hidden_folders(workspace: &mut WorkspaceFile, enabled: bool) {
+    let mut metadata = workspace.metadata();
+    let has_root = workspace
+        .folders
+        .iter()
+        .any(|folder| folder.uri.is_none() && normalize_folder_path(&folder.path) == ".");
+    let nested: Vec<String> = workspace
+        .folders
+        .iter()
+        .filter(|folder| folder.uri.is_none() && !folder.path.is_empty())
+        .map(|folder| normalize_folder_path(&folder.path))
+        .filter(|path| path != "." && !path.starts_with("../") && !Path::new(path).is_absolute())
+        .collect();
+    if metadata.hidden_paths.is_empty() && !(enabled && has_root && !nested.is_empty()) {
+        return;
+    }
+
+    let settings = workspace.settings.get_or_insert_with(serde_json::Map::new);
+    let entry = settings
+        .entry("files.exclude")
+        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+    // Not an object, so not something this tool wrote; leave it be
+    let Some(excludes) = entry.as_object_mut() else {
+        return;
+    };
+    for path in metadata.hidden_paths.drain(..) {
+        excludes.shift_remove(&path);
+    }
+    if enabled && has_root {
+        for path in nested {
+            if !excludes.contains_key(&path) {
+                excludes.insert(path.clone(), serde_json::Value::Bool(true));
+                metadata.hidden_paths.push(path);
+            }
+        }
+    }
+    if excludes.is_empty() {
+        settings.shift_remove("files.exclude");
+    }
+    if settings.is_empty() {
+        workspace.settings = None;
+    }
+    workspace.set_metadata(metadata);
+}
+
+/// Appends ` (branch)` to the display name of each folder that is a git checkout with
+/// `--show-branch`, and [`DIRTY_MARKER`] to those with uncommitted changes with
+/// `--git-status annotate`. Folders are resolved against `workspace_dir`. The suffixes
+/// written earlier are recorded in the metadata section, so a stale one is replaced
+/// even on a name the user chose, and removed when its option is turned off.
+pub fn sync_name_suffixes(
+    workspace: &mut WorkspaceFile,
+    workspace_dir: &Path,
+    opts: &GenerateOptions,
+) {
+    let mut metadata = workspace.metadata();
+    let annotate = opts.git_status == Some(GitStatusMode::Annotate);
+    let recorded = !metadata.branches.is_empty() || !metadata.dirty_paths.is_empty();
+    if !recorded && !opts.show_branch && !annotate {
+        return;
+    }
+    let branches = std::mem::take(&mut metadata.branches);
+    let dirty_paths = std::mem::take(&mut metadata.dirty_paths);
+    let dirty = match annotate {
+        true => {
+            let dirs: Vec<PathBuf> = workspace
+                .folders
+                .iter()
+                .filter_map(|folder| folder.resolve(workspace_dir))
+                .collect();
+            dirty_repositories(&dirs, opts.verbose)
+        }
+        false => vec![],
+    };
+    let marker = format!(" {}", DIRTY_MARKER);
+    for folder in workspace.folders.iter_mut() {
+        let Some(dir) = folder.resolve(workspace_dir) else {
+            continue;
+        };
+        let path = normalize_folder_path(&folder.path);
+        // Outermost first: the marker follows the branch
+        if dirty_paths.contains(&path) {
+            if let Some(name) = folder.name.strip_suffix(&marker) {
+                folder.name = name.to_string();
+            }
+        }
+        if let Some(branch) = branches.get(&path) {
+            if let Some(name) = folder.name.strip_suffix(&format!(" ({})", branch)) {
+                folder.name = name.to_string();
+            }
+        }
+        if opts.show_branch {
+            if let Some(worktree) = git::worktree(&dir) {
+                folder.name = format!("{} ({})", folder.name, worktree.head);
+                metadata.branches.insert(path.clone(), worktree.head);
+            }
+        }
+        if dirty.contains(&dir) {
+            folder.name.push_str(&marker);
+            metadata.dirty_paths.push(path);
+        }
+    }
+    workspace.set_metadata(metadata);
+}
+
+/// Reconciles the `rust-analyzer.linkedProjects` entries pointing at the Cargo.toml
+/// of each folder (other than `.`) that has one, resolved against `workspace_dir`.
+/// Entries written earlier are recorded in the metadata section and dropped when
+/// their folder is gone; entries the user added are kept. With `enabled` false only
+/// the recorded entries are removed.
+pub fn sync_rust_analyzer_links(
+    workspace: &mut WorkspaceFile,
+    workspace_dir: &Path,
+    enabled: bool,
+) {
+    const KEY: &str = "rust-analyzer.linkedProjects";
+    let mut metadata = workspace.metadata();
+    let wanted: Vec<String> = match enabled {
+        true => workspace
+            .folders
+            .iter()
+            .filter(|folder| normalize_folder_path(&folder.path) != ".")
+            .filter(|folder| {
+                folder
+                    .resolve(workspace_dir)
+                    .is_some_and(|dir| dir.join("Cargo.toml").is_file())
+            })
+            .map(|folder| format!("{}/Cargo.toml", normalize_folder_path(&folder.path)))
+            .collect(),
+        false => vec![],
+    };
+    if metadata.linked_projects.is_empty() && wanted.is_empty() {
+        return;
+    }
+
+    let settings = workspace.settings.get_or_insert_with(serde_json::Map::new);
+    let entry = settings
+        .entry(KEY)
+        .or_insert_with(|| serde_json::Value::Array(vec![]));
+    // Not a list, so not something this tool wrote; leave it be
+    let Some(projects) = entry.as_array_mut() else {
+        return;
+    };
+    projects.retain(|project| {
+        !project
+            .as_str()
+            .is_some_and(|project| metadata.linked_projects.iter().any(|own| own == project))
+    });
+    metadata.linked_projects.clear();
+    for project in wanted {
+        if !projects
+            .iter()
+            .any(|existing| existing.as_str() == Some(project.as_str()))
+        {
+            projects.push(serde_json::Value::String(project.clone()));
+            metadata.linked_projects.push(project);
+        }
+    }
+    if projects.is_empty() {
+        settings.shift_remove(KEY);
+    }
+    if settings.is_empty() {
+        workspace.settings = None;
+    }
+    workspace.set_metadata(metadata);
+}
+
+/// Reconciles the extension recommendations for the kinds of project found in the
+/// folders of `workspace`, resolved against `workspace_dir`. Recommendations added
+/// earlier are recorded in the metadata section and dropped when no folder needs them
+/// any more; those the user added are kept and not duplicated.
+pub fn sync_extension_recommendations(
+    workspace: &mut WorkspaceFile,
+    workspace_dir: &Path,
+    opts: &GenerateOptions,
+) {
+    let mut metadata = workspace.metadata();
+    let mut wanted: Vec<String> = vec![];
+    if opts.recommend_extensions {
+        for folder in &workspace.folders {
+            let Some(dir) = folder.resolve(workspace_dir) else {
+                continue;
+            };
+            for ecosystem in folder_tasks::Ecosystem::detect_all(&dir) {
+                let extensions = match opts.extension_map.get(ecosystem.name()) {
+                    Some(configured) => configured.clone(),
+                    None => ecosystem
+                        .default_extensions()
+                        .iter()
+                        .map(|extension| extension.to_string())
+                        .collect(),
+                };
+                for extension in extensions {
+                    if !wanted.contains(&extension) {
+                        wanted.push(extension);
+                    }
+                }
+            }
+        }
+    }
+    if metadata.recommended_extensions.is_empty() && wanted.is_empty() {
+        return;
+    }
+
+    let extensions = workspace.extensions.get_or_insert_with(Extensions::default);
+    extensions
+        .recommendations
+        .retain(|extension| !metadata.recommended_extensions.contains(extension));
+    metadata.recommended_extensions.clear();
+    for extension in wanted {
+        // Extension ids are case-insensitive
+        let present = extensions
+            .recommendations
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(&extension));
+        if !present {
+            extensions.recommendations.push(extension.clone());
+            metadata.recommended_extensions.push(extension);
+        }
+    }
+    if *extensions == Extensions::default() {
+        workspace.extensions = None;
+    }
+    workspace.set_metadata(metadata);
+}
+
+/// The settings `--exclude-heavy-dirs` writes globs to.
+const HEAVY_DIR_SETTINGS: [&str; 2] = ["search.exclude", "files.watcherExclude"];
+
+/// Reconciles the `search.exclude` and `files.watcherExclude` globs of the build and
+/// dependency directories of the kinds of project found in the folders of
+/// `workspace`, resolved against `workspace_dir`, such as `**/target/**` for cargo.
+/// Globs written earlier are recorded in the metadata section per setting and removed
+/// when no folder needs them any more; keys the user wrote are never changed. With
+/// `enabled` false, the globs are removed.
+pub fn sync_heavy_dir_excludes(workspace: &mut WorkspaceFile, workspace_dir: &Path, enabled: bool) {
+    let mut metadata = workspace.metadata();
+    let mut wanted: Vec<String> = vec![];
+    if enabled {
+        for folder in &workspace.folders {
+            let Some(dir) = folder.resolve(workspace_dir) else {
+                continue;
+            };
+            for ecosystem in folder_tasks::Ecosystem::detect_all(&dir) {
+                for name in ecosystem.heavy_dirs() {
+                    let glob = format!("**/{}/**", name);
+                    if !wanted.contains(&glob) {
+                        wanted.push(glob);
+                    }
+                }
+            }
+        }
+    }
+    if metadata.excluded_globs.is_empty() && wanted.is_empty() {
+        return;
+    }
+
+    let settings = workspace.settings.get_or_insert_with(serde_json::Map::new);
+    for setting in HEAVY_DIR_SETTINGS {
+        let mut written = metadata.excluded_globs.remove(setting).unwrap_or_default();
+        let entry = settings
+            .entry(setting)
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+        // Not an object, so not something this tool wrote; leave it be
+        let Some(excludes) = entry.as_object_mut() else {
+            continue;
+        };
+        for glob in written.drain(..) {
+            excludes.shift_remove(&glob);
+        }
+        for glob in &wanted {
+            if !excludes.contains_key(glob) {
+                excludes.insert(glob.clone(), serde_json::Value::Bool(true));
+                written.push(glob.clone());
+            }
+        }
+        if excludes.is_empty() {
+            settings.shift_remove(setting);
+        }
+        if !written.is_empty() {
+            metadata.excluded_globs.insert(setting.to_string(), written);
+        }
+    }
+    if settings.is_empty() {
+        workspace.settings = None;
+    }
+    workspace.set_metadata(metadata);
+}
+
+/// Records the `origin` URL of each folder that is a git checkout, resolved against
+/// `workspace_dir`, as `remote` under its normalized path in the `folders` map of the
+/// metadata section, where `restore` finds it. URLs are read from the repository's
+/// config, without running git. A recorded URL is replaced when the remote changes
+/// and removed when the checkout loses its origin or the folder leaves the workspace;
+/// a folder whose directory is missing keeps its URL. With `enabled` false, every
+/// recorded URL is removed. Other keys of a folder's entry are kept.
+pub fn sync_recorded_remotes(workspace: &mut WorkspaceFile, workspace_dir: &Path, enabled: bool) {
+    let mut metadata = workspace.metadata();
+    let recorded = metadata
+        .folders
+        .values()
+        .any(|folder| folder.remote.is_some());
+    if !enabled && !recorded {
+        return;
+    }
+    let mut remotes: BTreeMap<String, Option<String>> = BTreeMap::new();
+    if enabled {
+        for folder in &workspace.folders {
+            let Some(dir) = folder.resolve(workspace_dir) else {
+                continue;
+            };
+            let path = normalize_folder_path(&folder.path);
+            let remote = match dir.is_dir() {
+                true => git::origin_url(&dir),
+                false => metadata
+                    .folders
+                    .get(&path)
+                    .and_then(|folder| folder.remote.clone()),
+            };
+            remotes.insert(path, remote);
+        }
+    }
+
+    for (path, folder) in metadata.folders.iter_mut() {
+        folder.remote = remotes.remove(path).flatten();
+    }
+    for (path, remote) in remotes {
+        if let Some(remote) = remote {
+            metadata.folders.insert(
+                path,
+                FolderMetadata {
+                    remote: Some(remote),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+    metadata
+        .folders
+        .retain(|_, folder| folder.remote.is_some() || !folder.other.is_empty());
+    workspace.set_metadata(metadata);
+}
+
+/// Merges the configurations and compounds of the launch.json-style file at
+/// `template` into the launch section of `workspace`, replacing same-named ones.
+pub fn apply_launch_template(
+    fs: &dyn FileSystem,
+    workspace: &mut WorkspaceFile,
+    template: &Path,
+) -> Result<()> {
+    let content = fs
+        .read_to_string(template)
+        .map_err(WorkspaceError::io(template))?;
+    let launch: Launch = serde_json::from_str(&jsonc::to_json(&content))
+        .with_context(|| format!("Invalid launch template '{}'", template.display()))?;
+    workspace
+        .launch
+        .get_or_insert_with(Launch::default)
+        .merge(&launch, true);
+    Ok(())
+}
+
+/// The workspace file at `path` that new workspace files start from, without its
+/// folders, managed task or bookkeeping, which generation fills in itself.
+fn read_template(fs: &dyn FileSystem, path: &Path) -> Result<WorkspaceFile> {
+    let mut template = read_workspace_with(fs, path)
+        .with_context(|| format!("Invalid workspace template '{}'", path.display()))?;
+    template.folders.clear();
+    strip_managed_task(&mut template, None);
+    template.other.remove(METADATA_KEY);
+    Ok(template)
+}
+
+/// Merges the settings section of the workspace file at `settings_from` and the
+/// extensions section of the one at `extensions_from` into `workspace`, combining
+/// settings both have as `strategy` says. Only those sections are read, leniently,
+/// so the rest of a file doesn't have to be a valid workspace; a file without the
+/// section is warned about. Neither file is written to.
+pub(crate) fn copy_sections(
+    fs: &dyn FileSystem,
+    workspace: &mut WorkspaceFile,
+    settings_from: Option<&Path>,
+    extensions_from: Option<&Path>,
+    strategy: SettingsMerge,
+) -> Result<()> {
+    let mut imported = WorkspaceFile::default();
+    if let Some(path) = settings_from {
+        imported.settings = read_section(fs, path, "settings")?;
+    }
+    if let Some(path) = extensions_from {
+        imported.extensions = read_section(fs, path, "extensions")?;
+    }
+    let other = match strategy {
+        SettingsMerge::Theirs => MergeStrategy::Theirs,
+        _ => MergeStrategy::Ours,
+    };
+    workspace.merge(&imported, other, strategy)?;
+    Ok(())
+}
+
+/// The `key` section of the workspace file at `path`, or `None` with a warning when
+/// the file has none.
+fn read_section<T: serde::de::DeserializeOwned>(
+    fs: &dyn FileSystem,
+    path: &Path,
+    key: &str,
+) -> Result<Option<T>> {
+    let content = fs.read_to_string(path).map_err(WorkspaceError::io(path))?;
+    let invalid = || format!("Invalid workspace file '{}'", path.display());
+    let mut file: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&jsonc::to_json(&content)).with_context(invalid)?;
+    let Some(section) = file.remove(key) else {
+        console::warning(format_args!(
+            "'{}' has no {} section to copy",
+            path.display(),
+            key
+        ));
+        return Ok(None);
+    };
+    Ok(Some(serde_json::from_value(section).with_context(
+        || format!("Invalid {} section in '{}'", key, path.display()),
+    )?))
+}
+
+/// Suffix of the display names of folders with uncommitted changes.
+pub const DIRTY_MARKER: &str = "●";
+
+/// The workspace for `opts`, for a workspace file in `base_dir`. Folder paths are
+/// relative to `base_dir`, or to [`GenerateOptions::relative_to`] when set, and the
+/// `.` folder is `base_dir` itself. The file of that
+/// name in `base_dir` is regenerated when there is one, and the report says what
+/// changed compared to it. Fails without generating anything when that file isn't a
+/// valid workspace.
+pub fn create_workspace(
+    opts: &GenerateOptions,
+    base_dir: &Path,
+) -> Result<(WorkspaceFile, ChangeReport)> {
+    let existing = existing_workspace(&RealFs, &base_dir.join(opts.workspace_filename()))?;
+    let changes_from = existing.clone();
+    let namers = [default_namer(opts)];
+    let workspace = generate_workspace(&RealFs, &namers, &[], opts, base_dir, existing)?;
+    let changes = ChangeReport::new(changes_from.as_ref(), &workspace);
+    Ok((workspace, changes))
+}
+
+/// Like [`create_workspace`], without the report. Kept for compatibility.
+pub fn create_workspace_in(opts: &GenerateOptions, base_path: &Path) -> Result<WorkspaceFile> {
+    Ok(create_workspace(opts, base_path)?.0)
+}
+
+/// The workspace file at `path`, or `None` when there is none. A file that can't be
+/// read or parsed is an error, [`WorkspaceError::ParseWorkspace`] when it isn't a
+/// valid workspace, so that generating never overwrites the settings and other
+/// sections of a file it couldn't understand.
+pub(crate) fn existing_workspace(
+    fs: &dyn FileSystem,
+    path: &Path,
+) -> Result<Option<WorkspaceFile>> {
+    match fs.exists(path) {
+        true => Ok(Some(read_workspace_with(fs, path)?)),
+        false => Ok(None),
+    }
+}
+
+/// Regenerates `existing`, or a new workspace when there is none, for a workspace
+/// file in `base_path`. The scan asks `filters` after those of `opts`, and scanned
+/// folders are named by the first of `namers` that gives a name. The scan looks in
+/// `fs`; detecting git checkouts, project kinds and the members of Cargo and npm
+/// workspaces always looks at the disk.
+pub(crate) fn generate_workspace(
+    fs: &dyn FileSystem,
+    namers: &[Arc<dyn FolderNamer>],
+    filters: &[Arc<dyn FolderFilter>],
+    opts: &GenerateOptions,
+    base_path: &Path,
+    existing: Option<WorkspaceFile>,
+) -> Result<WorkspaceFile> {
+    // Folder paths are relative to --relative-to when given; VS Code still resolves
+    // them against the directory of the file
+    let file_dir = base_path;
+    let base_path = opts
+        .relative_to
+        .clone()
+        .unwrap_or_else(|| file_dir.to_path_buf());
+    let scan_path = opts.scan_path.as_path();
+    // An existing file only takes the template in again when asked to
+    let template = opts
+        .template
+        .as_deref()
+        .filter(|_| existing.is_none() || opts.reapply_template)
+        .map(|path| read_template(fs, path))
+        .transpose()?;
+    let mut workspace = WorkspaceFile::default();
+    let mut existing_folders = vec![];
+    let mut previous_folders = vec![];
+
+    if let Some(existing_workspace) = existing {
+        existing_folders = existing_workspace.folders;
+        previous_folders = existing_folders.clone();
+        // Preserve settings and other sections
+        workspace.settings = existing_workspace.settings;
+        workspace.extensions = existing_workspace.extensions;
+        workspace.launch = existing_workspace.launch;
+        workspace.other = existing_workspace.other;
+        // Preserve existing tasks
+        workspace.tasks = existing_workspace.tasks;
+        if let Some(template) = &template {
+            workspace.merge(template, MergeStrategy::Theirs, opts.settings_merge)?;
+        }
+        if opts.update_task {
+            update_managed_task(&mut workspace, opts);
+        } else if let Some(stale) = repair_task_command(&mut workspace, opts.task_command) {
+            console::status(format_args!(
+                "The '{}' task ran '{}', which no longer exists; it now runs '{}'",
+                workspace.managed_task_label(),
+                stale,
+                task_command(opts.task_command)
+            ));
+        }
+    } else {
+        workspace = template.unwrap_or_default();
+        update_managed_task(&mut workspace, opts);
+    }
+    copy_sections(
+        fs,
+        &mut workspace,
+        opts.copy_settings_from.as_deref(),
+        opts.copy_extensions_from.as_deref(),
+        opts.settings_merge,
+    )?;
+    if opts.no_task {
+        strip_managed_task(&mut workspace, None);
+    }
+    if let Some(authority) = workspace.remote_authority() {
+        // Match folders written as remote URIs by their local path again
+        for folder in &mut existing_folders {
+            local_folder(folder, authority, &base_path);
+        }
+    }
+
+    // Update folders
+    if !opts.exclude_current {
+        let root_name = match opts.no_emoji {
+            true => opts.name.clone(),
+            false => format!("🏗️ {}", opts.name),
+        };
+        workspace.folders.push(WorkspaceFolder::new(".", root_name));
+    }
+
+    let excluded_paths = workspace.metadata().excluded_paths;
+    let mut manifest = vec![];
+    let mut dirs = match (&opts.candidates, &opts.folders_file) {
+        (Some(candidates), _) => candidates.clone(),
+        (None, Some(file)) => {
+            manifest = read_manifest_with(fs, file, opts.missing_folders)?;
+            let mut dirs: Vec<PathBuf> = manifest.iter().map(|entry| entry.path.clone()).collect();
+            if opts.merge_scan {
+                for dir in scanned_directories(fs, opts, filters)? {
+                    if !dirs.contains(&dir) {
+                        dirs.push(dir);
+                    }
+                }
+            }
+            dirs
+        }
+        (None, None) => scanned_directories(fs, opts, filters)?,
+    };
+    if opts.sort == SortOrder::GitActivity {
+        sort_by_git_activity(&mut dirs);
+    }
+    if let Some(megabytes) = opts.warn_size {
+        warn_large_directories(&dirs, scan_path, megabytes, opts.verbose);
+    }
+    let prefix = name_prefix(opts);
+    let mut absorbed = vec![];
+    let mut members = member_folders(scan_path, &base_path, prefix, opts);
+    let dirty = match opts.git_status {
+        Some(GitStatusMode::OnlyDirty) => Some(dirty_repositories(&dirs, opts.verbose)),
+        _ => None,
+    };
+    let (mut added, mut left_out) = (0, 0);
+    for dir in dirs {
+        if dirty.as_ref().is_some_and(|dirty| !dirty.contains(&dir)) {
+            continue;
+        }
+        let mut folder = create_workspace_folder(&dir, &base_path, scan_path)?;
+        let path = normalize_folder_path(&folder.path);
+        let existing_name = existing_folders
+            .iter()
+            .find(|existing| {
+                !existing.path.is_empty() && normalize_folder_path(&existing.path) == path
+            })
+            .map(|existing| existing.name.as_str());
+        let name_of = |dir: &Path| {
+            let ctx = NamingContext {
+                scan_path,
+                relative_path: dir.strip_prefix(scan_path).unwrap_or(dir),
+                ecosystem: folder_tasks::Ecosystem::detect(dir),
+                existing_name,
+            };
+            folder_name(namers, dir, &ctx)
+        };
+        folder.name = name_of(&dir);
+        if let Some(mode) = opts.worktrees {
+            match (mode, git::worktree(&dir)) {
+                (WorktreeMode::SkipLinked, Some(worktree)) if worktree.linked => continue,
+                (WorktreeMode::Annotate, Some(worktree)) => {
+                    folder.name = format!("{} [{}]", folder.name, worktree.head);
+                }
+                (WorktreeMode::Group, Some(worktree)) => {
+                    folder.name = format!("{} [{}]", name_of(&worktree.main), worktree.head);
+                }
+                _ => {}
+            }
+        }
+        // The limit leaves folders out only after every filter, and never those
+        // already in the file
+        let new = existing_name.is_none() && !excluded_paths.contains(&path);
+        if new && opts.limit.is_some_and(|limit| added == limit) {
+            left_out += 1;
+            continue;
+        }
+        added += usize::from(new);
+        if opts.absorb_workspaces {
+            absorbed.extend(absorbed_folders(fs, &dir, &base_path));
+        }
+        members.extend(member_folders(&dir, &base_path, prefix, opts));
+        if !excluded_paths.contains(&path) {
+            workspace.folders.push(folder);
+        }
+    }
+    if left_out > 0 {
+        console::warning(format_args!(
+            "stopped at --limit {}: {} more new folder(s) were left out",
+            added, left_out
+        ));
+    }
+    // After all scanned folders, so those win over the same folder in a nested file
+    for folder in members.into_iter().chain(absorbed) {
+        let key = folder_key(&folder);
+        let present = workspace
+            .folders
+            .iter()
+            .any(|existing| folder_key(existing) == key);
+        if !present && !excluded_paths.contains(&key) {
+            workspace.folders.push(folder);
+        }
+    }
+    if opts.canonical_dedupe {
+        dedupe_canonical_folders(fs, &mut workspace.folders, &base_path, opts.verbose);
+    }
+    if !opts.fix_overlaps {
+        collapse_nested_folders(fs, &mut workspace, &base_path, opts);
+    }
+    let found = workspace.folders.len() - usize::from(!opts.exclude_current);
+    if found < opts.min_folders {
+        return Err(too_few_folders(opts, found, excluded_paths.len()));
+    }
+    if let Some(max) = opts.max_folders.filter(|max| found > *max) {
+        return Err(WorkspaceError::TooManyFolders {
+            scan_path: opts.scan_path.clone(),
+            found,
+            max,
+        });
+    }
+    if opts.warn_folders > 0 && found > opts.warn_folders {
+        console::warning(format_args!(
+            "found {} folders in '{}', more than {}; VS Code may struggle with a \
+             workspace this large. Narrow the scan with --exclude or --marker",
+            found,
+            opts.scan_path.display(),
+            opts.warn_folders
+        ));
+    }
+
+    if let Some(root) = &opts.container_root {
+        // Existing folders carry container paths; match them to the scanned ones
+        let mapped: Vec<(String, String)> = workspace
+            .folders
+            .iter()
+            .filter_map(|folder| {
+                let path = container_path(root, &base_path, &folder.path)?;
+                Some((path, folder.path.clone()))
+            })
+            .collect();
+        for folder in &mut existing_folders {
+            let path = normalize_folder_path(&folder.path);
+            if let Some((_, host)) = mapped.iter().find(|(container, _)| *container == path) {
+                folder.path = host.clone();
+            }
+        }
+    }
+    preserve_folder_entries(&mut workspace, &existing_folders);
+    apply_manifest_names(&mut workspace, &manifest, &base_path, scan_path)?;
+    apply_settings(&mut workspace, opts);
+    sync_hidden_folders(&mut workspace, opts.hide_nested);
+    sync_name_suffixes(&mut workspace, &base_path, opts);
+    sync_rust_analyzer_links(&mut workspace, &base_path, opts.rust_analyzer_links);
+    sync_extension_recommendations(&mut workspace, &base_path, opts);
+    sync_heavy_dir_excludes(&mut workspace, &base_path, opts.exclude_heavy_dirs);
+    sync_recorded_remotes(&mut workspace, &base_path, opts.record_remotes);
+    folder_tasks::sync_folder_tasks(&mut workspace, &base_path, opts.folder_tasks);
+    if let Some(template) = &opts.launch_template {
+        apply_launch_template(fs, &mut workspace, template)?;
+    }
+
+    let mut metadata = workspace.metadata();
+    metadata.container_root = opts.container_root.clone();
+    workspace.set_metadata(metadata);
+    if let Some(root) = &opts.container_root {
+        map_to_container(&mut workspace, &base_path, root);
+    }
+
+    if let Some(authority) = workspace.remote_authority().map(str::to_string) {
+        if opts.respect_remote_authority {
+            for folder in &mut workspace.folders {
+                remote_folder(folder, &authority, &base_path);
+            }
+        } else if opts.container_root.is_none() {
+            let added = workspace
+                .folders
+                .iter()
+                .filter(|folder| folder.uri.is_none() && !folder.path.is_empty())
+                .filter(|folder| {
+                    let path = normalize_folder_path(&folder.path);
+                    !existing_folders
+                        .iter()
+                        .any(|existing| normalize_folder_path(&existing.path) == path)
+                })
+                .count();
+            if added > 0 {
+                console::warning(format_args!(
+                    "'{}' is opened on '{}', where the {} local folder path(s) being \
+                     added may not resolve; --respect-remote-authority writes them as \
+                     vscode-remote:// URIs",
+                    opts.workspace_filename(),
+                    authority,
+                    added
+                ));
+            }
+        }
+    }
+
+    reconcile_folders(&mut workspace, previous_folders, opts.reconcile);
+    if opts.canonical_dedupe {
+        // Existing folders kept by the reconciliation may be links to scanned ones
+        dedupe_canonical_folders(fs, &mut workspace.folders, &base_path, opts.verbose);
+    }
+    if opts.fix_overlaps {
+        // Once the existing folders are back, so they are fixed too
+        for path in merge_duplicate_folders(&mut workspace) {
+            if opts.verbose {
+                console::diagnostic(format_args!("Merged the entries of folder {}", path));
+            }
+        }
+        collapse_nested_folders(fs, &mut workspace, &base_path, opts);
+    }
+    if opts.relative_to.is_some() {
+        warn_unresolved_folders(fs, &workspace, file_dir, &base_path);
+    }
+
+    Ok(workspace)
+}
+
+/// Reconciles the generated folders of `workspace` with the `existing` folders of
+/// the file it replaces, as `mode` asks. A new file has no existing folders, so
+/// every mode keeps the generated ones.
+fn reconcile_folders(
+    workspace: &mut WorkspaceFile,
+    existing: Vec<WorkspaceFolder>,
+    mode: ReconcileMode,
+) {
+    match mode {
+        ReconcileMode::Replace => {
+            // Folders it drops are no longer kept by later regenerations
+            let mut metadata = workspace.metadata();
+            if !metadata.added_paths.is_empty() {
+                let keys: Vec<String> = workspace.folders.iter().map(folder_key).collect();
+                metadata.added_paths.retain(|path| keys.contains(path));
+                workspace.set_metadata(metadata);
+            }
+        }
+        ReconcileMode::Prune => {
+            let added_paths = workspace.metadata().added_paths;
+            for folder in existing {
+                let key = folder_key(&folder);
+                let present = workspace
+                    .folders
+                    .iter()
+                    .any(|other| folder_key(other) == key);
+                if !present && added_paths.contains(&key) {
+                    workspace.folders.push(folder);
+                }
+            }
+        }
+        ReconcileMode::Append => {
+            let mut added: Vec<WorkspaceFolder> = vec![];
+            for folder in std::mem::take(&mut workspace.folders) {
+                let key = folder_key(&folder);
+                if !existing
+                    .iter()
+                    .chain(&added)
+                    .any(|other| folder_key(other) == key)
+                {
+                    added.push(folder);
+                }
+            }
+            added.sort_by_key(folder_key);
+            workspace.folders = existing;
+            workspace.folders.extend(added);
+        }
+    }
+}
+
+/// Warns about each of `dirs` holding more than `megabytes` MB of files, counted down
+/// to [`SIZE_PROBE_DEPTH`] levels below it. They are still added.
+fn warn_large_directories(dirs: &[PathBuf], scan_path: &Path, megabytes: usize, verbose: bool) {
+    let limit = megabytes as u64 * 1_000_000;
+    let mut large = 0;
+    for dir in dirs {
+        if estimate_size(dir, SIZE_PROBE_DEPTH, limit) > limit {
+            large += 1;
+            console::warning(format_args!(
+                "'{}' holds more than {} MB of files; VS Code may be slow to index it. \
+                 Leave it out with --exclude or --max-entries",
+                dir.strip_prefix(scan_path).unwrap_or(dir).display(),
+                megabytes
+            ));
+        }
+    }
+    if verbose {
+        console::diagnostic(format_args!(
+            "Checked the size of {} folder(s) against --warn-size {} MB: {} larger",
+            dirs.len(),
+            megabytes,
+            large
+        ));
+    }
+}
+
+/// The directories the scan of `opts` finds, asking `filters` after its own, or those
+/// cached by an earlier scan when the cache can tell.
+fn scanned_directories(
+    fs: &dyn FileSystem,
+    opts: &GenerateOptions,
+    filters: &[Arc<dyn FolderFilter>],
+) -> Result<Vec<PathBuf>> {
+    match &opts.cache {
+        // Directories chosen by filters of the caller, by the content of other
+        // workspace files, by files or directories deeper than those the cache
+        // fingerprints, by their number of entries or by modification times, which
+        // the clock moves past, can't be told apart from others
+        Some(cache)
+            if filters.is_empty()
+                && opts.exclude_in.is_empty()
+                && opts.max_entries.is_none()
+                && opts.newer_than.is_none()
+                && opts.older_than.is_none()
+                && opts.contains_depth.unwrap_or(1) <= 1
+                && opts.max_depth.unwrap_or(1) <= 1 =>
+        {
+            cached_directories(fs, opts, cache)
+        }
+        _ => Ok(filtered_scan(fs, opts, filters)?.directories),
+    }
+}
+
+/// Warns about the folders of `workspace`, written relative to `relative_to`, that
+/// aren't directories when resolved against `file_dir` as VS Code resolves them.
+fn warn_unresolved_folders(
+    fs: &dyn FileSystem,
+    workspace: &WorkspaceFile,
+    file_dir: &Path,
+    relative_to: &Path,
+) {
+    let unresolved: Vec<&str> = workspace
+        .folders
+        .iter()
+        .filter(|folder| folder.uri.is_none())
+        .map(|folder| folder.path.as_str())
+        .filter(|path| !fs.is_dir(&file_dir.join(path)))
+        .collect();
+    if let Some(first) = unresolved.first() {
+        console::warning(format_args!(
+            "{} folder path(s) relative to '{}' don't resolve from '{}', where VS Code \
+             looks for them, e.g. '{}'",
+            unresolved.len(),
+            relative_to.display(),
+            file_dir.display(),
+            first
+        ));
+    }
+}
+
+/// Keeps one of the `folders` that resolve to the same real directory, as
+/// `--canonical-dedupe` asks: the first reached without going through a symlink, or
+/// the first of them all when each is. Folders given by uri, and those that can't be
+/// canonicalized, such as dangling symlinks, are kept. With `verbose`, the folders
+/// dropped are reported.
+fn dedupe_canonical_folders(
+    fs: &dyn FileSystem,
+    folders: &mut Vec<WorkspaceFolder>,
+    base_path: &Path,
+    verbose: bool,
+) {
+    // The canonical path of each folder, and whether it is the path it is written as
+    let resolved: Vec<Option<(String, bool)>> = folders
+        .iter()
+        .map(|folder| {
+            if folder.uri.is_some() || folder.path.is_empty() {
+                return None;
+            }
+            let path = base_path.join(&folder.path);
+            let canonical = fs.canonicalize(&path).ok()?;
+            let canonical = normalize_folder_path(&canonical.to_string_lossy());
+            let direct = normalize_folder_path(&path.to_string_lossy()) == canonical;
+            Some((canonical, direct))
+        })
+        .collect();
+
+    let mut dropped = vec![false; folders.len()];
+    let mut seen = vec![];
+    for (index, entry) in resolved.iter().enumerate() {
+        let Some((canonical, _)) = entry else {
+            continue;
+        };
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+        let same: Vec<usize> = (index..resolved.len())
+            .filter(|other| {
+                resolved[*other]
+                    .as_ref()
+                    .is_some_and(|(other, _)| other == canonical)
+            })
+            .collect();
+        let kept = same
+            .iter()
+            .copied()
+            .find(|other| resolved[*other].as_ref().is_some_and(|(_, direct)| *direct))
+            .unwrap_or(index);
+        for other in same.into_iter().filter(|other| *other != kept) {
+            dropped[other] = true;
+            if verbose {
+                console::diagnostic(format_args!(
+                    "Dropped folder {}, the same directory as {}",
+                    folders[other].path, folders[kept].path
+                ));
+            }
+        }
+    }
+    let mut index = 0;
+    folders.retain(|_| {
+        index += 1;
+        !dropped[index - 1]
+    });
+}
+
+/// Drops one side of the folders of `workspace` inside other folders, as
+/// `--collapse-nested` asks, or warns about them without it. With `--verbose`, the
+/// folders dropped are reported.
+fn collapse_nested_folders(
+    fs: &dyn FileSystem,
+    workspace: &mut WorkspaceFile,
+    base_path: &Path,
+    opts: &GenerateOptions,
+) {
+    let nested = nested_folders(&workspace.folders, base_path, |path| {
+        fs.canonicalize(path).ok()
+    });
+    if nested.is_empty() {
+        return;
+    }
+    let Some(mode) = opts.collapse_nested else {
+        let pairs: Vec<String> = nested
+            .iter()
+            .map(|pair| {
+                format!(
+                    "\n  {} inside {}",
+                    workspace.folders[pair.inner].path, workspace.folders[pair.outer].path
+                )
+            })
+            .collect();
+        console::warning(format_args!(
+            "{} folder(s) are inside other folders, which VS Code shows twice:{}\n\
+             --collapse-nested keep-outer or keep-inner drops one side",
+            nested.len(),
+            pairs.concat()
+        ));
+        return;
+    };
+    let dropped: Vec<usize> = nested
+        .iter()
+        .map(|pair| match mode {
+            CollapseNested::KeepOuter => pair.inner,
+            CollapseNested::KeepInner => pair.outer,
+        })
+        .collect();
+    let mut index = 0;
+    workspace.folders.retain(|folder| {
+        let keep = !dropped.contains(&index);
+        if !keep && opts.verbose {
+            console::diagnostic(format_args!("Dropped nested folder {}", folder.path));
+        }
+        index += 1;
+        keep
+    });
+}
+
+/// Where the folder at the relative `path` is mounted in a dev container that mounts
+/// repositories by directory name under `root`. `None` for paths that are not relative.
+pub fn container_path(root: &str, workspace_dir: &Path, path: &str) -> Option<String> {
+    if path.is_empty() || Path::new(path).is_absolute() {
+        return None;
+    }
+    let resolved = normalize_folder_path(&workspace_dir.join(path).to_string_lossy());
+    let name = resolved
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())?;
+    Some(format!("{}/{}", root.trim_end_matches('/'), name))
+}
+
+/// Rewrites the relative folder paths of `workspace` with [`container_path`]. Of
+/// folders whose directories share a name only the first is kept, with a warning.
+fn map_to_container(workspace: &mut WorkspaceFile, workspace_dir: &Path, root: &str) {
+    let mut seen: Vec<(String, String)> = vec![];
+    workspace.folders.retain_mut(|folder| {
+        let Some(path) = container_path(root, workspace_dir, &folder.path) else {
+            return true;
+        };
+        if let Some((_, first)) = seen.iter().find(|(mapped, _)| *mapped == path) {
+            console::warning(format_args!(
+                "'{}' and '{}' both map to '{}' in the container; keeping '{}'",
+                first, folder.path, path, first
+            ));
+            return false;
+        }
+        seen.push((path.clone(), folder.path.clone()));
+        folder.path = path;
+        true
+    });
+}
+
+/// The `vscode-remote://` URI of the absolute `path` on the host named by `authority`.
+pub fn remote_uri(authority: &str, path: &Path) -> String {
+    let path = normalize_folder_path(&path.to_string_lossy());
+    let mut uri = format!("vscode-remote://{}", authority);
+    if !path.starts_with('/') {
+        // Windows drive paths
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// The path of `uri` if it is a `vscode-remote://` URI on `authority`.
+pub fn remote_uri_path(authority: &str, uri: &str) -> Option<PathBuf> {
+    let encoded = uri
+        .strip_prefix("vscode-remote://")?
+        .strip_prefix(authority)?;
+    if !encoded.starts_with('/') {
+        return None;
+    }
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // `/C:/src` on Windows
+    match path.get(1..3) {
+        Some(drive) if drive.ends_with(':') => Some(PathBuf::from(&path[1..])),
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+/// Rewrites a folder given by relative path as a URI on `authority`, resolving the
+/// path against `base_path`.
+fn remote_folder(folder: &mut WorkspaceFolder, authority: &str, base_path: &Path) {
+    if folder.uri.is_some() || folder.path.is_empty() {
+        return;
+    }
+    folder.uri = Some(remote_uri(authority, &base_path.join(&folder.path)));
+    folder.path.clear();
+}
+
+/// The inverse of [`remote_folder`] for URIs on `authority`.
+fn local_folder(folder: &mut WorkspaceFolder, authority: &str, base_path: &Path) {
+    let Some(path) = folder
+        .uri
+        .as_deref()
+        .and_then(|uri| remote_uri_path(authority, uri))
+    else {
+        return;
+    };
+    let relative = pathdiff::diff_paths(&path, base_path).unwrap_or(path);
+    folder.path = match relative.to_string_lossy().as_ref() {
+        "" => ".".to_string(),
+        relative => relative.to_string(),
+    };
+    folder.uri = None;
+}
+
+/// Restores from the previous version of the file what generation doesn't decide
+/// about its folders: the keys other than path and name that extensions added to
+/// them, and the display names of folders the user renamed (recorded in the metadata
+/// section).
+fn preserve_folder_entries(workspace: &mut WorkspaceFile, existing_folders: &[WorkspaceFolder]) {
+    let custom_names = workspace.metadata().custom_names;
+    for folder in workspace.folders.iter_mut().filter(|f| !f.path.is_empty()) {
+        let path = normalize_folder_path(&folder.path);
+        let Some(existing) = existing_folders.iter().find(|existing| {
+            !existing.path.is_empty() && normalize_folder_path(&existing.path) == path
+        }) else {
+            continue;
+        };
+        folder.other = existing.other.clone();
+        if custom_names.contains(&path) {
+            folder.name = existing.name.clone();
+        }
+    }
+}
+
+/// Names the folders of `workspace` as the entries of a folder manifest with a name
+/// ask, recording the names as chosen by the user so they survive regeneration
+/// without the manifest.
+fn apply_manifest_names(
+    workspace: &mut WorkspaceFile,
+    manifest: &[ManifestEntry],
+    base_path: &Path,
+    scan_path: &Path,
+) -> Result<()> {
+    let mut metadata = workspace.metadata();
+    for entry in manifest {
+        let Some(name) = &entry.name else {
+            continue;
+        };
+        let path = create_workspace_folder(&entry.path, base_path, scan_path)?.path;
+        let path = normalize_folder_path(&path);
+        for folder in &mut workspace.folders {
+            if folder.uri.is_none() && normalize_folder_path(&folder.path) == path {
+                folder.name = name.clone();
+            }
+        }
+        if !metadata.custom_names.contains(&path) {
+            metadata.custom_names.push(path);
+        }
+    }
+    workspace.set_metadata(metadata);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::MemoryFs;
+    use crate::folders::{rename_folder, FolderSelector};
+    use crate::io::parse_workspace;
+    use crate::model::ManagerMetadata;
+    use crate::options::{NameStyle, SettingsMerge, SortOrder};
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_create_workspace() -> Result<()> {
+        let temp = TempDir::new()?;
+        // Not the current directory of the test process
+        let base = TempDir::new()?;
+        let base_path = base.path().canonicalize()?;
+        let scan_path = &temp.path().canonicalize()?;
+
+        // Create test directories
+        fs::create_dir_all(scan_path.join("folder1"))?;
+        fs::create_dir_all(scan_path.join("folder2"))?;
+
+        let opts = GenerateOptions {
+            scan_path: scan_path.to_path_buf(),
+            name: "test".to_string(),
+            exclude_current: false,
+            update_task: true,
+            task_args: vec![],
+            ..Default::default()
+        };
+
+        let (workspace, _) = create_workspace(&opts, &base_path)?;
+
+        // Verify structure
+        assert_eq!(workspace.folders.len(), 3);
+
+        // Current directory should be first
+        assert_eq!(workspace.folders[0].path, ".");
+        assert_eq!(workspace.folders[0].name, "🏗️ test");
+
+        // Get relative paths for comparison
+        let rel_path =
+            pathdiff::diff_paths(scan_path, &base_path).expect("Failed to get relative path");
+
+        // Verify folder paths are relative to workspace file location
+        let expected_path1 = rel_path.join("folder1");
+        let expected_path2 = rel_path.join("folder2");
+
+        assert!(
+            workspace
+                .folders
+                .iter()
+                .any(|f| f.path == expected_path1.to_string_lossy() && f.name == "📦 folder1"),
+            "folder1 not found with correct path"
+        );
+
+        assert!(
+            workspace
+                .folders
+                .iter()
+                .any(|f| f.path == expected_path2.to_string_lossy() && f.name == "📦 folder2"),
+            "folder2 not found with correct path"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_style() -> Result<()> {
+        let temp = TempDir::new()?;
+        let scan = temp.path().join("work");
+        for dir in ["clientA/api/v2", "clientB/api/v1"] {
+            fs::create_dir_all(scan.join(dir))?;
+        }
+        let nested = scan.join("clientA").join("api").join("v2");
+        assert_eq!(
+            NameStyle::Relpath.folder_name(&nested, &scan),
+            "clientA/api/v2"
+        );
+        assert_eq!(NameStyle::Basename.folder_name(&nested, &scan), "v2");
+        assert_eq!(NameStyle::Relpath.folder_name(&scan, &scan), "work");
+
+        let opts = GenerateOptions {
+            name: "work".to_string(),
+            scan_path: scan.join("clientA"),
+            name_style: NameStyle::Relpath,
+            sort: SortOrder::Name,
+            ..Default::default()
+        };
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        let names: Vec<&str> = workspace.folders.iter().map(|f| f.name.as_str()).collect();
+        // The root entry keeps its own name
+        assert_eq!(names, vec!["🏗️ work", "📦 api"]);
+
+        let opts = GenerateOptions {
+            scan_path: scan.clone(),
+            folder_prefix: Some(String::new()),
+            ..opts
+        };
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        let names: Vec<&str> = workspace.folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["🏗️ work", "clientA", "clientB"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_to() -> Result<()> {
+        let temp = TempDir::new()?;
+        let home = temp.path().canonicalize()?.join("home");
+        let scan = home.join("work");
+        let file_dir = home.join("workspaces");
+        for dir in ["work/api", "work/web", "workspaces"] {
+            fs::create_dir_all(home.join(dir))?;
+        }
+        let elsewhere = TempDir::new()?;
+        let elsewhere = elsewhere.path().canonicalize()?;
+        let paths = |relative_to: &Path| -> Result<Vec<String>> {
+            let opts = GenerateOptions {
+                name: "work".to_string(),
+                scan_path: scan.clone(),
+                relative_to: Some(relative_to.to_path_buf()),
+                sort: SortOrder::Name,
+                ..Default::default()
+            };
+            let workspace = create_workspace_in(&opts, &file_dir)?;
+            let paths: Vec<String> = workspace.folders.iter().map(|f| f.path.clone()).collect();
+            // Each resolves against the base it was written relative to
+            for path in &paths[1..] {
+                let dir = relative_to.join(path).canonicalize()?;
+                assert!(dir.starts_with(&scan), "{}", dir.display());
+            }
+            Ok(paths)
+        };
+
+        // Above the scanned directories
+        assert_eq!(paths(&home)?, vec![".", "work/api", "work/web"]);
+        // Beside them, where the file is: the same as without the option
+        let beside = paths(&file_dir)?;
+        assert_eq!(
+            beside[1],
+            Path::new("..").join("work").join("api").to_string_lossy()
+        );
+        // Unrelated to them
+        let unrelated = paths(&elsewhere)?;
+        assert!(unrelated[1].starts_with(".."), "{}", unrelated[1]);
+        assert!(unrelated[1].ends_with("api"), "{}", unrelated[1]);
+
+        let opts = GenerateOptions {
+            name: "work".to_string(),
+            scan_path: scan.clone(),
+            ..Default::default()
+        };
+        let plain = create_workspace_in(&opts, &file_dir)?;
+        assert!(plain.folders.iter().any(|f| f.path == beside[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconcile_modes() -> Result<()> {
+        let fs = MemoryFs::new();
+        for dir in ["zeta", "beta", "alpha", "gamma"] {
+            fs.add_dir(Path::new("/work").join(dir));
+        }
+        // `../shared` was added by hand, `gone` was found by an earlier scan
+        let existing: WorkspaceFile = parse_workspace(
+            r#"{
+                "folders": [
+                    {"path": "zeta", "name": "Last letter"},
+                    {"path": "gone", "name": "📦 gone"},
+                    {"path": "../shared", "name": "📦 shared"},
+                    {"path": ".", "name": "🏗️ work"}
+                ],
+                "workspaceManager": {"addedPaths": ["../shared"]}
+            }"#,
+        )?;
+        let generate = |reconcile, existing| -> Result<Vec<(String, String)>> {
+            let opts = GenerateOptions {
+                scan_path: PathBuf::from("/work"),
+                name: "work".to_string(),
+                sort: SortOrder::Name,
+                reconcile,
+                ..Default::default()
+            };
+            let namers = [default_namer(&opts)];
+            let workspace =
+                generate_workspace(&fs, &namers, &[], &opts, Path::new("/work"), Some(existing))?;
+            Ok(workspace
+                .folders
+                .into_iter()
+                .map(|f| (f.path, f.name))
+                .collect())
+        };
+        let folders = |expected: &[(&str, &str)]| -> Vec<(String, String)> {
+            expected
+                .iter()
+                .map(|(path, name)| (path.to_string(), name.to_string()))
+                .collect()
+        };
+
+        // Generated folders first, then those added by hand
+        assert_eq!(
+            generate(ReconcileMode::Prune, existing.clone())?,
+            folders(&[
+                (".", "🏗️ work"),
+                ("alpha", "📦 alpha"),
+                ("beta", "📦 beta"),
+                ("gamma", "📦 gamma"),
+                ("zeta", "📦 zeta"),
+                ("../shared", "📦 shared"),
+            ])
+        );
+        // Exactly the generated folders
+        assert_eq!(
+            generate(ReconcileMode::Replace, existing.clone())?,
+            folders(&[
+                (".", "🏗️ work"),
+                ("alpha", "📦 alpha"),
+                ("beta", "📦 beta"),
+                ("gamma", "📦 gamma"),
+                ("zeta", "📦 zeta"),
+            ])
+        );
+        // Everything kept as it was, new folders after it and sorted by path
+        let appended = generate(ReconcileMode::Append, existing)?;
+        assert_eq!(
+            appended,
+            folders(&[
+                ("zeta", "Last letter"),
+                ("gone", "📦 gone"),
+                ("../shared", "📦 shared"),
+                (".", "🏗️ work"),
+                ("alpha", "📦 alpha"),
+                ("beta", "📦 beta"),
+                ("gamma", "📦 gamma"),
+            ])
+        );
+        // Running again adds nothing
+        let again = WorkspaceFile {
+            folders: appended
+                .iter()
+                .map(|(path, name)| WorkspaceFolder::new(path.clone(), name.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(generate(ReconcileMode::Append, again)?, appended);
+        Ok(())
+    }
+
+    #[test]
+    fn test_template() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_dir("/work/api");
+        fs.add_file(
+            "/team/base.code-workspace",
+            r#"{
+                // Standard team setup
+                "folders": [{"path": "template-only"}],
+                "settings": {"editor.tabSize": 4, "files.exclude": {"**/target": true}},
+                "extensions": {"recommendations": ["rust-lang.rust-analyzer"]},
+                "tasks": {"version": "2.0.0", "tasks": [
+                    {"label": "Update Workspace", "type": "shell", "command": "old"},
+                    {"label": "Lint", "type": "shell", "command": "make lint"},
+                ]},
+                "workspaceManager": {"customNames": ["template-only"]},
+            }"#,
+        );
+        let generate = |existing, reapply_template, settings_merge| -> Result<WorkspaceFile> {
+            let opts = GenerateOptions {
+                scan_path: PathBuf::from("/work"),
+                name: "work".to_string(),
+                template: Some(PathBuf::from("/team/base.code-workspace")),
+                reapply_template,
+                settings_merge,
+                ..Default::default()
+            };
+            let namers = [default_namer(&opts)];
+            Ok(generate_workspace(
+                &fs,
+                &namers,
+                &[],
+                &opts,
+                Path::new("/work"),
+                existing,
+            )?)
+        };
+        let settings = |workspace: &WorkspaceFile| serde_json::to_value(&workspace.settings);
+
+        // A new file starts from the template, with folders and task of its own
+        let seeded = generate(None, false, SettingsMerge::Ours)?;
+        let paths: Vec<&str> = seeded.folders.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, [".", "api"]);
+        assert_eq!(settings(&seeded)?["editor.tabSize"], 4);
+        assert_eq!(
+            seeded.extensions.as_ref().unwrap().recommendations,
+            ["rust-lang.rust-analyzer"]
+        );
+        let tasks = &seeded.tasks.as_ref().unwrap().tasks;
+        let labels: Vec<&str> = tasks.iter().map(|task| task.label.as_str()).collect();
+        assert_eq!(labels, ["Lint", "Update Workspace"]);
+        assert_ne!(tasks[1].command, "old");
+        assert!(seeded.metadata().custom_names.is_empty());
+
+        // An existing file keeps its own sections
+        let existing = parse_workspace(
+            r#"{"folders": [{"path": "api"}],
+                "settings": {"editor.tabSize": 2, "files.exclude": {"**/dist": true}}}"#,
+        )?;
+        let kept = generate(Some(existing.clone()), false, SettingsMerge::Ours)?;
+        assert_eq!(kept.settings, existing.settings);
+        assert_eq!(kept.extensions, None);
+
+        // unless the template is applied again, merged key by key, its other values
+        // winning and its settings only filling in the missing keys
+        let reapplied = generate(Some(existing.clone()), true, SettingsMerge::Ours)?;
+        assert_eq!(
+            settings(&reapplied)?,
+            serde_json::json!({
+                "editor.tabSize": 2,
+                "files.exclude": {"**/dist": true, "**/target": true}
+            })
+        );
+        assert!(reapplied.extensions.is_some());
+        let reapplied = generate(Some(existing.clone()), true, SettingsMerge::Theirs)?;
+        assert_eq!(settings(&reapplied)?["editor.tabSize"], 4);
+        assert!(generate(Some(existing), true, SettingsMerge::Error).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_sections() -> Result<()> {
+        let fs = MemoryFs::new();
+        let source = r#"{
+            // Shared team setup; the rest of the file isn't a valid workspace
+            "folders": "elsewhere",
+            "settings": {
+                "editor.rulers": [100],
+                "editor.tabSize": 4,
+                "files.exclude": {"**/target": true},
+            },
+            "extensions": {"recommendations": ["rust-lang.rust-analyzer"]},
+        }"#;
+        fs.add_file("/team/base.code-workspace", source);
+        fs.add_file("/team/bare.code-workspace", r#"{"folders": []}"#);
+        let existing = parse_workspace(
+            r#"{"folders": [{"path": "api"}],
+                "settings": {"editor.rulers": [80], "editor.tabSize": 2,
+                             "files.exclude": {"**/dist": true}},
+                "extensions": {"recommendations": ["esbenp.prettier-vscode"]}}"#,
+        )?;
+        let base = Path::new("/team/base.code-workspace");
+        let copy = |settings: Option<&Path>, extensions, strategy| -> Result<WorkspaceFile> {
+            let mut workspace = existing.clone();
+            copy_sections(&fs, &mut workspace, settings, extensions, strategy)?;
+            Ok(workspace)
+        };
+        let settings = |workspace: &WorkspaceFile| serde_json::to_value(&workspace.settings);
+
+        // Keys only the source has are added, nested objects merged key by key
+        let ours = copy(Some(base), None, SettingsMerge::Ours)?;
+        assert_eq!(
+            settings(&ours)?,
+            serde_json::json!({
+                "editor.rulers": [80],
+                "editor.tabSize": 2,
+                "files.exclude": {"**/dist": true, "**/target": true}
+            })
+        );
+        assert_eq!(ours.extensions, existing.extensions);
+        assert_eq!(ours.folders, existing.folders);
+        let theirs = copy(Some(base), None, SettingsMerge::Theirs)?;
+        assert_eq!(
+            settings(&theirs)?["editor.rulers"],
+            serde_json::json!([100])
+        );
+        assert_eq!(settings(&theirs)?["editor.tabSize"], 4);
+        let union = copy(Some(base), None, SettingsMerge::UnionDeep)?;
+        assert_eq!(
+            settings(&union)?["editor.rulers"],
+            serde_json::json!([80, 100])
+        );
+        assert_eq!(settings(&union)?["editor.tabSize"], 2);
+        let err = copy(Some(base), None, SettingsMerge::Error).unwrap_err();
+        assert!(
+            err.to_string().contains("settings.editor.tabSize"),
+            "{}",
+            err
+        );
+
+        let with_extensions = copy(None, Some(base), SettingsMerge::Ours)?;
+        assert_eq!(with_extensions.settings, existing.settings);
+        assert_eq!(
+            with_extensions.extensions.unwrap().recommendations,
+            ["esbenp.prettier-vscode", "rust-lang.rust-analyzer"]
+        );
+
+        // A file without the section changes nothing, and the source stays as it was
+        let bare = Path::new("/team/bare.code-workspace");
+        assert_eq!(
+            copy(Some(bare), Some(bare), SettingsMerge::Error)?,
+            existing
+        );
+        assert_eq!(fs.read_to_string(base)?, source);
+        assert!(copy(Some(Path::new("/team/gone")), None, SettingsMerge::Ours).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit() -> Result<()> {
+        let fs = MemoryFs::new();
+        for dir in ["echo", "delta", "charlie", "bravo", "alpha"] {
+            fs.add_dir(Path::new("/work").join(dir));
+        }
+        let generate = |existing| -> Result<Vec<String>> {
+            let opts = GenerateOptions {
+                scan_path: PathBuf::from("/work"),
+                name: "work".to_string(),
+                sort: SortOrder::Name,
+                exclude: vec!["bravo".to_string()],
+                limit: Some(2),
+                ..Default::default()
+            };
+            let namers = [default_namer(&opts)];
+            let workspace =
+                generate_workspace(&fs, &namers, &[], &opts, Path::new("/work"), existing)?;
+            Ok(workspace.folders.into_iter().map(|f| f.path).collect())
+        };
+
+        // The first folders in the sort order, after filtering
+        assert_eq!(generate(None)?, vec![".", "alpha", "charlie"]);
+        // Folders already in the file don't count towards it
+        let existing = parse_workspace(r#"{"folders": [{"path": "echo"}, {"path": "."}]}"#)?;
+        assert_eq!(
+            generate(Some(existing))?,
+            vec![".", "alpha", "charlie", "echo"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_worktree_modes() -> Result<()> {
+        let temp = TempDir::new()?;
+        let repo = temp.path().join("myrepo");
+        fs::create_dir_all(repo.join(".git/worktrees/myrepo-hotfix"))?;
+        fs::write(repo.join(".git/HEAD"), "ref: refs/heads/main\n")?;
+        let linked_git = repo.join(".git/worktrees/myrepo-hotfix");
+        fs::write(linked_git.join("HEAD"), "ref: refs/heads/hotfix\n")?;
+        fs::write(linked_git.join("commondir"), "../..\n")?;
+        let hotfix = temp.path().join("myrepo-hotfix");
+        fs::create_dir(&hotfix)?;
+        fs::write(
+            hotfix.join(".git"),
+            "gitdir: ../myrepo/.git/worktrees/myrepo-hotfix\n",
+        )?;
+        fs::create_dir(temp.path().join("notes"))?;
+
+        let names = |worktrees| -> Result<Vec<String>> {
+            let opts = GenerateOptions {
+                scan_path: temp.path().to_path_buf(),
+                exclude_current: true,
+                sort: SortOrder::Name,
+                worktrees,
+                ..Default::default()
+            };
+            let workspace = create_workspace_in(&opts, temp.path())?;
+            Ok(workspace.folders.into_iter().map(|f| f.name).collect())
+        };
+        assert_eq!(
+            names(None)?,
+            vec!["📦 myrepo", "📦 myrepo-hotfix", "📦 notes"]
+        );
+        assert_eq!(
+            names(Some(WorktreeMode::Annotate))?,
+            vec!["📦 myrepo [main]", "📦 myrepo-hotfix [hotfix]", "📦 notes"]
+        );
+        assert_eq!(
+            names(Some(WorktreeMode::Group))?,
+            vec!["📦 myrepo [main]", "📦 myrepo [hotfix]", "📦 notes"]
+        );
+        assert_eq!(
+            names(Some(WorktreeMode::SkipLinked))?,
+            vec!["📦 myrepo", "📦 notes"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_show_branch() -> Result<()> {
+        let temp = TempDir::new()?;
+        for (dir, head) in [
+            ("api", "ref: refs/heads/feature/login\n"),
+            ("web", "8f3e2a91c0d4b5e6f7a8b9c0d1e2f3a4b5c6d7e8\n"),
+        ] {
+            fs::create_dir_all(temp.path().join(dir).join(".git"))?;
+            fs::write(temp.path().join(dir).join(".git/HEAD"), head)?;
+        }
+        fs::create_dir(temp.path().join("notes"))?;
+        let opts = GenerateOptions {
+            name: "team".to_string(),
+            scan_path: temp.path().to_path_buf(),
+            exclude_current: true,
+            sort: SortOrder::Name,
+            show_branch: true,
+            ..Default::default()
+        };
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        let names: Vec<&str> = workspace.folders.iter().map(|f| f.name.as_str()).collect();
+        // A detached HEAD shows the commit
+        assert_eq!(
+            names,
+            vec!["📦 api (feature/login)", "📦 notes", "📦 web (8f3e2a91)"]
+        );
+
+        // The suffix of a renamed folder follows the branch
+        let path = temp.path().join("team.code-workspace");
+        let mut workspace = workspace;
+        let api = FolderSelector::Path(vec!["api".into()]);
+        rename_folder(&mut workspace, &api, "Backend (feature/login)", false)?;
+        fs::write(&path, serde_json::to_string_pretty(&workspace)?)?;
+        fs::write(temp.path().join("api/.git/HEAD"), "ref: refs/heads/main\n")?;
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        assert_eq!(workspace.folders[0].name, "Backend (main)");
+
+        // Turning it off removes the suffixes
+        fs::write(&path, serde_json::to_string_pretty(&workspace)?)?;
+        let opts = GenerateOptions {
+            show_branch: false,
+            ..opts
+        };
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        let names: Vec<&str> = workspace.folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["Backend", "📦 notes", "📦 web"]);
+        assert!(workspace.metadata().branches.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_status() -> Result<()> {
+        let temp = TempDir::new()?;
+        let (api, web) = (temp.path().join("api"), temp.path().join("web"));
+        for dir in [&api, &web] {
+            fs::create_dir(dir)?;
+            let init = std::process::Command::new("git")
+                .args(["init", "-q"])
+                .current_dir(dir)
+                .status();
+            if !init.is_ok_and(|status| status.success()) {
+                // git isn't installed
+                return Ok(());
+            }
+        }
+        fs::write(api.join("todo.txt"), "untracked")?;
+        fs::create_dir(temp.path().join("notes"))?;
+        let opts = GenerateOptions {
+            name: "team".to_string(),
+            scan_path: temp.path().to_path_buf(),
+            exclude_current: true,
+            sort: SortOrder::Name,
+            git_status: Some(GitStatusMode::OnlyDirty),
+            ..Default::default()
+        };
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        assert_eq!(
+            workspace.folders,
+            vec![WorkspaceFolder::new("api", "📦 api")]
+        );
+
+        let opts = GenerateOptions {
+            git_status: Some(GitStatusMode::Annotate),
+            show_branch: true,
+            ..opts
+        };
+        let path = temp.path().join("team.code-workspace");
+        let mut workspace = create_workspace_in(&opts, temp.path())?;
+        let names: Vec<&str> = workspace.folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(
+            names[0],
+            format!("📦 api ({}) ●", workspace.metadata().branches["api"])
+        );
+        assert_eq!(names[1], "📦 notes");
+        assert!(names[2].starts_with("📦 web (") && !names[2].contains('●'));
+
+        // The marker goes once the changes are gone, also from a name the user chose
+        let api_selector = FolderSelector::Path(vec!["api".into()]);
+        rename_folder(&mut workspace, &api_selector, "Backend", false)?;
+        fs::write(&path, serde_json::to_string_pretty(&workspace)?)?;
+        fs::remove_file(api.join("todo.txt"))?;
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        assert!(workspace.folders[0].name.starts_with("Backend ("));
+        assert!(!workspace.folders[0].name.contains('●'));
+        assert!(workspace.metadata().dirty_paths.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_absorb_workspaces() -> Result<()> {
+        let temp = TempDir::new()?;
+        let scan = temp.path().join("work");
+        for dir in ["app/frontend", "lib", "bad"] {
+            fs::create_dir_all(scan.join(dir))?;
+        }
+        fs::write(
+            scan.join("app").join("app.code-workspace"),
+            r#"{
+                // curated by the app team
+                "folders": [
+                    {"path": ".", "name": "App"},
+                    {"path": "../lib", "name": "Shared lib"},
+                    {"path": "./frontend", "name": "Frontend"},
+                    {"path": "/opt/sdk", "name": "SDK"},
+                    {"uri": "vscode-remote://ssh-remote+box/srv", "name": "Server"},
+                ],
+            }"#,
+        )?;
+        fs::write(
+            scan.join("bad").join("bad.code-workspace"),
+            "{\"folders\": [",
+        )?;
+        let mut opts = GenerateOptions {
+            name: "all".to_string(),
+            scan_path: scan.clone(),
+            exclude_current: true,
+            sort: SortOrder::Name,
+            absorb_workspaces: true,
+            ..Default::default()
+        };
+        let folders = |opts: &GenerateOptions| -> Result<Vec<(String, String)>> {
+            Ok(create_workspace_in(opts, temp.path())?
+                .folders
+                .into_iter()
+                .map(|f| (f.uri.unwrap_or(f.path), f.name))
+                .collect())
+        };
+
+        // Scanned folders keep their own entries; the nested file adds what's new
+        let expected = vec![
+            ("work/app", "📦 app"),
+            ("work/bad", "📦 bad"),
+            ("work/lib", "📦 lib"),
+            ("work/app/frontend", "Frontend"),
+            ("/opt/sdk", "SDK"),
+            ("vscode-remote://ssh-remote+box/srv", "Server"),
+        ];
+        let expected: Vec<(String, String)> = expected
+            .into_iter()
+            .map(|(path, name)| (path.to_string(), name.to_string()))
+            .collect();
+        assert_eq!(folders(&opts)?, expected);
+
+        // Either side of the app and its frontend can be dropped
+        let paths = |opts: &GenerateOptions| -> Result<Vec<String>> {
+            Ok(folders(opts)?.into_iter().map(|(path, _)| path).collect())
+        };
+        opts.collapse_nested = Some(CollapseNested::KeepOuter);
+        assert!(!paths(&opts)?.contains(&"work/app/frontend".to_string()));
+        opts.collapse_nested = Some(CollapseNested::KeepInner);
+        let kept = paths(&opts)?;
+        assert!(kept.contains(&"work/app/frontend".to_string()));
+        assert!(!kept.contains(&"work/app".to_string()));
+        assert_eq!(kept.len(), expected.len() - 1);
+
+        opts.collapse_nested = None;
+        opts.absorb_workspaces = false;
+        assert_eq!(folders(&opts)?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cargo_members_become_folders() -> Result<()> {
+        let temp = TempDir::new()?;
+        let repo = temp.path().join("repo");
+        for (dir, name) in [("crates/core", "repo-core"), ("crates/old", "repo-old")] {
+            fs::create_dir_all(repo.join(dir))?;
+            fs::write(
+                repo.join(dir).join("Cargo.toml"),
+                format!("package.name = \"{}\"", name),
+            )?;
+        }
+        fs::write(
+            repo.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/old\"]\n",
+        )?;
+        let opts = GenerateOptions {
+            name: "all".to_string(),
+            scan_path: temp.path().to_path_buf(),
+            exclude_current: true,
+            cargo_members: true,
+            ..Default::default()
+        };
+        let workspace = create_workspace_in(&opts, temp.path())?;
+        assert_eq!(
+            workspace.folders,
+            vec![
+                WorkspaceFolder::new("repo", "📦 repo"),
+                WorkspaceFolder::new("repo/crates/core", "📦 repo-core"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_regeneration_keeps_custom_names() -> Result<()> {
+        let mut workspace = WorkspaceFile {
+            folders: vec![
+                WorkspaceFolder::new("api", "📦 api"),
+                WorkspaceFolder::new("web", "📦 web"),
+            ],
+            ..Default::default()
+        };
+        let existing = vec![
+            WorkspaceFolder::new("./api", "Backend"),
+            WorkspaceFolder::new("web", "Old web"),
+        ];
+        workspace.set_metadata(ManagerMetadata {
+            custom_names: vec!["api".to_string()],
+            ..Default::default()
+        });
+
+        preserve_folder_entries(&mut workspace, &existing);
+
+        assert_eq!(workspace.folders[0].name, "Backend");
+        assert_eq!(workspace.folders[1].name, "📦 web");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_canonical_dedupe() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let temp = TempDir::new()?;
+        let work = temp.path().canonicalize()?.join("work");
+        std::fs::create_dir_all(work.join("projects/api"))?;
+        symlink(work.join("projects/api"), work.join("current-api"))?;
+        symlink(work.join("projects/gone"), work.join("dangling"))?;
+        let paths = |folders: &[WorkspaceFolder]| -> Vec<String> {
+            folders.iter().map(|folder| folder.path.clone()).collect()
+        };
+
+        // The link is dropped for the real directory, even when listed first
+        let mut folders = vec![
+            WorkspaceFolder::new(".", "root"),
+            WorkspaceFolder::new("current-api", "link"),
+            WorkspaceFolder::new("./projects/api/", "real"),
+            WorkspaceFolder::new("dangling", "dangling"),
+            WorkspaceFolder::new("dangling", "dangling again"),
+            WorkspaceFolder::new("projects", "projects"),
+        ];
+        dedupe_canonical_folders(&RealFs, &mut folders, &work, true);
+        assert_eq!(
+            paths(&folders),
+            [".", "./projects/api/", "dangling", "dangling", "projects"]
+        );
+        // Of links only, the first is kept
+        let mut folders = vec![
+            WorkspaceFolder::new("current-api", "link"),
+            WorkspaceFolder::new("projects/../current-api", "same link"),
+        ];
+        dedupe_canonical_folders(&RealFs, &mut folders, &work, false);
+        assert_eq!(paths(&folders), ["current-api"]);
+
+        let generate = |canonical_dedupe, existing| -> Result<Vec<String>> {
+            let opts = GenerateOptions {
+                scan_path: work.clone(),
+                name: "work".to_string(),
+                sort: SortOrder::Name,
+                max_depth: Some(2),
+                canonical_dedupe,
+                ..Default::default()
+            };
+            let namers = [default_namer(&opts)];
+            let workspace = generate_workspace(&RealFs, &namers, &[], &opts, &work, existing)?;
+            Ok(paths(&workspace.folders))
+        };
+        assert_eq!(
+            generate(false, None)?,
+            [".", "current-api", "projects", "projects/api"]
+        );
+        assert_eq!(generate(true, None)?, [".", "projects", "projects/api"]);
+        // A folder added by hand is kept by regeneration, unless a link to another
+        symlink(work.join("projects"), work.join("all"))?;
+        let existing = parse_workspace(
+            r#"{"folders": [{"path": "all"}, {"path": "api-link"}],
+                "workspaceManager": {"addedPaths": ["all", "api-link"]}}"#,
+        )?;
+        assert_eq!(
+            generate(true, Some(existing))?,
+            [".", "projects", "projects/api", "api-link"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fix_overlaps() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_dir("/team/services/api");
+        fs.add_dir("/team/web");
+        // Edited by hand, and kept as it is by --append
+        let existing = parse_workspace(
+            r#"{"folders": [
+                {"path": "./services", "name": "📦 services"},
+                {"path": "services/", "name": "Services"},
+                {"path": "./services/api"},
+                {"path": "services/api/", "name": "📦 api"}
+            ]}"#,
+        )?;
+        let generate = |fix_overlaps, collapse_nested| -> Result<Vec<(String, String)>> {
+            let opts = GenerateOptions {
+                scan_path: PathBuf::from("/team"),
+                name: "team".to_string(),
+                exclude_current: true,
+                sort: SortOrder::Name,
+                reconcile: ReconcileMode::Append,
+                fix_overlaps,
+                collapse_nested,
+                ..Default::default()
+            };
+            let namers = [default_namer(&opts)];
+            let workspace = generate_workspace(
+                &fs,
+                &namers,
+                &[],
+                &opts,
+                Path::new("/team"),
+                Some(existing.clone()),
+            )?;
+            Ok(workspace
+                .folders
+                .into_iter()
+                .map(|folder| (folder.path, folder.name))
+                .collect())
+        };
+        let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(path, name)| (path.to_string(), name.to_string()))
+                .collect()
+        };
+
+        assert_eq!(generate(false, None)?.len(), 5);
+        assert_eq!(
+            generate(true, None)?,
+            pairs(&[
+                ("services", "Services"),
+                ("services/api", "📦 api"),
+                ("web", "📦 web")
+            ])
+        );
+        assert_eq!(
+            generate(true, Some(CollapseNested::KeepOuter))?,
+            pairs(&[("services", "Services"), ("web", "📦 web")])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_regeneration_keeps_extra_folder_keys() -> Result<()> {
+        let fs = MemoryFs::new();
+        fs.add_dir("/work/api");
+        fs.add_dir("/work/web");
+        let existing = parse_workspace(
+            r#"{"folders": [
+                {"path": "./api", "name": "Backend", "color": "red", "pinned": {"at": 1}},
+                {"path": ".", "name": "🏗️ work"}
+            ], "workspaceManager": {"customNames": ["api"]}}"#,
+        )?;
+        let opts = GenerateOptions {
+            scan_path: PathBuf::from("/work"),
+            name: "work".to_string(),
+            sort: SortOrder::Name,
+            ..Default::default()
+        };
+        let namers = [default_namer(&opts)];
+        let workspace =
+            generate_workspace(&fs, &namers, &[], &opts, Path::new("/work"), Some(existing))?;
+
+        let folders = serde_json::to_value(&workspace.folders)?;
+        assert_eq!(
+            folders,
+            serde_json::json!([
+                {"path": ".", "name": "🏗️ work"},
+                {"path": "api", "name": "Backend", "color": "red", "pinned": {"at": 1}},
+                {"path": "web", "name": "📦 web"}
+            ])
+        );
+        // Extra keys don't keep a folder from matching its generated entry
+        assert_eq!(workspace.folders[1], WorkspaceFolder::new("api", "Backend"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_settings_round_trip() -> Result<()> {
+        let content = r#"{"folders": [], "settings": {
+            "editor.tabSize": 2,
+            "[rust]": {"editor.defaultFormatter": "rust-lang.rust-analyzer", "editor.formatOnSave": true},
+            "files.exclude": {"**/target": true},
+            "search.exclude": {"dist": true}
+        }, "launch": {"version": "0.2.0"}}"#;
+        let mut workspace = parse_workspace(content)?;
+        let original = workspace.settings.clone().unwrap();
+        let opts = GenerateOptions {
+            settings: vec![
+                ("editor.tabSize".to_string(), serde_json::json!(4)),
+                (
+                    "files.trimTrailingWhitespace".to_string(),
+                    serde_json::json!(true),
+                ),
+            ],
+            unset: vec!["search.exclude".to_string(), "not.there".to_string()],
+            ..Default::default()
+        };
+        apply_settings(&mut workspace, &opts);
+
+        let written = parse_workspace(&serde_json::to_string_pretty(&workspace)?)?;
+        assert_eq!(written, workspace);
+        let settings = written.settings.unwrap();
+        let keys: Vec<&str> = settings.keys().map(String::as_str).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "editor.tabSize",
+                "[rust]",
+                "files.exclude",
+                "files.trimTrailingWhitespace"
+            ]
+        );
+        assert_eq!(settings["editor.tabSize"], 4);
+        assert_eq!(settings["[rust]"], original["[rust]"]);
+        assert_eq!(settings["files.exclude"], original["files.exclude"]);
+        assert_eq!(written.launch.unwrap().version.as_deref(), Some("0.2.0"));
+
+        // Unsetting in a file without settings does not add an empty section
+        let mut empty = WorkspaceFile::default();
+        apply_settings(
+            &mut empty,
+            &GenerateOptions {
+                unset: vec!["a".into()],
+                ..Default::default()
+            },
+        );
+        assert!(empty.settings.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_hidden_folders() -> Result<()> {
+        let mut workspace = parse_workspace(
+            r#"{"folders": [
+                {"path": ".", "name": "root"},
+                {"path": "services/api", "name": "api"},
+                {"path": "./web/", "name": "web"},
+                {"path": "../shared", "name": "shared"}
+            ], "settings": {"files.exclude": {"**/.git": true, "web": false}, "editor.tabSize": 2}}"#,
+        )?;
+        sync_hidden_folders(&mut workspace, true);
+        let excludes = |ws: &WorkspaceFile| ws.settings.as_ref().unwrap()["files.exclude"].clone();
+        // The user's own "web" entry is left as it is, and not claimed
+        assert_eq!(
+            excludes(&workspace),
+            serde_json::json!({"**/.git": true, "web": false, "services/api": true})
+        );
+        assert_eq!(workspace.metadata().hidden_paths, vec!["services/api"]);
+
+        // A pruned folder loses its entry, a new one gains one
+        workspace.folders[1] = WorkspaceFolder::new("docs", "docs");
+        sync_hidden_folders(&mut workspace, true);
+        assert_eq!(
+            excludes(&workspace),
+            serde_json::json!({"**/.git": true, "web": false, "docs": true})
+        );
+
+        sync_hidden_folders(&mut workspace, false);
+        assert_eq!(
+            excludes(&workspace),
+            serde_json::json!({"**/.git": true, "web": false})
+        );
+        assert!(workspace.metadata().hidden_paths.is_empty());
+
+        // Entries are only added while the current directory is a folder
+        let mut workspace = WorkspaceFile {
+            folders: vec![WorkspaceFolder::new("api", "api")],
+            ..Default::default()
+        };
+        sync_hidden_folders(&mut workspace, true);
+        assert!(workspace.settings.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_rust_analyzer_links() -> Result<()> {
+        let temp = TempDir::new()?;
+        for dir in ["api", "cli", "web", "tools"] {
+            fs::create_dir(temp.path().join(dir))?;
+        }
+        fs::write(temp.path().join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("api").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("cli").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("web").join("package.json"), "{}")?;
+        let mut workspace = parse_workspace(
+            r#"{"folders": [
+                {"path": ".", "name": "root"},
+                {"path": "api", "name": "api"},
+                {"path": "./cli/", "name": "cli"},
+                {"path": "web", "name": "web"}
+            ], "settings": {
+                "rust-analyzer.linkedProjects": ["../vendor/Cargo.toml", "cli/Cargo.toml"]
+            }}"#,
+        )?;
+        let links = |ws: &WorkspaceFile| {
+            ws.settings.as_ref().unwrap()["rust-analyzer.linkedProjects"].clone()
+        };
+
+        sync_rust_analyzer_links(&mut workspace, temp.path(), true);
+        let expected =
+            serde_json::json!(["../vendor/Cargo.toml", "cli/Cargo.toml", "api/Cargo.toml"]);
+        assert_eq!(links(&workspace), expected);
+        sync_rust_analyzer_links(&mut workspace, temp.path(), true);
+        assert_eq!(links(&workspace), expected);
+
+        // A vanished crate loses its entry; a new one is added
+        workspace.folders.remove(1);
+        fs::write(temp.path().join("tools").join("Cargo.toml"), "")?;
+        workspace
+            .folders
+            .push(WorkspaceFolder::new("tools", "tools"));
+        sync_rust_analyzer_links(&mut workspace, temp.path(), true);
+        assert_eq!(
+            links(&workspace),
+            serde_json::json!(["../vendor/Cargo.toml", "cli/Cargo.toml", "tools/Cargo.toml"])
+        );
+
+        sync_rust_analyzer_links(&mut workspace, temp.path(), false);
+        assert_eq!(
+            links(&workspace),
+            serde_json::json!(["../vendor/Cargo.toml", "cli/Cargo.toml"])
+        );
+        assert!(workspace.metadata().linked_projects.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_recorded_remotes() -> Result<()> {
+        let temp = TempDir::new()?;
+        let set_origin = |dir: &str, url: &str| -> Result<()> {
+            let git_dir = temp.path().join(dir).join(".git");
+            fs::create_dir_all(&git_dir)?;
+            let config = format!("[remote \"origin\"]\n\turl = {}\n", url);
+            fs::write(git_dir.join("config"), config)?;
+            Ok(())
+        };
+        set_origin("api", "git@example.com:org/api.git")?;
+        fs::create_dir_all(temp.path().join("web").join(".git"))?;
+        fs::create_dir(temp.path().join("docs"))?;
+        let mut workspace = parse_workspace(
+            r#"{"folders": [
+                    {"path": "api"}, {"path": "./web"}, {"path": "docs"}, {"path": "lib"}
+                ],
+                "workspaceManager": {"folders": {
+                    "web": {"remote": "git@example.com:org/old-web.git", "owner": "web-team"},
+                    "lib": {"remote": "git@example.com:org/lib.git"},
+                    "pruned": {"remote": "git@example.com:org/pruned.git"}
+                }}}"#,
+        )?;
+        let recorded = |ws: &WorkspaceFile| serde_json::to_value(ws.metadata().folders).unwrap();
+
+        sync_recorded_remotes(&mut workspace, temp.path(), true);
+        // web lost its origin but keeps its other keys; lib is missing, so keeps its URL
+        let expected = serde_json::json!({
+            "api": {"remote": "git@example.com:org/api.git"},
+            "lib": {"remote": "git@example.com:org/lib.git"},
+            "web": {"owner": "web-team"}
+        });
+        assert_eq!(recorded(&workspace), expected);
+        let first = serde_json::to_string(&workspace)?;
+        sync_recorded_remotes(&mut workspace, temp.path(), true);
+        assert_eq!(serde_json::to_string(&workspace)?, first);
+
+        set_origin("api", "https://example.com/org/api.git")?;
+        set_origin("web", "git@example.com:org/web.git")?;
+        sync_recorded_remotes(&mut workspace, temp.path(), true);
+        let metadata = workspace.metadata();
+        assert_eq!(
+            metadata.folders["api"].remote.as_deref(),
+            Some("https://example.com/org/api.git")
+        );
+        assert_eq!(
+            metadata.folders["web"].remote.as_deref(),
+            Some("git@example.com:org/web.git")
+        );
+
+        workspace.folders.remove(0);
+        sync_recorded_remotes(&mut workspace, temp.path(), true);
+        assert!(!workspace.metadata().folders.contains_key("api"));
+
+        sync_recorded_remotes(&mut workspace, temp.path(), false);
+        assert_eq!(
+            recorded(&workspace),
+            serde_json::json!({"web": {"owner": "web-team"}})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_heavy_dir_excludes() -> Result<()> {
+        let temp = TempDir::new()?;
+        for dir in ["api", "web", "docs"] {
+            fs::create_dir(temp.path().join(dir))?;
+        }
+        fs::write(temp.path().join("api").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("web").join("package.json"), "{}")?;
+        let mut workspace = parse_workspace(
+            r#"{"folders": [{"path": "api"}, {"path": "web"}, {"path": "docs"}],
+                "settings": {
+                    "editor.tabSize": 2,
+                    "search.exclude": {"**/dist": true, "**/node_modules/**": false}
+                }}"#,
+        )?;
+        let settings = |ws: &WorkspaceFile| serde_json::to_value(&ws.settings).unwrap();
+
+        sync_heavy_dir_excludes(&mut workspace, temp.path(), true);
+        let expected = serde_json::json!({
+            "editor.tabSize": 2,
+            "search.exclude": {
+                "**/dist": true,
+                "**/node_modules/**": false,
+                "**/target/**": true
+            },
+            "files.watcherExclude": {"**/target/**": true, "**/node_modules/**": true}
+        });
+        assert_eq!(settings(&workspace), expected);
+        let written = workspace.metadata().excluded_globs;
+        assert_eq!(written["search.exclude"], ["**/target/**"]);
+        // Running again changes nothing
+        let first = serde_json::to_string(&workspace)?;
+        sync_heavy_dir_excludes(&mut workspace, temp.path(), true);
+        sync_heavy_dir_excludes(&mut workspace, temp.path(), true);
+        assert_eq!(serde_json::to_string(&workspace)?, first);
+
+        // The globs of a vanished project type go, the user's stay
+        workspace.folders.remove(0);
+        sync_heavy_dir_excludes(&mut workspace, temp.path(), true);
+        assert_eq!(
+            settings(&workspace),
+            serde_json::json!({
+                "editor.tabSize": 2,
+                "search.exclude": {"**/dist": true, "**/node_modules/**": false},
+                "files.watcherExclude": {"**/node_modules/**": true}
+            })
+        );
+
+        sync_heavy_dir_excludes(&mut workspace, temp.path(), false);
+        assert_eq!(
+            settings(&workspace),
+            serde_json::json!({
+                "editor.tabSize": 2,
+                "search.exclude": {"**/dist": true, "**/node_modules/**": false}
+            })
+        );
+        assert!(workspace.metadata().excluded_globs.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_extension_recommendations() -> Result<()> {
+        let temp = TempDir::new()?;
+        for dir in ["api", "web"] {
+            fs::create_dir(temp.path().join(dir))?;
+        }
+        fs::write(temp.path().join("api").join("Cargo.toml"), "")?;
+        fs::write(temp.path().join("api").join("pyproject.toml"), "")?;
+        fs::write(temp.path().join("web").join("package.json"), "{}")?;
+        let mut workspace = parse_workspace(
+            r#"{"folders": [{"path": "api", "name": "api"}, {"path": "web", "name": "web"}],
+                "extensions": {
+                    "recommendations": ["Rust-Lang.rust-analyzer", "eamodio.gitlens"],
+                    "unwantedRecommendations": ["ms-vscode.cpptools"]
+                }}"#,
+        )?;
+        let mut opts = GenerateOptions {
+            recommend_extensions: true,
+            ..Default::default()
+        };
+        opts.extension_map
+            .insert("npm".to_string(), vec!["svelte.svelte-vscode".to_string()]);
+        let recommendations =
+            |ws: &WorkspaceFile| ws.extensions.as_ref().unwrap().recommendations.clone();
+
+        sync_extension_recommendations(&mut workspace, temp.path(), &opts);
+        let expected = vec![
+            "Rust-Lang.rust-analyzer",
+            "eamodio.gitlens",
+            "ms-python.python",
+            "svelte.svelte-vscode",
+        ];
+        assert_eq!(recommendations(&workspace), expected);
+        sync_extension_recommendations(&mut workspace, temp.path(), &opts);
+        assert_eq!(recommendations(&workspace), expected);
+
+        // The recommendations of a vanished project type go, the user's stay
+        workspace.folders.pop();
+        sync_extension_recommendations(&mut workspace, temp.path(), &opts);
+        assert_eq!(
+            recommendations(&workspace),
+            vec![
+                "Rust-Lang.rust-analyzer",
+                "eamodio.gitlens",
+                "ms-python.python"
+            ]
+        );
+
+        opts.recommend_extensions = false;
+        sync_extension_recommendations(&mut workspace, temp.path(), &opts);
+        let extensions = workspace.extensions.as_ref().unwrap();
+        assert_eq!(
+            extensions.recommendations,
+            vec!["Rust-Lang.rust-analyzer", "eamodio.gitlens"]
+        );
+        assert_eq!(
+            extensions.other["unwantedRecommendations"],
+            serde_json::json!(["ms-vscode.cpptools"])
+        );
+        assert!(workspace.metadata().recommended_extensions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_launch_template_merges_by_name() -> Result<()> {
+        let content = r#"{"folders": [], "launch": {
+            "version": "0.2.0",
+            "configurations": [
+                {"name": "Debug api", "type": "lldb", "request": "launch", "cargo": {}},
+                {"name": "Attach", "type": "lldb", "request": "attach", "pid": "${command:pid}"}
+            ],
+            "compounds": [{"name": "All", "configurations": ["Debug api", "Attach"]}],
+            "inputs": [{"id": "port", "type": "promptString"}]
+        }}"#;
+        let mut workspace = parse_workspace(content)?;
+        // Everything survives a round trip, including keys the model doesn't name
+        let original: serde_json::Value = serde_json::from_str(content)?;
+        assert_eq!(serde_json::to_value(&workspace)?, original);
+
+        let fs = MemoryFs::new();
+        let template = Path::new("/work/launch.json");
+        fs.add_file(
+            template,
+            r#"{
+                // shared with the team
+                "version": "0.2.0",
+                "configurations": [
+                    {"name": "Attach", "type": "lldb", "request": "attach", "pid": 4242},
+                    {"name": "Debug web", "type": "node", "request": "launch"},
+                ],
+            }"#,
+        );
+        apply_launch_template(&fs, &mut workspace, template)?;
+        let launch = workspace.launch.as_ref().unwrap();
+        let names: Vec<&str> = launch
+            .configurations
+            .iter()
+            .flatten()
+            .map(|configuration| configuration["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Debug api", "Attach", "Debug web"]);
+        assert_eq!(launch.configurations.as_ref().unwrap()[1]["pid"], 4242);
+        assert_eq!(
+            launch.compounds,
+            original["launch"]["compounds"].as_array().cloned()
+        );
+        assert_eq!(launch.other["inputs"], original["launch"]["inputs"]);
+
+        // Applying it again changes nothing
+        let merged = workspace.clone();
+        apply_launch_template(&fs, &mut workspace, template)?;
+        assert_eq!(workspace, merged);
+
+        fs.add_file(template, "{\"configurations\": 1}");
+        let err = apply_launch_template(&fs, &mut workspace, template).unwrap_err();
+        assert!(err.to_string().contains("Invalid launch template"));
+
+        // A missing template is an error naming it, not an empty merge
+        let missing = Path::new("/work/missing.json");
+        let err = apply_launch_template(&fs, &mut workspace, missing).unwrap_err();
+        assert!(matches!(&err, WorkspaceError::Io { path, source }
+            if path == missing && source.kind() == std::io::ErrorKind::NotFound));
+        assert_eq!(workspace, merged);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remote_uri_round_trip() {
+        let authority = "ssh-remote+build-box";
+        let uri = remote_uri(authority, Path::new("/home/me/src/./my api"));
+        assert_eq!(
+            uri,
+            "vscode-remote://ssh-remote+build-box/home/me/src/my%20api"
+        );
+        assert_eq!(
+            remote_uri_path(authority, &uri),
+            Some(PathBuf::from("/home/me/src/my api"))
+        );
+        assert_eq!(
+            remote_uri(authority, Path::new("/src/📦")),
+            "vscode-remote://ssh-remote+build-box/src/%F0%9F%93%A6"
+        );
+        assert_eq!(remote_uri_path("wsl+Ubuntu", &uri), None);
+        assert_eq!(remote_uri_path(authority, "file:///home/me"), None);
+
+        let mut folder = WorkspaceFolder::new("../api", "api");
+        remote_folder(&mut folder, authority, Path::new("/home/me/ws"));
+        assert_eq!(folder.path, "");
+        assert_eq!(
+            folder.uri.as_deref(),
+            Some("vscode-remote://ssh-remote+build-box/home/me/api")
+        );
+        local_folder(&mut folder, authority, Path::new("/home/me/ws"));
+        assert_eq!(folder, WorkspaceFolder::new("../api", "api"));
+    }
+
+    #[test]
+    fn test_map_to_container() {
+        let dir = Path::new("/home/me/src/team");
+        assert_eq!(
+            container_path("/workspaces/", dir, "."),
+            Some("/workspaces/team".to_string())
+        );
+        assert_eq!(
+            container_path("/workspaces", dir, "../shared/lib"),
+            Some("/workspaces/lib".to_string())
+        );
+        assert_eq!(container_path("/workspaces", dir, "/opt/lib"), None);
+
+        let mut workspace = WorkspaceFile {
+            folders: vec![
+                WorkspaceFolder::new("api", "api"),
+                WorkspaceFolder::new("vendor/api", "vendored api"),
+                WorkspaceFolder::new("web", "web"),
+            ],
+            ..Default::default()
+        };
+        map_to_container(&mut workspace, dir, "/workspaces");
+        let paths: Vec<&str> = workspace.folders.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, vec!["/workspaces/api", "/workspaces/web"]);
+    }
+}
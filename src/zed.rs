@@ -0,0 +1,106 @@
+//! Launcher scripts written next to a workspace by `--emit zed`. Zed has no multi-root
+//! workspace file, so the script opens every folder of the workspace with `zed`.
+
+use crate::quote::Dialect;
+use crate::{normalize_folder_path, write_atomic_executable, WorkspaceFile};
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The script written for the workspace file at `workspace_path`: `<name>.zed.sh`, or
+/// `<name>.zed.ps1` for PowerShell.
+pub fn script_path(workspace_path: &Path, dialect: Dialect) -> PathBuf {
+    let extension = match dialect {
+        Dialect::Posix => "zed.sh",
+        Dialect::PowerShell => "zed.ps1",
+    };
+    workspace_path.with_extension(extension)
+}
+
+/// A script that opens the folders of `workspace`, resolved against `workspace_dir`,
+/// in Zed. Paths are absolute so the script works from any directory, and arguments
+/// given to the script are passed on to `zed`. uri folders are left out.
+pub fn zed_script(workspace: &WorkspaceFile, workspace_dir: &Path, dialect: Dialect) -> String {
+    let paths: Vec<String> = workspace
+        .folders
+        .iter()
+        .filter_map(|folder| folder.resolve(workspace_dir))
+        .map(|dir| normalize_folder_path(&dir.to_string_lossy()))
+        .collect();
+    let command = dialect.command_line("zed", &paths);
+    match dialect {
+        Dialect::Posix => format!("#!/bin/sh\nexec {} \"$@\"\n", command),
+        Dialect::PowerShell => format!("{} @args\n", command),
+    }
+}
+
+/// Writes the Zed script of the workspace file at `workspace_path` for the shell of
+/// this platform unless it already has this content. Returns the path of the script
+/// and whether it was written. `workspace_path` must name its directory, which the
+/// folders are resolved against.
+pub fn write_script(workspace_path: &Path, workspace: &WorkspaceFile) -> Result<(PathBuf, bool)> {
+    let dialect = Dialect::native();
+    let path = script_path(workspace_path, dialect);
+    let workspace_dir = match workspace_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => anyhow::bail!(
+            "The directory of '{}' is needed to write its Zed script",
+            workspace_path.display()
+        ),
+    };
+    let content = zed_script(workspace, workspace_dir, dialect);
+    if fs::read(&path).is_ok_and(|existing| existing == content.as_bytes()) {
+        return Ok((path, false));
+    }
+    write_atomic_executable(&path, content.as_bytes())?;
+    Ok((path, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_workspace;
+
+    #[test]
+    fn test_zed_script() -> anyhow::Result<()> {
+        let workspace = parse_workspace(
+            r#"{"folders": [
+                {"path": ".", "name": "root"},
+                {"path": "my api", "name": "api"},
+                {"path": "../bob's tools", "name": "tools"},
+                {"uri": "vscode-remote://ssh-remote+box/srv", "name": "remote"}
+            ]}"#,
+        )?;
+        let dir = Path::new("/home/me/ws");
+        assert_eq!(
+            zed_script(&workspace, dir, Dialect::Posix),
+            "#!/bin/sh\nexec zed /home/me/ws '/home/me/ws/my api' \
+             '/home/me/bob'\"'\"'s tools' \"$@\"\n"
+        );
+        assert_eq!(
+            zed_script(&workspace, dir, Dialect::PowerShell),
+            "zed /home/me/ws '/home/me/ws/my api' '/home/me/bob''s tools' @args\n"
+        );
+        assert_eq!(
+            script_path(&dir.join("team.code-workspace"), Dialect::Posix),
+            dir.join("team.zed.sh")
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_script_is_executable() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::TempDir::new()?;
+        let workspace_path = temp.path().join("team.code-workspace");
+        let workspace = parse_workspace(r#"{"folders": [{"path": "api", "name": "api"}]}"#)?;
+        let (path, written) = write_script(&workspace_path, &workspace)?;
+        assert!(written);
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o111, 0o111);
+        assert!(fs::read_to_string(&path)?.contains("/api"));
+        assert!(!write_script(&workspace_path, &workspace)?.1);
+        Ok(())
+    }
+}
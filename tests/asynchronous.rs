@@ -0,0 +1,48 @@
+//! The async API, built with the `async` feature: generate a workspace, write it and
+//! read it back without blocking the runtime.
+#![cfg(feature = "async")]
+
+use anyhow::Result;
+use std::fs;
+use tempfile::TempDir;
+use workspace_manager::asynchronous::{read_workspace, scan, write_workspace};
+use workspace_manager::{ScanOptions, SortOrder, TaskAction, WorkspaceBuilder, WriteOptions};
+
+#[tokio::test]
+async fn test_generate_write_and_read() -> Result<()> {
+    let temp = TempDir::new()?;
+    let root = temp.path().canonicalize()?;
+    for dir in ["api", "web", ".cache"] {
+        fs::create_dir(root.join(dir))?;
+    }
+
+    let outcome = scan(
+        &root,
+        ScanOptions {
+            sort: SortOrder::Name,
+            ..Default::default()
+        },
+    )
+    .await?;
+    assert_eq!(
+        outcome.directories,
+        vec![root.join("api"), root.join("web")]
+    );
+
+    let (workspace, changes) = WorkspaceBuilder::new(&root)
+        .name("team")
+        .sort(SortOrder::Name)
+        .build_async()
+        .await?;
+    assert_eq!(changes.task_action, TaskAction::Created);
+    let path = root.join("team.code-workspace");
+    assert!(write_workspace(&path, workspace.clone(), WriteOptions::default()).await?);
+    // Unchanged, so not written again
+    assert!(!write_workspace(&path, workspace.clone(), WriteOptions::default()).await?);
+
+    let read = read_workspace(&path).await?;
+    assert_eq!(read, workspace);
+    let names: Vec<&str> = read.folders.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["🏗️ team", "📦 api", "📦 web"]);
+    Ok(())
+}
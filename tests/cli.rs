@@ -0,0 +1,1523 @@
+//! End-to-end runs of the `workspace-manager` binary against temporary directories.
+
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+/// Runs the binary in `dir`, isolated from the user's configuration, registry and
+/// `WORKSPACE_MANAGER_*` variables, whether or not it succeeds.
+fn run_unchecked(dir: &Path, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_workspace-manager"));
+    for (key, _) in std::env::vars_os() {
+        if key.to_string_lossy().starts_with("WORKSPACE_MANAGER_") {
+            command.env_remove(key);
+        }
+    }
+    command
+        .args(args)
+        .current_dir(dir)
+        .env("WORKSPACE_MANAGER_REGISTRY", dir.join(".registry.json"))
+        .env("XDG_CONFIG_HOME", dir.join(".config"))
+        .output()
+        .expect("failed to run workspace-manager")
+}
+
+/// Like [`run_unchecked`], failing the test when the run fails.
+fn run(dir: &Path, args: &[&str]) -> Output {
+    let output = run_unchecked(dir, args);
+    assert!(
+        output.status.success(),
+        "workspace-manager {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn read_json(path: &Path) -> Value {
+    serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn folder_paths(workspace: &Value) -> Vec<&str> {
+    workspace["folders"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|folder| folder["path"].as_str().unwrap())
+        .collect()
+}
+
+fn projects() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    fs::create_dir(temp.path().join("api")).unwrap();
+    fs::create_dir(temp.path().join("web")).unwrap();
+    temp
+}
+
+#[test]
+fn test_generate_includes_current_directory_by_default() {
+    let temp = projects();
+    run(temp.path(), &["--name", "team", "--sort", "name"]);
+
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "api", "web"]);
+    let task = &workspace["tasks"]["tasks"][0];
+    assert_eq!(task["label"], "Update Workspace");
+    assert_eq!(task["args"][0], "--name");
+
+    run(
+        temp.path(),
+        &["-n", "team", "--exclude-current", "--sort", "name"],
+    );
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec!["api", "web"]);
+}
+
+#[test]
+fn test_regeneration_preserves_settings_and_user_tasks() {
+    let temp = projects();
+    let path = temp.path().join("team.code-workspace");
+    fs::write(
+        &path,
+        r#"{
+  // written by hand
+  "folders": [],
+  "settings": {"editor.tabSize": 2},
+  "extensions": {"recommendations": ["rust-lang.rust-analyzer"]},
+  "tasks": {"version": "2.0.0", "tasks": [
+    {"label": "Build", "type": "shell", "command": "make", "problemMatcher": ["$gcc"]}
+  ]},
+}"#,
+    )
+    .unwrap();
+
+    run(temp.path(), &["-n", "team", "--update-task"]);
+    let workspace = read_json(&path);
+    assert_eq!(workspace["settings"]["editor.tabSize"], 2);
+    assert_eq!(
+        workspace["extensions"]["recommendations"][0],
+        "rust-lang.rust-analyzer"
+    );
+    let labels: Vec<&str> = workspace["tasks"]["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, vec!["Build", "Update Workspace"]);
+    assert_eq!(workspace["tasks"]["tasks"][0]["problemMatcher"][0], "$gcc");
+}
+
+#[test]
+fn test_flags_of_early_versions_still_parse() {
+    let temp = projects();
+    run(
+        temp.path(),
+        &[
+            "--name",
+            "old",
+            "--include-current",
+            "--update-tasks",
+            "--path",
+            ".",
+        ],
+    );
+
+    let workspace = read_json(&temp.path().join("old.code-workspace"));
+    assert_eq!(folder_paths(&workspace)[0], ".");
+    let args = &workspace["tasks"]["tasks"][0]["args"];
+    assert_eq!(
+        args,
+        &serde_json::json!([
+            "--name",
+            "old",
+            "--no-exclude-current",
+            "--update-task",
+            "--path",
+            "."
+        ])
+    );
+}
+
+#[test]
+fn test_update_reruns_the_stored_options() {
+    let temp = projects();
+    run(temp.path(), &["-n", "team", "--exclude", "web"]);
+    fs::create_dir(temp.path().join("docs")).unwrap();
+
+    run(temp.path(), &["update", "team.code-workspace"]);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    let mut paths = folder_paths(&workspace);
+    paths.sort();
+    assert_eq!(paths, vec![".", "api", "docs"]);
+}
+
+#[test]
+fn test_surgical_rewrites_only_the_folders() {
+    let temp = projects();
+    let path = temp.path().join("team.code-workspace");
+    let settings = "  \"settings\": {\"files.exclude\": {\"**/.git\": true}, \"n\": 1.0e2},\n";
+    let original = format!(
+        "{{\n  // reviewed\n{}  \"folders\": [{{\"path\": \"api\"}}],\n  /* end */\n}}\n",
+        settings
+    );
+    fs::write(&path, &original).unwrap();
+
+    let output = run(
+        temp.path(),
+        &[
+            "-n",
+            "team",
+            "--exclude-current",
+            "--sort",
+            "name",
+            "--surgical",
+        ],
+    );
+    let written = fs::read_to_string(&path).unwrap();
+    let start = format!("{{\n  // reviewed\n{}  \"folders\": [\n", settings);
+    assert!(written.starts_with(&start), "{}", written);
+    assert!(written.ends_with("\n  ],\n  /* end */\n}\n"), "{}", written);
+    assert!(written.contains("\"path\": \"web\""));
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+}
+
+#[test]
+fn test_color_choice() {
+    let temp = TempDir::new().unwrap();
+    fs::write(temp.path().join("old.code-workspace"), r#"{"folders": []}"#).unwrap();
+    fs::write(
+        temp.path().join("new.code-workspace"),
+        r#"{"folders": [{"path": "api", "name": "📦 api"}]}"#,
+    )
+    .unwrap();
+    let diff = |args_after: &[&str]| {
+        let mut args = vec!["diff", "old.code-workspace", "new.code-workspace"];
+        args.extend(args_after);
+        String::from_utf8(run_unchecked(temp.path(), &args).stdout).unwrap()
+    };
+
+    // Output that isn't a terminal is plain unless asked otherwise
+    assert_eq!(diff(&[]), "+ folder '📦 api' (api)\n");
+    assert_eq!(
+        diff(&["--color", "always"]),
+        "\x1b[32m+ folder '📦 api' (api)\x1b[0m\n"
+    );
+    assert_eq!(diff(&["--color=never"]), "+ folder '📦 api' (api)\n");
+}
+
+#[test]
+fn test_exclude_name() {
+    let temp = projects();
+    fs::create_dir(temp.path().join("docs")).unwrap();
+    fs::write(
+        temp.path().join(".workspace-manager.toml"),
+        "exclude_name = [\"docs\"]\nsort = \"name\"\n",
+    )
+    .unwrap();
+
+    let output = run(temp.path(), &["-n", "team", "--verbose"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("excluded by name 'docs'"), "{}", stderr);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "api", "web"]);
+
+    // Names given on the command line replace those of the configuration
+    let args = ["-n", "team", "--exclude-name", "API", "--verbose"];
+    run(temp.path(), &args);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "api", "docs", "web"]);
+
+    // A new file's task carries the names on
+    fs::remove_file(temp.path().join("team.code-workspace")).unwrap();
+    let output = run(temp.path(), &[&args[..], &["--exclude-name-ci"]].concat());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("excluded by name 'API'"), "{}", stderr);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "docs", "web"]);
+    let task_args = &workspace["tasks"]["tasks"][0]["args"];
+    assert_eq!(
+        task_args,
+        &serde_json::json!([
+            "--name",
+            "team",
+            "--exclude-name",
+            "API",
+            "--exclude-name-ci",
+            "--path",
+            "."
+        ])
+    );
+}
+
+#[test]
+fn test_exclude_in() {
+    let temp = projects();
+    fs::create_dir(temp.path().join("all")).unwrap();
+    fs::write(
+        temp.path().join("all/everything.code-workspace"),
+        r#"{"folders": [{"path": "../api"}]}"#,
+    )
+    .unwrap();
+
+    let output = run(
+        temp.path(),
+        &[
+            "-n",
+            "web-only",
+            "--sort",
+            "name",
+            "--exclude-in",
+            "all/everything.code-workspace",
+            "--exclude-in",
+            "missing.code-workspace",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("missing.code-workspace"), "{}", stderr);
+    let workspace = read_json(&temp.path().join("web-only.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "all", "web"]);
+    let task_args = workspace["tasks"]["tasks"][0]["args"].as_array().unwrap();
+    assert!(task_args.contains(&Value::from("all/everything.code-workspace")));
+}
+
+#[test]
+fn test_min_depth() {
+    let temp = TempDir::new().unwrap();
+    for dir in ["acme/api/src", "acme/web/src", "globex/app/src"] {
+        fs::create_dir_all(temp.path().join(dir)).unwrap();
+    }
+    let depths = |min: &str, max: &str| {
+        let args = [
+            "-n",
+            "work",
+            "--sort",
+            "name",
+            "--min-depth",
+            min,
+            "--max-depth",
+            max,
+        ];
+        fs::remove_file(temp.path().join("work.code-workspace")).ok();
+        run(temp.path(), &args);
+        read_json(&temp.path().join("work.code-workspace"))
+    };
+
+    let workspace = depths("2", "2");
+    assert_eq!(
+        folder_paths(&workspace),
+        vec![".", "acme/api", "acme/web", "globex/app"]
+    );
+    let task_args = workspace["tasks"]["tasks"][0]["args"].as_array().unwrap();
+    assert!(task_args.contains(&Value::from("--min-depth")));
+    let workspace = depths("3", "3");
+    assert_eq!(
+        folder_paths(&workspace),
+        vec![".", "acme/api/src", "acme/web/src", "globex/app/src"]
+    );
+    let workspace = depths("1", "1");
+    assert_eq!(folder_paths(&workspace), vec![".", "acme", "globex"]);
+
+    let output = run_unchecked(temp.path(), &["-n", "work", "--min-depth", "2"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--min-depth 2 is greater than --max-depth 1"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_relative_to() {
+    let temp = TempDir::new().unwrap();
+    for dir in ["work/api", "workspaces"] {
+        fs::create_dir_all(temp.path().join(dir)).unwrap();
+    }
+    let file_dir = temp.path().join("workspaces");
+    let args = ["-n", "work", "--path", "../work", "--relative-to", ".."];
+    let output = run(&file_dir, &args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("don't resolve from"), "{}", stderr);
+    let workspace = read_json(&file_dir.join("work.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "work/api"]);
+    let task_args = workspace["tasks"]["tasks"][0]["args"].as_array().unwrap();
+    assert!(task_args.contains(&Value::from("--relative-to")));
+
+    let output = run_unchecked(
+        &file_dir,
+        &[&args[..], &["--container-root", "/w"]].concat(),
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_paths_from() {
+    let temp = projects();
+    fs::write(temp.path().join("dirs.txt"), "web\nmissing\n").unwrap();
+    let output = run(temp.path(), &["-n", "team", "--paths-from", "dirs.txt"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("skipping 'missing'"), "{}", stderr);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "web"]);
+    let task_args = workspace["tasks"]["tasks"][0]["args"].as_array().unwrap();
+    assert!(task_args.contains(&Value::from("dirs.txt")));
+
+    // A list piped in can't be piped in again by the task
+    fs::remove_file(temp.path().join("team.code-workspace")).unwrap();
+    run(
+        temp.path(),
+        &["-n", "team", "--paths-from0", "-", "--min-folders", "0"],
+    );
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec!["."]);
+    assert!(workspace.get("tasks").is_none());
+}
+
+#[test]
+fn test_folders_file() {
+    let temp = projects();
+    fs::write(
+        temp.path().join("folders.txt"),
+        "# Curated in review\nweb\nretired\n",
+    )
+    .unwrap();
+    let args = ["-n", "team", "--folders-file", "folders.txt"];
+    let output = run(temp.path(), &args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("retired"), "{}", stderr);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "web"]);
+    let task_args = workspace["tasks"]["tasks"][0]["args"].as_array().unwrap();
+    assert!(task_args.contains(&Value::from("folders.txt")));
+
+    let output = run_unchecked(
+        temp.path(),
+        &[&args[..], &["--missing-folders", "error"]].concat(),
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_append() {
+    let temp = projects();
+    run(temp.path(), &["-n", "team", "--sort", "name"]);
+    fs::create_dir(temp.path().join("aaa")).unwrap();
+    fs::remove_dir(temp.path().join("web")).unwrap();
+
+    // A dry run shows the folders it would add without recording the mode
+    let output = run(temp.path(), &["-n", "team", "--append", "--dry-run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("(aaa)"), "{}", stdout);
+    assert!(!stdout.contains("(web)"), "{}", stdout);
+    assert!(!temp.path().join(".workspace-manager.toml").exists());
+
+    run(temp.path(), &["-n", "team", "--append"]);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "api", "web", "aaa"]);
+    let config = fs::read_to_string(temp.path().join(".workspace-manager.toml")).unwrap();
+    assert!(config.contains("append = true"), "{}", config);
+
+    // The remembered mode applies to later runs
+    fs::create_dir(temp.path().join("abc")).unwrap();
+    run(temp.path(), &["-n", "team"]);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(
+        folder_paths(&workspace),
+        vec![".", "api", "web", "aaa", "abc"]
+    );
+}
+
+#[test]
+fn test_dry_run_records_nothing() {
+    let temp = projects();
+    let output = run(
+        temp.path(),
+        &[
+            "-n",
+            "team",
+            "--dry-run",
+            "--no-task",
+            "--task-command",
+            "portable",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Recorded"), "{}", stdout);
+    assert!(!temp.path().join(".workspace-manager.toml").exists());
+    assert!(!temp.path().join("team.code-workspace").exists());
+
+    run(temp.path(), &["-n", "team", "--no-task"]);
+    let config = fs::read_to_string(temp.path().join(".workspace-manager.toml")).unwrap();
+    assert!(config.contains("no_task = true"), "{}", config);
+}
+
+#[test]
+fn test_name_defaults_to_repository() {
+    let temp = TempDir::new().unwrap();
+    let scripts = temp.path().join("monorepo/scripts");
+    fs::create_dir_all(temp.path().join("monorepo/.git")).unwrap();
+    fs::create_dir_all(scripts.join("deploy")).unwrap();
+
+    let output = run(&scripts, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Named the workspace 'monorepo' after the git repository"),
+        "{}",
+        stdout
+    );
+    assert!(scripts.join("monorepo.code-workspace").exists());
+
+    // --name always wins
+    let output = run(&scripts, &["--name", "tools"]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Named the workspace"));
+    assert!(scripts.join("tools.code-workspace").exists());
+}
+
+#[test]
+fn test_discover_workspace_in_parent() {
+    let temp = projects();
+    run(temp.path(), &["-n", "team", "--sort", "name"]);
+    let src = temp.path().join("api/src");
+    fs::create_dir_all(src.join("handlers")).unwrap();
+
+    let output = run(&src, &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("team.code-workspace' found in a parent"),
+        "{}",
+        stdout
+    );
+    assert!(!src.join("src.code-workspace").exists());
+    // Updated with the folders found here, relative to the file
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert!(folder_paths(&workspace).contains(&"api/src/handlers"));
+
+    run(&src, &["--no-discover"]);
+    assert!(src.join("src.code-workspace").exists());
+
+    fs::remove_file(src.join("src.code-workspace")).unwrap();
+    fs::write(
+        temp.path().join("other.code-workspace"),
+        "{\"folders\": []}",
+    )
+    .unwrap();
+    let output = run_unchecked(&src, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("other.code-workspace"), "{}", stderr);
+}
+
+#[test]
+fn test_refresh_all() {
+    let temp = TempDir::new().unwrap();
+    for dir in ["team/api", "team/web", "solo/lib", "broken/app"] {
+        fs::create_dir_all(temp.path().join(dir)).unwrap();
+    }
+    run(&temp.path().join("team"), &["-n", "team"]);
+    run(&temp.path().join("solo"), &["-n", "solo"]);
+    run(&temp.path().join("broken"), &["-n", "broken"]);
+    fs::write(
+        temp.path().join("hand-made.code-workspace"),
+        "{\"folders\": []}",
+    )
+    .unwrap();
+    fs::create_dir(temp.path().join("team/docs")).unwrap();
+    // Its stored options no longer parse
+    let broken = temp.path().join("broken/broken.code-workspace");
+    let content = fs::read_to_string(&broken).unwrap();
+    fs::write(
+        &broken,
+        content.replacen("\"--path\"", "\"--no-such-flag\"", 1),
+    )
+    .unwrap();
+
+    let output = run_unchecked(temp.path(), &["refresh-all", "--root", "."]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("4 workspace file(s)") && stdout.contains("1 changed, 1 unchanged"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("1 failed, 1 unmanaged"), "{}", stdout);
+    assert!(stdout.contains("  hand-made.code-workspace"), "{}", stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("broken.code-workspace"), "{}", stderr);
+    // The failure didn't stop the others
+    let workspace = read_json(&temp.path().join("team/team.code-workspace"));
+    assert!(folder_paths(&workspace).contains(&"docs"));
+}
+
+#[test]
+fn test_limit() {
+    let temp = projects();
+    let output = run(
+        temp.path(),
+        &["-n", "team", "--sort", "name", "--limit", "1"],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("1 more new folder(s) were left out"),
+        "{}",
+        stderr
+    );
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "api"]);
+    let task_args = workspace["tasks"]["tasks"][0]["args"].as_array().unwrap();
+    assert!(!task_args.contains(&Value::from("--limit")));
+}
+
+#[test]
+fn test_replace() {
+    let temp = projects();
+    fs::create_dir(temp.path().join("shared")).unwrap();
+    run(temp.path(), &["-n", "team", "--exclude", "shared"]);
+    run(temp.path(), &["add", "shared"]);
+
+    // Regeneration keeps the added folder
+    run(temp.path(), &["-n", "team", "--exclude", "shared"]);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert!(folder_paths(&workspace).contains(&"shared"));
+
+    // Removing it needs confirmation, which isn't possible without a terminal
+    let args = ["-n", "team", "--exclude", "shared", "--replace"];
+    let output = run_unchecked(temp.path(), &args);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("removes 1 folder(s)"), "{}", stderr);
+    assert!(stderr.contains("--yes"), "{}", stderr);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert!(folder_paths(&workspace).contains(&"shared"));
+
+    run(temp.path(), &[&args[..], &["--yes"]].concat());
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert!(!folder_paths(&workspace).contains(&"shared"));
+    assert!(run_unchecked(temp.path(), &["--prune", "--replace"])
+        .status
+        .code()
+        .is_some_and(|code| code == 2));
+}
+
+#[test]
+fn test_exit_codes() {
+    let temp = projects();
+    let code = |args: &[&str]| run_unchecked(temp.path(), args).status.code();
+
+    assert_eq!(code(&["-n", "team"]), Some(0));
+    // Running again changes nothing and still succeeds
+    assert_eq!(code(&["-n", "team"]), Some(0));
+    assert_eq!(code(&["rename", "nothing", "else"]), Some(1));
+    assert_eq!(code(&["--no-such-flag"]), Some(2));
+    assert_eq!(code(&["-n", "a/b"]), Some(2));
+    let output = run_unchecked(temp.path(), &["-n", "team", "--exclude-regex", "(a"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid regex '(a'"));
+
+    fs::write(temp.path().join("same.code-workspace"), "{\"folders\": []}").unwrap();
+    fs::write(
+        temp.path().join("other.code-workspace"),
+        "{\"folders\": []}",
+    )
+    .unwrap();
+    let diff = ["diff", "same.code-workspace", "other.code-workspace"];
+    assert_eq!(code(&diff), Some(0));
+    fs::write(
+        temp.path().join("other.code-workspace"),
+        r#"{"folders": [{"path": "api"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(code(&diff), Some(3));
+    let output = run_unchecked(temp.path(), &[&diff[..], &["--json"]].concat());
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["status"], 3);
+
+    assert_eq!(code(&["-n", "team", "--min-folders", "5"]), Some(4));
+    assert_eq!(code(&["-n", "team", "--max-folders", "1"]), Some(4));
+
+    fs::write(
+        temp.path().join("broken.code-workspace"),
+        "{\"folders\": 3}",
+    )
+    .unwrap();
+    assert_eq!(code(&["list", "broken.code-workspace"]), Some(5));
+    assert_eq!(code(&["doctor", "broken.code-workspace"]), Some(5));
+    // Generating over a malformed file leaves it as it was
+    let output = run_unchecked(temp.path(), &["-n", "broken"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("broken.code-workspace"));
+    assert_eq!(
+        fs::read_to_string(temp.path().join("broken.code-workspace")).unwrap(),
+        "{\"folders\": 3}"
+    );
+}
+
+#[test]
+fn test_tasks_remove() {
+    let temp = projects();
+    run(temp.path(), &["-n", "one"]);
+    run(temp.path(), &["-n", "two", "--task-label", "Refresh"]);
+
+    let output = run(
+        temp.path(),
+        &[
+            "tasks",
+            "remove",
+            "one.code-workspace",
+            "two.code-workspace",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Removed the 'Update Workspace' task"));
+    assert!(stdout.contains("Removed the 'Refresh' task"));
+    for name in ["one", "two"] {
+        let workspace = read_json(&temp.path().join(format!("{}.code-workspace", name)));
+        assert!(workspace.get("tasks").is_none());
+        assert!(workspace.get("workspaceManager").is_none());
+        assert_eq!(folder_paths(&workspace).len(), 3);
+    }
+
+    // Nothing left to remove is not an error
+    let output = run(temp.path(), &["tasks", "remove", "one.code-workspace"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No managed task"));
+}
+
+#[test]
+fn test_set_and_unset_settings() {
+    let temp = projects();
+    let path = temp.path().join("team.code-workspace");
+    fs::write(
+        &path,
+        r#"{"folders": [], "settings": {"[rust]": {"editor.formatOnSave": true}, "old": 1}}"#,
+    )
+    .unwrap();
+
+    run(
+        temp.path(),
+        &[
+            "-n",
+            "team",
+            "--set",
+            "files.trimTrailingWhitespace=true",
+            "--set",
+            "editor.fontFamily=Fira Code",
+            "--unset",
+            "old",
+        ],
+    );
+    let settings = &read_json(&path)["settings"];
+    assert_eq!(settings["[rust]"]["editor.formatOnSave"], true);
+    assert_eq!(settings["files.trimTrailingWhitespace"], true);
+    assert_eq!(settings["editor.fontFamily"], "Fira Code");
+    assert!(settings.get("old").is_none());
+}
+
+#[test]
+fn test_launch_template() {
+    let temp = projects();
+    let path = temp.path().join("team.code-workspace");
+    let launch = r#"{"version": "0.2.0", "configurations": [
+        {"name": "Debug", "type": "lldb", "request": "launch", "program": "old"}
+    ], "compounds": [{"name": "Both", "configurations": ["Debug", "Web"]}]}"#;
+    fs::write(&path, format!(r#"{{"folders": [], "launch": {}}}"#, launch)).unwrap();
+
+    // Without the option the section is left exactly as it was
+    run(temp.path(), &["-n", "team"]);
+    assert_eq!(
+        read_json(&path)["launch"],
+        serde_json::from_str::<Value>(launch).unwrap()
+    );
+
+    fs::write(
+        temp.path().join("template.json"),
+        r#"{"configurations": [
+            {"name": "Debug", "type": "lldb", "request": "launch", "program": "new"},
+            {"name": "Web", "type": "node", "request": "launch"}
+        ]}"#,
+    )
+    .unwrap();
+    run(
+        temp.path(),
+        &["-n", "team", "--launch-template", "template.json"],
+    );
+    let workspace = read_json(&path);
+    let configurations = workspace["launch"]["configurations"].as_array().unwrap();
+    assert_eq!(configurations.len(), 2);
+    assert_eq!(configurations[0]["program"], "new");
+    assert_eq!(configurations[1]["name"], "Web");
+    assert_eq!(workspace["launch"]["compounds"][0]["name"], "Both");
+}
+
+#[test]
+fn test_copy_settings_from() {
+    let temp = projects();
+    let path = temp.path().join("team.code-workspace");
+    run(temp.path(), &["-n", "team"]);
+    let mut workspace = read_json(&path);
+    workspace["settings"] = serde_json::json!({"editor.tabSize": 2});
+    fs::write(&path, workspace.to_string()).unwrap();
+    let source = r#"{
+        // The team's shared setup
+        "settings": {"editor.tabSize": 4, "files.trimTrailingWhitespace": true},
+        "extensions": {"recommendations": ["rust-lang.rust-analyzer"]},
+    }"#;
+    fs::write(temp.path().join("shared.code-workspace"), source).unwrap();
+    let copy = ["--copy-settings-from", "shared.code-workspace"];
+    let extensions = ["--copy-extensions-from", "shared.code-workspace"];
+
+    // A dry run lists the keys that would arrive and writes nothing
+    let output = run(
+        temp.path(),
+        &[&["-n", "team", "--dry-run"][..], &copy, &extensions].concat(),
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("~ setting 'files.trimTrailingWhitespace'"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("editor.tabSize"), "{}", stdout);
+    assert!(
+        stdout.contains("+ extension 'rust-lang.rust-analyzer'"),
+        "{}",
+        stdout
+    );
+    assert_eq!(read_json(&path), workspace);
+
+    let output = run_unchecked(
+        temp.path(),
+        &[&["-n", "team", "--settings-merge", "error"][..], &copy].concat(),
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("settings.editor.tabSize"));
+    assert_eq!(read_json(&path), workspace);
+
+    run(
+        temp.path(),
+        &[&["-n", "team", "--settings-merge", "theirs"][..], &copy].concat(),
+    );
+    let settings = &read_json(&path)["settings"];
+    assert_eq!(settings["editor.tabSize"], 4);
+    assert_eq!(settings["files.trimTrailingWhitespace"], true);
+    assert_eq!(
+        fs::read_to_string(temp.path().join("shared.code-workspace")).unwrap(),
+        source
+    );
+
+    // Without a settings section there is only a warning
+    fs::write(temp.path().join("bare.code-workspace"), "{}").unwrap();
+    let output = run(
+        temp.path(),
+        &["-n", "team", "--copy-settings-from", "bare.code-workspace"],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("has no settings section"), "{}", stderr);
+
+    // The merge strategy needs something to merge
+    let output = run_unchecked(temp.path(), &["-n", "team", "--settings-merge", "theirs"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_remote_authority_is_preserved() {
+    let temp = projects();
+    let path = temp.path().join("team.code-workspace");
+    fs::write(
+        &path,
+        r#"{"remoteAuthority": "ssh-remote+build-box", "transient": false, "folders": []}"#,
+    )
+    .unwrap();
+
+    let output = run(temp.path(), &["-n", "team"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("opened on 'ssh-remote+build-box'"));
+    let workspace = read_json(&path);
+    let keys: Vec<&String> = workspace.as_object().unwrap().keys().collect();
+    assert_eq!(keys[..3], ["remoteAuthority", "transient", "folders"]);
+    assert_eq!(workspace["transient"], false);
+
+    // Nothing new to add, nothing to warn about
+    let output = run(temp.path(), &["-n", "team"]);
+    assert!(output.stderr.is_empty());
+
+    run(temp.path(), &["-n", "team", "--respect-remote-authority"]);
+    let workspace = read_json(&path);
+    assert_eq!(workspace["remoteAuthority"], "ssh-remote+build-box");
+    let root = temp.path().canonicalize().unwrap();
+    for folder in workspace["folders"].as_array().unwrap() {
+        assert!(folder.get("path").is_none());
+        let uri = folder["uri"].as_str().unwrap();
+        assert!(uri.starts_with(&format!(
+            "vscode-remote://ssh-remote+build-box{}",
+            root.display()
+        )));
+    }
+}
+
+#[test]
+fn test_dual_host_and_container_files() {
+    let temp = projects();
+    run(
+        temp.path(),
+        &["-n", "team", "--container-root", "/workspaces", "--dual"],
+    );
+
+    let host_path = temp.path().join("team.code-workspace");
+    let container_path = temp.path().join("team.container.code-workspace");
+    let mut host = folder_paths(&read_json(&host_path))
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    host.sort();
+    assert_eq!(host, vec![".", "api", "web"]);
+    let root_name = temp.path().file_name().unwrap().to_string_lossy();
+    let container = read_json(&container_path);
+    let mut paths = folder_paths(&container);
+    paths.sort();
+    let mut expected = vec![
+        "/workspaces/api".to_string(),
+        format!("/workspaces/{}", root_name),
+        "/workspaces/web".to_string(),
+    ];
+    expected.sort();
+    assert_eq!(paths, expected);
+    assert_eq!(
+        container["workspaceManager"]["containerRoot"],
+        "/workspaces"
+    );
+
+    // Each file keeps its own path style when regenerated, and they don't merge
+    run(temp.path(), &["update", "team.container.code-workspace"]);
+    assert!(folder_paths(&read_json(&host_path))
+        .iter()
+        .all(|path| !path.starts_with('/')));
+    assert!(folder_paths(&read_json(&container_path))
+        .iter()
+        .all(|path| path.starts_with("/workspaces/")));
+    let output = Command::new(env!("CARGO_BIN_EXE_workspace-manager"))
+        .args([
+            "merge",
+            "team.code-workspace",
+            "team.container.code-workspace",
+            "-o",
+            "all.code-workspace",
+        ])
+        .current_dir(temp.path())
+        .env(
+            "WORKSPACE_MANAGER_REGISTRY",
+            temp.path().join(".registry.json"),
+        )
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("merge files of one kind"));
+}
+
+#[test]
+fn test_group_by_parent() {
+    let temp = TempDir::new().unwrap();
+    for dir in ["work/acme/api", "work/acme/web", "work/globex/tool"] {
+        fs::create_dir_all(temp.path().join(dir)).unwrap();
+    }
+    let work = temp.path().join("work");
+    let output = run(&work, &["--group-by-parent", "--sort", "name"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("'acme/acme.code-workspace'"));
+    assert!(stdout.contains("'globex/globex.code-workspace'"));
+    assert!(!work.join("work.code-workspace").exists());
+
+    let acme = read_json(&work.join("acme/acme.code-workspace"));
+    assert_eq!(folder_paths(&acme), vec![".", "api", "web"]);
+    let args = &acme["tasks"]["tasks"][0]["args"];
+    assert_eq!(
+        args,
+        &serde_json::json!(["--name", "acme", "--sort", "name", "--path", "."])
+    );
+
+    // Into one directory, with paths relative to it
+    run(
+        &work,
+        &[
+            "--group-by-parent",
+            "--output-dir",
+            "../all",
+            "--sort",
+            "name",
+        ],
+    );
+    let globex = read_json(&temp.path().join("all/globex.code-workspace"));
+    assert_eq!(folder_paths(&globex), vec!["../work/globex/tool"]);
+
+    // The task of a group regenerates that group alone
+    fs::create_dir(work.join("globex/docs")).unwrap();
+    run(
+        &temp.path().join("all"),
+        &["update", "globex.code-workspace"],
+    );
+    let globex = read_json(&temp.path().join("all/globex.code-workspace"));
+    assert_eq!(
+        folder_paths(&globex),
+        vec!["../work/globex/docs", "../work/globex/tool"]
+    );
+}
+
+#[test]
+fn test_empty_scan_fails_unless_allowed() {
+    let temp = TempDir::new().unwrap();
+    let output = run_unchecked(temp.path(), &["--name", "team", "--exclude", "ap*"]);
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Found 0 folder(s)"), "{}", stderr);
+    assert!(stderr.contains("--exclude 'ap*'"), "{}", stderr);
+    assert!(!temp.path().join("team.code-workspace").exists());
+
+    run(temp.path(), &["--name", "team", "--allow-empty"]);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec!["."]);
+
+    // The minimum can be raised too
+    let projects = projects();
+    let output = run_unchecked(projects.path(), &["--name", "team", "--min-folders", "3"]);
+    assert!(!output.status.success());
+    run(projects.path(), &["--name", "team", "--min-folders", "2"]);
+}
+
+#[test]
+fn test_large_scan_warns_and_caps() {
+    let temp = projects();
+    let output = run(temp.path(), &["--name", "team", "--warn-folders", "1"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: found 2 folders"), "{}", stderr);
+
+    fs::remove_file(temp.path().join("team.code-workspace")).unwrap();
+    let output = run_unchecked(temp.path(), &["--name", "team", "--max-folders", "1"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("more than --max-folders 1"));
+    assert!(!temp.path().join("team.code-workspace").exists());
+
+    run(
+        temp.path(),
+        &["--name", "team", "--max-folders", "1", "--force"],
+    );
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace).len(), 3);
+}
+
+#[test]
+fn test_settings_merge() {
+    let temp = TempDir::new().unwrap();
+    fs::write(
+        temp.path().join("a.code-workspace"),
+        r#"{"folders": [], "settings": {"editor.rulers": [80], "editor.tabSize": 2}}"#,
+    )
+    .unwrap();
+    fs::write(
+        temp.path().join("b.code-workspace"),
+        r#"{"folders": [], "settings": {"editor.rulers": [100], "editor.tabSize": 4}}"#,
+    )
+    .unwrap();
+    let merge = |strategy: &str| {
+        run_unchecked(
+            temp.path(),
+            &[
+                "merge",
+                "a.code-workspace",
+                "b.code-workspace",
+                "-o",
+                "all.code-workspace",
+                "--settings-merge",
+                strategy,
+            ],
+        )
+    };
+
+    assert!(merge("union-deep").status.success());
+    let settings = &read_json(&temp.path().join("all.code-workspace"))["settings"];
+    assert_eq!(settings["editor.rulers"], serde_json::json!([80, 100]));
+    assert_eq!(settings["editor.tabSize"], 2);
+
+    fs::remove_file(temp.path().join("all.code-workspace")).unwrap();
+    let output = merge("error");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("settings.editor.rulers"), "{}", stderr);
+    assert!(stderr.contains("settings.editor.tabSize"), "{}", stderr);
+    assert!(!temp.path().join("all.code-workspace").exists());
+}
+
+#[test]
+fn test_list_tree() {
+    let temp = projects();
+    fs::write(temp.path().join("api").join("Cargo.toml"), "").unwrap();
+    fs::write(
+        temp.path().join("team.code-workspace"),
+        r#"{"folders": [
+            {"path": "web", "name": "web"},
+            {"path": "api", "name": "api"},
+            {"path": "./services/auth", "name": "auth"},
+            {"path": "services/billing/worker", "name": "worker"}
+        ]}"#,
+    )
+    .unwrap();
+
+    let output = run(temp.path(), &["list", "--tree"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "team.code-workspace\n\
+         ├── api  api [cargo]\n\
+         ├── services/\n\
+         │   ├── auth  auth  (missing)\n\
+         │   └── billing/\n\
+         │       └── worker  worker  (missing)\n\
+         └── web  web\n"
+    );
+
+    let output = run(temp.path(), &["list", "--tree", "--depth", "1"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("├── services/  (+2 folder(s) below)\n"),
+        "{}",
+        stdout
+    );
+    assert!(!run_unchecked(temp.path(), &["list", "--depth", "1"])
+        .status
+        .success());
+}
+
+#[test]
+fn test_stats() {
+    let temp = projects();
+    fs::write(temp.path().join("api").join("Cargo.toml"), "").unwrap();
+    run(temp.path(), &["-n", "team", "--exclude-current"]);
+    fs::remove_dir(temp.path().join("web")).unwrap();
+
+    let output = run(temp.path(), &["stats"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Folders: 2\n\
+         Project types: cargo: 1, unknown: 0\n\
+         Missing: 1\n\
+         Managed task: yes ('Update Workspace')\n"
+    );
+
+    let output = run(temp.path(), &["stats", "team.code-workspace", "--json"]);
+    let stats: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["folders"], 2);
+    assert_eq!(stats["projectTypes"]["cargo"], 1);
+    assert_eq!(stats["missing"], 1);
+    assert_eq!(stats["managedTask"], true);
+}
+
+#[test]
+fn test_workspace_file() {
+    let temp = TempDir::new().unwrap();
+    let scan_dir = temp.path().join("projects");
+    fs::create_dir_all(scan_dir.join("api")).unwrap();
+    fs::create_dir(scan_dir.join("web")).unwrap();
+    fs::create_dir(temp.path().join("ws")).unwrap();
+    let path = temp.path().join("ws").join("team-alpha.code-workspace");
+    fs::write(
+        &path,
+        r#"{"folders": [{"path": "../projects/api", "name": "Backend"}],
+            "settings": {"editor.tabSize": 2},
+            "workspaceManager": {"customNames": ["../projects/api"]}}"#,
+    )
+    .unwrap();
+
+    // The file is updated in place, its name unrelated to the scanned directory
+    let file = "../ws/team-alpha.code-workspace";
+    run(&scan_dir, &["--workspace-file", file, "-n", "Alpha", "-u"]);
+    let workspace = read_json(&path);
+    let mut paths = folder_paths(&workspace);
+    paths.sort();
+    assert_eq!(paths, [".", "../projects/api", "../projects/web"]);
+    let name_of = |workspace: &Value, path: &str| {
+        let folders = workspace["folders"].as_array().unwrap();
+        let folder = folders.iter().find(|folder| folder["path"] == path);
+        folder.unwrap()["name"].clone()
+    };
+    assert_eq!(name_of(&workspace, "."), "🏗️ Alpha");
+    assert_eq!(name_of(&workspace, "../projects/api"), "Backend");
+    assert_eq!(workspace["settings"]["editor.tabSize"], 2);
+    assert!(!scan_dir.join("Alpha.code-workspace").exists());
+    assert!(!scan_dir.join("projects.code-workspace").exists());
+
+    // The task regenerates the same file from its directory
+    let args = &workspace["tasks"]["tasks"][0]["args"];
+    assert_eq!(
+        args,
+        &serde_json::json!([
+            "--name",
+            "Alpha",
+            "--workspace-file",
+            "team-alpha.code-workspace",
+            "--update-task",
+            "--path",
+            "../projects"
+        ])
+    );
+    fs::create_dir(scan_dir.join("docs")).unwrap();
+    run(temp.path(), &["update", "ws/team-alpha.code-workspace"]);
+    let workspace = read_json(&path);
+    assert!(folder_paths(&workspace).contains(&"../projects/docs"));
+    assert_eq!(name_of(&workspace, "."), "🏗️ Alpha");
+    let files: Vec<_> = fs::read_dir(temp.path().join("ws")).unwrap().collect();
+    assert_eq!(files.len(), 1);
+
+    // A new file is fine, one named unlike a workspace file is warned about
+    let output = run(&scan_dir, &["--workspace-file", "../ws/other.json"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("doesn't end in .code-workspace"),
+        "{}",
+        stderr
+    );
+    let workspace = read_json(&temp.path().join("ws").join("other.json"));
+    assert_eq!(name_of(&workspace, "."), "🏗️ other.json");
+}
+
+#[test]
+fn test_export_markdown() {
+    let temp = projects();
+    fs::write(temp.path().join("api").join("Cargo.toml"), "").unwrap();
+    fs::create_dir_all(temp.path().join("api").join(".git")).unwrap();
+    fs::write(temp.path().join("api/.git/HEAD"), "ref: refs/heads/main\n").unwrap();
+    run(temp.path(), &["-n", "team", "--exclude-current"]);
+
+    let output = run(temp.path(), &["export", "--markdown"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# team\n"), "{}", stdout);
+    assert!(stdout.contains("| api | cargo |  | main |"), "{}", stdout);
+    assert!(stdout.contains("| web | unknown |  |  |"), "{}", stdout);
+    assert!(
+        stdout.contains("_Generated by workspace-manager "),
+        "{}",
+        stdout
+    );
+
+    run(
+        temp.path(),
+        &["export", "--markdown", "--output", "inventory.md"],
+    );
+    let written = fs::read_to_string(temp.path().join("inventory.md")).unwrap();
+    assert!(written.contains("| Folder | Path | Type | Remote | Branch |"));
+    let output = run_unchecked(temp.path(), &["export", "--markdown", "--names"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_print_path() {
+    let temp = projects();
+    let expected = format!(
+        "{}\n",
+        temp.path()
+            .canonicalize()
+            .unwrap()
+            .join("team.code-workspace")
+            .display()
+    );
+
+    let output = run(temp.path(), &["-n", "team", "--print-path"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("updated successfully"), "{}", stderr);
+
+    // Also when the file was already up to date
+    let output = run(temp.path(), &["-n", "team", "--print-path"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("already up to date"), "{}", stderr);
+
+    // A dry run prints the path it would write, its report going to stderr
+    fs::remove_file(temp.path().join("team.code-workspace")).unwrap();
+    let output = run(temp.path(), &["-n", "team", "--print-path", "--dry-run"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("(api)"), "{}", stderr);
+    assert!(!temp.path().join("team.code-workspace").exists());
+
+    // The option isn't stored in the task
+    run(temp.path(), &["-n", "team", "--print-path"]);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    let args = workspace["tasks"]["tasks"][0]["args"].to_string();
+    assert!(!args.contains("--print-path"), "{}", args);
+}
+
+#[test]
+fn test_restore() {
+    let temp = projects();
+    fs::write(temp.path().join("api").join("README.md"), "").unwrap();
+    let origin = temp.path().join("origin");
+    let init = Command::new("git")
+        .args(["init", "--quiet"])
+        .arg(&origin)
+        .status();
+    if !init.is_ok_and(|status| status.success()) {
+        // git isn't installed
+        return;
+    }
+    let workspace = serde_json::json!({
+        "folders": [{"path": "api"}, {"path": "lib"}, {"path": "docs"}, {"path": "gone"}],
+        "workspaceManager": {"folders": {
+            "api": {"remote": "git@example.com:org/api.git"},
+            "lib": {"remote": origin.to_string_lossy()},
+            "gone": {"remote": temp.path().join("nowhere").to_string_lossy()}
+        }}
+    });
+    fs::write(
+        temp.path().join("team.code-workspace"),
+        workspace.to_string(),
+    )
+    .unwrap();
+
+    let output = run(temp.path(), &["restore", "--dry-run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Skipped 'docs': no remote recorded"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Would clone"), "{}", stdout);
+    assert!(!stdout.contains("'api'"), "{}", stdout);
+    assert!(!temp.path().join("lib").exists());
+
+    let output = run_unchecked(temp.path(), &["restore", "--jobs", "2"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(temp.path().join("lib").join(".git").is_dir());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Cloned"), "{}", stdout);
+    // git's own message explains the failure
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed to clone"), "{}", stderr);
+    assert!(stderr.contains("1 of 2 missing folder(s)"), "{}", stderr);
+}
+
+#[test]
+fn test_delete() {
+    let temp = projects();
+    run(temp.path(), &["-n", "team"]);
+    run(temp.path(), &["-n", "other"]);
+    let registry = temp.path().join(".registry.json");
+    let registered = || fs::read_to_string(&registry).unwrap();
+    assert!(registered().contains("team.code-workspace"));
+
+    // Without a terminal to ask on, nothing is deleted without --yes
+    let output = run_unchecked(temp.path(), &["delete", "team"]);
+    assert!(!output.status.success());
+    assert!(temp.path().join("team.code-workspace").exists());
+
+    let output = run(temp.path(), &["delete", "team", "--yes"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Deleted 'team.code-workspace'"),
+        "{}",
+        stdout
+    );
+    assert!(!temp.path().join("team.code-workspace").exists());
+    assert!(!registered().contains("team.code-workspace"));
+    assert!(registered().contains("other.code-workspace"));
+
+    // Already gone is only a warning
+    let output = run(temp.path(), &["uninstall", "team.code-workspace", "-y"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("does not exist"), "{}", stderr);
+
+    fs::write(temp.path().join("notes.txt"), "{\"folders\": []}").unwrap();
+    let output = run_unchecked(temp.path(), &["delete", "notes.txt", "--yes"]);
+    assert!(!output.status.success());
+    assert!(temp.path().join("notes.txt").exists());
+
+    run(temp.path(), &["delete", "other", "--clean-task-only"]);
+    let workspace = read_json(&temp.path().join("other.code-workspace"));
+    assert!(workspace.get("tasks").is_none());
+    assert_eq!(folder_paths(&workspace).len(), 3);
+    assert!(!registered().contains("other.code-workspace"));
+}
+
+// Directories can't be opened to set their times on Windows
+#[cfg(unix)]
+#[test]
+fn test_newer_than() {
+    use std::time::{Duration, SystemTime};
+    let temp = projects();
+    let days_ago = |days: u64| SystemTime::now() - Duration::from_secs(days * 86_400);
+    let touch = |path: &Path, days: u64| {
+        fs::File::open(path)
+            .unwrap()
+            .set_modified(days_ago(days))
+            .unwrap();
+    };
+    fs::write(temp.path().join("web").join("index.html"), "").unwrap();
+    touch(&temp.path().join("web").join("index.html"), 1);
+    touch(&temp.path().join("web"), 60);
+    touch(&temp.path().join("api"), 60);
+
+    let args = ["-n", "team", "--newer-than", "30d", "--verbose"];
+    let output = run(temp.path(), &args);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("api: not modified within --newer-than 30d"),
+        "{}",
+        stderr
+    );
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "web"]);
+    let task_args = workspace["tasks"]["tasks"][0]["args"].to_string();
+    assert!(
+        task_args.contains(r#""--newer-than","30d""#),
+        "{}",
+        task_args
+    );
+
+    // Nothing is that old, and the error says which filter left everything out
+    let output = run_unchecked(temp.path(), &["-n", "old", "--older-than", "2024-01-01"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("  --older-than 2024-01-01"), "{}", stderr);
+
+    let output = run_unchecked(temp.path(), &["-n", "team", "--newer-than", "yesterday"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid time 'yesterday'"), "{}", stderr);
+}
+
+#[test]
+fn test_max_entries_and_warn_size() {
+    let temp = projects();
+    for i in 0..5 {
+        fs::write(temp.path().join("web").join(format!("{}.json", i)), "").unwrap();
+    }
+    fs::write(temp.path().join("api").join("blob.bin"), vec![0; 1_500_000]).unwrap();
+
+    let args = ["-n", "team", "--max-entries", "3", "--warn-size", "1"];
+    let output = run(temp.path(), &[&args[..], &["--verbose"]].concat());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("web: more than 3 entries (--max-entries)"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("with more than 3 entries (--max-entries):\n  web"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("'api' holds more than 1 MB of files"),
+        "{}",
+        stderr
+    );
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec![".", "api"]);
+    let task_args = workspace["tasks"]["tasks"][0]["args"].to_string();
+    assert!(
+        task_args.contains(r#""--max-entries","3","--warn-size","1""#),
+        "{}",
+        task_args
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stale_task_command_is_repaired() {
+    let temp = projects();
+    let path = temp.path().join("team.code-workspace");
+    let stale = r#"{
+  "folders": [{"path": "api", "name": "api"}],
+  "tasks": {"version": "2.0.0", "tasks": [
+    {"label": "Deploy", "type": "process", "command": "/nonexistent/deploy"},
+    {"label": "Update Workspace", "type": "process",
+     "command": "/nonexistent/bin/workspace-manager", "args": ["--name", "team"]}
+  ]}
+}"#;
+    fs::write(&path, stale).unwrap();
+
+    let output = run(temp.path(), &["-n", "team", "--task-command", "portable"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("ran '/nonexistent/bin/workspace-manager', which no longer exists"),
+        "{}",
+        stdout
+    );
+    let workspace = read_json(&path);
+    let tasks = &workspace["tasks"]["tasks"];
+    assert_eq!(tasks[0]["command"], "/nonexistent/deploy");
+    assert_eq!(tasks[1]["command"], "workspace-manager");
+    assert_eq!(tasks[1]["args"], serde_json::json!(["--name", "team"]));
+
+    // doctor reports it, and repairs it with --fix, as the configuration --task-command
+    // portable was recorded in asks
+    assert!(
+        fs::read_to_string(temp.path().join(".workspace-manager.toml"))
+            .unwrap()
+            .contains("task_command = \"portable\"")
+    );
+    fs::write(&path, stale).unwrap();
+    let output = run_unchecked(temp.path(), &["doctor"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("task-command-missing"));
+    // Whether the checks then pass depends on the tool being on PATH here
+    let output = run_unchecked(temp.path(), &["doctor", "--fix"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Repaired the 'Update Workspace' task")
+            && stderr.contains("now runs 'workspace-manager'"),
+        "{}",
+        stderr
+    );
+    let command = read_json(&path)["tasks"]["tasks"][1]["command"].clone();
+    assert_eq!(command, "workspace-manager");
+
+    // Without it, the task runs the binary doing the repair
+    fs::remove_file(temp.path().join(".workspace-manager.toml")).unwrap();
+    fs::write(&path, stale).unwrap();
+    run(temp.path(), &["doctor", "--fix"]);
+    let command = read_json(&path)["tasks"]["tasks"][1]["command"].clone();
+    assert_eq!(command, env!("CARGO_BIN_EXE_workspace-manager"));
+}
+
+#[test]
+fn test_init() {
+    let temp = projects();
+    fs::write(temp.path().join("api").join("Cargo.toml"), "").unwrap();
+    let args = [
+        "init",
+        "--exclude",
+        "build",
+        "--marker",
+        "Cargo.toml",
+        "--no-emoji",
+    ];
+    let output = run(temp.path(), &args);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("with 3 setting(s)"), "{}", stdout);
+    assert!(stdout.contains("Next steps:"), "{}", stdout);
+    let config = fs::read_to_string(temp.path().join(".workspace-manager.toml")).unwrap();
+    assert!(
+        config.contains("\nmarkers = [\"Cargo.toml\"]\n"),
+        "{}",
+        config
+    );
+    assert!(config.contains("\n# max_depth = 2\n"), "{}", config);
+
+    // Generating picks the settings up without the flags
+    run(temp.path(), &["-n", "team", "--exclude-current"]);
+    let workspace = read_json(&temp.path().join("team.code-workspace"));
+    assert_eq!(folder_paths(&workspace), vec!["api"]);
+    assert_eq!(workspace["folders"][0]["name"], "api");
+
+    let output = run_unchecked(temp.path(), &["init", "--max-depth", "2"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("pass --force to overwrite it"),
+        "{}",
+        stderr
+    );
+    run(temp.path(), &["init", "--max-depth", "2", "--force"]);
+    let config = fs::read_to_string(temp.path().join(".workspace-manager.toml")).unwrap();
+    assert!(config.contains("\nmax_depth = 2\n"), "{}", config);
+    assert!(config.contains("\n# markers = "), "{}", config);
+}
@@ -0,0 +1,78 @@
+//! The library used without the binary: parse the flags, resolve the options, build
+//! and write the workspace, then edit it the way the folder commands do.
+
+use anyhow::Result;
+use clap::Parser;
+use std::fs;
+use tempfile::TempDir;
+use workspace_manager::config::ConfigLayers;
+use workspace_manager::{
+    add_folder, create_workspace, list_folders, read_workspace, rename_folder,
+    runs_workspace_manager, write_workspace, Cli, FolderSelector, TaskAction, WriteOptions,
+    UPDATE_TASK_LABEL,
+};
+
+#[test]
+fn test_generate_and_edit_pipeline() -> Result<()> {
+    let temp = TempDir::new()?;
+    let root = temp.path().canonicalize()?;
+    for dir in ["api", "web", ".cache", "notes"] {
+        fs::create_dir(root.join(dir))?;
+    }
+
+    let cli = Cli::try_parse_from([
+        "workspace-manager",
+        "--name",
+        "team",
+        "--exclude",
+        "notes",
+        "--exclude-current",
+        "--sort",
+        "name",
+    ])?;
+    let opts = cli
+        .generate
+        .resolve_with(root.clone(), &root, &ConfigLayers::default())?
+        .options;
+    assert_eq!(opts.workspace_filename(), "team.code-workspace");
+
+    let (workspace, changes) = create_workspace(&opts, &root)?;
+    assert_eq!(changes.folders_added.len(), 2);
+    assert_eq!(changes.task_action, TaskAction::Created);
+    let path = root.join(opts.workspace_filename());
+    write_workspace(&path, &workspace, &WriteOptions::default())?;
+
+    let mut workspace = read_workspace(&path)?;
+    let (regenerated, changes) = create_workspace(&opts, &root)?;
+    assert_eq!(workspace, regenerated);
+    assert!(changes.is_empty(), "{}", changes);
+    let paths: Vec<String> = list_folders(&workspace, &root)
+        .into_iter()
+        .map(|folder| folder.path)
+        .collect();
+    assert_eq!(paths, vec!["api", "web"]);
+    let tasks = workspace.tasks.as_ref().expect("managed task");
+    let task = tasks
+        .tasks
+        .iter()
+        .find(|task| task.label == UPDATE_TASK_LABEL)
+        .expect("update task");
+    assert!(runs_workspace_manager(task));
+
+    fs::create_dir(root.join("docs"))?;
+    add_folder(&mut workspace, &root, &root.join("docs"), None, false)?;
+    let (old, new) = rename_folder(
+        &mut workspace,
+        &FolderSelector::Path(vec!["api".to_string()]),
+        "backend",
+        true,
+    )?;
+    assert_eq!((old.as_str(), new.as_str()), ("📦 api", "📦 backend"));
+    write_workspace(&path, &workspace, &WriteOptions::default())?;
+
+    let listing = list_folders(&read_workspace(&path)?, &root);
+    let names: Vec<&str> = listing.iter().map(|folder| folder.name.as_str()).collect();
+    assert_eq!(names, vec!["📦 backend", "📦 web", "📦 docs"]);
+    assert!(listing.iter().all(|folder| folder.exists == Some(true)));
+    Ok(())
+}